@@ -1,9 +1,8 @@
-use std::collections::HashSet;
 
-use proc_macro2::{TokenStream, TokenTree};
-use quote::{format_ident, quote};
-use syn::{Attribute, Data, DeriveInput, Field, Fields, Ident, parse_quote, Path, spanned::Spanned, Token, Type, Visibility};
-use syn::parse::{Parse, ParseStream};
+use proc_macro2::{Delimiter, Group, Span, TokenStream, TokenTree};
+use quote::{format_ident, quote, ToTokens};
+use syn::{Attribute, Data, DataStruct, DeriveInput, Field, Fields, FieldsNamed, FieldsUnnamed, Generics, Ident, parse_quote, Path, spanned::Spanned, Token, Type, Visibility};
+use syn::parse::{Parse, ParseStream, Parser};
 use syn::punctuated::Punctuated;
 use syn::token::Comma;
 
@@ -11,7 +10,58 @@ const RENAME_ATTRIBUTE: &str = "optional_rename";
 const SKIP_WRAP_ATTRIBUTE: &str = "optional_skip_wrap";
 const WRAP_ATTRIBUTE: &str = "optional_wrap";
 const SERDE_SKIP_SERIALIZING_NONE: &str = "optional_serde_skip_none";
+const CONVERT_ATTRIBUTE: &str = "optional_convert";
+const SET_IF_ATTRIBUTE: &str = "optional_set_if";
+const WRAP_WITH_ATTRIBUTE: &str = "optional_wrap_with";
+const FIELD_NAME_ATTRIBUTE: &str = "optional_field_name";
+const SKIP_ATTRIBUTE: &str = "optional_skip";
+const MERGE_OP_ATTRIBUTE: &str = "optional_merge_op";
+const GROUP_ATTRIBUTE: &str = "optional_group";
+const READ_ONLY_ATTRIBUTE: &str = "optional_read_only";
+const IMMUTABLE_ATTRIBUTE: &str = "optional_immutable";
+const NOT_OPTION_ATTRIBUTE: &str = "optional_not_option";
+const OPTION_LIKE_ATTRIBUTE: &str = "optional_option_like";
+const ENUM_MERGE_ATTRIBUTE: &str = "optional_enum_merge";
+const TRANSPARENT_ATTRIBUTE: &str = "optional_transparent";
+const ATOMIC_ATTRIBUTE: &str = "optional_atomic";
+const REPLACE_ONLY_ATTRIBUTE: &str = "optional_replace_only";
+const ELEMENTWISE_ATTRIBUTE: &str = "optional_elementwise";
+const SECRET_ATTRIBUTE: &str = "optional_secret";
+const PROTO_NAME_ATTRIBUTE: &str = "optional_proto_name";
+const COLUMN_ATTRIBUTE: &str = "optional_column";
+const REDIS_WITH_ATTRIBUTE: &str = "optional_redis_with";
 const CFG_ATTRIBUTE: &str = "cfg";
+const PASSTHROUGH_ATTRIBUTE: &str = "optional_struct_attr";
+const MAP_TYPE_ATTRIBUTE: &str = "optional_map_type";
+const VERSION_FIELD_ATTRIBUTE: &str = "optional_version_field";
+const TARGETS_ATTRIBUTE: &str = "optional_targets";
+
+const HELPER_ATTRIBUTES: &[&str] = &[
+    RENAME_ATTRIBUTE,
+    SKIP_WRAP_ATTRIBUTE,
+    WRAP_ATTRIBUTE,
+    SERDE_SKIP_SERIALIZING_NONE,
+    CONVERT_ATTRIBUTE,
+    SET_IF_ATTRIBUTE,
+    WRAP_WITH_ATTRIBUTE,
+    FIELD_NAME_ATTRIBUTE,
+    SKIP_ATTRIBUTE,
+    MERGE_OP_ATTRIBUTE,
+    GROUP_ATTRIBUTE,
+    READ_ONLY_ATTRIBUTE,
+    IMMUTABLE_ATTRIBUTE,
+    NOT_OPTION_ATTRIBUTE,
+    OPTION_LIKE_ATTRIBUTE,
+    ENUM_MERGE_ATTRIBUTE,
+    TRANSPARENT_ATTRIBUTE,
+    ATOMIC_ATTRIBUTE,
+    REPLACE_ONLY_ATTRIBUTE,
+    ELEMENTWISE_ATTRIBUTE,
+    SECRET_ATTRIBUTE,
+    PROTO_NAME_ATTRIBUTE,
+    COLUMN_ATTRIBUTE,
+    REDIS_WITH_ATTRIBUTE,
+];
 
 #[cfg(test)]
 mod test;
@@ -20,97 +70,554 @@ struct FieldOptions {
     wrapping_behavior: bool,
     serde_skip: bool,
     cfg_attribute: Option<Attribute>,
-    new_type: Option<TokenTree>,
+    new_type: Option<Type>,
+    convert: Option<FieldConversion>,
+    // Predicate telling whether a non-wrapped field is set
+    set_if: Option<TokenStream>,
+    // Wraps the field instead of `Option`, only set for wrapped fields that aren't nested
+    wrapper: Option<Path>,
+    // How applied values are combined with the current ones, instead of replacing them
+    merge_op: Option<Ident>,
+    // Fields of a same group are either all set or all unset
+    group: Option<Ident>,
+    // Kept in the optional struct, but never applied to the original one
+    read_only: bool,
+    // A read-only field which can't be set by name either, e.g. an id
+    immutable: bool,
+    // Only ever moved into the original struct, its type being neither comparable nor printable
+    replace_only: bool,
+    // An array stored as `[Option<T>; N]`, each element being set on its own
+    elementwise: bool,
+    // A nested patch of an enum, see `optional_enum_merge`
+    enum_merge: bool,
+    // Its values are redacted when printed or logged
+    secret: bool,
+    // Name of the field in the protobuf message of the `prost` option, if it differs
+    proto_name: Option<Ident>,
+    // Name of the database column listed by `set_columns`, if it differs
+    column: Option<Ident>,
+    // Function turning a value into the string written to the Redis hash, instead of `ToString`
+    redis_with: Option<Path>,
+    // A nested field whose original type is a `Box`, e.g. of the struct itself, the nested patch
+    // being boxed as well
+    boxed: bool,
+    // Whether the type of the original field is an `Option`
+    is_base_opt: bool,
+    // Whether it is an `Option`-like type rather than an actual `Option`
+    option_like: bool,
     field_ident: TokenStream,
+    // Differs from `field_ident` when renamed with `optional_field_name`
+    new_field_ident: TokenStream,
+    // String versions of the identifiers above, as used in names reported at runtime
+    field_name: String,
+    new_field_name: String,
+}
+
+// The optional struct stores `ty` instead of the original type of the field
+struct FieldConversion {
+    ty: Type,
+    into: Path,
+    from: Path,
+}
+
+impl FieldOptions {
+    // Whether an `Option` of the original struct is wrapped once more, the outer `Option` telling
+    // whether the field is set and the inner one holding its value. An unset field stands for
+    // `None` when building the original struct.
+    fn is_double_wrapped(&self) -> bool {
+        self.is_base_opt && self.wrapping_behavior && self.new_type.is_none() && self.convert.is_none()
+    }
+
+    // Whether it is a nested patch that isn't wrapped, which can only be taken out of its patch if
+    // it implements `Default`
+    fn is_moved_nested(&self) -> bool {
+        self.new_type.is_some() && !self.wrapping_behavior
+    }
+
+    // Whether it is an `Option` set to `Some(None)` to be cleared, like a double wrapped field
+    fn is_clearable(&self) -> bool {
+        self.is_double_wrapped() && !self.option_like
+    }
+
+    // Type of the value a set field gives, the inner one of a clearable field
+    fn set_value_type(&self, old_field: &Field, new_field: &Field) -> TokenStream {
+        if self.is_clearable() {
+            let old_ty = &old_field.ty;
+            let inner = first_type_argument(old_ty).unwrap_or(old_ty);
+            quote! { #inner }
+        } else if self.wrapping_behavior {
+            self.value_type(old_field)
+        } else {
+            let ty = &new_field.ty;
+            quote! { #ty }
+        }
+    }
+
+    // Type of the values the optional struct holds for this field
+    fn value_type(&self, old_field: &Field) -> TokenStream {
+        if let (true, Type::Array(array)) = (self.elementwise, &old_field.ty) {
+            let (elem, len) = (&array.elem, &array.len);
+            return quote! { [Option<#elem>; #len] };
+        }
+        match (&self.new_type, &self.convert) {
+            (Some(new_type), _) if self.boxed => quote! { ::optional_struct::__private::Box<#new_type> },
+            (Some(new_type), _) => quote! { #new_type },
+            (None, Some(convert)) => {
+                let ty = &convert.ty;
+                quote! { #ty }
+            }
+            (None, None) => {
+                let old_ty = &old_field.ty;
+                quote! { #old_ty }
+            }
+        }
+    }
+
+    fn wrapped_type(&self, value_ty: TokenStream) -> TokenStream {
+        match &self.wrapper {
+            Some(wrapper) => quote! { #wrapper<#value_ty> },
+            None => quote! { Option<#value_ty> },
+        }
+    }
+
+    // Value of a wrapped field which is set to `value`
+    fn wrap(&self, value: TokenStream) -> TokenStream {
+        match &self.wrapper {
+            Some(_) => quote! { ::optional_struct::Wrapper::wrap(#value) },
+            None => quote! { Some(#value) },
+        }
+    }
+
+    // Turns a wrapped field into an `Option`
+    fn unwrap(&self, field: TokenStream) -> TokenStream {
+        match &self.wrapper {
+            Some(_) => quote! { ::optional_struct::Wrapper::into_option(#field) },
+            None => field,
+        }
+    }
+
+    // Borrows a wrapped field as an `Option`
+    fn unwrap_ref(&self, field: TokenStream) -> TokenStream {
+        match &self.wrapper {
+            Some(_) => quote! { ::optional_struct::Wrapper::as_option(&#field) },
+            None => quote! { #field.as_ref() },
+        }
+    }
+
+    // Converts a nested patch into a value of the original type, giving the patch back on failure
+    fn nested_try_into(&self, patch: TokenStream) -> TokenStream {
+        if self.boxed {
+            quote! {
+                TryInto::try_into(*#patch)
+                    .map(::optional_struct::__private::Box::new)
+                    .map_err(::optional_struct::__private::Box::new)
+            }
+        } else {
+            quote! { #patch.try_into() }
+        }
+    }
+
+    // Pattern binding `local` and the value it matches, which moves the field out of the patch once
+    // it is known to be complete, an empty value being left in its place. `None` for the fields
+    // that can't be missing, which are moved as they are.
+    fn take(&self, old_field: &Field, field: TokenStream, local: &Ident) -> Option<(TokenStream, TokenStream)> {
+        let taken = quote! { ::core::mem::take(&mut #field) };
+        let nested_try_into = self.nested_try_into(quote! { nested });
+        let take = match (self.is_base_opt, self.wrapping_behavior, self.new_type.is_some()) {
+            (true, true, false) if self.is_double_wrapped() => return None,
+            (_, true, false) => (quote! { Some(#local) }, self.unwrap(taken)),
+            (true, true, true) => (quote! { Ok(#local) }, quote! { #taken.map(|nested| #nested_try_into).transpose() }),
+            (_, true, true) => (quote! { Some(Ok(#local)) }, quote! { #taken.map(|nested| #nested_try_into) }),
+            (_, false, true) => (quote! { Ok(#local) }, self.nested_try_into(taken)),
+            (_, false, false) if self.elementwise => {
+                let old_ty = &old_field.ty;
+                (quote! { Ok(#local) }, quote! {
+                    <#old_ty>::try_from(
+                        ::core::mem::replace(&mut #field, ::core::array::from_fn(|_| None))
+                            .into_iter()
+                            .flatten()
+                            .collect::<::optional_struct::__private::Vec<_>>(),
+                    )
+                })
+            }
+            (_, false, false) => return None,
+        };
+        Some(take)
+    }
+
+    // Boxes a nested patch if the field holds it in a `Box`
+    fn boxed_if_needed(&self, patch: TokenStream) -> TokenStream {
+        if self.boxed {
+            quote! { ::optional_struct::__private::Box::new(#patch) }
+        } else {
+            patch
+        }
+    }
+
+    // Converts a value held by the optional struct into one of the original type
+    fn convert_from_patch(&self, value: TokenStream) -> TokenStream {
+        match &self.convert {
+            Some(FieldConversion { from, .. }) => {
+                let from = user_tokens(from);
+                quote! { #from(#value) }
+            }
+            None => value,
+        }
+    }
+
+    // Value a field ends up with when `value` is applied to it
+    fn merge_value(&self, current: TokenStream, value: TokenStream) -> TokenStream {
+        match &self.merge_op {
+            Some(op) if op == "add" => quote! { ::core::ops::Add::add(#current, #value) },
+            Some(op) if op == "saturating_add" => quote! { #current.saturating_add(#value) },
+            Some(op) if op == "min" => quote! { ::core::cmp::min(#current, #value) },
+            Some(op) if op == "max" => quote! { ::core::cmp::max(#current, #value) },
+            Some(op) => panic!("Unknown '{MERGE_OP_ATTRIBUTE}' operation '{op}', expected one of add, saturating_add, min or max"),
+            None => value,
+        }
+    }
+
+    // Expression telling whether the field of `this` is set
+    fn is_set(&self, this: TokenStream) -> TokenStream {
+        let ident = &self.new_field_ident;
+        match (self.is_base_opt, self.wrapping_behavior, self.new_type.is_some()) {
+            (_, true, false) => {
+                let field = self.unwrap_ref(quote! { #this.#ident });
+                quote! { #field.is_some() }
+            }
+            (true, false, false) | (_, true, true) => quote! { #this.#ident.is_some() },
+            (_, false, true) => quote! { #this.#ident.set_count() != 0 },
+            (false, false, false) if self.elementwise => quote! { #this.#ident.iter().any(Option::is_some) },
+            (false, false, false) => match &self.set_if {
+                Some(predicate) => quote! { ::optional_struct::__private::is_set(&#this.#ident, #predicate) },
+                None => quote! { true },
+            },
+        }
+    }
+
+    // Moves `value` into `target` (merged with its current value if needed), evaluating to the
+    // value it replaced
+    fn replace_value(&self, target: TokenStream, value: TokenStream) -> TokenStream {
+        if self.merge_op.is_some() {
+            let merged = self.merge_value(target.clone(), value);
+            quote! {
+                {
+                    let value = #merged;
+                    ::core::mem::replace(&mut #target, value)
+                }
+            }
+        } else {
+            quote! { ::core::mem::replace(&mut #target, #value) }
+        }
+    }
+
+    // Wraps `inc` so that it only runs when the field of `this` is considered set
+    fn when_set(&self, this: TokenStream, inc: TokenStream) -> TokenStream {
+        if self.elementwise {
+            let is_set = self.is_set(this);
+            return quote! {
+                if #is_set {
+                    #inc
+                }
+            };
+        }
+        match &self.set_if {
+            Some(predicate) => {
+                let ident = &self.new_field_ident;
+                quote! {
+                    if ::optional_struct::__private::is_set(&#this.#ident, #predicate) {
+                        #inc
+                    }
+                }
+            }
+            None => inc,
+        }
+    }
+
+    // Converts a value of the original type into one held by the optional struct
+    fn convert_to_patch(&self, value: TokenStream) -> TokenStream {
+        if self.elementwise {
+            return quote! { #value.map(Some) };
+        }
+        match &self.convert {
+            Some(FieldConversion { into, .. }) => {
+                let into = user_tokens(into);
+                quote! { #into(#value) }
+            }
+            None => value,
+        }
+    }
 }
 
 trait OptionalFieldVisitor {
-    fn visit(&mut self, global_options: &GlobalOptions, old_field: &mut Field, new_field: &mut Field, field_options: &FieldOptions);
+    fn visit(&mut self, global_options: &GlobalOptions, old_field: &Field, new_field: &mut Field, field_options: &FieldOptions);
+
+    // Called instead of `visit` for fields left out of the generated struct with `optional_skip`,
+    // `default` being the expression used to initialize them.
+    fn visit_skipped(&mut self, _global_options: &GlobalOptions, _old_field: &Field, _field_options: &FieldOptions, _default: &TokenStream) {}
+}
+
+// Statements of a generated function, the consecutive ones gated by the same `cfg` attribute
+// sharing a single block instead of one each.
+#[derive(Default)]
+struct CfgStatements(Vec<(Option<Attribute>, TokenStream)>);
+
+impl CfgStatements {
+    fn push(&mut self, cfg_attribute: &Option<Attribute>, statement: TokenStream) {
+        let cfg = cfg_attribute.as_ref().map(|a| quote! { #a }.to_string());
+        match self.0.last_mut() {
+            Some((last, statements)) if last.as_ref().map(|a| quote! { #a }.to_string()) == cfg => statements.extend(statement),
+            _ => self.0.push((cfg_attribute.clone(), statement)),
+        }
+    }
 }
 
+impl ToTokens for CfgStatements {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        for (cfg_attribute, statements) in &self.0 {
+            match cfg_attribute {
+                Some(cfg_attribute) => tokens.extend(quote! { #cfg_attribute { #statements } }),
+                None => tokens.extend(statements.clone()),
+            }
+        }
+    }
+}
+
+// `can_convert` and `TryFrom` share the same check, `__missing_field`
 struct GenerateCanConvertImpl {
-    acc: TokenStream,
+    acc: CfgStatements,
+    missing_acc: CfgStatements,
+    paths_acc: CfgStatements,
+    has_groups: bool,
 }
 
 impl GenerateCanConvertImpl {
     fn new() -> Self {
         GenerateCanConvertImpl {
-            acc: quote! { }
+            acc: CfgStatements::default(),
+            missing_acc: CfgStatements::default(),
+            paths_acc: CfgStatements::default(),
+            has_groups: false,
         }
     }
 
-    fn get_implementation(self, derive_input: &DeriveInput, new: &DeriveInput) -> TokenStream {
+    // `missing_fields` tells whether the fields preventing the conversion can be listed
+    fn get_implementation(self, global_options: &GlobalOptions, derive_input: &DeriveInput, new: &DeriveInput, missing_fields: bool) -> TokenStream {
         let (impl_generics, ty_generics, _) = derive_input.generics.split_for_impl();
         let new_name = &new.ident;
+        let must_use = global_options.must_use_attribute();
+        let inline = global_options.inline_attribute();
         let acc = self.acc;
+        let missing_acc = self.missing_acc;
+        let paths_acc = self.paths_acc;
+        let (group_check, missing_group, path_group) = if self.has_groups {
+            (
+                quote! {
+                    if let Some(group) = self.incomplete_group() {
+                        return Some(group);
+                    }
+                },
+                quote! { missing.extend(self.incomplete_group()); },
+                quote! { missing.extend(self.incomplete_group().map(::optional_struct::__private::String::from)); },
+            )
+        } else {
+            (quote! {}, quote! {}, quote! {})
+        };
 
+        let missing_fields = emit_if(missing_fields, || quote! {
+            // Every field preventing the conversion, the fields of a tuple struct being
+            // labelled by their index (e.g. `.0`)
+            #must_use
+            fn missing_fields(&self) -> ::optional_struct::__private::Vec<&'static str> {
+                #[allow(unused_mut)]
+                let mut missing = ::optional_struct::__private::Vec::new();
+                #missing_group
+                #missing_acc
+                missing
+            }
+
+            // Like `missing_fields`, with the full path of the fields of nested structs, e.g.
+            // `database.url`
+            #must_use
+            fn missing_field_paths(&self) -> ::optional_struct::__private::Vec<::optional_struct::__private::String> {
+                #[allow(unused_mut)]
+                let mut missing = ::optional_struct::__private::Vec::new();
+                #path_group
+                #paths_acc
+                missing
+            }
+        });
         quote! {
             impl #impl_generics #new_name #ty_generics {
-                fn can_convert(&self) -> bool {
+                // Name of the first field preventing the conversion, or of the first
+                // group that is only partly set
+                fn __missing_field(&self) -> Option<&'static str> {
+                    #group_check
                     #acc
-                    true
+                    None
+                }
+
+                #must_use
+                #inline
+                fn can_convert(&self) -> bool {
+                    self.__missing_field().is_none()
                 }
+
+                #missing_fields
             }
         }
     }
 }
 
 impl OptionalFieldVisitor for GenerateCanConvertImpl {
-    fn visit(&mut self, _global_options: &GlobalOptions, old_field: &mut Field, _new_field: &mut Field, field_options: &FieldOptions) {
-        let ident = &field_options.field_ident;
+    fn visit(&mut self, _global_options: &GlobalOptions, old_field: &Field, _new_field: &mut Field, field_options: &FieldOptions) {
+        let ident = &field_options.new_field_ident;
         let cfg_attr = &field_options.cfg_attribute;
+        let name = if old_field.ident.is_some() {
+            field_options.new_field_name.clone()
+        } else {
+            format!(".{}", field_options.new_field_name)
+        };
 
         let is_wrapped = field_options.wrapping_behavior;
         let is_nested = field_options.new_type.is_some();
-        let is_base_opt = is_type_option(&old_field.ty);
-        let inc = match (is_base_opt, is_wrapped, is_nested) {
-            (_, true, false) =>
-                quote! { self.#ident.is_some() },
-            (_, true, true) =>
+        let is_base_opt = field_options.is_base_opt;
+        self.has_groups |= field_options.group.is_some();
+
+        // A wrapped field is missing when unset, and a nested patch when it can't be converted
+        // itself. A wrapped nested patch thus has to be both set and complete.
+        let missing = match (is_base_opt, is_wrapped, is_nested) {
+            (true, true, false) if field_options.is_double_wrapped() => return,
+            (_, true, false) => {
+                let field = field_options.unwrap_ref(quote! { self.#ident });
+                quote! { #field.is_none() }
+            }
+            // An `Option` in the original struct can be left to `None`
+            (true, true, true) =>
                 quote! { if let Some(i) = &self.#ident { !i.can_convert() } else { false } },
+            (_, true, true) =>
+                quote! { if let Some(i) = &self.#ident { !i.can_convert() } else { true } },
             (_, false, true) =>
-                quote! { self.#ident.can_convert() },
-            (_, false, false) => quote! { true }
+                quote! { !self.#ident.can_convert() },
+            (_, false, false) if field_options.elementwise =>
+                quote! { self.#ident.iter().any(Option::is_none) },
+            (_, false, false) => return,
         };
-        let acc = &self.acc;
-        self.acc = quote! {
-            #acc
-            #cfg_attr
-            if !#inc {
-                return false;
+        self.acc.push(cfg_attr, quote! {
+            if #missing {
+                return Some(#name);
+            }
+        });
+        self.missing_acc.push(cfg_attr, quote! {
+            if #missing {
+                missing.push(#name);
+            }
+        });
+
+        // Enum patches and boxed ones, which may be recursive, are reported as a whole
+        let nested_paths = |nested: TokenStream| {
+            if field_options.enum_merge || field_options.boxed {
+                quote! { missing.push(::optional_struct::__private::String::from(#name)); }
+            } else {
+                quote! {
+                    missing.extend(#nested.missing_field_paths().into_iter().map(|path| ::optional_struct::__private::format!("{}.{}", #name, path)));
+                }
+            }
+        };
+        let paths = match (is_base_opt, is_wrapped, is_nested) {
+            (true, true, true) => {
+                let nested_paths = nested_paths(quote! { i });
+                quote! {
+                    if let Some(i) = &self.#ident {
+                        if !i.can_convert() {
+                            #nested_paths
+                        }
+                    }
+                }
+            }
+            (_, true, true) => {
+                let nested_paths = nested_paths(quote! { i });
+                quote! {
+                    match &self.#ident {
+                        Some(i) if i.can_convert() => {}
+                        Some(i) => { #nested_paths }
+                        None => missing.push(::optional_struct::__private::String::from(#name)),
+                    }
+                }
             }
+            (_, false, true) => {
+                let nested_paths = nested_paths(quote! { self.#ident });
+                quote! {
+                    if !self.#ident.can_convert() {
+                        #nested_paths
+                    }
+                }
+            }
+            // Elements are reported with their index, e.g. `table[2]`
+            (_, false, false) => quote! {
+                for (i, value) in self.#ident.iter().enumerate() {
+                    if value.is_none() {
+                        missing.push(::optional_struct::__private::format!("{}[{}]", #name, i));
+                    }
+                }
+            },
+            _ => quote! {
+                if #missing {
+                    missing.push(::optional_struct::__private::String::from(#name));
+                }
+            },
         };
+        self.paths_acc.push(cfg_attr, paths);
     }
 }
 
 struct GenerateTryFromImpl {
-    field_assign_acc: TokenStream,
-    field_check_acc: TokenStream,
+    field_assign_acc: Vec<TokenStream>,
+    // Fields that can be missing, moved into locals one by one
+    field_take_acc: Vec<TokenStream>,
+    // Nested patches that aren't wrapped, converted last: each one is taken and converted, or moved
+    // and given back along with the rest of the patch when it is the last one
+    nested_take_acc: Vec<(TokenStream, TokenStream)>,
 }
 
 impl GenerateTryFromImpl {
     fn new() -> Self {
         GenerateTryFromImpl {
-            field_check_acc: quote! {},
-            field_assign_acc: quote! {},
+            field_assign_acc: vec![],
+            field_take_acc: vec![],
+            nested_take_acc: vec![],
         }
     }
 
-    fn get_implementation(self, derive_input: &DeriveInput, new: &DeriveInput) -> TokenStream {
+    fn get_implementation(self, global_options: &GlobalOptions, derive_input: &DeriveInput, new: &DeriveInput) -> TokenStream {
         let (impl_generics, ty_generics, where_clause) = derive_input.generics.split_for_impl();
         let old_name = &derive_input.ident;
         let new_name = &new.ident;
-        let field_check_acc = self.field_check_acc;
+        let hidden = global_options.hidden_attribute();
         let field_assign_acc = self.field_assign_acc;
+        let mut field_take_acc = self.field_take_acc;
+        // Only the patches taken before the last one have to implement `Default`
+        if let Some(((_, moved), taken)) = self.nested_take_acc.split_last() {
+            field_take_acc.extend(taken.iter().map(|(take, _)| take.clone()));
+            field_take_acc.push(moved.clone());
+        }
 
         quote! {
+            #hidden
             impl #impl_generics TryFrom<#new_name #ty_generics > #where_clause for #old_name #ty_generics {
                 type Error = #new_name #ty_generics;
 
-                fn try_from(v: Self::Error) -> Result<Self, Self::Error> {
-                    #field_check_acc
+                // Once the patch is known to be complete, its values are moved out of it rather
+                // than unwrapped. The empty values left behind let the `else` branches, which
+                // can't be taken, still give the patch back.
+                #[allow(unused_mut)]
+                fn try_from(mut v: Self::Error) -> Result<Self, Self::Error> {
+                    if v.__missing_field().is_some() {
+                        return Err(v);
+                    }
+                    #(#field_take_acc)*
                     Ok(Self {
-                        #field_assign_acc
+                        #(#field_assign_acc)*
                     })
                 }
             }
@@ -119,74 +626,123 @@ impl GenerateTryFromImpl {
 }
 
 impl OptionalFieldVisitor for GenerateTryFromImpl {
-    fn visit(&mut self, _global_options: &GlobalOptions, old_field: &mut Field, _new_field: &mut Field, field_options: &FieldOptions) {
+    fn visit_skipped(&mut self, _global_options: &GlobalOptions, _old_field: &Field, field_options: &FieldOptions, default: &TokenStream) {
         let ident = &field_options.field_ident;
         let cfg_attr = &field_options.cfg_attribute;
+        self.field_assign_acc.push(quote! {
+            #cfg_attr
 
-        let is_wrapped = field_options.wrapping_behavior;
-        let is_nested = field_options.new_type.is_some();
-        let is_base_opt = is_type_option(&old_field.ty);
-        let (unwrap, check) = match (is_base_opt, is_wrapped, is_nested) {
-            (_, true, false) =>
-                (
-                    quote! { .unwrap() },
-                    quote! { #cfg_attr if v.#ident.is_none() { return Err(v); } }
-                ),
-            (_, true, true) =>
-                (
-                    quote! { .unwrap().try_into().unwrap() },
-                    quote! { #cfg_attr if let Some(i) = &v.#ident { if !i.can_convert() { return Err(v); } } else { return Err(v); } }
-                ),
-            (_, false, true) =>
-                (
-                    quote! { .try_into().unwrap() },
-                    quote! { #cfg_attr if !v.#ident.can_convert() { return Err(v); } }
-                ),
-            (_, false, false) =>
-                (
-                    quote! {},
-                    quote! {}
-                )
-        };
+            #ident: #default,
+        });
+    }
 
-        let field_assign_acc = &self.field_assign_acc;
-        self.field_assign_acc = quote! {
-            #field_assign_acc
-            #cfg_attr
+    fn visit(&mut self, _global_options: &GlobalOptions, old_field: &Field, _new_field: &mut Field, field_options: &FieldOptions) {
+        let ident = &field_options.field_ident;
+        let new_ident = &field_options.new_field_ident;
+        let cfg_attr = &field_options.cfg_attribute;
 
-            #ident: v.#ident #unwrap,
+        // Fields that can't be missing are only moved once nothing can fail anymore
+        let local = format_ident!("__{}", new_ident.to_string().trim_start_matches("r#"));
+        let value = match field_options.take(old_field, quote! { v.#new_ident }, &local) {
+            Some((pattern, taken)) => {
+                let take = quote! {
+                    #cfg_attr
+                    let #pattern = #taken else {
+                        return Err(v);
+                    };
+                };
+                if field_options.is_moved_nested() {
+                    let moved = field_options.nested_try_into(quote! { v.#new_ident });
+                    self.nested_take_acc.push((take, quote! {
+                        #cfg_attr
+                        let #local = match #moved {
+                            Ok(value) => value,
+                            Err(nested) => {
+                                v.#new_ident = nested;
+                                return Err(v);
+                            }
+                        };
+                    }));
+                } else {
+                    self.field_take_acc.push(take);
+                }
+                field_options.convert_from_patch(quote! { #local })
+            }
+            None if field_options.is_double_wrapped() => {
+                let taken = field_options.unwrap(quote! { v.#new_ident });
+                field_options.convert_from_patch(quote! { #taken.flatten() })
+            }
+            None => field_options.convert_from_patch(quote! { v.#new_ident }),
         };
+        self.field_assign_acc.push(quote! {
+            #cfg_attr
 
-        let field_check_acc = &self.field_check_acc;
-        self.field_check_acc = quote! {
-            #field_check_acc
-            #check
-        };
+            #ident: #value,
+        });
     }
 }
 
 
 struct GenerateApplyFnVisitor {
-    acc_concrete: TokenStream,
-    acc_opt: TokenStream,
+    acc_concrete: CfgStatements,
+    acc_opt: CfgStatements,
+    // One per struct given to `optional_targets`
+    target_accs: Vec<CfgStatements>,
+    field_idents: Vec<Ident>,
 }
 
 impl GenerateApplyFnVisitor {
     fn new() -> Self {
         GenerateApplyFnVisitor {
-            acc_concrete: quote! {},
-            acc_opt: quote! {},
+            acc_concrete: CfgStatements::default(),
+            acc_opt: CfgStatements::default(),
+            target_accs: Vec::new(),
+            field_idents: Vec::new(),
         }
     }
 
-    fn get_implementation(self, orig: &DeriveInput, new: &DeriveInput) -> TokenStream {
+    fn get_implementation(self, global_options: &GlobalOptions, orig: &DeriveInput, new: &DeriveInput) -> TokenStream {
         let (impl_generics, ty_generics, _) = orig.generics.split_for_impl();
         let orig_name = &orig.ident;
         let new_name = &new.ident;
+        let must_use = global_options.must_use_attribute();
         let acc_concrete = self.acc_concrete;
         let acc_opt = self.acc_opt;
+        // Fields are moved out of the box one by one, the patch as a whole never being copied on
+        // the stack
+        let apply_boxed = emit_if(global_options.emits("apply_boxed"), || quote! {
+            #[allow(clippy::boxed_local)]
+            fn apply_boxed(self: ::optional_struct::__private::Box<Self>, t: &mut #orig_name #ty_generics) {
+                #acc_concrete
+            }
+        });
+        let patch_log = emit_if(global_options.emits("patch_log"), || quote! {
+            fn patch_log() -> ::optional_struct::PatchLog<#orig_name #ty_generics, Self> {
+                ::optional_struct::PatchLog::new()
+            }
+        });
+        for target in &global_options.targets {
+            let unknown = target.fields.iter().flatten().find(|f| !self.field_idents.contains(f));
+            if let Some(field) = unknown {
+                panic!("'{TARGETS_ATTRIBUTE}' attribute lists '{field}', which isn't a field of the struct");
+            }
+        }
+        let target_impls = global_options.targets.iter().zip(self.target_accs).map(|(target, acc)| {
+            let target = &target.path;
+            quote! {
+                impl #impl_generics ::optional_struct::Applyable<#target> for #new_name #ty_generics {
+                    #[allow(unused_variables)]
+                    fn apply_to(self, t: &mut #target) {
+                        #acc
+                    }
+                }
+            }
+        });
         quote! {
+            #(#target_impls)*
+
             impl #impl_generics #new_name #ty_generics {
+                #must_use
                 fn build(self, mut t: #orig_name #ty_generics) -> #orig_name #ty_generics {
                     self.apply_to(&mut t);
                     t
@@ -196,6 +752,8 @@ impl GenerateApplyFnVisitor {
                     #acc_concrete
                 }
 
+                #apply_boxed
+
                 fn try_build(self) -> Result<#orig_name #ty_generics, Self> {
                     self.try_into()
                 }
@@ -204,32 +762,66 @@ impl GenerateApplyFnVisitor {
                     #acc_opt
                 }
 
+                #must_use
                 fn apply(mut self, t: Self) -> Self {
                     t.apply_to_opt(&mut self);
                     self
                 }
+
+                #patch_log
+            }
+
+            impl #impl_generics ::optional_struct::Applyable<#orig_name #ty_generics> for #new_name #ty_generics {
+                fn apply_to(self, t: &mut #orig_name #ty_generics) {
+                    Self::apply_to(self, t)
+                }
             }
         }
     }
 
-    fn get_incremental_setter_concrete(ident: &TokenStream, is_wrapped: bool, is_nested: bool, is_base_opt: bool) -> TokenStream {
+    fn get_incremental_setter_concrete(field_options: &FieldOptions, is_base_opt: bool) -> TokenStream {
+        let ident = &field_options.field_ident;
+        let new_ident = &field_options.new_field_ident;
+        let is_wrapped = field_options.wrapping_behavior;
+        let is_nested = field_options.new_type.is_some();
+        let nested_try_into = field_options.nested_try_into(quote! { nested });
         match (is_base_opt, is_wrapped, is_nested) {
-            (true, false, true) => quote! {
-                                   match (&mut t.#ident, self.#ident) {
-                                       (None, Some(nested)) => t.#ident = nested.#ident.try_into(),
-                                       (Some(existing), Some(nested)) => nested.#ident.apply_to(existing),
-                                       (_, None) => {},
-                                   }
-                                },
-            (true, false, false) => quote! {
-                                    if self.#ident.is_some() {
-                                        t.#ident = self.#ident;
-                                    }
-                                },
-            (false, false, true) => quote! { self.#ident.apply_to(&mut t.#ident); },
-            (false, false, false) => quote! { t.#ident = self.#ident; },
-            (_, true, true) => quote! { if let Some(inner) = self.#ident { inner.apply_to(&mut t.#ident); } },
-            (_, true, false) => quote! { if let Some(inner) = self.#ident { t.#ident = inner; } },
+            // The patch is applied to the existing value, and only replaces a missing one if it
+            // is complete
+            (true, true, true) => quote! {
+                match (&mut t.#ident, self.#new_ident) {
+                    (Some(existing), Some(nested)) => nested.apply_to(existing),
+                    (None, Some(nested)) => t.#ident = #nested_try_into.ok(),
+                    (_, None) => {},
+                }
+            },
+            (true, false, false) => {
+                let value = field_options.convert_from_patch(quote! { self.#new_ident });
+                quote! {
+                    if self.#new_ident.is_some() {
+                        t.#ident = #value;
+                    }
+                }
+            }
+            (true, false, true) => unreachable!("Nested `Option` fields are always wrapped"),
+            (false, false, true) => quote! { self.#new_ident.apply_to(&mut t.#ident); },
+            (false, false, false) if field_options.elementwise => quote! {
+                for (current, value) in t.#ident.iter_mut().zip(self.#new_ident) {
+                    if let Some(value) = value {
+                        *current = value;
+                    }
+                }
+            },
+            (false, false, false) => {
+                let value = field_options.merge_value(quote! { t.#ident }, field_options.convert_from_patch(quote! { self.#new_ident }));
+                field_options.when_set(quote! { self }, quote! { t.#ident = #value; })
+            }
+            (_, true, true) => quote! { if let Some(inner) = self.#new_ident { inner.apply_to(&mut t.#ident); } },
+            (_, true, false) => {
+                let field = field_options.unwrap(quote! { self.#new_ident });
+                let value = field_options.merge_value(quote! { t.#ident }, field_options.convert_from_patch(quote! { inner }));
+                quote! { if let Some(inner) = #field { t.#ident = #value; } }
+            }
         }
     }
     fn get_incremental_setter_opt(ident: &TokenStream, is_wrapped: bool, is_nested: bool, is_base_opt: bool) -> TokenStream {
@@ -255,104 +847,4418 @@ impl GenerateApplyFnVisitor {
 }
 
 impl OptionalFieldVisitor for GenerateApplyFnVisitor {
-    fn visit(&mut self, _global_options: &GlobalOptions, old_field: &mut Field, _new_field: &mut Field, field_options: &FieldOptions) {
-        let ident = &field_options.field_ident;
+    fn visit(&mut self, global_options: &GlobalOptions, _old_field: &Field, _new_field: &mut Field, field_options: &FieldOptions) {
+        let ident = &field_options.new_field_ident;
         let cfg_attr = &field_options.cfg_attribute;
 
         let is_wrapped = field_options.wrapping_behavior;
         let is_nested = field_options.new_type.is_some();
-        let is_base_opt = is_type_option(&old_field.ty);
+        let is_base_opt = field_options.is_base_opt;
 
-        let inc_concrete = Self::get_incremental_setter_concrete(ident, is_wrapped, is_nested, is_base_opt);
+        let inc_concrete = if field_options.read_only {
+            quote! {}
+        } else {
+            Self::get_incremental_setter_concrete(field_options, is_base_opt)
+        };
         // Opt <-> Opt is never wrapped. But both have an Option<> if the initial type IS wrapped!
-        let inc_opt = Self::get_incremental_setter_opt(ident, false, is_nested, is_wrapped || is_base_opt);
+        let inc_opt = if field_options.elementwise {
+            quote! {
+                for (current, value) in t.#ident.iter_mut().zip(self.#ident) {
+                    if value.is_some() {
+                        *current = value;
+                    }
+                }
+            }
+        } else if field_options.wrapper.is_some() {
+            // The whole wrapper is kept, along with whatever it records besides the value
+            let field = field_options.unwrap_ref(quote! { self.#ident });
+            quote! { if #field.is_some() { t.#ident = self.#ident; } }
+        } else if field_options.merge_op.is_some() {
+            // Both patches are combined, so that applying them in a row gives the same result
+            let value = field_options.merge_value(quote! { current }, quote! { inner });
+            if is_wrapped {
+                quote! {
+                    if let Some(inner) = self.#ident {
+                        t.#ident = Some(match t.#ident {
+                            Some(current) => #value,
+                            None => inner,
+                        });
+                    }
+                }
+            } else {
+                let value = field_options.merge_value(quote! { t.#ident }, quote! { self.#ident });
+                quote! { t.#ident = #value; }
+            }
+        } else {
+            Self::get_incremental_setter_opt(ident, false, is_nested, is_wrapped || is_base_opt)
+        };
+        let inc_opt = field_options.when_set(quote! { self }, inc_opt);
 
-        let acc_concrete = &self.acc_concrete;
-        self.acc_concrete = quote! {
-            #acc_concrete
+        self.target_accs.resize_with(global_options.targets.len(), CfgStatements::default);
+        if let Ok(field_ident) = syn::parse2::<Ident>(field_options.field_ident.clone()) {
+            for (target, acc) in global_options.targets.iter().zip(&mut self.target_accs) {
+                if target.includes(&field_ident) {
+                    acc.push(cfg_attr, inc_concrete.clone());
+                }
+            }
+            self.field_idents.push(field_ident);
+        } else {
+            for acc in &mut self.target_accs {
+                acc.push(cfg_attr, inc_concrete.clone());
+            }
+        }
 
-            #cfg_attr
+        self.acc_concrete.push(cfg_attr, quote! {
             #inc_concrete
-        };
+        });
 
-        let acc_opt = &self.acc_opt;
-        self.acc_opt = quote! {
-            #acc_opt
-
-            #cfg_attr
+        self.acc_opt.push(cfg_attr, quote! {
             #inc_opt
-        };
+        });
     }
 }
 
-struct SetNewFieldVisibilityVisitor;
+struct GenerateTracedApplyFnVisitor {
+    acc: CfgStatements,
+    debug_types: Vec<TokenStream>,
+}
 
-impl OptionalFieldVisitor for SetNewFieldVisibilityVisitor {
-    fn visit(&mut self, global_options: &GlobalOptions, _old_field: &mut Field, new_field: &mut Field, _field_options: &FieldOptions) {
-        if global_options.make_fields_public {
-            new_field.vis = Visibility::Public(syn::token::Pub(new_field.vis.span()))
+impl GenerateTracedApplyFnVisitor {
+    fn new() -> Self {
+        GenerateTracedApplyFnVisitor {
+            acc: CfgStatements::default(),
+            debug_types: vec![],
+        }
+    }
+
+    fn get_implementation(self, global_options: &GlobalOptions, orig: &DeriveInput, new: &DeriveInput) -> TokenStream {
+        if global_options.tracing_level.is_none() {
+            return quote! {};
+        }
+
+        let (impl_generics, ty_generics, _) = orig.generics.split_for_impl();
+        let where_clause = where_clause_with_bounds(&orig.generics, self.debug_types.iter().map(|t| quote! { #t: core::fmt::Debug }));
+        let orig_name = &orig.ident;
+        let new_name = &new.ident;
+        let acc = self.acc;
+        quote! {
+            impl #impl_generics #new_name #ty_generics #where_clause {
+                fn apply_to_traced(self, t: &mut #orig_name #ty_generics) {
+                    #acc
+                }
+            }
         }
     }
 }
 
-struct SetNewFieldTypeVisitor;
+impl OptionalFieldVisitor for GenerateTracedApplyFnVisitor {
+    fn visit(&mut self, global_options: &GlobalOptions, old_field: &Field, new_field: &mut Field, field_options: &FieldOptions) {
+        if field_options.read_only {
+            return;
+        }
+        let level = match &global_options.tracing_level {
+            Some(level) => level,
+            None => return,
+        };
+        let ident = &field_options.field_ident;
+        let new_ident = &field_options.new_field_ident;
+        let name = &field_options.field_name;
+        let cfg_attr = &field_options.cfg_attribute;
 
-impl OptionalFieldVisitor for SetNewFieldTypeVisitor {
-    fn visit(&mut self, _global_options: &GlobalOptions, old_field: &mut Field, new_field: &mut Field, field_options: &FieldOptions) {
-        let mut new_type = if let Some(t) = &field_options.new_type {
-            quote! {#t}
+        let is_wrapped = field_options.wrapping_behavior;
+        let is_nested = field_options.new_type.is_some();
+        let is_base_opt = field_options.is_base_opt;
+
+        let inc = if field_options.replace_only {
+            // Its value can't be logged
+            GenerateApplyFnVisitor::get_incremental_setter_concrete(field_options, is_base_opt)
+        } else if field_options.secret {
+            let is_set = field_options.is_set(quote! { self });
+            let setter = GenerateApplyFnVisitor::get_incremental_setter_concrete(field_options, is_base_opt);
+            quote! {
+                if #is_set {
+                    ::tracing::event!(::tracing::Level::#level, field = #name, "applying redacted patch field");
+                }
+                #setter
+            }
+        } else if is_nested {
+            // The nested patch is logged as a whole, its fields are applied as usual
+            let setter = GenerateApplyFnVisitor::get_incremental_setter_concrete(field_options, is_base_opt);
+            let new_ty = &new_field.ty;
+            self.debug_types.push(quote! { #new_ty });
+            quote! {
+                ::tracing::event!(::tracing::Level::#level, field = #name, patch = ?self.#new_ident, "applying patch field");
+                #setter
+            }
         } else {
-            let t = &old_field.ty;
-            quote! {#t}
+            let old_ty = &old_field.ty;
+            self.debug_types.push(quote! { #old_ty });
+            let apply = |value: TokenStream| {
+                let replace = field_options.replace_value(quote! { t.#ident }, value);
+                quote! {
+                    let old = #replace;
+                    ::tracing::event!(::tracing::Level::#level, field = #name, old = ?old, new = ?t.#ident, "applying patch field");
+                }
+            };
+            match (is_base_opt, is_wrapped) {
+                // Each element is logged on its own
+                _ if field_options.elementwise => quote! {
+                    for (i, (current, value)) in t.#ident.iter_mut().zip(self.#new_ident).enumerate() {
+                        if let Some(value) = value {
+                            let old = ::core::mem::replace(current, value);
+                            ::tracing::event!(::tracing::Level::#level, field = #name, index = i, old = ?old, new = ?current, "applying patch field");
+                        }
+                    }
+                },
+                (_, true) => {
+                    let field = field_options.unwrap(quote! { self.#new_ident });
+                    let apply = apply(field_options.convert_from_patch(quote! { inner }));
+                    quote! { if let Some(inner) = #field { #apply } }
+                }
+                (true, false) => {
+                    let apply = apply(field_options.convert_from_patch(quote! { self.#new_ident }));
+                    quote! { if self.#new_ident.is_some() { #apply } }
+                }
+                (false, false) => field_options.when_set(quote! { self }, apply(field_options.convert_from_patch(quote! { self.#new_ident }))),
+            }
         };
 
-        if field_options.wrapping_behavior {
-            new_type = quote! {Option<#new_type>};
-        };
-        new_field.ty = Type::Verbatim(new_type);
+        self.acc.push(cfg_attr, quote! {
+            {
+                #inc
+            }
+        });
     }
 }
 
-struct AddSerdeSkipAttribute;
-
-impl OptionalFieldVisitor for AddSerdeSkipAttribute {
-    fn visit(&mut self, _global_options: &GlobalOptions, _old_field: &mut Field, new_field: &mut Field, field_options: &FieldOptions) {
-        if !field_options.serde_skip { return; }
-
-        let attribute : Attribute = parse_quote!{ #[serde(skip_serializing_if = "Option::is_none")] };
-        new_field.attrs.push(attribute);
-    }
+struct GenerateApplyReportFnVisitor {
+    acc: CfgStatements,
+    debug_types: Vec<TokenStream>,
 }
 
-// https://github.com/rust-lang/rust/issues/65823 :(
-struct RemoveHelperAttributesVisitor;
+impl GenerateApplyReportFnVisitor {
+    fn new() -> Self {
+        GenerateApplyReportFnVisitor {
+            acc: CfgStatements::default(),
+            debug_types: vec![],
+        }
+    }
 
-impl OptionalFieldVisitor for RemoveHelperAttributesVisitor {
-    fn visit(&mut self, _global_options: &GlobalOptions, old_field: &mut Field, new_field: &mut Field, _field_options: &FieldOptions) {
-        let indexes_to_remove = old_field
-            .attrs
-            .iter()
-            .enumerate()
-            .filter_map(|(i, a)| {
-                if a.path().is_ident(RENAME_ATTRIBUTE) {
-                    Some(i)
-                } else if a.path().is_ident(SKIP_WRAP_ATTRIBUTE) {
-                    Some(i)
-                } else if a.path().is_ident(WRAP_ATTRIBUTE) {
-                    Some(i)
-                } else if a.path().is_ident(SERDE_SKIP_SERIALIZING_NONE) {
-                    Some(i)
+    fn get_implementation(self, orig: &DeriveInput, new: &DeriveInput) -> TokenStream {
+        let (impl_generics, ty_generics, _) = orig.generics.split_for_impl();
+        let where_clause = where_clause_with_bounds(&orig.generics, self.debug_types.iter().map(|t| quote! { #t: core::fmt::Debug }));
+        let orig_name = &orig.ident;
+        let new_name = &new.ident;
+        let acc = self.acc;
+        quote! {
+            impl #impl_generics #new_name #ty_generics #where_clause {
+                fn apply_report(self, t: &mut #orig_name #ty_generics) -> ::optional_struct::__private::Vec<::optional_struct::FieldChange> {
+                    let mut changes = ::optional_struct::__private::Vec::new();
+                    #acc
+                    changes
+                }
+            }
+        }
+    }
+}
+
+impl OptionalFieldVisitor for GenerateApplyReportFnVisitor {
+    fn visit(&mut self, _global_options: &GlobalOptions, old_field: &Field, _new_field: &mut Field, field_options: &FieldOptions) {
+        if field_options.read_only {
+            return;
+        }
+        let ident = &field_options.field_ident;
+        let new_ident = &field_options.new_field_ident;
+        let name = &field_options.field_name;
+        let cfg_attr = &field_options.cfg_attribute;
+
+        let is_wrapped = field_options.wrapping_behavior;
+        let is_nested = field_options.new_type.is_some();
+        let is_base_opt = field_options.is_base_opt;
+
+        // Applied without being reported, its values can't be compared
+        if field_options.replace_only {
+            let inc = GenerateApplyFnVisitor::get_incremental_setter_concrete(field_options, is_base_opt);
+            self.acc.push(cfg_attr, quote! { { #inc } });
+            return;
+        }
+        if !is_nested || is_base_opt {
+            let old_ty = &old_field.ty;
+            self.debug_types.push(quote! { #old_ty });
+        }
+        let nested_try_into = field_options.nested_try_into(quote! { nested });
+        let apply = |value: TokenStream| {
+            let replace = field_options.replace_value(quote! { t.#ident }, value);
+            quote! {
+                let old = #replace;
+                changes.extend(::optional_struct::FieldChange::from_debug(#name, &old, &t.#ident));
+            }
+        };
+        let inc = match (is_base_opt, is_wrapped, is_nested) {
+            // Elements are reported with their index, e.g. `table[2]`
+            (false, false, false) if field_options.elementwise => quote! {
+                for (i, (current, value)) in t.#ident.iter_mut().zip(self.#new_ident).enumerate() {
+                    if let Some(value) = value {
+                        let old = ::core::mem::replace(current, value);
+                        let field = ::optional_struct::__private::format!("{}[{}]", #name, i);
+                        changes.extend(::optional_struct::FieldChange::from_debug(&field, &old, current));
+                    }
+                }
+            },
+            (true, true, true) => quote! {
+                match (&mut t.#ident, self.#new_ident) {
+                    (Some(existing), Some(nested)) => {
+                        changes.extend(nested.apply_report(existing).into_iter().map(|c| c.nested_in(#name)));
+                    }
+                    (None, Some(nested)) => {
+                        let value = #nested_try_into.ok();
+                        changes.extend(::optional_struct::FieldChange::from_debug(#name, &t.#ident, &value));
+                        t.#ident = value;
+                    }
+                    (_, None) => {}
+                }
+            },
+            (_, true, true) => quote! {
+                if let Some(inner) = self.#new_ident {
+                    changes.extend(inner.apply_report(&mut t.#ident).into_iter().map(|c| c.nested_in(#name)));
+                }
+            },
+            (true, false, true) => unreachable!("Nested `Option` fields are always wrapped"),
+            (false, false, true) => quote! {
+                changes.extend(self.#new_ident.apply_report(&mut t.#ident).into_iter().map(|c| c.nested_in(#name)));
+            },
+            (_, true, false) => {
+                let field = field_options.unwrap(quote! { self.#new_ident });
+                let apply = apply(field_options.convert_from_patch(quote! { inner }));
+                quote! { if let Some(inner) = #field { #apply } }
+            }
+            (true, false, false) => {
+                let apply = apply(field_options.convert_from_patch(quote! { self.#new_ident }));
+                quote! { if self.#new_ident.is_some() { #apply } }
+            }
+            (false, false, false) => field_options.when_set(quote! { self }, apply(field_options.convert_from_patch(quote! { self.#new_ident }))),
+        };
+        // Changes are still detected from the values, which are then left out of the report
+        let inc = if field_options.secret {
+            quote! {
+                let reported = changes.len();
+                #inc
+                changes[reported..].iter_mut().for_each(::optional_struct::FieldChange::redact);
+            }
+        } else {
+            inc
+        };
+
+        self.acc.push(cfg_attr, quote! {
+            {
+                #inc
+            }
+        });
+    }
+}
+
+// Values are compared with `PartialEq` rather than reported, and a replace-only field that is set
+// counts as a change since it can't be compared
+struct GenerateApplyToWatchFnVisitor {
+    acc: CfgStatements,
+    eq_types: Vec<TokenStream>,
+}
+
+impl GenerateApplyToWatchFnVisitor {
+    fn new() -> Self {
+        GenerateApplyToWatchFnVisitor {
+            acc: CfgStatements::default(),
+            eq_types: vec![],
+        }
+    }
+
+    fn get_implementation(self, orig: &DeriveInput, new: &DeriveInput) -> TokenStream {
+        let (impl_generics, ty_generics, _) = orig.generics.split_for_impl();
+        let where_clause = where_clause_with_bounds(&orig.generics, self.eq_types.iter().map(|t| quote! { #t: PartialEq }));
+        let orig_name = &orig.ident;
+        let new_name = &new.ident;
+        let acc = self.acc;
+        quote! {
+            impl #impl_generics #new_name #ty_generics #where_clause {
+                fn apply_to_watch(self, tx: &::tokio::sync::watch::Sender<#orig_name #ty_generics>) -> bool {
+                    tx.send_if_modified(|t| self.__apply_changed(t))
+                }
+
+                // Applies the patch like `apply_to`, and tells whether a field changed
+                fn __apply_changed(self, t: &mut #orig_name #ty_generics) -> bool {
+                    let mut changed = false;
+                    #acc
+                    changed
+                }
+            }
+        }
+    }
+}
+
+impl OptionalFieldVisitor for GenerateApplyToWatchFnVisitor {
+    fn visit(&mut self, global_options: &GlobalOptions, old_field: &Field, _new_field: &mut Field, field_options: &FieldOptions) {
+        if !global_options.tokio_watch || field_options.read_only {
+            return;
+        }
+        let ident = &field_options.field_ident;
+        let new_ident = &field_options.new_field_ident;
+        let cfg_attr = &field_options.cfg_attribute;
+
+        let is_wrapped = field_options.wrapping_behavior;
+        let is_nested = field_options.new_type.is_some();
+        let is_base_opt = field_options.is_base_opt;
+
+        if field_options.replace_only {
+            let is_set = field_options.is_set(quote! { self });
+            let inc = GenerateApplyFnVisitor::get_incremental_setter_concrete(field_options, is_base_opt);
+            self.acc.push(cfg_attr, quote! {
+                {
+                    changed |= #is_set;
+                    #inc
+                }
+            });
+            return;
+        }
+        if !is_nested || is_base_opt {
+            let old_ty = &old_field.ty;
+            self.eq_types.push(quote! { #old_ty });
+        }
+        let nested_try_into = field_options.nested_try_into(quote! { nested });
+        let apply = |value: TokenStream| {
+            let replace = field_options.replace_value(quote! { t.#ident }, value);
+            quote! {
+                let old = #replace;
+                changed |= old != t.#ident;
+            }
+        };
+        let inc = match (is_base_opt, is_wrapped, is_nested) {
+            (false, false, false) if field_options.elementwise => quote! {
+                for (current, value) in t.#ident.iter_mut().zip(self.#new_ident) {
+                    if let Some(value) = value {
+                        changed |= ::core::mem::replace(current, value) != *current;
+                    }
+                }
+            },
+            (true, true, true) => quote! {
+                match (&mut t.#ident, self.#new_ident) {
+                    (Some(existing), Some(nested)) => changed |= nested.__apply_changed(existing),
+                    (None, Some(nested)) => {
+                        t.#ident = #nested_try_into.ok();
+                        changed |= t.#ident.is_some();
+                    }
+                    (_, None) => {}
+                }
+            },
+            (_, true, true) => quote! {
+                if let Some(nested) = self.#new_ident {
+                    changed |= nested.__apply_changed(&mut t.#ident);
+                }
+            },
+            (true, false, true) => unreachable!("Nested `Option` fields are always wrapped"),
+            (false, false, true) => quote! {
+                changed |= self.#new_ident.__apply_changed(&mut t.#ident);
+            },
+            (_, true, false) => {
+                let field = field_options.unwrap(quote! { self.#new_ident });
+                let apply = apply(field_options.convert_from_patch(quote! { inner }));
+                quote! { if let Some(inner) = #field { #apply } }
+            }
+            (true, false, false) => {
+                let apply = apply(field_options.convert_from_patch(quote! { self.#new_ident }));
+                quote! { if self.#new_ident.is_some() { #apply } }
+            }
+            (false, false, false) => field_options.when_set(quote! { self }, apply(field_options.convert_from_patch(quote! { self.#new_ident }))),
+        };
+        self.acc.push(cfg_attr, quote! {
+            {
+                #inc
+            }
+        });
+    }
+}
+
+// `PartialEq` and `Debug` can't be derived once a field is replace-only, and are implemented
+// without it instead
+struct GenerateComparisonImpls {
+    eq_acc: CfgStatements,
+    debug_acc: CfgStatements,
+    eq_bounds: Vec<TokenStream>,
+    debug_bounds: Vec<TokenStream>,
+    has_replace_only: bool,
+    // `Debug` is implemented by hand to redact secret fields, `PartialEq` can still be derived
+    has_secret: bool,
+}
+
+impl GenerateComparisonImpls {
+    fn new() -> Self {
+        GenerateComparisonImpls {
+            eq_acc: CfgStatements::default(),
+            debug_acc: CfgStatements::default(),
+            eq_bounds: vec![],
+            debug_bounds: vec![],
+            has_replace_only: false,
+            has_secret: false,
+        }
+    }
+
+    fn get_implementation(self, orig: &DeriveInput, new: &DeriveInput, eq: bool, debug: bool) -> TokenStream {
+        let (impl_generics, ty_generics, _) = orig.generics.split_for_impl();
+        let new_name = &new.ident;
+        let name = new_name.to_string();
+        let eq_impl = emit_if(eq, || {
+            let where_clause = where_clause_with_bounds(&orig.generics, self.eq_bounds.into_iter());
+            let eq_acc = self.eq_acc;
+            quote! {
+                impl #impl_generics PartialEq for #new_name #ty_generics #where_clause {
+                    fn eq(&self, other: &Self) -> bool {
+                        #eq_acc
+                        true
+                    }
+                }
+            }
+        });
+        let debug_impl = emit_if(debug, || {
+            let where_clause = where_clause_with_bounds(&orig.generics, self.debug_bounds.into_iter());
+            let debug_acc = self.debug_acc;
+            let finish = if self.has_replace_only { quote! { finish_non_exhaustive } } else { quote! { finish } };
+            quote! {
+                impl #impl_generics core::fmt::Debug for #new_name #ty_generics #where_clause {
+                    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                        let mut fields = f.debug_struct(#name);
+                        #debug_acc
+                        fields.#finish()
+                    }
+                }
+            }
+        });
+        quote! {
+            #eq_impl
+            #debug_impl
+        }
+    }
+}
+
+impl OptionalFieldVisitor for GenerateComparisonImpls {
+    fn visit(&mut self, _global_options: &GlobalOptions, _old_field: &Field, new_field: &mut Field, field_options: &FieldOptions) {
+        if field_options.replace_only {
+            self.has_replace_only = true;
+            return;
+        }
+        let ident = &field_options.new_field_ident;
+        let name = &field_options.new_field_name;
+        let cfg_attr = &field_options.cfg_attribute;
+        let new_ty = &new_field.ty;
+        self.eq_bounds.push(quote! { #new_ty: PartialEq });
+        self.eq_acc.push(cfg_attr, quote! {
+            if self.#ident != other.#ident {
+                return false;
+            }
+        });
+        if field_options.secret {
+            self.has_secret = true;
+            let is_set = field_options.is_set(quote! { self });
+            self.debug_acc.push(cfg_attr, quote! { fields.field(#name, &::optional_struct::__private::Redacted(#is_set)); });
+        } else {
+            self.debug_bounds.push(quote! { #new_ty: core::fmt::Debug });
+            self.debug_acc.push(cfg_attr, quote! { fields.field(#name, &self.#ident); });
+        }
+    }
+}
+
+struct GenerateDisplayImpl {
+    acc: CfgStatements,
+    bounds: Vec<TokenStream>,
+}
+
+impl GenerateDisplayImpl {
+    fn new() -> Self {
+        GenerateDisplayImpl {
+            acc: CfgStatements::default(),
+            bounds: vec![],
+        }
+    }
+
+    fn get_implementation(self, orig: &DeriveInput, new: &DeriveInput) -> TokenStream {
+        let (impl_generics, ty_generics, _) = orig.generics.split_for_impl();
+        let where_clause = where_clause_with_bounds(&orig.generics, self.bounds.into_iter());
+        let new_name = &new.ident;
+        let acc = self.acc;
+        quote! {
+            impl #impl_generics core::fmt::Display for #new_name #ty_generics #where_clause {
+                #[allow(unused_assignments, unused_variables)]
+                fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                    let mut separator = "";
+                    f.write_str("{")?;
+                    #acc
+                    f.write_str("}")
+                }
+            }
+        }
+    }
+}
+
+impl OptionalFieldVisitor for GenerateDisplayImpl {
+    fn visit(&mut self, _global_options: &GlobalOptions, old_field: &Field, _new_field: &mut Field, field_options: &FieldOptions) {
+        if field_options.replace_only {
+            return;
+        }
+        let ident = &field_options.new_field_ident;
+        let name = &field_options.new_field_name;
+        let cfg_attr = &field_options.cfg_attribute;
+
+        let is_wrapped = field_options.wrapping_behavior;
+        let is_nested = field_options.new_type.is_some();
+        let is_base_opt = field_options.is_base_opt;
+        // Nested patches are displayed as summaries themselves
+        let (format, bound) = match &field_options.new_type {
+            _ if field_options.secret => (format!("{{}}{name}: <redacted>"), None),
+            // Bounding a boxed patch, which may be the struct itself, would be recursive
+            Some(_) if field_options.boxed => (format!("{{}}{name}: {{}}"), None),
+            Some(new_type) => (format!("{{}}{name}: {{}}"), Some(quote! { #new_type: core::fmt::Display })),
+            None => {
+                let old_ty = &old_field.ty;
+                (format!("{{}}{name}: {{:?}}"), Some(quote! { #old_ty: core::fmt::Debug }))
+            }
+        };
+        self.bounds.extend(bound);
+
+        let value = if field_options.secret { quote! {} } else { quote! { , value } };
+        let write = quote! {
+            write!(f, #format, separator #value)?;
+            separator = ", ";
+        };
+        let inc = if is_wrapped || (is_base_opt && !is_nested) {
+            let field = field_options.unwrap_ref(quote! { self.#ident });
+            quote! {
+                if let Some(value) = #field {
+                    #write
+                }
+            }
+        } else if field_options.elementwise {
+            field_options.when_set(quote! { self }, quote! {
+                let value = &self.#ident;
+                #write
+            })
+        } else {
+            quote! {
+                let value = &self.#ident;
+                #write
+            }
+        };
+
+        self.acc.push(cfg_attr, quote! {
+            {
+                #inc
+            }
+        });
+    }
+}
+
+struct GenerateIterSetFieldsFn {
+    acc: CfgStatements,
+    bounds: Vec<TokenStream>,
+}
+
+impl GenerateIterSetFieldsFn {
+    fn new() -> Self {
+        GenerateIterSetFieldsFn {
+            acc: CfgStatements::default(),
+            bounds: vec![],
+        }
+    }
+
+    fn get_implementation(self, global_options: &GlobalOptions, orig: &DeriveInput, new: &DeriveInput) -> TokenStream {
+        let (impl_generics, ty_generics, _) = orig.generics.split_for_impl();
+        let where_clause = where_clause_with_bounds(&orig.generics, self.bounds.into_iter());
+        let new_name = &new.ident;
+        let must_use = global_options.must_use_attribute();
+        let acc = self.acc;
+        quote! {
+            impl #impl_generics #new_name #ty_generics #where_clause {
+                #must_use
+                fn iter_set_fields(&self) -> impl Iterator<Item = (&'static str, &dyn core::any::Any)> {
+                    let mut fields = ::optional_struct::__private::Vec::<(&'static str, &dyn core::any::Any)>::new();
+                    #acc
+                    fields.into_iter()
+                }
+            }
+        }
+    }
+}
+
+impl OptionalFieldVisitor for GenerateIterSetFieldsFn {
+    fn visit(&mut self, _global_options: &GlobalOptions, _old_field: &Field, new_field: &mut Field, field_options: &FieldOptions) {
+        let ident = &field_options.new_field_ident;
+        let name = &field_options.new_field_name;
+        let cfg_attr = &field_options.cfg_attribute;
+
+        let is_wrapped = field_options.wrapping_behavior;
+        let is_nested = field_options.new_type.is_some();
+        let is_base_opt = field_options.is_base_opt;
+        let new_ty = &new_field.ty;
+        self.bounds.push(quote! { #new_ty: 'static });
+
+        let inc = if is_wrapped || (is_base_opt && !is_nested) {
+            let field = field_options.unwrap_ref(quote! { self.#ident });
+            quote! {
+                if let Some(value) = #field {
+                    fields.push((#name, value));
+                }
+            }
+        } else {
+            field_options.when_set(quote! { self }, quote! { fields.push((#name, &self.#ident)); })
+        };
+
+        self.acc.push(cfg_attr, quote! {
+            #inc
+        });
+    }
+}
+
+struct GenerateSetCountFns {
+    set_count_acc: CfgStatements,
+    field_count_acc: CfgStatements,
+}
+
+impl GenerateSetCountFns {
+    fn new() -> Self {
+        GenerateSetCountFns {
+            set_count_acc: CfgStatements::default(),
+            field_count_acc: CfgStatements::default(),
+        }
+    }
+
+    fn get_implementation(self, global_options: &GlobalOptions, orig: &DeriveInput, new: &DeriveInput) -> TokenStream {
+        let (impl_generics, ty_generics, where_clause) = orig.generics.split_for_impl();
+        let new_name = &new.ident;
+        let must_use = global_options.must_use_attribute();
+        let inline = global_options.inline_attribute();
+        let set_count_acc = self.set_count_acc;
+        let field_count_acc = self.field_count_acc;
+        quote! {
+            impl #impl_generics #new_name #ty_generics #where_clause {
+                #must_use
+                fn set_count(&self) -> usize {
+                    #[allow(unused_mut)]
+                    let mut count = 0;
+                    #set_count_acc
+                    count
+                }
+
+                #must_use
+                const fn field_count() -> usize {
+                    #[allow(unused_mut)]
+                    let mut count = 0;
+                    #field_count_acc
+                    count
+                }
+
+                #must_use
+                #inline
+                fn is_complete(&self) -> bool {
+                    self.set_count() == Self::field_count()
+                }
+            }
+        }
+    }
+}
+
+impl OptionalFieldVisitor for GenerateSetCountFns {
+    fn visit(&mut self, _global_options: &GlobalOptions, _old_field: &Field, _new_field: &mut Field, field_options: &FieldOptions) {
+        let ident = &field_options.new_field_ident;
+        let cfg_attr = &field_options.cfg_attribute;
+
+        let is_wrapped = field_options.wrapping_behavior;
+        let is_base_opt = field_options.is_base_opt;
+
+        // Nested patches count each of their own fields. Increments are
+        // wrapped in blocks so that they can carry the cfg attribute.
+        let (inc_set, inc_total) = match &field_options.new_type {
+            // Boxed patches may be recursive, and count as a single field set if any of theirs is
+            Some(_) if field_options.boxed => (
+                if is_wrapped {
+                    quote! {
+                        if let Some(value) = &self.#ident {
+                            count += usize::from(value.set_count() != 0);
+                        }
+                    }
                 } else {
+                    quote! { { count += usize::from(self.#ident.set_count() != 0); } }
+                },
+                quote! { { count += 1; } },
+            ),
+            Some(new_type) if is_wrapped => (
+                quote! {
+                    if let Some(value) = &self.#ident {
+                        count += value.set_count();
+                    }
+                },
+                quote! { { count += <#new_type>::field_count(); } },
+            ),
+            Some(new_type) => (
+                quote! { { count += self.#ident.set_count(); } },
+                quote! { { count += <#new_type>::field_count(); } },
+            ),
+            None if is_wrapped || is_base_opt => (
+                {
+                    let field = field_options.unwrap_ref(quote! { self.#ident });
+                    quote! {
+                        if #field.is_some() {
+                            count += 1;
+                        }
+                    }
+                },
+                quote! { { count += 1; } },
+            ),
+            None => (field_options.when_set(quote! { self }, quote! { { count += 1; } }), quote! { { count += 1; } }),
+        };
+
+        self.set_count_acc.push(cfg_attr, quote! {
+            #inc_set
+        });
+        self.field_count_acc.push(cfg_attr, quote! {
+            #inc_total
+        });
+    }
+}
+
+struct GenerateFieldGroupsFns {
+    // Name of each group along with the statements counting its set fields
+    groups: Vec<(Ident, CfgStatements)>,
+}
+
+impl GenerateFieldGroupsFns {
+    fn new() -> Self {
+        GenerateFieldGroupsFns {
+            groups: vec![],
+        }
+    }
+
+    fn get_implementation(self, global_options: &GlobalOptions, orig: &DeriveInput, new: &DeriveInput) -> TokenStream {
+        if self.groups.is_empty() {
+            return quote! {};
+        }
+        let (impl_generics, ty_generics, where_clause) = orig.generics.split_for_impl();
+        let orig_name = &orig.ident;
+        let new_name = &new.ident;
+        let must_use = global_options.must_use_attribute();
+        let checks = self.groups.iter().map(|(group, acc)| {
+            let group = group.to_string();
+            quote! {
+                {
+                    let mut set = 0;
+                    let mut total = 0;
+                    #acc
+                    if set != 0 && set != total {
+                        return Some(#group);
+                    }
+                }
+            }
+        });
+        quote! {
+            impl #impl_generics #new_name #ty_generics #where_clause {
+                #must_use
+                fn incomplete_group(&self) -> Option<&'static str> {
+                    #(#checks)*
                     None
                 }
-            })
+
+                fn try_apply_to(self, t: &mut #orig_name #ty_generics) -> Result<(), Self> {
+                    if self.incomplete_group().is_some() {
+                        return Err(self);
+                    }
+                    self.apply_to(t);
+                    Ok(())
+                }
+            }
+        }
+    }
+}
+
+// The version is checked and bumped by `try_apply_to` rather than set by the patch
+struct GenerateVersionedApplyFn {
+    // Identifiers of the version field in the original and the optional struct, and its type
+    version: Option<(TokenStream, TokenStream, Type)>,
+}
+
+impl GenerateVersionedApplyFn {
+    fn new() -> Self {
+        GenerateVersionedApplyFn { version: None }
+    }
+
+    fn get_implementation(self, global_options: &GlobalOptions, orig: &DeriveInput, new: &DeriveInput) -> TokenStream {
+        let Some(version_field) = &global_options.version_field else {
+            return quote! {};
+        };
+        let Some((ident, new_ident, ty)) = self.version else {
+            panic!("'{VERSION_FIELD_ATTRIBUTE}' attribute expects the name of a field, found '{version_field}'");
+        };
+        let (impl_generics, ty_generics, where_clause) = orig.generics.split_for_impl();
+        let orig_name = &orig.ident;
+        let new_name = &new.ident;
+        quote! {
+            impl #impl_generics #new_name #ty_generics #where_clause {
+                // Applies the patch if it expects the current version of `t`, which is then bumped
+                fn try_apply_to(mut self, t: &mut #orig_name #ty_generics) -> Result<(), ::optional_struct::VersionConflict<#ty>> {
+                    match self.#new_ident.take() {
+                        Some(expected) if expected == t.#ident => {}
+                        expected => return Err(::optional_struct::VersionConflict { expected, actual: t.#ident.clone() }),
+                    }
+                    self.apply_to(t);
+                    t.#ident += 1;
+                    Ok(())
+                }
+            }
+        }
+    }
+}
+
+impl OptionalFieldVisitor for GenerateVersionedApplyFn {
+    fn visit(&mut self, global_options: &GlobalOptions, old_field: &Field, _new_field: &mut Field, field_options: &FieldOptions) {
+        let Some(version_field) = &global_options.version_field else {
+            return;
+        };
+        if field_options.group.is_some() {
+            panic!("'{VERSION_FIELD_ATTRIBUTE}' attribute can't be used along with '{GROUP_ATTRIBUTE}', which generates its own `try_apply_to`");
+        }
+        if old_field.ident.as_ref() != Some(version_field) {
+            return;
+        }
+        let is_plain = field_options.wrapping_behavior
+            && !field_options.is_base_opt
+            && field_options.new_type.is_none()
+            && field_options.convert.is_none()
+            && field_options.wrapper.is_none()
+            && !field_options.elementwise;
+        if !is_plain {
+            panic!("'{VERSION_FIELD_ATTRIBUTE}' attribute expects a field stored as an `Option` of its own type, which '{version_field}' isn't");
+        }
+        self.version = Some((field_options.field_ident.clone(), field_options.new_field_ident.clone(), old_field.ty.clone()));
+    }
+}
+
+impl OptionalFieldVisitor for GenerateFieldGroupsFns {
+    fn visit(&mut self, _global_options: &GlobalOptions, _old_field: &Field, _new_field: &mut Field, field_options: &FieldOptions) {
+        let Some(group) = &field_options.group else {
+            return;
+        };
+        let cfg_attr = &field_options.cfg_attribute;
+        let is_set = field_options.is_set(quote! { self });
+        let inc = quote! {
+            {
+                total += 1;
+                if #is_set {
+                    set += 1;
+                }
+            }
+        };
+        match self.groups.iter_mut().find(|(g, _)| g == group) {
+            Some((_, acc)) => acc.push(cfg_attr, inc),
+            None => {
+                let mut acc = CfgStatements::default();
+                acc.push(cfg_attr, inc);
+                self.groups.push((group.clone(), acc));
+            }
+        }
+    }
+}
+
+struct GenerateIgnoredFieldsFn {
+    acc: CfgStatements,
+    has_read_only: bool,
+}
+
+impl GenerateIgnoredFieldsFn {
+    fn new() -> Self {
+        GenerateIgnoredFieldsFn {
+            acc: CfgStatements::default(),
+            has_read_only: false,
+        }
+    }
+
+    fn get_implementation(self, global_options: &GlobalOptions, orig: &DeriveInput, new: &DeriveInput) -> TokenStream {
+        if !self.has_read_only {
+            return quote! {};
+        }
+        let (impl_generics, ty_generics, where_clause) = orig.generics.split_for_impl();
+        let new_name = &new.ident;
+        let must_use = global_options.must_use_attribute();
+        let acc = self.acc;
+        quote! {
+            impl #impl_generics #new_name #ty_generics #where_clause {
+                #must_use
+                fn ignored_fields(&self) -> ::optional_struct::__private::Vec<&'static str> {
+                    let mut fields = ::optional_struct::__private::Vec::new();
+                    #acc
+                    fields
+                }
+            }
+        }
+    }
+}
+
+impl OptionalFieldVisitor for GenerateIgnoredFieldsFn {
+    fn visit(&mut self, _global_options: &GlobalOptions, _old_field: &Field, _new_field: &mut Field, field_options: &FieldOptions) {
+        if !field_options.read_only {
+            return;
+        }
+        self.has_read_only = true;
+        let cfg_attr = &field_options.cfg_attribute;
+        let name = &field_options.new_field_name;
+        let is_set = field_options.is_set(quote! { self });
+        self.acc.push(cfg_attr, quote! {
+            if #is_set {
+                fields.push(#name);
+            }
+        });
+    }
+}
+
+// With `tokio_postgres`, `bind_set_fields` pushes the values of the same fields, in the same order,
+// with `sea_query`, `to_sea_query_values` pairs them with their column, and with `rusqlite`,
+// `to_named_params` with a parameter named after it
+struct GenerateSetColumnsFn {
+    acc: CfgStatements,
+    bind_acc: CfgStatements,
+    bind_bounds: Vec<TokenStream>,
+    sea_query_acc: CfgStatements,
+    sea_query_bounds: Vec<TokenStream>,
+    named_params_acc: CfgStatements,
+    named_params_bounds: Vec<TokenStream>,
+}
+
+impl GenerateSetColumnsFn {
+    fn new() -> Self {
+        GenerateSetColumnsFn {
+            acc: CfgStatements::default(),
+            bind_acc: CfgStatements::default(),
+            bind_bounds: vec![],
+            sea_query_acc: CfgStatements::default(),
+            sea_query_bounds: vec![],
+            named_params_acc: CfgStatements::default(),
+            named_params_bounds: vec![],
+        }
+    }
+
+    fn get_implementation(self, global_options: &GlobalOptions, orig: &DeriveInput, new: &DeriveInput) -> TokenStream {
+        let (impl_generics, ty_generics, where_clause) = orig.generics.split_for_impl();
+        let new_name = &new.ident;
+        let must_use = global_options.must_use_attribute();
+        let acc = self.acc;
+        let bind_acc = self.bind_acc;
+        let bind_where_clause = where_clause_with_bounds(&orig.generics, self.bind_bounds.into_iter());
+        let bind_set_fields = emit_if(global_options.tokio_postgres, || quote! {
+            impl #impl_generics #new_name #ty_generics #bind_where_clause {
+                #[allow(unused_variables)]
+                fn bind_set_fields<'__a>(&'__a self, params: &mut ::optional_struct::__private::Vec<&'__a (dyn ::tokio_postgres::types::ToSql + Sync)>) {
+                    #bind_acc
+                }
+            }
+        });
+        let sea_query_acc = self.sea_query_acc;
+        let sea_query_where_clause = where_clause_with_bounds(&orig.generics, self.sea_query_bounds.into_iter());
+        let to_sea_query_values = emit_if(global_options.sea_query, || quote! {
+            impl #impl_generics #new_name #ty_generics #sea_query_where_clause {
+                #must_use
+                fn to_sea_query_values(&self) -> ::optional_struct::__private::Vec<(::sea_query::Alias, ::sea_query::SimpleExpr)> {
+                    #[allow(unused_mut)]
+                    let mut values = ::optional_struct::__private::Vec::new();
+                    #sea_query_acc
+                    values
+                }
+            }
+        });
+        let named_params_acc = self.named_params_acc;
+        let named_params_where_clause = where_clause_with_bounds(&orig.generics, self.named_params_bounds.into_iter());
+        let to_named_params = emit_if(global_options.rusqlite, || quote! {
+            impl #impl_generics #new_name #ty_generics #named_params_where_clause {
+                #must_use
+                fn to_named_params(&self) -> ::optional_struct::__private::Vec<(&'static str, &dyn ::rusqlite::ToSql)> {
+                    #[allow(unused_mut)]
+                    let mut params = ::optional_struct::__private::Vec::new();
+                    #named_params_acc
+                    params
+                }
+            }
+
+            impl #impl_generics #new_name #ty_generics #where_clause {
+                // e.g. `name = :name, email = :email`, the parameters of `to_named_params`
+                #must_use
+                fn set_clause(&self) -> ::optional_struct::__private::String {
+                    self.set_columns()
+                        .iter()
+                        .map(|column| ::optional_struct::__private::format!("{0} = :{0}", column))
+                        .collect::<::optional_struct::__private::Vec<_>>()
+                        .join(", ")
+                }
+            }
+        });
+        quote! {
+            impl #impl_generics #new_name #ty_generics #where_clause {
+                // The columns an UPDATE statement has to set, in the order of the fields
+                #must_use
+                fn set_columns(&self) -> ::optional_struct::__private::Vec<&'static str> {
+                    #[allow(unused_mut)]
+                    let mut columns = ::optional_struct::__private::Vec::new();
+                    #acc
+                    columns
+                }
+            }
+
+            #bind_set_fields
+            #to_sea_query_values
+            #to_named_params
+        }
+    }
+
+    // A nested patch is bound as a whole, like any other value, e.g. to a JSON column
+    fn visit_bind(&mut self, old_field: &Field, new_field: &Field, field_options: &FieldOptions) {
+        let ident = &field_options.new_field_ident;
+        let cfg_attr = &field_options.cfg_attribute;
+        let (ty, bind) = if field_options.wrapping_behavior {
+            let field = field_options.unwrap_ref(quote! { self.#ident });
+            (field_options.value_type(old_field), quote! {
+                if let Some(value) = #field {
+                    params.push(value);
+                }
+            })
+        } else {
+            let is_set = field_options.is_set(quote! { self });
+            let ty = &new_field.ty;
+            (quote! { #ty }, quote! {
+                if #is_set {
+                    params.push(&self.#ident);
+                }
+            })
+        };
+        self.bind_bounds.push(quote! { #ty: ::tokio_postgres::types::ToSql + Sync });
+        self.bind_acc.push(cfg_attr, bind);
+    }
+
+    // Values are cloned, the patch being left as is
+    fn visit_sea_query(&mut self, old_field: &Field, new_field: &Field, field_options: &FieldOptions, column: &str) {
+        let ident = &field_options.new_field_ident;
+        let cfg_attr = &field_options.cfg_attribute;
+        let (ty, push) = if field_options.wrapping_behavior {
+            let field = field_options.unwrap_ref(quote! { self.#ident });
+            (field_options.value_type(old_field), quote! {
+                if let Some(value) = #field {
+                    values.push((::sea_query::Alias::new(#column), Clone::clone(value).into()));
+                }
+            })
+        } else {
+            let is_set = field_options.is_set(quote! { self });
+            let ty = &new_field.ty;
+            (quote! { #ty }, quote! {
+                if #is_set {
+                    values.push((::sea_query::Alias::new(#column), Clone::clone(&self.#ident).into()));
+                }
+            })
+        };
+        self.sea_query_bounds.push(quote! { #ty: Clone + Into<::sea_query::SimpleExpr> });
+        self.sea_query_acc.push(cfg_attr, push);
+    }
+
+    fn visit_named_param(&mut self, old_field: &Field, new_field: &Field, field_options: &FieldOptions, column: &str) {
+        let ident = &field_options.new_field_ident;
+        let cfg_attr = &field_options.cfg_attribute;
+        let param = format!(":{column}");
+        let (ty, push) = if field_options.wrapping_behavior {
+            let field = field_options.unwrap_ref(quote! { self.#ident });
+            (field_options.value_type(old_field), quote! {
+                if let Some(value) = #field {
+                    params.push((#param, value as &dyn ::rusqlite::ToSql));
+                }
+            })
+        } else {
+            let is_set = field_options.is_set(quote! { self });
+            let ty = &new_field.ty;
+            (quote! { #ty }, quote! {
+                if #is_set {
+                    params.push((#param, &self.#ident as &dyn ::rusqlite::ToSql));
+                }
+            })
+        };
+        self.named_params_bounds.push(quote! { #ty: ::rusqlite::ToSql });
+        self.named_params_acc.push(cfg_attr, push);
+    }
+}
+
+impl OptionalFieldVisitor for GenerateSetColumnsFn {
+    fn visit(&mut self, global_options: &GlobalOptions, old_field: &Field, new_field: &mut Field, field_options: &FieldOptions) {
+        // Read-only fields are never written back
+        if field_options.read_only {
+            return;
+        }
+        if global_options.tokio_postgres {
+            self.visit_bind(old_field, new_field, field_options);
+        }
+        let cfg_attr = &field_options.cfg_attribute;
+        let column = match &field_options.column {
+            Some(column) => column.to_string(),
+            None => field_options.field_name.clone(),
+        };
+        if global_options.sea_query {
+            self.visit_sea_query(old_field, new_field, field_options, &column);
+        }
+        if global_options.rusqlite {
+            self.visit_named_param(old_field, new_field, field_options, &column);
+        }
+        let is_set = field_options.is_set(quote! { self });
+        self.acc.push(cfg_attr, quote! {
+            if #is_set {
+                columns.push(#column);
+            }
+        });
+    }
+}
+
+// Hash fields are named after the struct fields, those of nested patches being prefixed with the
+// name of the nested field, e.g. `limits.uploads`
+struct GenerateRedisHashFns {
+    hset_acc: CfgStatements,
+    hdel_acc: CfgStatements,
+    bounds: Vec<TokenStream>,
+}
+
+impl GenerateRedisHashFns {
+    fn new() -> Self {
+        GenerateRedisHashFns {
+            hset_acc: CfgStatements::default(),
+            hdel_acc: CfgStatements::default(),
+            bounds: vec![],
+        }
+    }
+
+    fn get_implementation(self, global_options: &GlobalOptions, orig: &DeriveInput, new: &DeriveInput) -> TokenStream {
+        if !global_options.redis {
+            return quote! {};
+        }
+        let (impl_generics, ty_generics, _) = orig.generics.split_for_impl();
+        let where_clause = where_clause_with_bounds(&orig.generics, self.bounds.into_iter());
+        let new_name = &new.ident;
+        let must_use = global_options.must_use_attribute();
+        let hset_acc = self.hset_acc;
+        let hdel_acc = self.hdel_acc;
+        quote! {
+            impl #impl_generics #new_name #ty_generics #where_clause {
+                // The values of the set fields, e.g. for `HSET`
+                #must_use
+                fn to_redis_hset(&self) -> ::optional_struct::__private::Vec<(::optional_struct::__private::String, ::optional_struct::__private::String)> {
+                    #[allow(unused_mut)]
+                    let mut fields = ::optional_struct::__private::Vec::new();
+                    #hset_acc
+                    fields
+                }
+
+                // The double wrapped fields set to `Some(None)`, e.g. for `HDEL`
+                #must_use
+                fn to_redis_hdel(&self) -> ::optional_struct::__private::Vec<::optional_struct::__private::String> {
+                    #[allow(unused_mut)]
+                    let mut fields = ::optional_struct::__private::Vec::new();
+                    #hdel_acc
+                    fields
+                }
+            }
+        }
+    }
+}
+
+impl OptionalFieldVisitor for GenerateRedisHashFns {
+    fn visit(&mut self, global_options: &GlobalOptions, old_field: &Field, new_field: &mut Field, field_options: &FieldOptions) {
+        // Read-only fields are never written back
+        if !global_options.redis || field_options.read_only {
+            return;
+        }
+        let ident = &field_options.new_field_ident;
+        let name = &field_options.field_name;
+        let cfg_attr = &field_options.cfg_attribute;
+        let is_wrapped = field_options.wrapping_behavior;
+        let is_base_opt = field_options.is_base_opt;
+        if field_options.enum_merge || field_options.elementwise {
+            panic!("'redis' option can't be used along with fields that are enum-merged or elementwise, such as '{name}'");
+        }
+
+        if field_options.new_type.is_some() {
+            let with_nested = |body: TokenStream| if is_wrapped || is_base_opt {
+                quote! { if let Some(nested) = &self.#ident { #body } }
+            } else {
+                quote! { { let nested = &self.#ident; #body } }
+            };
+            self.hset_acc.push(cfg_attr, with_nested(quote! {
+                for (field, value) in nested.to_redis_hset() {
+                    fields.push((::optional_struct::__private::format!("{}.{}", #name, field), value));
+                }
+            }));
+            self.hdel_acc.push(cfg_attr, with_nested(quote! {
+                for field in nested.to_redis_hdel() {
+                    fields.push(::optional_struct::__private::format!("{}.{}", #name, field));
+                }
+            }));
+            return;
+        }
+
+        let is_double_wrapped = field_options.is_clearable();
+        let value_ty = field_options.set_value_type(old_field, new_field);
+        let to_string = match &field_options.redis_with {
+            Some(redis_with) => {
+                let redis_with = user_tokens(redis_with);
+                quote! { #redis_with(value) }
+            }
+            None => {
+                self.bounds.push(quote! { #value_ty: ::core::fmt::Display });
+                quote! { ::optional_struct::__private::ToString::to_string(value) }
+            }
+        };
+        let push = quote! { fields.push((::optional_struct::__private::String::from(#name), #to_string)); };
+        let hset = if is_double_wrapped {
+            let field = field_options.unwrap_ref(quote! { self.#ident });
+            self.hdel_acc.push(cfg_attr, quote! {
+                if let Some(None) = #field {
+                    fields.push(::optional_struct::__private::String::from(#name));
+                }
+            });
+            quote! {
+                if let Some(Some(value)) = #field {
+                    #push
+                }
+            }
+        } else if is_wrapped {
+            let field = field_options.unwrap_ref(quote! { self.#ident });
+            quote! {
+                if let Some(value) = #field {
+                    #push
+                }
+            }
+        } else {
+            let is_set = field_options.is_set(quote! { self });
+            quote! {
+                if #is_set {
+                    let value = &self.#ident;
+                    #push
+                }
+            }
+        };
+        self.hset_acc.push(cfg_attr, hset);
+    }
+}
+
+// Nested patches add their fields below the path of the nested field, e.g. `#limits.#uploads`
+struct GenerateDynamoUpdateFn {
+    acc: CfgStatements,
+    bounds: Vec<TokenStream>,
+}
+
+impl GenerateDynamoUpdateFn {
+    fn new() -> Self {
+        GenerateDynamoUpdateFn {
+            acc: CfgStatements::default(),
+            bounds: vec![],
+        }
+    }
+
+    fn get_implementation(self, global_options: &GlobalOptions, orig: &DeriveInput, new: &DeriveInput) -> TokenStream {
+        if !global_options.dynamodb {
+            return quote! {};
+        }
+        let (impl_generics, ty_generics, _) = orig.generics.split_for_impl();
+        let where_clause = where_clause_with_bounds(&orig.generics, self.bounds.into_iter());
+        let new_name = &new.ident;
+        let acc = self.acc;
+        quote! {
+            impl #impl_generics #new_name #ty_generics #where_clause {
+                #[doc(hidden)]
+                fn __dynamo_update(
+                    &self,
+                    parent: &str,
+                    update: &mut ::optional_struct::__private::DynamoUpdate<::aws_sdk_dynamodb::types::AttributeValue>,
+                ) -> Result<(), ::serde_dynamo::Error> {
+                    #acc
+                    Ok(())
+                }
+
+                // The expression along with its attribute names and values
+                #[allow(clippy::type_complexity)]
+                fn to_dynamo_update(&self) -> Result<
+                    (
+                        ::optional_struct::__private::String,
+                        ::std::collections::HashMap<::optional_struct::__private::String, ::optional_struct::__private::String>,
+                        ::std::collections::HashMap<::optional_struct::__private::String, ::aws_sdk_dynamodb::types::AttributeValue>,
+                    ),
+                    ::serde_dynamo::Error,
+                > {
+                    let mut update = ::optional_struct::__private::DynamoUpdate::default();
+                    self.__dynamo_update("", &mut update)?;
+                    Ok((update.expression(), update.names.into_iter().collect(), update.values.into_iter().collect()))
+                }
+            }
+        }
+    }
+}
+
+impl OptionalFieldVisitor for GenerateDynamoUpdateFn {
+    fn visit(&mut self, global_options: &GlobalOptions, old_field: &Field, new_field: &mut Field, field_options: &FieldOptions) {
+        // Read-only fields are never written back
+        if !global_options.dynamodb || field_options.read_only {
+            return;
+        }
+        let ident = &field_options.new_field_ident;
+        let name = &field_options.field_name;
+        let cfg_attr = &field_options.cfg_attribute;
+        let is_wrapped = field_options.wrapping_behavior;
+        if field_options.enum_merge || field_options.elementwise {
+            panic!("'dynamodb' option can't be used along with fields that are enum-merged or elementwise, such as '{name}'");
+        }
+
+        let update = if field_options.new_type.is_some() {
+            let body = quote! {
+                let path = ::optional_struct::__private::dynamo_path(parent, #name);
+                nested.__dynamo_update(&path, update)?;
+            };
+            if is_wrapped || field_options.is_base_opt {
+                quote! { if let Some(nested) = &self.#ident { #body } }
+            } else {
+                quote! { { let nested = &self.#ident; #body } }
+            }
+        } else {
+            let value_ty = field_options.set_value_type(old_field, new_field);
+            self.bounds.push(quote! { #value_ty: ::serde::Serialize });
+            let set = quote! {
+                let path = ::optional_struct::__private::dynamo_path(parent, #name);
+                update.set(path, ::serde_dynamo::to_attribute_value(value)?);
+            };
+            if field_options.is_clearable() {
+                let field = field_options.unwrap_ref(quote! { self.#ident });
+                quote! {
+                    match #field {
+                        Some(Some(value)) => { #set }
+                        Some(None) => {
+                            let path = ::optional_struct::__private::dynamo_path(parent, #name);
+                            update.remove(path);
+                        }
+                        None => {}
+                    }
+                }
+            } else if is_wrapped {
+                let field = field_options.unwrap_ref(quote! { self.#ident });
+                quote! { if let Some(value) = #field { #set } }
+            } else {
+                let is_set = field_options.is_set(quote! { self });
+                quote! { if #is_set { let value = &self.#ident; #set } }
+            }
+        };
+        self.acc.push(cfg_attr, update);
+    }
+}
+
+struct GenerateFieldsByNameFns {
+    set_acc: Vec<TokenStream>,
+    get_acc: Vec<TokenStream>,
+    deserialize_bounds: Vec<TokenStream>,
+    serialize_bounds: Vec<TokenStream>,
+    // The sparse conversions go through the nested patches, which don't have to support serde
+    to_sparse_acc: CfgStatements,
+    from_sparse_acc: Vec<TokenStream>,
+    sparse_deserialize_bounds: Vec<TokenStream>,
+    sparse_serialize_bounds: Vec<TokenStream>,
+}
+
+impl GenerateFieldsByNameFns {
+    fn new() -> Self {
+        GenerateFieldsByNameFns {
+            set_acc: vec![],
+            get_acc: vec![],
+            deserialize_bounds: vec![],
+            serialize_bounds: vec![],
+            to_sparse_acc: CfgStatements::default(),
+            from_sparse_acc: vec![],
+            sparse_deserialize_bounds: vec![],
+            sparse_serialize_bounds: vec![],
+        }
+    }
+
+    // `from_value_sparse` starts from the default patch
+    fn get_implementation(self, global_options: &GlobalOptions, orig: &DeriveInput, new: &DeriveInput, has_default: bool) -> TokenStream {
+        if !global_options.serde_json && !global_options.sparse_json {
+            return quote! {};
+        }
+
+        let (impl_generics, ty_generics, _) = orig.generics.split_for_impl();
+        let set_where_clause = where_clause_with_bounds(&orig.generics, self.deserialize_bounds.into_iter());
+        let get_where_clause = where_clause_with_bounds(&orig.generics, self.serialize_bounds.into_iter());
+        let new_name = &new.ident;
+        let must_use = global_options.must_use_attribute();
+        let set_acc = self.set_acc;
+        let get_acc = self.get_acc;
+        let to_sparse_acc = self.to_sparse_acc;
+        let from_sparse_acc = self.from_sparse_acc;
+        let to_sparse_where_clause = where_clause_with_bounds(&orig.generics, self.sparse_serialize_bounds.into_iter());
+        let from_sparse_where_clause = where_clause_with_bounds(&orig.generics, self.sparse_deserialize_bounds.into_iter());
+        let from_value_sparse = emit_if(global_options.sparse_json && has_default, || quote! {
+            impl #impl_generics #new_name #ty_generics #from_sparse_where_clause {
+                // The counterpart of `to_value_sparse`, missing fields being left unset
+                fn from_value_sparse(value: ::serde_json::Value) -> Result<Self, ::optional_struct::SetError> {
+                    let ::serde_json::Value::Object(fields) = value else {
+                        return Err(::optional_struct::SetError::NotAnObject);
+                    };
+                    let mut patch = Self::default();
+                    for (field, value) in fields {
+                        match field.as_str() {
+                            #(#from_sparse_acc)*
+                            _ => return Err(::optional_struct::SetError::UnknownField(field)),
+                        }
+                    }
+                    Ok(patch)
+                }
+            }
+        });
+        let to_value_sparse = emit_if(global_options.sparse_json, || quote! {
+            impl #impl_generics #new_name #ty_generics #to_sparse_where_clause {
+                // A JSON object holding the set fields only, nested patches leaving out theirs
+                #must_use
+                fn to_value_sparse(&self) -> ::serde_json::Value {
+                    #[allow(unused_mut)]
+                    let mut fields = ::serde_json::Map::new();
+                    #to_sparse_acc
+                    ::serde_json::Value::Object(fields)
+                }
+            }
+        });
+        let by_name = emit_if(global_options.serde_json, || quote! {
+            impl #impl_generics #new_name #ty_generics #set_where_clause {
+                fn set(&mut self, field: &str, value: ::serde_json::Value) -> Result<(), ::optional_struct::SetError> {
+                    match field {
+                        #(#set_acc)*
+                        _ => return Err(::optional_struct::SetError::UnknownField(field.into())),
+                    }
+                    Ok(())
+                }
+            }
+
+            impl #impl_generics #new_name #ty_generics #get_where_clause {
+                #must_use
+                fn get(&self, field: &str) -> Option<::serde_json::Value> {
+                    match field {
+                        #(#get_acc)*
+                        _ => None,
+                    }
+                }
+            }
+        });
+        quote! {
+            #by_name
+            #to_value_sparse
+            #from_value_sparse
+        }
+    }
+}
+
+impl OptionalFieldVisitor for GenerateFieldsByNameFns {
+    fn visit(&mut self, global_options: &GlobalOptions, old_field: &Field, new_field: &mut Field, field_options: &FieldOptions) {
+        if !global_options.serde_json && !global_options.sparse_json { return; }
+
+        let ident = &field_options.new_field_ident;
+        let name = &field_options.new_field_name;
+        let cfg_attr = &field_options.cfg_attribute;
+
+        let is_wrapped = field_options.wrapping_behavior;
+        let is_nested = field_options.new_type.is_some();
+        let is_base_opt = field_options.is_base_opt;
+        // Values are (de)serialized on their own, a custom wrapper doesn't have to support serde
+        let new_ty = match &field_options.wrapper {
+            Some(_) => field_options.value_type(old_field),
+            None => {
+                let new_ty = &new_field.ty;
+                quote! { #new_ty }
+            }
+        };
+        self.deserialize_bounds.push(quote! { #new_ty: ::serde::de::DeserializeOwned });
+        self.serialize_bounds.push(quote! { #new_ty: ::serde::Serialize });
+
+        let value = quote! {
+            ::serde_json::from_value(value).map_err(|e| ::optional_struct::SetError::invalid_value(#name, &e))?
+        };
+        let (assign, get) = if is_wrapped || (is_base_opt && !is_nested) {
+            let assign = field_options.wrap(value);
+            let field = field_options.unwrap_ref(quote! { self.#ident });
+            (
+                quote! { self.#ident = #assign },
+                quote! { #field.and_then(|v| ::serde_json::to_value(v).ok()) },
+            )
+        } else {
+            (
+                quote! { self.#ident = #value },
+                quote! { ::serde_json::to_value(&self.#ident).ok() },
+            )
+        };
+
+        let immutable = quote! { return Err(::optional_struct::SetError::Immutable(#name)) };
+        let assign = if field_options.immutable { immutable.clone() } else { assign };
+        self.set_acc.push(quote! {
+            #cfg_attr
+            #name => #assign,
+        });
+
+        self.get_acc.push(quote! {
+            #cfg_attr
+            #name => #get,
+        });
+
+        let is_nested = is_nested && !field_options.enum_merge;
+        let sparse_value = quote! {
+            ::serde_json::from_value(value).map_err(|e| ::optional_struct::SetError::invalid_value(#name, &e))?
+        };
+        let (to_sparse, from_sparse) = match &field_options.new_type {
+            Some(new_type) if is_nested => {
+                let nested = field_options.boxed_if_needed(quote! { <#new_type>::from_value_sparse(value)? });
+                if is_wrapped || is_base_opt {
+                    (
+                        quote! {
+                            if let Some(nested) = &self.#ident {
+                                fields.insert(#name.into(), nested.to_value_sparse());
+                            }
+                        },
+                        quote! { patch.#ident = Some(#nested) },
+                    )
+                } else {
+                    // An empty nested patch is as good as a missing one
+                    (
+                        quote! {
+                            let nested = self.#ident.to_value_sparse();
+                            if nested.as_object().is_some_and(|nested| !nested.is_empty()) {
+                                fields.insert(#name.into(), nested);
+                            }
+                        },
+                        quote! { patch.#ident = #nested },
+                    )
+                }
+            }
+            _ => {
+                self.sparse_deserialize_bounds.push(quote! { #new_ty: ::serde::de::DeserializeOwned });
+                self.sparse_serialize_bounds.push(quote! { #new_ty: ::serde::Serialize });
+                if is_wrapped || is_base_opt {
+                    let field = field_options.unwrap_ref(quote! { self.#ident });
+                    let value = field_options.wrap(sparse_value);
+                    (
+                        quote! {
+                            if let Some(value) = #field.and_then(|v| ::serde_json::to_value(v).ok()) {
+                                fields.insert(#name.into(), value);
+                            }
+                        },
+                        quote! { patch.#ident = #value },
+                    )
+                } else {
+                    (
+                        quote! {
+                            if let Ok(value) = ::serde_json::to_value(&self.#ident) {
+                                fields.insert(#name.into(), value);
+                            }
+                        },
+                        quote! { patch.#ident = #sparse_value },
+                    )
+                }
+            }
+        };
+        let from_sparse = if field_options.immutable { immutable } else { from_sparse };
+        self.to_sparse_acc.push(cfg_attr, to_sparse);
+        self.from_sparse_acc.push(quote! {
+            #cfg_attr
+            #name => #from_sparse,
+        });
+    }
+}
+
+// Fields are copied from the message by name, converted with `Into`, and nested messages (always
+// optional with prost) are copied by the nested patch, along with the paths below them
+struct GenerateFromProtoFn {
+    acc: Vec<TokenStream>,
+    bounds: Vec<TokenStream>,
+}
+
+impl GenerateFromProtoFn {
+    fn new() -> Self {
+        GenerateFromProtoFn {
+            acc: vec![],
+            bounds: vec![],
+        }
+    }
+
+    fn get_implementation(self, global_options: &GlobalOptions, orig: &DeriveInput, new: &DeriveInput) -> TokenStream {
+        let Some(message) = &global_options.prost else {
+            return quote! {};
+        };
+
+        let (impl_generics, ty_generics, _) = orig.generics.split_for_impl();
+        let where_clause = where_clause_with_bounds(&orig.generics, self.bounds.into_iter());
+        let new_name = &new.ident;
+        let must_use = global_options.must_use_attribute();
+        let acc = self.acc;
+        let extra_fields = global_options.extra_fields.iter().map(|f| &f.ident);
+        quote! {
+            impl #impl_generics #new_name #ty_generics #where_clause {
+                // Only the fields whose path is in `mask` are set, every one of them if it is empty
+                #must_use
+                #[allow(unused_variables)]
+                fn from_proto(msg: &#message, mask: &::prost_types::FieldMask) -> Self {
+                    Self {
+                        #(#acc)*
+                        #(#extra_fields: Default::default(),)*
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl OptionalFieldVisitor for GenerateFromProtoFn {
+    fn visit(&mut self, global_options: &GlobalOptions, old_field: &Field, _new_field: &mut Field, field_options: &FieldOptions) {
+        if global_options.prost.is_none() {
+            return;
+        }
+        if old_field.ident.is_none() {
+            panic!("'prost' option can't be used on a tuple struct");
+        }
+        let field_name = &field_options.field_name;
+        let is_wrapped = field_options.wrapping_behavior;
+        let is_base_opt = field_options.is_base_opt;
+        if field_options.enum_merge || field_options.elementwise || (!is_wrapped && !is_base_opt && field_options.new_type.is_none()) {
+            panic!("'prost' option can't be used along with fields that are enum-merged, elementwise or always set, such as '{field_name}'");
+        }
+        let new_ident = &field_options.new_field_ident;
+        let cfg_attr = &field_options.cfg_attribute;
+        let proto_ident = match &field_options.proto_name {
+            Some(proto_name) => quote! { #proto_name },
+            None => field_options.field_ident.clone(),
+        };
+        let path = proto_ident.to_string();
+        let value = match &field_options.new_type {
+            Some(new_type) => {
+                let nested = field_options.boxed_if_needed(quote! {
+                    <#new_type>::from_proto(nested, &::prost_types::FieldMask { paths })
+                });
+                let nested = if is_wrapped || is_base_opt { quote! { Some(#nested) } } else { nested };
+                quote! {
+                    match (::optional_struct::__private::masked_paths(&mask.paths, #path), &msg.#proto_ident) {
+                        (Some(paths), Some(nested)) => #nested,
+                        _ => Default::default(),
+                    }
+                }
+            }
+            None => {
+                let value_ty = field_options.value_type(old_field);
+                let value = quote! { ::core::convert::Into::<#value_ty>::into(msg.#proto_ident.clone()) };
+                let value = if is_wrapped { field_options.wrap(value) } else { value };
+                quote! {
+                    match ::optional_struct::__private::masked_paths(&mask.paths, #path) {
+                        Some(_) => #value,
+                        None => Default::default(),
+                    }
+                }
+            }
+        };
+        self.acc.push(quote! {
+            #cfg_attr
+            #new_ident: #value,
+        });
+    }
+}
+
+// Only the arguments the user actually gave are taken, so that the default values of the command
+// line don't override the values of the previous configuration layers
+struct GenerateArgMatchesFn {
+    acc: CfgStatements,
+    bounds: Vec<TokenStream>,
+}
+
+impl GenerateArgMatchesFn {
+    fn new() -> Self {
+        GenerateArgMatchesFn {
+            acc: CfgStatements::default(),
+            bounds: vec![],
+        }
+    }
+
+    fn get_implementation(self, global_options: &GlobalOptions, orig: &DeriveInput, new: &DeriveInput) -> TokenStream {
+        if !global_options.clap {
+            return quote! {};
+        }
+
+        let (impl_generics, ty_generics, _) = orig.generics.split_for_impl();
+        let where_clause = where_clause_with_bounds(&orig.generics, self.bounds.into_iter());
+        let new_name = &new.ident;
+        let acc = self.acc;
+        quote! {
+            impl #impl_generics #new_name #ty_generics #where_clause {
+                // Fields without an argument of the same name are left as they are, and an argument
+                // of another type than its field panics
+                #[allow(unused_variables)]
+                fn update_from_arg_matches(&mut self, matches: &::clap::ArgMatches) {
+                    #acc
+                }
+            }
+        }
+    }
+}
+
+impl OptionalFieldVisitor for GenerateArgMatchesFn {
+    fn visit(&mut self, global_options: &GlobalOptions, old_field: &Field, _new_field: &mut Field, field_options: &FieldOptions) {
+        if !global_options.clap || field_options.elementwise {
+            return;
+        }
+        let ident = &field_options.new_field_ident;
+        let cfg_attr = &field_options.cfg_attribute;
+
+        let is_wrapped = field_options.wrapping_behavior;
+        let is_base_opt = field_options.is_base_opt;
+        // Nested patches share the matches, like flattened arguments
+        if field_options.new_type.is_some() {
+            if !is_wrapped && !is_base_opt && !field_options.boxed && !field_options.enum_merge {
+                self.acc.push(cfg_attr, quote! { self.#ident.update_from_arg_matches(matches); });
+            }
+            return;
+        }
+
+        // Arguments hold the values themselves, not an `Option` of them
+        let ty: Type = syn::parse2(field_options.value_type(old_field)).expect("the value type is a type");
+        let inner_ty = first_type_argument(&ty).unwrap_or(&ty);
+        let value_ty = if field_options.is_double_wrapped() || (is_base_opt && !is_wrapped) { inner_ty } else { &ty };
+        // Arguments taking several values are read all at once
+        let (get, value) = match vec_element_type(value_ty) {
+            Some(element_ty) => {
+                self.bounds.push(quote! { #element_ty: Clone + Send + Sync + 'static });
+                (quote! { try_get_many::<#element_ty> }, quote! { value.cloned().collect() })
+            }
+            None => {
+                self.bounds.push(quote! { #value_ty: Clone + Send + Sync + 'static });
+                (quote! { try_get_one::<#value_ty> }, quote! { value.clone() })
+            }
+        };
+        let value = if field_options.is_double_wrapped() {
+            field_options.wrap(quote! { Some(#value) })
+        } else if is_wrapped {
+            field_options.wrap(value)
+        } else if is_base_opt {
+            quote! { Some(#value) }
+        } else {
+            value
+        };
+
+        let id = field_options.field_name.trim_start_matches("r#");
+        let mismatch = format!("argument '{id}' doesn't have the type of the field of the same name: {{}}");
+        self.acc.push(cfg_attr, quote! {
+            match matches.#get(#id) {
+                Ok(Some(value)) => {
+                    if matches.value_source(#id) != Some(::clap::parser::ValueSource::DefaultValue) {
+                        self.#ident = #value;
+                    }
+                }
+                Ok(None) | Err(::clap::parser::MatchesError::UnknownArgument { .. }) => {}
+                Err(e) => panic!(#mismatch, e),
+            }
+        });
+    }
+}
+
+struct GenerateChangeEnum {
+    enum_name: Ident,
+    variants_acc: Vec<TokenStream>,
+    into_changes_acc: CfgStatements,
+    // Replace-only values can't be cloned, compared nor printed
+    derivable: bool,
+}
+
+impl GenerateChangeEnum {
+    fn new(orig: &DeriveInput) -> Self {
+        GenerateChangeEnum {
+            enum_name: format_ident!("{}Change", orig.ident),
+            variants_acc: vec![],
+            into_changes_acc: CfgStatements::default(),
+            derivable: true,
+        }
+    }
+
+    fn variant_name(field_ident: &TokenStream) -> Ident {
+        let name = field_ident.to_string();
+        let mut variant = if name.starts_with(|c: char| c.is_ascii_digit()) { "Field".to_owned() } else { String::new() };
+        for word in name.trim_start_matches("r#").split('_').filter(|w| !w.is_empty()) {
+            let mut chars = word.chars();
+            variant.extend(chars.next().map(|c| c.to_ascii_uppercase()));
+            variant.extend(chars);
+        }
+        format_ident!("{variant}")
+    }
+
+    fn get_implementation(self, global_options: &GlobalOptions, orig: &DeriveInput, new: &DeriveInput) -> TokenStream {
+        let (impl_generics, ty_generics, where_clause) = orig.generics.split_for_impl();
+        let generics = &orig.generics;
+        let vis = &new.vis;
+        let new_name = &new.ident;
+        let enum_name = self.enum_name;
+        let variants_acc = self.variants_acc;
+        let into_changes_acc = self.into_changes_acc;
+        let hidden = global_options.hidden_attribute();
+        let must_use = global_options.must_use_attribute();
+        let derives = if self.derivable { quote! { #[derive(Clone, PartialEq, Debug)] } } else { quote! {} };
+        quote! {
+            #derives
+            #hidden
+            #vis enum #enum_name #generics #where_clause {
+                #(#variants_acc)*
+            }
+
+            impl #impl_generics #new_name #ty_generics #where_clause {
+                #must_use
+                fn into_changes(self) -> ::optional_struct::__private::Vec<#enum_name #ty_generics> {
+                    let mut changes = ::optional_struct::__private::Vec::new();
+                    #into_changes_acc
+                    changes
+                }
+            }
+        }
+    }
+}
+
+impl OptionalFieldVisitor for GenerateChangeEnum {
+    fn visit(&mut self, _global_options: &GlobalOptions, old_field: &Field, _new_field: &mut Field, field_options: &FieldOptions) {
+        let ident = &field_options.new_field_ident;
+        let enum_name = &self.enum_name;
+        let variant = Self::variant_name(ident);
+        self.derivable &= !field_options.replace_only;
+        let cfg_attr = &field_options.cfg_attribute;
+
+        let is_wrapped = field_options.wrapping_behavior;
+        let is_base_opt = field_options.is_base_opt;
+        // Each change carries the value the field is set to, or the nested patch
+        let (value_ty, inc) = match &field_options.new_type {
+            Some(_) => (
+                field_options.value_type(old_field),
+                if is_wrapped {
+                    quote! { if let Some(value) = self.#ident { changes.push(#enum_name::#variant(value)); } }
+                } else {
+                    quote! { changes.push(#enum_name::#variant(self.#ident)); }
+                },
+            ),
+            None => {
+                (
+                    field_options.value_type(old_field),
+                    match (is_base_opt, is_wrapped) {
+                        (_, true) => {
+                            let field = field_options.unwrap(quote! { self.#ident });
+                            quote! { if let Some(value) = #field { changes.push(#enum_name::#variant(value)); } }
+                        }
+                        (true, false) => quote! { if self.#ident.is_some() { changes.push(#enum_name::#variant(self.#ident)); } },
+                        (false, false) => field_options.when_set(quote! { self }, quote! { changes.push(#enum_name::#variant(self.#ident)); }),
+                    },
+                )
+            }
+        };
+
+        self.variants_acc.push(quote! {
+            #cfg_attr
+            #variant(#value_ty),
+        });
+
+        self.into_changes_acc.push(cfg_attr, quote! {
+            #inc
+        });
+    }
+}
+
+struct GenerateFieldEnum {
+    enum_name: Ident,
+    variants_acc: Vec<TokenStream>,
+    retain_acc: CfgStatements,
+    snapshot_acc: Vec<TokenStream>,
+    project_acc: Vec<TokenStream>,
+    bounds: Vec<TokenStream>,
+    // Fields that are neither wrapped, nested nor an `Option` are always set
+    retainable: bool,
+    // Replace-only values can't be cloned, and enum patches can't be built from a value
+    snapshottable: bool,
+}
+
+impl GenerateFieldEnum {
+    fn new(orig: &DeriveInput) -> Self {
+        GenerateFieldEnum {
+            enum_name: format_ident!("{}Field", orig.ident),
+            variants_acc: vec![],
+            retain_acc: CfgStatements::default(),
+            snapshot_acc: vec![],
+            project_acc: vec![],
+            bounds: vec![],
+            retainable: true,
+            snapshottable: true,
+        }
+    }
+
+    // `methods` is false when the enum is only needed by the sourced wrapper
+    fn get_implementation(self, global_options: &GlobalOptions, orig: &DeriveInput, new: &DeriveInput, methods: bool) -> TokenStream {
+        let (impl_generics, ty_generics, where_clause) = orig.generics.split_for_impl();
+        let vis = &new.vis;
+        let orig_name = &orig.ident;
+        let new_name = &new.ident;
+        let enum_name = self.enum_name;
+        let variants_acc = self.variants_acc;
+        let retain_acc = self.retain_acc;
+        let hidden = global_options.hidden_attribute();
+        let must_use = global_options.must_use_attribute();
+        let retain_fields = emit_if(methods && self.retainable, || quote! {
+            impl #impl_generics #new_name #ty_generics #where_clause {
+                #[allow(unused_variables)]
+                fn retain_fields(&mut self, allowed: &[#enum_name]) {
+                    #retain_acc
+                }
+            }
+        });
+        let snapshot_where_clause = where_clause_with_bounds(&orig.generics, self.bounds.into_iter());
+        let snapshot_acc = self.snapshot_acc;
+        let project_acc = self.project_acc;
+        let extra_fields = global_options.extra_fields.iter().map(|f| &f.ident).collect::<Vec<_>>();
+        let project = emit_if(self.retainable, || quote! {
+            // Only the fields in `fields` are set, nested ones as a whole
+            #must_use
+            #[allow(unused_variables)]
+            fn project(value: &#orig_name #ty_generics, fields: &[#enum_name]) -> Self {
+                Self {
+                    #(#project_acc)*
+                    #(#extra_fields: Default::default(),)*
+                }
+            }
+        });
+        let snapshot = emit_if(methods && self.snapshottable, || quote! {
+            impl #impl_generics #new_name #ty_generics #snapshot_where_clause {
+                // Every field is set, to its value in `value`
+                #must_use
+                #[allow(unused_variables)]
+                fn snapshot(value: &#orig_name #ty_generics) -> Self {
+                    Self {
+                        #(#snapshot_acc)*
+                        #(#extra_fields: Default::default(),)*
+                    }
+                }
+
+                #project
+            }
+        });
+        quote! {
+            #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+            #hidden
+            #vis enum #enum_name {
+                #(#variants_acc)*
+            }
+
+            #retain_fields
+            #snapshot
+        }
+    }
+}
+
+impl OptionalFieldVisitor for GenerateFieldEnum {
+    fn visit(&mut self, _global_options: &GlobalOptions, old_field: &Field, _new_field: &mut Field, field_options: &FieldOptions) {
+        let ident = &field_options.new_field_ident;
+        let enum_name = &self.enum_name;
+        let variant = GenerateChangeEnum::variant_name(ident);
+        let cfg_attr = &field_options.cfg_attribute;
+
+        let unset = if field_options.elementwise {
+            quote! { core::array::from_fn(|_| None) }
+        } else if field_options.wrapping_behavior || field_options.is_base_opt || field_options.new_type.is_some() {
+            quote! { Default::default() }
+        } else {
+            self.retainable = false;
+            quote! {}
+        };
+
+        self.variants_acc.push(quote! {
+            #cfg_attr
+            #variant,
+        });
+        self.retain_acc.push(cfg_attr, quote! {
+            if !allowed.contains(&#enum_name::#variant) {
+                self.#ident = #unset;
+            }
+        });
+
+        if field_options.replace_only || field_options.enum_merge {
+            self.snapshottable = false;
+            return;
+        }
+        let orig_ident = &field_options.field_ident;
+        let old_ty = &old_field.ty;
+        let is_wrapped = field_options.wrapping_behavior;
+        let value = match (&field_options.new_type, field_options.is_base_opt, is_wrapped) {
+            // A `None` can't be told apart from an unset field
+            (Some(new_type), true, _) => {
+                let nested = field_options.boxed_if_needed(quote! { <#new_type>::snapshot(nested) });
+                quote! { value.#orig_ident.as_ref().map(|nested| #nested) }
+            }
+            (Some(new_type), false, _) => {
+                let nested = field_options.boxed_if_needed(quote! { <#new_type>::snapshot(&value.#orig_ident) });
+                if is_wrapped { quote! { Some(#nested) } } else { nested }
+            }
+            (None, _, _) => {
+                self.bounds.push(quote! { #old_ty: Clone });
+                let value = field_options.convert_to_patch(quote! { value.#orig_ident.clone() });
+                if is_wrapped { field_options.wrap(value) } else { value }
+            }
+        };
+        self.snapshot_acc.push(quote! {
+            #cfg_attr
+            #ident: #value,
+        });
+        self.project_acc.push(quote! {
+            #cfg_attr
+            #ident: if fields.contains(&#enum_name::#variant) { #value } else { #unset },
+        });
+    }
+}
+
+struct GenerateSetOperationsFns {
+    intersect_acc: Vec<TokenStream>,
+    subtract_acc: Vec<TokenStream>,
+    bounds: Vec<TokenStream>,
+    // Replace-only values can't be cloned
+    clonable: bool,
+}
+
+impl GenerateSetOperationsFns {
+    fn new() -> Self {
+        GenerateSetOperationsFns {
+            intersect_acc: vec![],
+            subtract_acc: vec![],
+            bounds: vec![],
+            clonable: true,
+        }
+    }
+
+    fn get_implementation(self, global_options: &GlobalOptions, orig: &DeriveInput, new: &DeriveInput) -> TokenStream {
+        if !self.clonable {
+            return quote! {};
+        }
+
+        let (impl_generics, ty_generics, _) = orig.generics.split_for_impl();
+        let where_clause = where_clause_with_bounds(&orig.generics, self.bounds.into_iter());
+        let new_name = &new.ident;
+        let must_use = global_options.must_use_attribute();
+        let intersect_acc = self.intersect_acc;
+        let subtract_acc = self.subtract_acc;
+        let extra_fields = global_options.extra_fields.iter().map(|f| &f.ident).collect::<Vec<_>>();
+        quote! {
+            impl #impl_generics #new_name #ty_generics #where_clause {
+                // Fields set in both patches, with the values of `self`
+                #must_use
+                #[allow(unused_variables)]
+                fn intersect(&self, other: &Self) -> Self {
+                    Self {
+                        #(#intersect_acc)*
+                        #(#extra_fields: self.#extra_fields.clone(),)*
+                    }
+                }
+
+                // Fields set in `self` but not in `other`
+                #must_use
+                #[allow(unused_variables)]
+                fn subtract(&self, other: &Self) -> Self {
+                    Self {
+                        #(#subtract_acc)*
+                        #(#extra_fields: self.#extra_fields.clone(),)*
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl OptionalFieldVisitor for GenerateSetOperationsFns {
+    fn visit(&mut self, _global_options: &GlobalOptions, _old_field: &Field, new_field: &mut Field, field_options: &FieldOptions) {
+        if field_options.replace_only {
+            self.clonable = false;
+            return;
+        }
+        let ident = &field_options.new_field_ident;
+        let cfg_attr = &field_options.cfg_attribute;
+        let new_ty = &new_field.ty;
+
+        let is_wrapped = field_options.wrapping_behavior;
+        let is_base_opt = field_options.is_base_opt;
+        let (intersect, subtract) = match (&field_options.new_type, is_wrapped || is_base_opt) {
+            // Enum patches are taken as a whole
+            (Some(_), true) if field_options.enum_merge => {
+                self.bounds.push(quote! { #new_ty: Clone });
+                (
+                    quote! { other.#ident.as_ref().and(self.#ident.clone()) },
+                    quote! { if other.#ident.is_some() { None } else { self.#ident.clone() } },
+                )
+            }
+            (Some(_), true) => {
+                let intersect = field_options.boxed_if_needed(quote! { nested.intersect(value) });
+                let subtract = field_options.boxed_if_needed(quote! { nested.subtract(value) });
+                (
+                    quote! {
+                        match (&self.#ident, &other.#ident) {
+                            (Some(nested), Some(value)) => Some(#intersect),
+                            _ => None,
+                        }
+                    },
+                    quote! {
+                        match (&self.#ident, &other.#ident) {
+                            (Some(nested), Some(value)) => Some(#subtract),
+                            (nested, _) => nested.clone(),
+                        }
+                    },
+                )
+            }
+            (Some(_), false) => (
+                field_options.boxed_if_needed(quote! { self.#ident.intersect(&other.#ident) }),
+                field_options.boxed_if_needed(quote! { self.#ident.subtract(&other.#ident) }),
+            ),
+            (None, false) if field_options.elementwise => {
+                self.bounds.push(quote! { #new_ty: Clone });
+                (
+                    quote! { core::array::from_fn(|i| other.#ident[i].as_ref().and(self.#ident[i].clone())) },
+                    quote! { core::array::from_fn(|i| if other.#ident[i].is_some() { None } else { self.#ident[i].clone() }) },
+                )
+            }
+            (None, true) => {
+                self.bounds.push(quote! { #new_ty: Clone + Default });
+                let (self_set, other_set) = (field_options.is_set(quote! { self }), field_options.is_set(quote! { other }));
+                (
+                    quote! { if #self_set && #other_set { self.#ident.clone() } else { Default::default() } },
+                    quote! { if #other_set { Default::default() } else { self.#ident.clone() } },
+                )
+            }
+            // Fields that are always set are kept as they are
+            (None, false) => {
+                self.bounds.push(quote! { #new_ty: Clone });
+                (quote! { self.#ident.clone() }, quote! { self.#ident.clone() })
+            }
+        };
+
+        self.intersect_acc.push(quote! {
+            #cfg_attr
+            #ident: #intersect,
+        });
+        self.subtract_acc.push(quote! {
+            #cfg_attr
+            #ident: #subtract,
+        });
+    }
+}
+
+struct GenerateStripDefaultsFn {
+    acc: CfgStatements,
+    bounds: Vec<TokenStream>,
+}
+
+impl GenerateStripDefaultsFn {
+    fn new() -> Self {
+        GenerateStripDefaultsFn {
+            acc: CfgStatements::default(),
+            bounds: vec![],
+        }
+    }
+
+    fn get_implementation(self, orig: &DeriveInput, new: &DeriveInput) -> TokenStream {
+        let (impl_generics, ty_generics, _) = orig.generics.split_for_impl();
+        let where_clause = where_clause_with_bounds(&orig.generics, self.bounds.into_iter());
+        let orig_name = &orig.ident;
+        let new_name = &new.ident;
+        let acc = self.acc;
+        quote! {
+            impl #impl_generics ::optional_struct::__private::StripDefaults<#orig_name #ty_generics> for #new_name #ty_generics #where_clause {
+                // Nested patches left with no field set are unset as well
+                #[allow(unused_variables)]
+                fn strip_defaults(&mut self, reference: &#orig_name #ty_generics) {
+                    #acc
+                }
+            }
+
+            impl #impl_generics #new_name #ty_generics #where_clause {
+                fn strip_defaults(&mut self, reference: &#orig_name #ty_generics) {
+                    <Self as ::optional_struct::__private::StripDefaults<#orig_name #ty_generics>>::strip_defaults(self, reference);
+                }
+            }
+        }
+    }
+}
+
+impl OptionalFieldVisitor for GenerateStripDefaultsFn {
+    fn visit(&mut self, _global_options: &GlobalOptions, old_field: &Field, _new_field: &mut Field, field_options: &FieldOptions) {
+        // Neither replace-only values nor enum patches can be compared, and merged values
+        // aren't meant to be compared to the current ones
+        if field_options.replace_only || field_options.enum_merge || field_options.merge_op.is_some() {
+            return;
+        }
+        let ident = &field_options.field_ident;
+        let new_ident = &field_options.new_field_ident;
+        let cfg_attr = &field_options.cfg_attribute;
+        let old_ty = &old_field.ty;
+
+        let is_wrapped = field_options.wrapping_behavior;
+        let is_base_opt = field_options.is_base_opt;
+        // Nested patches are stripped through the trait, which carries the bounds of their impl
+        let strip_nested = match &field_options.new_type {
+            Some(new_type) => {
+                let mut inner_ty = old_ty;
+                if is_base_opt {
+                    inner_ty = first_type_argument(inner_ty).unwrap_or(inner_ty);
+                }
+                // Bounding a boxed patch, which may be the struct itself, would be recursive
+                if field_options.boxed {
+                    inner_ty = first_type_argument(inner_ty).unwrap_or(inner_ty);
+                } else {
+                    self.bounds.push(quote! { #new_type: ::optional_struct::__private::StripDefaults<#inner_ty> });
+                }
+                quote! { <#new_type as ::optional_struct::__private::StripDefaults<#inner_ty>>::strip_defaults }
+            }
+            None => quote! {},
+        };
+        let strip = match (&field_options.new_type, is_base_opt, is_wrapped) {
+            (Some(_), true, _) => quote! {
+                if let (Some(nested), Some(reference)) = (&mut self.#new_ident, &reference.#ident) {
+                    #strip_nested(nested, reference);
+                    if nested.set_count() == 0 {
+                        self.#new_ident = None;
+                    }
+                }
+            },
+            (Some(_), false, true) => quote! {
+                if let Some(nested) = &mut self.#new_ident {
+                    #strip_nested(nested, &reference.#ident);
+                    if nested.set_count() == 0 {
+                        self.#new_ident = None;
+                    }
+                }
+            },
+            (Some(_), false, false) => quote! { #strip_nested(&mut self.#new_ident, &reference.#ident); },
+            (None, false, false) if field_options.elementwise => {
+                self.bounds.push(quote! { #old_ty: PartialEq });
+                quote! {
+                    for (value, reference) in self.#new_ident.iter_mut().zip(&reference.#ident) {
+                        if value.as_ref() == Some(reference) {
+                            *value = None;
+                        }
+                    }
+                }
+            }
+            (None, true, false) if !field_options.option_like => {
+                let value_ty = field_options.value_type(old_field);
+                self.bounds.push(quote! { #value_ty: PartialEq });
+                let reference = self.reference_value(old_ty, field_options);
+                quote! {
+                    if self.#new_ident.is_some() && self.#new_ident.as_ref() == #reference.as_ref() {
+                        self.#new_ident = None;
+                    }
+                }
+            }
+            (None, _, true) => {
+                let value_ty = field_options.value_type(old_field);
+                self.bounds.push(quote! { #value_ty: PartialEq });
+                let reference = self.reference_value(old_ty, field_options);
+                let value = field_options.unwrap_ref(quote! { self.#new_ident });
+                quote! {
+                    if #value == Some(&#reference) {
+                        self.#new_ident = Default::default();
+                    }
+                }
+            }
+            // Fields that are always set can't be unset
+            (None, _, false) => return,
+        };
+
+        self.acc.push(cfg_attr, quote! {
+            #strip
+        });
+    }
+}
+
+impl GenerateStripDefaultsFn {
+    // Value of the reference field, as held by the optional struct
+    fn reference_value(&mut self, old_ty: &Type, field_options: &FieldOptions) -> TokenStream {
+        let ident = &field_options.field_ident;
+        match field_options.convert {
+            Some(_) => {
+                self.bounds.push(quote! { #old_ty: Clone });
+                field_options.convert_to_patch(quote! { reference.#ident.clone() })
+            }
+            None => quote! { reference.#ident },
+        }
+    }
+}
+
+struct GenerateAssertAppliesToFn {
+    acc: CfgStatements,
+    bounds: Vec<TokenStream>,
+}
+
+impl GenerateAssertAppliesToFn {
+    fn new() -> Self {
+        GenerateAssertAppliesToFn {
+            acc: CfgStatements::default(),
+            bounds: vec![],
+        }
+    }
+
+    fn get_implementation(self, orig: &DeriveInput, new: &DeriveInput) -> TokenStream {
+        let (impl_generics, ty_generics, _) = orig.generics.split_for_impl();
+        let orig_name = &orig.ident;
+        let new_name = &new.ident;
+        let clone_bounds = [quote! { #orig_name #ty_generics: Clone }, quote! { #new_name #ty_generics: Clone }];
+        let where_clause = where_clause_with_bounds(&orig.generics, clone_bounds.into_iter().chain(self.bounds));
+        let acc = self.acc;
+        quote! {
+            #[cfg(test)]
+            impl #impl_generics #new_name #ty_generics #where_clause {
+                #[track_caller]
+                fn assert_applies_to(&self, before: &#orig_name #ty_generics, after: &#orig_name #ty_generics) {
+                    let mut t = before.clone();
+                    self.clone().apply_to(&mut t);
+                    #[allow(unused_mut)]
+                    let mut mismatches = ::optional_struct::__private::Vec::<::optional_struct::__private::String>::new();
+                    #acc
+                    if !mismatches.is_empty() {
+                        panic!("the patch didn't give the expected value:\n{}", mismatches.join("\n"));
+                    }
+                }
+            }
+        }
+    }
+
+    fn push_field(&mut self, old_field: &Field, field_options: &FieldOptions) {
+        let ident = &field_options.field_ident;
+        let name = &field_options.field_name;
+        let ty = &old_field.ty;
+        self.bounds.push(quote! { #ty: PartialEq + core::fmt::Debug });
+        self.acc.push(&field_options.cfg_attribute, quote! {
+            if t.#ident != after.#ident {
+                mismatches.push(::optional_struct::__private::format!("  {}: expected {:?}, got {:?}", #name, after.#ident, t.#ident));
+            }
+        });
+    }
+}
+
+impl OptionalFieldVisitor for GenerateAssertAppliesToFn {
+    fn visit(&mut self, _global_options: &GlobalOptions, old_field: &Field, _new_field: &mut Field, field_options: &FieldOptions) {
+        self.push_field(old_field, field_options);
+    }
+
+    fn visit_skipped(&mut self, _global_options: &GlobalOptions, old_field: &Field, field_options: &FieldOptions, _default: &TokenStream) {
+        self.push_field(old_field, field_options);
+    }
+}
+
+struct GenerateTrackedWrapper {
+    setters_acc: Vec<TokenStream>,
+    bounds: Vec<TokenStream>,
+}
+
+impl GenerateTrackedWrapper {
+    fn new() -> Self {
+        GenerateTrackedWrapper {
+            setters_acc: vec![],
+            bounds: vec![],
+        }
+    }
+
+    fn get_implementation(self, global_options: &GlobalOptions, orig: &DeriveInput, new: &DeriveInput) -> TokenStream {
+        let (impl_generics, ty_generics, where_clause) = orig.generics.split_for_impl();
+        let setters_where_clause = where_clause_with_bounds(&orig.generics, self.bounds.into_iter());
+        let generics = &orig.generics;
+        let vis = &orig.vis;
+        let orig_name = &orig.ident;
+        let new_name = &new.ident;
+        // Derived traits of the generated struct are bounded on the generic parameters
+        let patch_where_clause = where_clause_with_bounds(&orig.generics, std::iter::once(quote! { #new_name #ty_generics: Default + PartialEq }));
+        let tracked_name = format_ident!("Tracked{}", orig.ident);
+        let hidden = global_options.hidden_attribute();
+        let must_use = global_options.must_use_attribute();
+        let inline = global_options.inline_attribute();
+        let setters_acc = self.setters_acc;
+        quote! {
+            #hidden
+            #vis struct #tracked_name #generics #where_clause {
+                value: #orig_name #ty_generics,
+                dirty: #new_name #ty_generics,
+            }
+
+            impl #impl_generics #tracked_name #ty_generics #patch_where_clause {
+                #must_use
+                fn new(value: #orig_name #ty_generics) -> Self {
+                    Self {
+                        value,
+                        dirty: Default::default(),
+                    }
+                }
+
+                #must_use
+                fn is_dirty(&self) -> bool {
+                    self.dirty != Default::default()
+                }
+
+                fn flush(&mut self) -> #new_name #ty_generics {
+                    core::mem::take(&mut self.dirty)
+                }
+            }
+
+            impl #impl_generics #tracked_name #ty_generics #where_clause {
+                #inline
+                fn get(&self) -> &#orig_name #ty_generics {
+                    &self.value
+                }
+
+                #inline
+                fn pending(&self) -> &#new_name #ty_generics {
+                    &self.dirty
+                }
+
+                #inline
+                #must_use
+                fn into_inner(self) -> #orig_name #ty_generics {
+                    self.value
+                }
+            }
+
+            impl #impl_generics core::ops::Deref for #tracked_name #ty_generics #where_clause {
+                type Target = #orig_name #ty_generics;
+
+                fn deref(&self) -> &Self::Target {
+                    &self.value
+                }
+            }
+
+            impl #impl_generics #tracked_name #ty_generics #setters_where_clause {
+                #(#setters_acc)*
+            }
+        }
+    }
+}
+
+struct GenerateSourcedWrapper {
+    field_enum_name: Ident,
+    sources_acc: CfgStatements,
+}
+
+impl GenerateSourcedWrapper {
+    fn new(orig: &DeriveInput) -> Self {
+        GenerateSourcedWrapper {
+            field_enum_name: format_ident!("{}Field", orig.ident),
+            sources_acc: CfgStatements::default(),
+        }
+    }
+
+    fn get_implementation(self, global_options: &GlobalOptions, orig: &DeriveInput, new: &DeriveInput) -> TokenStream {
+        if !global_options.track_source {
+            return quote! {};
+        }
+
+        let (impl_generics, ty_generics, where_clause) = orig.generics.split_for_impl();
+        let generics = &orig.generics;
+        let vis = &orig.vis;
+        let new_name = &new.ident;
+        let sourced_name = format_ident!("Sourced{}", orig.ident);
+        let field_enum_name = self.field_enum_name;
+        let sources_acc = self.sources_acc;
+        let hidden = global_options.hidden_attribute();
+        let must_use = global_options.must_use_attribute();
+        let inline = global_options.inline_attribute();
+        let patch_where_clause = where_clause_with_bounds(&orig.generics, std::iter::once(quote! { #new_name #ty_generics: Default }));
+        quote! {
+            #hidden
+            #vis struct #sourced_name #generics #where_clause {
+                patch: #new_name #ty_generics,
+                sources: ::optional_struct::__private::Vec<(#field_enum_name, ::optional_struct::__private::String)>,
+            }
+
+            impl #impl_generics #sourced_name #ty_generics #patch_where_clause {
+                #must_use
+                fn new() -> Self {
+                    Self {
+                        patch: Default::default(),
+                        sources: ::optional_struct::__private::Vec::new(),
+                    }
+                }
+            }
+
+            impl #impl_generics #sourced_name #ty_generics #where_clause {
+                // Applies the fields set in `patch` over the current ones, recording `layer` as
+                // their source
+                fn apply_layer(&mut self, layer: &str, patch: #new_name #ty_generics) {
+                    #sources_acc
+                    patch.apply_to_opt(&mut self.patch);
+                }
+
+                // Name of the last layer which set `field`
+                #must_use
+                fn source_of(&self, field: #field_enum_name) -> Option<&str> {
+                    self.sources.iter().find(|(f, _)| *f == field).map(|(_, layer)| layer.as_str())
+                }
+
+                #inline
+                fn patch(&self) -> &#new_name #ty_generics {
+                    &self.patch
+                }
+
+                #inline
+                #must_use
+                fn into_patch(self) -> #new_name #ty_generics {
+                    self.patch
+                }
+
+                fn record_source(&mut self, field: #field_enum_name, layer: &str) {
+                    let layer = ::optional_struct::__private::String::from(layer);
+                    match self.sources.iter_mut().find(|(f, _)| *f == field) {
+                        Some((_, source)) => *source = layer,
+                        None => self.sources.push((field, layer)),
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl OptionalFieldVisitor for GenerateSourcedWrapper {
+    fn visit(&mut self, global_options: &GlobalOptions, _old_field: &Field, _new_field: &mut Field, field_options: &FieldOptions) {
+        if !global_options.track_source {
+            return;
+        }
+        let field_enum_name = &self.field_enum_name;
+        let variant = GenerateChangeEnum::variant_name(&field_options.new_field_ident);
+        let is_set = field_options.is_set(quote! { patch });
+        self.sources_acc.push(&field_options.cfg_attribute, quote! {
+            if #is_set {
+                self.record_source(#field_enum_name::#variant, layer);
+            }
+        });
+    }
+}
+
+impl OptionalFieldVisitor for GenerateTrackedWrapper {
+    fn visit(&mut self, _global_options: &GlobalOptions, old_field: &Field, _new_field: &mut Field, field_options: &FieldOptions) {
+        let ident = &field_options.field_ident;
+        let new_ident = &field_options.new_field_ident;
+        let setter = format_ident!("set_{}", ident.to_string().trim_start_matches("r#"));
+        let cfg_attr = &field_options.cfg_attribute;
+
+        let is_wrapped = field_options.wrapping_behavior;
+        let is_base_opt = field_options.is_base_opt;
+        let old_ty = &old_field.ty;
+        // Nested fields are modified through a patch, which is merged with the pending one
+        let inc = match (&field_options.new_type, is_base_opt, is_wrapped) {
+            // A patch can't be applied to a value that may not exist
+            (Some(_), true, _) => return,
+            // The value can't be both stored and recorded
+            _ if field_options.replace_only => return,
+            // Boxed patches are only applied as a whole
+            (Some(_), _, _) if field_options.boxed => return,
+            (Some(new_type), _, true) => quote! {
+                fn #setter(&mut self, patch: #new_type) {
+                    patch.clone().apply_to(&mut self.value.#ident);
+                    self.dirty.#new_ident = Some(match self.dirty.#new_ident.take() {
+                        Some(pending) => pending.apply(patch),
+                        None => patch,
+                    });
+                }
+            },
+            (Some(new_type), false, false) => quote! {
+                fn #setter(&mut self, patch: #new_type) {
+                    patch.clone().apply_to(&mut self.value.#ident);
+                    self.dirty.#new_ident = core::mem::take(&mut self.dirty.#new_ident).apply(patch);
+                }
+            },
+            (None, _, true) => {
+                self.bounds.push(quote! { #old_ty: Clone });
+                let wrapped = field_options.wrap(field_options.convert_to_patch(quote! { value }));
+                quote! {
+                    fn #setter(&mut self, value: #old_ty) {
+                        self.value.#ident = value.clone();
+                        self.dirty.#new_ident = #wrapped;
+                    }
+                }
+            }
+            // Setting an already optional field to `None` can't be recorded in the patch
+            (None, _, false) => {
+                self.bounds.push(quote! { #old_ty: Clone });
+                let pending = field_options.convert_to_patch(quote! { value });
+                quote! {
+                    fn #setter(&mut self, value: #old_ty) {
+                        self.value.#ident = value.clone();
+                        self.dirty.#new_ident = #pending;
+                    }
+                }
+            }
+        };
+
+        self.setters_acc.push(quote! {
+            #cfg_attr
+            #inc
+        });
+    }
+}
+
+// FNV-1a, which is stable across compiler versions and platforms unlike std's hashers
+fn schema_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| (hash ^ *b as u64).wrapping_mul(0x100000001b3))
+}
+
+struct GenerateSchemaHashConst {
+    acc: Vec<TokenStream>,
+    field_index: usize,
+}
+
+impl GenerateSchemaHashConst {
+    fn new() -> Self {
+        GenerateSchemaHashConst {
+            acc: vec![],
+            field_index: 0,
+        }
+    }
+
+    fn get_implementation(self, orig: &DeriveInput, new: &DeriveInput) -> TokenStream {
+        let (impl_generics, ty_generics, where_clause) = orig.generics.split_for_impl();
+        let new_name = &new.ident;
+        let seed = schema_hash(new_name.to_string().as_bytes());
+        let acc = self.acc;
+        quote! {
+            impl #impl_generics #new_name #ty_generics #where_clause {
+                pub const PATCH_SCHEMA_HASH: u64 = {
+                    let hash = #seed;
+                    #(#acc)*
+                    hash
+                };
+            }
+        }
+    }
+}
+
+impl OptionalFieldVisitor for GenerateSchemaHashConst {
+    fn visit(&mut self, _global_options: &GlobalOptions, _old_field: &Field, new_field: &mut Field, field_options: &FieldOptions) {
+        let ident = &field_options.new_field_ident;
+        let cfg_attr = &field_options.cfg_attribute;
+        let new_ty = &new_field.ty;
+        let field_hash = schema_hash(format!("{}:{}:{}", self.field_index, ident, quote! { #new_ty }).as_bytes());
+        self.field_index += 1;
+
+        let mut hash = quote! { ::optional_struct::__private::combine_schema_hash(hash, #field_hash) };
+        // The type of a boxed patch is part of the field hash, but its own hash may be recursive
+        if let (Some(new_type), false) = (&field_options.new_type, field_options.boxed) {
+            hash = quote! { ::optional_struct::__private::combine_schema_hash(#hash, <#new_type>::PATCH_SCHEMA_HASH) };
+        }
+
+        self.acc.push(quote! {
+            #cfg_attr
+            let hash = #hash;
+        });
+    }
+}
+
+// Only the secret fields are zeroized, the other ones being harmless
+struct GenerateZeroizeImpl {
+    acc: CfgStatements,
+    bounds: Vec<TokenStream>,
+}
+
+impl GenerateZeroizeImpl {
+    fn new() -> Self {
+        GenerateZeroizeImpl {
+            acc: CfgStatements::default(),
+            bounds: vec![],
+        }
+    }
+
+    fn get_implementation(self, global_options: &GlobalOptions, orig: &DeriveInput, new: &DeriveInput) -> TokenStream {
+        if !global_options.zeroize {
+            return quote! {};
+        }
+
+        let (impl_generics, ty_generics, _) = orig.generics.split_for_impl();
+        let where_clause = where_clause_with_bounds(&orig.generics, self.bounds.into_iter());
+        let new_name = &new.ident;
+        let acc = self.acc;
+        quote! {
+            impl #impl_generics ::zeroize::Zeroize for #new_name #ty_generics #where_clause {
+                fn zeroize(&mut self) {
+                    #acc
+                }
+            }
+        }
+    }
+}
+
+impl OptionalFieldVisitor for GenerateZeroizeImpl {
+    fn visit(&mut self, global_options: &GlobalOptions, old_field: &Field, _new_field: &mut Field, field_options: &FieldOptions) {
+        if !global_options.zeroize || !field_options.secret {
+            return;
+        }
+        if field_options.wrapper.is_some() {
+            panic!("'zeroize' option can't be used along with a secret field in a custom wrapper");
+        }
+        let ident = &field_options.new_field_ident;
+        let value_ty = field_options.value_type(old_field);
+        self.bounds.push(quote! { #value_ty: ::zeroize::Zeroize });
+        self.acc.push(&field_options.cfg_attribute, quote! { ::zeroize::Zeroize::zeroize(&mut self.#ident); });
+    }
+}
+
+// Nested structs list their own environment variables, with their own prefix
+struct GenerateEnvVarsConst {
+    acc: Vec<TokenStream>,
+}
+
+impl GenerateEnvVarsConst {
+    fn new() -> Self {
+        GenerateEnvVarsConst { acc: vec![] }
+    }
+
+    fn get_implementation(self, orig: &DeriveInput, new: &DeriveInput) -> TokenStream {
+        let (impl_generics, ty_generics, where_clause) = orig.generics.split_for_impl();
+        let new_name = &new.ident;
+        let acc = self.acc;
+        quote! {
+            impl #impl_generics #new_name #ty_generics #where_clause {
+                pub const ENV_VARS: &'static [(&'static str, &'static str)] = &[
+                    #(#acc)*
+                ];
+            }
+        }
+    }
+}
+
+impl OptionalFieldVisitor for GenerateEnvVarsConst {
+    fn visit(&mut self, global_options: &GlobalOptions, _old_field: &Field, _new_field: &mut Field, field_options: &FieldOptions) {
+        if field_options.new_type.is_some() {
+            return;
+        }
+        let cfg_attr = &field_options.cfg_attribute;
+        let name = field_options.field_name.trim_start_matches("r#");
+        let prefix = global_options.env_prefix.as_deref().unwrap_or_default();
+        let env_var = format!("{prefix}{}", name.to_uppercase());
+        self.acc.push(quote! {
+            #cfg_attr
+            (#name, #env_var),
+        });
+    }
+}
+
+struct GenerateDiffImpl {
+    acc: Vec<TokenStream>,
+    changed_acc: CfgStatements,
+    // Plain `Option` fields going back to `None`, which a patch can't unset, are assigned by
+    // `reload_from` instead
+    assign_acc: CfgStatements,
+    bounds: Vec<TokenStream>,
+    // Replace-only fields can't be compared, and neither merged values nor enums be diffed
+    diffable: bool,
+}
+
+impl GenerateDiffImpl {
+    fn new() -> Self {
+        GenerateDiffImpl {
+            acc: vec![],
+            changed_acc: CfgStatements::default(),
+            assign_acc: CfgStatements::default(),
+            bounds: vec![],
+            diffable: true,
+        }
+    }
+
+    // `merge_patch` tells whether `apply_merge_patch` is generated, which needs `reload_from`
+    fn get_implementation(self, global_options: &GlobalOptions, orig: &DeriveInput, new: &DeriveInput, reload_from: bool, merge_patch: bool) -> TokenStream {
+        if !self.diffable {
+            if merge_patch {
+                panic!("'merge_patch' needs `Diffable`, which isn't implemented for structs with merged or enum-merged fields");
+            }
+            return quote! {};
+        }
+
+        let (impl_generics, ty_generics, _) = orig.generics.split_for_impl();
+        let where_clause = where_clause_with_bounds(&orig.generics, self.bounds.into_iter());
+        let orig_name = &orig.ident;
+        let new_name = &new.ident;
+        let acc = self.acc;
+        let changed_acc = self.changed_acc;
+        let assign_acc = self.assign_acc;
+        let extra_fields = global_options.extra_fields.iter().map(|f| &f.ident);
+        let apply_merge_patch = emit_if(merge_patch, || quote! {
+            // Applies a JSON merge patch (RFC 7396), returning the names of the fields it changed.
+            // Nothing is applied if the body isn't a valid patch.
+            fn apply_merge_patch(&mut self, body: &[u8]) -> Result<::optional_struct::__private::Vec<&'static str>, ::serde_json::Error>
+            where
+                Self: Clone,
+                #new_name #ty_generics: ::optional_struct::__private::DeserializeOwned,
+            {
+                let value: ::serde_json::Value = ::serde_json::from_slice(body)?;
+                // Any other value would replace the whole struct
+                if !value.is_object() {
+                    return Err(<::serde_json::Error as ::optional_struct::__private::DeError>::custom("a merge patch must be a JSON object"));
+                }
+                let patch: #new_name #ty_generics = ::serde_json::from_value(value)?;
+                let mut merged = self.clone();
+                patch.apply_to(&mut merged);
+                Ok(self.reload_from(merged))
+            }
+        });
+        let reload_from = emit_if(reload_from, || quote! {
+            impl #impl_generics #orig_name #ty_generics #where_clause {
+                // Applies the fields of `other` which differ, returning their names
+                fn reload_from(&mut self, other: Self) -> ::optional_struct::__private::Vec<&'static str> {
+                    let patch = ::optional_struct::Diffable::diff(&*self, &other);
+                    #[allow(unused_mut)]
+                    let mut changes = ::optional_struct::__private::Vec::new();
+                    #changed_acc
+                    patch.apply_to(self);
+                    #assign_acc
+                    changes
+                }
+
+                #apply_merge_patch
+            }
+        });
+        quote! {
+            impl #impl_generics ::optional_struct::Diffable for #orig_name #ty_generics #where_clause {
+                type Patch = #new_name #ty_generics;
+
+                fn diff(&self, other: &Self) -> Self::Patch {
+                    #new_name {
+                        #(#acc)*
+                        #(#extra_fields: Default::default(),)*
+                    }
+                }
+            }
+
+            #reload_from
+        }
+    }
+}
+
+impl OptionalFieldVisitor for GenerateDiffImpl {
+    fn visit(&mut self, _global_options: &GlobalOptions, old_field: &Field, _new_field: &mut Field, field_options: &FieldOptions) {
+        if field_options.merge_op.is_some() || field_options.enum_merge {
+            self.diffable = false;
+            return;
+        }
+        let ident = &field_options.field_ident;
+        let new_ident = &field_options.new_field_ident;
+        let cfg_attr = &field_options.cfg_attribute;
+        let old_ty = &old_field.ty;
+
+        // Replace-only fields can't be compared, and are left unset
+        if field_options.replace_only {
+            self.acc.push(quote! {
+                #cfg_attr
+                #new_ident: ::core::default::Default::default(),
+            });
+            return;
+        }
+
+        let is_wrapped = field_options.wrapping_behavior;
+        let is_base_opt = field_options.is_base_opt;
+        // Fields that are always set in the diff are compared instead
+        let always_set = field_options.new_type.is_none()
+            && !field_options.elementwise
+            && !is_wrapped
+            && (!is_base_opt || field_options.option_like);
+        let plain_option = field_options.new_type.is_none() && !is_wrapped && is_base_opt && !field_options.option_like;
+        if plain_option {
+            self.assign_acc.push(cfg_attr, quote! { self.#ident = other.#ident; });
+        }
+        let changed = if always_set || plain_option { quote! { self.#ident != other.#ident } } else { field_options.is_set(quote! { patch }) };
+        let name = &field_options.field_name;
+        self.changed_acc.push(cfg_attr, quote! {
+            if #changed {
+                changes.push(#name);
+            }
+        });
+
+        let value = match &field_options.new_type {
+            Some(new_type) => {
+                let mut inner_ty = old_ty;
+                if is_base_opt {
+                    inner_ty = first_type_argument(inner_ty).unwrap_or(inner_ty);
+                }
+                // Bounding a boxed patch, which may be the struct itself, would be recursive
+                if field_options.boxed {
+                    inner_ty = first_type_argument(inner_ty).unwrap_or(inner_ty);
+                } else {
+                    self.bounds.push(quote! { #inner_ty: ::optional_struct::Diffable<Patch = #new_type> });
+                }
+                let diff = |this: TokenStream, that: TokenStream| {
+                    let diff = quote! { <#inner_ty as ::optional_struct::Diffable>::diff(#this, #that) };
+                    field_options.boxed_if_needed(diff)
+                };
+                // Going from `None` to `Some` would take a patch setting every field, which
+                // isn't something a diff can give
+                match (is_base_opt, is_wrapped) {
+                    (true, _) => {
+                        let diff = diff(quote! { current }, quote! { value });
+                        quote! {
+                            match (&self.#ident, &other.#ident) {
+                                (Some(current), Some(value)) => Some(#diff),
+                                _ => None,
+                            }
+                        }
+                    }
+                    (false, true) => {
+                        let diff = diff(quote! { &self.#ident }, quote! { &other.#ident });
+                        quote! { Some(#diff) }
+                    }
+                    (false, false) => diff(quote! { &self.#ident }, quote! { &other.#ident }),
+                }
+            }
+            None => {
+                self.bounds.push(quote! { #old_ty: PartialEq + Clone });
+                if field_options.elementwise {
+                    quote! {
+                        core::array::from_fn(|i| (self.#ident[i] != other.#ident[i]).then(|| other.#ident[i].clone()))
+                    }
+                } else {
+                    let value = field_options.convert_to_patch(quote! { other.#ident.clone() });
+                    // Fields that are always set can only be given the new value
+                    if is_wrapped {
+                        let value = field_options.wrap(value);
+                        quote! { if self.#ident != other.#ident { #value } else { Default::default() } }
+                    } else if is_base_opt && !field_options.option_like {
+                        quote! { if self.#ident != other.#ident { #value } else { None } }
+                    } else {
+                        value
+                    }
+                }
+            }
+        };
+
+        self.acc.push(quote! {
+            #cfg_attr
+            #new_ident: #value,
+        });
+    }
+}
+
+struct GenerateMerge3Fn {
+    acc: Vec<TokenStream>,
+    bounds: Vec<TokenStream>,
+    // Replace-only fields and enums can't be compared
+    mergeable: bool,
+}
+
+impl GenerateMerge3Fn {
+    fn new() -> Self {
+        GenerateMerge3Fn {
+            acc: vec![],
+            bounds: vec![],
+            mergeable: true,
+        }
+    }
+
+    fn get_implementation(self, global_options: &GlobalOptions, orig: &DeriveInput, new: &DeriveInput) -> TokenStream {
+        if !self.mergeable {
+            return quote! {};
+        }
+
+        let (impl_generics, ty_generics, _) = orig.generics.split_for_impl();
+        let where_clause = where_clause_with_bounds(&orig.generics, self.bounds.into_iter());
+        let orig_name = &orig.ident;
+        let new_name = &new.ident;
+        let acc = self.acc;
+        // Extra fields aren't part of the original struct, ours win
+        let extra_fields = global_options.extra_fields.iter().map(|f| &f.ident);
+        quote! {
+            impl #impl_generics ::optional_struct::__private::Merge3<#orig_name #ty_generics> for #new_name #ty_generics #where_clause {
+                #[allow(unused_variables)]
+                fn merge3(base: &#orig_name #ty_generics, ours: Self, theirs: Self, conflicts: &mut ::optional_struct::Conflicts) -> Self {
+                    Self {
+                        #(#acc)*
+                        #(#extra_fields: ours.#extra_fields,)*
+                    }
+                }
+            }
+
+            impl #impl_generics #new_name #ty_generics #where_clause {
+                fn merge3(base: &#orig_name #ty_generics, ours: Self, theirs: Self) -> Result<Self, ::optional_struct::Conflicts> {
+                    let mut conflicts = ::optional_struct::Conflicts::default();
+                    let merged = <Self as ::optional_struct::__private::Merge3<#orig_name #ty_generics>>::merge3(base, ours, theirs, &mut conflicts);
+                    if conflicts.fields.is_empty() { Ok(merged) } else { Err(conflicts) }
+                }
+            }
+        }
+    }
+}
+
+impl OptionalFieldVisitor for GenerateMerge3Fn {
+    fn visit(&mut self, _global_options: &GlobalOptions, old_field: &Field, _new_field: &mut Field, field_options: &FieldOptions) {
+        if field_options.replace_only || field_options.enum_merge {
+            self.mergeable = false;
+            return;
+        }
+        let ident = &field_options.field_ident;
+        let new_ident = &field_options.new_field_ident;
+        let name = &field_options.field_name;
+        let cfg_attr = &field_options.cfg_attribute;
+        let old_ty = &old_field.ty;
+
+        let is_wrapped = field_options.wrapping_behavior;
+        let is_base_opt = field_options.is_base_opt;
+        let conflict = quote! { conflicts.fields.push(#name.into()); };
+        let value = if let Some(new_type) = &field_options.new_type {
+            let mut inner_ty = old_ty;
+            if is_base_opt {
+                inner_ty = first_type_argument(inner_ty).unwrap_or(inner_ty);
+            }
+            // Bounding a boxed patch, which may be the struct itself, would be recursive
+            if field_options.boxed {
+                inner_ty = first_type_argument(inner_ty).unwrap_or(inner_ty);
+            } else {
+                self.bounds.push(quote! { #new_type: ::optional_struct::__private::Merge3<#inner_ty> });
+            }
+            let merge = |base: TokenStream, ours: TokenStream, theirs: TokenStream| {
+                let (ours, theirs) = if field_options.boxed { (quote! { *#ours }, quote! { *#theirs }) } else { (ours, theirs) };
+                let merged = quote! {
+                    {
+                        let mut nested = ::optional_struct::Conflicts::default();
+                        let merged = <#new_type as ::optional_struct::__private::Merge3<#inner_ty>>::merge3(#base, #ours, #theirs, &mut nested);
+                        conflicts.fields.extend(nested.nested_in(#name).fields);
+                        merged
+                    }
+                };
+                field_options.boxed_if_needed(merged)
+            };
+            match (is_base_opt, is_wrapped) {
+                // Without a base value, both patches would have to be complete and equal
+                (true, _) => {
+                    let merged = merge(quote! { current }, quote! { ours }, quote! { theirs });
+                    quote! {
+                        match (ours.#new_ident, theirs.#new_ident, &base.#ident) {
+                            (Some(ours), Some(theirs), Some(current)) => Some(#merged),
+                            (Some(ours), Some(_), None) => {
+                                #conflict
+                                Some(ours)
+                            }
+                            (ours, None, _) => ours,
+                            (None, theirs, _) => theirs,
+                        }
+                    }
+                }
+                (false, true) => {
+                    let merged = merge(quote! { &base.#ident }, quote! { ours }, quote! { theirs });
+                    quote! {
+                        match (ours.#new_ident, theirs.#new_ident) {
+                            (Some(ours), Some(theirs)) => Some(#merged),
+                            (ours, None) => ours,
+                            (None, theirs) => theirs,
+                        }
+                    }
+                }
+                (false, false) => merge(quote! { &base.#ident }, quote! { ours.#new_ident }, quote! { theirs.#new_ident }),
+            }
+        } else if field_options.elementwise {
+            let Type::Array(array) = old_ty else { unreachable!("checked when parsing the attribute") };
+            let elem = &array.elem;
+            self.bounds.push(quote! { #elem: PartialEq });
+            quote! {
+                {
+                    let mut merged = ours.#new_ident;
+                    for (i, (current, value)) in merged.iter_mut().zip(theirs.#new_ident).enumerate() {
+                        match (current.is_some(), value) {
+                            (_, None) => {}
+                            (false, value) => *current = value,
+                            (true, Some(value)) => {
+                                let base_value = Some(&base.#ident[i]);
+                                if current.as_ref() == base_value {
+                                    *current = Some(value);
+                                } else if current.as_ref() != Some(&value) && Some(&value) != base_value {
+                                    conflicts.fields.push(::optional_struct::__private::format!("{}[{}]", #name, i));
+                                }
+                            }
+                        }
+                    }
+                    merged
+                }
+            }
+        } else if field_options.merge_op.is_some() {
+            // Both sides are accumulated, like when applying one patch after the other
+            if is_wrapped {
+                let merged = field_options.merge_value(quote! { current }, quote! { value });
+                quote! {
+                    match (ours.#new_ident, theirs.#new_ident) {
+                        (Some(current), Some(value)) => Some(#merged),
+                        (ours, None) => ours,
+                        (None, theirs) => theirs,
+                    }
+                }
+            } else {
+                field_options.merge_value(quote! { ours.#new_ident }, quote! { theirs.#new_ident })
+            }
+        } else {
+            let value_ty = field_options.value_type(old_field);
+            self.bounds.push(quote! { #value_ty: PartialEq });
+            let base_value = match field_options.convert {
+                Some(_) => {
+                    self.bounds.push(quote! { #old_ty: Clone });
+                    field_options.convert_to_patch(quote! { base.#ident.clone() })
+                }
+                None => quote! { base.#ident },
+            };
+            // Values are compared as `Option`s of references, or as references for fields that
+            // are always set
+            let (ours_value, theirs_value, base_value) = if is_wrapped {
+                (field_options.unwrap_ref(quote! { ours.#new_ident }), field_options.unwrap_ref(quote! { theirs.#new_ident }), quote! { Some(&#base_value) })
+            } else if is_base_opt {
+                (quote! { ours.#new_ident.as_ref() }, quote! { theirs.#new_ident.as_ref() }, quote! { #base_value.as_ref() })
+            } else {
+                (quote! { &ours.#new_ident }, quote! { &theirs.#new_ident }, quote! { &#base_value })
+            };
+            let ours_set = field_options.is_set(quote! { ours });
+            let theirs_set = field_options.is_set(quote! { theirs });
+            quote! {
+                {
+                    let base_value = #base_value;
+                    match (#ours_set, #theirs_set) {
+                        (_, false) => ours.#new_ident,
+                        (false, true) => theirs.#new_ident,
+                        (true, true) if #ours_value == #theirs_value || #theirs_value == base_value => ours.#new_ident,
+                        (true, true) if #ours_value == base_value => theirs.#new_ident,
+                        (true, true) => {
+                            #conflict
+                            ours.#new_ident
+                        }
+                    }
+                }
+            }
+        };
+
+        self.acc.push(quote! {
+            #cfg_attr
+            #new_ident: #value,
+        });
+    }
+}
+
+struct GenerateInvertFn {
+    acc: Vec<TokenStream>,
+    bounds: Vec<TokenStream>,
+    // Replace-only fields can't be copied, and neither merged values nor enums be undone
+    invertible: bool,
+}
+
+impl GenerateInvertFn {
+    fn new() -> Self {
+        GenerateInvertFn {
+            acc: vec![],
+            bounds: vec![],
+            invertible: true,
+        }
+    }
+
+    fn get_implementation(self, global_options: &GlobalOptions, orig: &DeriveInput, new: &DeriveInput) -> TokenStream {
+        if !self.invertible {
+            return quote! {};
+        }
+
+        let (impl_generics, ty_generics, _) = orig.generics.split_for_impl();
+        let where_clause = where_clause_with_bounds(&orig.generics, self.bounds.into_iter());
+        let orig_name = &orig.ident;
+        let new_name = &new.ident;
+        let must_use = global_options.must_use_attribute();
+        let acc = self.acc;
+        let extra_fields = global_options.extra_fields.iter().map(|f| &f.ident);
+        quote! {
+            impl #impl_generics #new_name #ty_generics #where_clause {
+                // Applied after this patch, the inverted one gives the fields it sets their
+                // value in `base` back
+                #must_use
+                #[allow(unused_variables)]
+                fn invert(&self, base: &#orig_name #ty_generics) -> Self {
+                    Self {
+                        #(#acc)*
+                        #(#extra_fields: Default::default(),)*
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl OptionalFieldVisitor for GenerateInvertFn {
+    fn visit(&mut self, _global_options: &GlobalOptions, old_field: &Field, _new_field: &mut Field, field_options: &FieldOptions) {
+        if field_options.replace_only || field_options.merge_op.is_some() || field_options.enum_merge {
+            self.invertible = false;
+            return;
+        }
+        let ident = &field_options.field_ident;
+        let new_ident = &field_options.new_field_ident;
+        let cfg_attr = &field_options.cfg_attribute;
+        let old_ty = &old_field.ty;
+
+        let is_wrapped = field_options.wrapping_behavior;
+        let is_base_opt = field_options.is_base_opt;
+        let value = match (&field_options.new_type, is_base_opt, is_wrapped) {
+            // Read-only fields are never applied, so there is nothing to undo
+            (_, _, true) if field_options.read_only => quote! { Default::default() },
+            (None, true, false) if field_options.read_only && !field_options.option_like => quote! { None },
+            (Some(_), true, _) => {
+                let inverted = field_options.boxed_if_needed(quote! { nested.invert(current) });
+                // Clearing the value isn't something a patch can do
+                quote! {
+                    match (&self.#new_ident, &base.#ident) {
+                        (Some(nested), Some(current)) => Some(#inverted),
+                        _ => None,
+                    }
+                }
+            }
+            (Some(_), false, true) => {
+                let inverted = field_options.boxed_if_needed(quote! { nested.invert(&base.#ident) });
+                quote! { self.#new_ident.as_ref().map(|nested| #inverted) }
+            }
+            (Some(_), false, false) => field_options.boxed_if_needed(quote! { self.#new_ident.invert(&base.#ident) }),
+            (None, false, false) if field_options.elementwise => {
+                self.bounds.push(quote! { #old_ty: Clone });
+                quote! { core::array::from_fn(|i| self.#new_ident[i].as_ref().map(|_| base.#ident[i].clone())) }
+            }
+            (None, _, _) => {
+                self.bounds.push(quote! { #old_ty: Clone });
+                let value = field_options.convert_to_patch(quote! { base.#ident.clone() });
+                let is_set = field_options.is_set(quote! { self });
+                // Giving a field that is always set its base value is a no-op when the patch
+                // didn't change it
+                if is_wrapped {
+                    let value = field_options.wrap(value);
+                    quote! { if #is_set { #value } else { Default::default() } }
+                } else if is_base_opt && !field_options.option_like {
+                    quote! { if #is_set { #value } else { None } }
+                } else {
+                    value
+                }
+            }
+        };
+
+        self.acc.push(quote! {
+            #cfg_attr
+            #new_ident: #value,
+        });
+    }
+}
+
+struct GenerateSettersFns {
+    acc: Vec<TokenStream>,
+    setters: Vec<Ident>,
+}
+
+impl GenerateSettersFns {
+    fn new() -> Self {
+        GenerateSettersFns {
+            acc: vec![],
+            setters: vec![],
+        }
+    }
+
+    // `generated` lists the other `set_*` functions of the optional struct, which a setter can't shadow
+    fn get_implementation(self, orig: &DeriveInput, new: &DeriveInput, generated: &[&str]) -> TokenStream {
+        if let Some(setter) = self.setters.iter().find(|setter| generated.iter().any(|name| setter == name)) {
+            let field = setter.to_string().trim_start_matches("set_").to_owned();
+            panic!("The setter of '{field}' would clash with the generated `{setter}`, rename the field with '{FIELD_NAME_ATTRIBUTE}' or leave 'setters' out of 'impls'");
+        }
+        let (impl_generics, ty_generics, where_clause) = orig.generics.split_for_impl();
+        let new_name = &new.ident;
+        let acc = self.acc;
+        quote! {
+            impl #impl_generics #new_name #ty_generics #where_clause {
+                #(#acc)*
+            }
+        }
+    }
+}
+
+impl OptionalFieldVisitor for GenerateSettersFns {
+    fn visit(&mut self, global_options: &GlobalOptions, old_field: &Field, new_field: &mut Field, field_options: &FieldOptions) {
+        let inline = global_options.inline_attribute();
+        let ident = &field_options.new_field_ident;
+        let setter = format_ident!("set_{}", ident.to_string().trim_start_matches("r#"));
+        let cfg_attr = &field_options.cfg_attribute;
+
+        let mut inc = if field_options.wrapping_behavior {
+            let value_ty = field_options.value_type(old_field);
+            let wrapped = field_options.wrap(quote! { value.into() });
+            quote! {
+                #inline
+                fn #setter(&mut self, value: impl Into<#value_ty>) -> &mut Self {
+                    self.#ident = #wrapped;
+                    self
+                }
+            }
+        } else {
+            let new_ty = &new_field.ty;
+            quote! {
+                #inline
+                fn #setter(&mut self, value: impl Into<#new_ty>) -> &mut Self {
+                    self.#ident = value.into();
+                    self
+                }
+            }
+        };
+
+        // The fields of a tuple struct can also be set by value and read by position
+        if old_field.ident.is_none() {
+            let with = format_ident!("with_{}", field_options.new_field_name);
+            let getter = format_ident!("get_{}", field_options.new_field_name);
+            let (value_ty, get_ty, get) = if field_options.wrapping_behavior {
+                let value_ty = field_options.value_type(old_field);
+                let get = field_options.unwrap_ref(quote! { self.#ident });
+                (value_ty.clone(), quote! { Option<&#value_ty> }, get)
+            } else {
+                let new_ty = &new_field.ty;
+                (quote! { #new_ty }, quote! { &#new_ty }, quote! { &self.#ident })
+            };
+            inc.extend(quote! {
+                #cfg_attr
+                #inline
+                fn #with(mut self, value: impl Into<#value_ty>) -> Self {
+                    self.#setter(value);
+                    self
+                }
+
+                #cfg_attr
+                #inline
+                fn #getter(&self) -> #get_ty {
+                    #get
+                }
+            });
+        }
+
+        self.setters.push(setter);
+        self.acc.push(quote! {
+            #cfg_attr
+            #inc
+        });
+    }
+}
+
+struct GenerateEmptyConst {
+    acc: Vec<TokenStream>,
+    // First field that is not optional, which can't be built in a const context
+    non_const_field: Option<String>,
+}
+
+impl GenerateEmptyConst {
+    fn new() -> Self {
+        GenerateEmptyConst {
+            acc: vec![],
+            non_const_field: None,
+        }
+    }
+
+    fn get_implementation(self, global_options: &GlobalOptions, orig: &DeriveInput, new: &DeriveInput) -> TokenStream {
+        let non_const_field = self.non_const_field.or_else(|| {
+            global_options.extra_fields.iter().find(|f| !is_type_option(&f.ty)).and_then(|f| f.ident.as_ref()).map(ToString::to_string)
+        });
+        if let Some(field) = non_const_field {
+            panic!("'empty' can't be generated: '{field}' isn't an `Option` that can be set to `None` in a const context");
+        }
+
+        let (impl_generics, ty_generics, where_clause) = orig.generics.split_for_impl();
+        let new_name = &new.ident;
+        let must_use = global_options.must_use_attribute();
+        let inline = global_options.inline_attribute();
+        let acc = self.acc;
+        let extra_fields = global_options.extra_fields.iter().map(|f| &f.ident);
+        quote! {
+            impl #impl_generics #new_name #ty_generics #where_clause {
+                pub const EMPTY: Self = Self::empty();
+
+                #inline
+                #must_use
+                pub const fn empty() -> Self {
+                    Self {
+                        #(#acc)*
+                        #(#extra_fields: None,)*
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl OptionalFieldVisitor for GenerateEmptyConst {
+    fn visit(&mut self, _global_options: &GlobalOptions, _old_field: &Field, _new_field: &mut Field, field_options: &FieldOptions) {
+        let ident = &field_options.new_field_ident;
+        let cfg_attr = &field_options.cfg_attribute;
+
+        let is_wrapped = field_options.wrapping_behavior;
+        let is_nested = field_options.new_type.is_some();
+        let is_base_opt = field_options.is_base_opt;
+        // We can't tell whether a nested optional struct, a custom wrapper or an `Option`-like type has an
+        // EMPTY value itself
+        if (!is_wrapped && (is_nested || !is_base_opt || field_options.option_like)) || field_options.wrapper.is_some() {
+            self.non_const_field.get_or_insert_with(|| field_options.field_name.clone());
+            return;
+        }
+
+        self.acc.push(quote! {
+            #cfg_attr
+            #ident: None,
+        });
+    }
+}
+
+// Compact form of the optional struct for `repr = "mask"`: values of wrapped fields are stored
+// unwrapped, and whether they are set is kept in a bitset.
+struct GenerateMaskRepr {
+    fields: Vec<TokenStream>,
+    to_mask: CfgStatements,
+    from_mask: Vec<TokenStream>,
+    apply: CfgStatements,
+    masked_count: usize,
+}
+
+impl GenerateMaskRepr {
+    fn new() -> Self {
+        GenerateMaskRepr {
+            fields: vec![],
+            to_mask: CfgStatements::default(),
+            from_mask: vec![],
+            apply: CfgStatements::default(),
+            masked_count: 0,
+        }
+    }
+
+    fn get_implementation(self, global_options: &GlobalOptions, orig: &DeriveInput, new: &DeriveInput) -> TokenStream {
+        if !global_options.mask_repr {
+            return quote! {};
+        }
+        let (impl_generics, ty_generics, where_clause) = orig.generics.split_for_impl();
+        let generics = &orig.generics;
+        let vis = &orig.vis;
+        let orig_name = &orig.ident;
+        let new_name = &new.ident;
+        let mask_name = format_ident!("{}Mask", new.ident);
+        let hidden = global_options.hidden_attribute();
+        let words = self.masked_count.div_ceil(64);
+        let fields = self.fields;
+        let to_mask = self.to_mask;
+        let from_mask = self.from_mask;
+        let apply = self.apply;
+        let extra_fields = global_options.extra_fields.iter().map(|f| &f.ident);
+        let doc = format!("Compact version of [`{new_name}`], which tracks the fields that are set in a bitset.");
+        quote! {
+            #[doc = #doc]
+            #hidden
+            #[derive(Clone, Default)]
+            #vis struct #mask_name #generics #where_clause {
+                mask: [u64; #words],
+                #(#fields)*
+            }
+
+            impl #impl_generics From<#new_name #ty_generics> for #mask_name #ty_generics #where_clause {
+                fn from(v: #new_name #ty_generics) -> Self {
+                    let mut m = Self::default();
+                    #to_mask
+                    m
+                }
+            }
+
+            impl #impl_generics From<#mask_name #ty_generics> for #new_name #ty_generics #where_clause {
+                fn from(m: #mask_name #ty_generics) -> Self {
+                    Self {
+                        #(#from_mask)*
+                        #(#extra_fields: ::core::default::Default::default(),)*
+                    }
+                }
+            }
+
+            impl #impl_generics #mask_name #ty_generics #where_clause {
+                fn apply_to(self, t: &mut #orig_name #ty_generics) {
+                    #apply
+                }
+            }
+
+            impl #impl_generics ::optional_struct::Applyable<#orig_name #ty_generics> for #mask_name #ty_generics #where_clause {
+                fn apply_to(self, t: &mut #orig_name #ty_generics) {
+                    Self::apply_to(self, t)
+                }
+            }
+        }
+    }
+}
+
+impl OptionalFieldVisitor for GenerateMaskRepr {
+    fn visit(&mut self, global_options: &GlobalOptions, old_field: &Field, new_field: &mut Field, field_options: &FieldOptions) {
+        if !global_options.mask_repr {
+            return;
+        }
+        let ident = &field_options.new_field_ident;
+        let orig_ident = &field_options.field_ident;
+        let cfg_attr = &field_options.cfg_attribute;
+        if old_field.ident.is_none() {
+            panic!("'repr = \"mask\"' option can't be used on a tuple struct");
+        }
+
+        if !field_options.wrapping_behavior || field_options.new_type.is_some() {
+            // Stored as in the optional struct, and applied the same way
+            let ty = &new_field.ty;
+            self.fields.push(quote! { #cfg_attr #ident: #ty, });
+            self.to_mask.push(cfg_attr, quote! { m.#ident = v.#ident; });
+            self.from_mask.push(quote! { #cfg_attr #ident: m.#ident, });
+            if !field_options.read_only {
+                let setter = GenerateApplyFnVisitor::get_incremental_setter_concrete(field_options, field_options.is_base_opt);
+                self.apply.push(cfg_attr, quote! { { #setter } });
+            }
+            return;
+        }
+
+        let index = self.masked_count;
+        self.masked_count += 1;
+        let word = index / 64;
+        let bit = proc_macro2::Literal::u64_suffixed(1 << (index % 64));
+        let value_ty = field_options.value_type(old_field);
+        let field = field_options.unwrap(quote! { v.#ident });
+        let wrapped = field_options.wrap(quote! { m.#ident });
+        self.fields.push(quote! { #cfg_attr #ident: #value_ty, });
+        self.to_mask.push(cfg_attr, quote! {
+            if let Some(value) = #field {
+                m.#ident = value;
+                m.mask[#word] |= #bit;
+            }
+        });
+        self.from_mask.push(quote! {
+            #cfg_attr
+            #ident: if m.mask[#word] & #bit != 0 { #wrapped } else { ::core::default::Default::default() },
+        });
+        if !field_options.read_only {
+            let value = field_options.merge_value(quote! { t.#orig_ident }, field_options.convert_from_patch(quote! { self.#ident }));
+            self.apply.push(cfg_attr, quote! {
+                if self.mask[#word] & #bit != 0 {
+                    t.#orig_ident = #value;
+                }
+            });
+        }
+    }
+}
+
+fn to_snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i != 0 {
+                snake.push('_');
+            }
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+struct GenerateConstructionMacro {
+    acc: Vec<TokenStream>,
+}
+
+impl GenerateConstructionMacro {
+    fn new() -> Self {
+        GenerateConstructionMacro {
+            acc: vec![],
+        }
+    }
+
+    fn get_implementation(self, new: &DeriveInput) -> TokenStream {
+        let new_name = &new.ident;
+        let macro_name = format_ident!("{}", to_snake_case(&new_name.to_string()));
+        let inferred_generics = new.generics.params.iter().map(|p| match p {
+            syn::GenericParam::Lifetime(_) => quote! { '_ },
+            _ => quote! { _ },
+        });
+        let acc = self.acc;
+        quote! {
+            #[allow(unused_macros)]
+            macro_rules! #macro_name {
+                #(#acc)*
+                ($($field:tt : $value:expr),* $(,)?) => {{
+                    #[allow(unused_mut)]
+                    let mut patch: #new_name<#(#inferred_generics),*> = ::core::default::Default::default();
+                    $( #macro_name!(@set patch, $field, $value); )*
+                    patch
+                }};
+            }
+        }
+    }
+}
+
+impl OptionalFieldVisitor for GenerateConstructionMacro {
+    fn visit(&mut self, _global_options: &GlobalOptions, _old_field: &Field, _new_field: &mut Field, field_options: &FieldOptions) {
+        let ident = &field_options.new_field_ident;
+
+        let is_wrapped = field_options.wrapping_behavior;
+        let is_nested = field_options.new_type.is_some();
+        let is_base_opt = field_options.is_base_opt;
+        let value = if is_wrapped || (is_base_opt && !is_nested) {
+            field_options.wrap(quote! { ::optional_struct::__private::IntoField::into_field($value) })
+        } else {
+            quote! { ::optional_struct::__private::IntoField::into_field($value) }
+        };
+
+        // Fields removed by a cfg attribute are simply missing from the struct
+        self.acc.push(quote! {
+            (@set $patch:ident, #ident, $value:expr) => {
+                $patch.#ident = #value;
+            };
+        });
+    }
+}
+
+fn generic_arguments(generics: &Generics) -> Vec<TokenStream> {
+    generics
+        .params
+        .iter()
+        .map(|p| match p {
+            syn::GenericParam::Type(t) => {
+                let ident = &t.ident;
+                quote! { #ident }
+            }
+            syn::GenericParam::Lifetime(l) => {
+                let lifetime = &l.lifetime;
+                quote! { #lifetime }
+            }
+            syn::GenericParam::Const(c) => {
+                let ident = &c.ident;
+                quote! { #ident }
+            }
+        })
+        .collect()
+}
+
+struct BuilderSetter {
+    cfg_attribute: Option<Attribute>,
+    method: Ident,
+    value_ty: TokenStream,
+    assign: TokenStream,
+    required: bool,
+}
+
+struct GenerateBuilder {
+    setters: Vec<BuilderSetter>,
+}
+
+impl GenerateBuilder {
+    fn new() -> Self {
+        GenerateBuilder {
+            setters: vec![],
+        }
+    }
+
+    fn get_implementation(self, global_options: &GlobalOptions, orig: &DeriveInput, new: &DeriveInput) -> TokenStream {
+        let typestate = match global_options.builder {
+            Some(typestate) => typestate,
+            None => return quote! {},
+        };
+
+        let (impl_generics, ty_generics, where_clause) = orig.generics.split_for_impl();
+        let vis = &orig.vis;
+        let orig_name = &orig.ident;
+        let new_name = &new.ident;
+        let builder_name = format_ident!("{}Builder", orig.ident);
+        let hidden = global_options.hidden_attribute();
+        let must_use = global_options.must_use_attribute();
+        let params = orig.generics.params.iter().collect::<Vec<_>>();
+        let args = generic_arguments(&orig.generics);
+
+        // Without typestate, every field is optional when building the patch
+        let states = self
+            .setters
+            .iter()
+            .filter(|s| typestate && s.required)
+            .enumerate()
+            .map(|(i, _)| format_ident!("S{i}"))
+            .collect::<Vec<_>>();
+        let mut state_index = 0;
+        let mut setters = vec![];
+        for setter in &self.setters {
+            let BuilderSetter { cfg_attribute, method, value_ty, assign, required } = setter;
+            let setter = if typestate && *required {
+                let set_states = states
+                    .iter()
+                    .enumerate()
+                    .map(|(i, s)| if i == state_index { quote! { ::optional_struct::typestate::Set } } else { quote! { #s } })
+                    .collect::<Vec<_>>();
+                state_index += 1;
+                quote! {
+                    #must_use
+                    fn #method(self, value: #value_ty) -> #builder_name<#(#args,)* #(#set_states,)*> {
+                        let mut patch = self.patch;
+                        #assign;
+                        #builder_name { patch, state: core::marker::PhantomData }
+                    }
+                }
+            } else {
+                quote! {
+                    #must_use
+                    fn #method(mut self, value: #value_ty) -> Self {
+                        let patch = &mut self.patch;
+                        #assign;
+                        self
+                    }
+                }
+            };
+            setters.push(quote! {
+                #cfg_attribute
+                #setter
+            });
+        }
+
+        let unset_states = states.iter().map(|_| quote! { ::optional_struct::typestate::Unset });
+        let set_states = states.iter().map(|_| quote! { ::optional_struct::typestate::Set });
+        quote! {
+            #hidden
+            #vis struct #builder_name<#(#params,)* #(#states,)*> #where_clause {
+                patch: #new_name #ty_generics,
+                state: core::marker::PhantomData<(#(#states,)*)>,
+            }
+
+            impl #impl_generics #orig_name #ty_generics #where_clause {
+                #must_use
+                fn builder() -> #builder_name<#(#args,)* #(#unset_states,)*> where #new_name #ty_generics: Default {
+                    #builder_name {
+                        patch: Default::default(),
+                        state: core::marker::PhantomData,
+                    }
+                }
+            }
+
+            impl<#(#params,)* #(#states,)*> #builder_name<#(#args,)* #(#states,)*> #where_clause {
+                #(#setters)*
+            }
+
+            impl #impl_generics #builder_name<#(#args,)* #(#set_states,)*> #where_clause {
+                fn build(self) -> Result<#orig_name #ty_generics, #new_name #ty_generics> {
+                    self.patch.try_into()
+                }
+            }
+        }
+    }
+}
+
+impl OptionalFieldVisitor for GenerateBuilder {
+    fn visit(&mut self, global_options: &GlobalOptions, old_field: &Field, new_field: &mut Field, field_options: &FieldOptions) {
+        if global_options.builder.is_none() { return; }
+
+        let ident = &field_options.new_field_ident;
+        let method = match &new_field.ident {
+            Some(ident) => ident.clone(),
+            None => format_ident!("field_{}", ident.to_string()),
+        };
+
+        let is_wrapped = field_options.wrapping_behavior;
+        // Nested fields are given as patches
+        let value_ty = field_options.value_type(old_field);
+        let assign = if is_wrapped {
+            let wrapped = field_options.wrap(quote! { value });
+            quote! { patch.#ident = #wrapped }
+        } else {
+            quote! { patch.#ident = value }
+        };
+
+        self.setters.push(BuilderSetter {
+            // A field which may not exist can't be required by the builder
+            required: is_wrapped && !field_options.is_base_opt && field_options.cfg_attribute.is_none(),
+            cfg_attribute: field_options.cfg_attribute.clone(),
+            method,
+            value_ty,
+            assign,
+        });
+    }
+}
+
+// Conversions with struct-patch's generated types, whose fields are always `Option<T>`
+struct GenerateStructPatchConversions {
+    to_patch_acc: Vec<TokenStream>,
+    from_patch_acc: Vec<TokenStream>,
+}
+
+impl GenerateStructPatchConversions {
+    fn new() -> Self {
+        GenerateStructPatchConversions {
+            to_patch_acc: vec![],
+            from_patch_acc: vec![],
+        }
+    }
+
+    fn get_implementation(self, global_options: &GlobalOptions, derive_input: &DeriveInput, new: &DeriveInput) -> TokenStream {
+        let patch_name = match &global_options.struct_patch {
+            Some(patch_name) => patch_name,
+            None => return quote! {},
+        };
+        let (impl_generics, ty_generics, where_clause) = derive_input.generics.split_for_impl();
+        let new_name = &new.ident;
+        let hidden = global_options.hidden_attribute();
+        let to_patch_acc = self.to_patch_acc;
+        let from_patch_acc = self.from_patch_acc;
+        let extra_fields = global_options.extra_fields.iter().map(|f| &f.ident);
+
+        quote! {
+            #hidden
+            impl #impl_generics From<#new_name #ty_generics> for #patch_name #ty_generics #where_clause {
+                fn from(v: #new_name #ty_generics) -> Self {
+                    Self {
+                        #(#to_patch_acc)*
+                    }
+                }
+            }
+
+            impl #impl_generics From<#patch_name #ty_generics> for #new_name #ty_generics #where_clause {
+                fn from(v: #patch_name #ty_generics) -> Self {
+                    Self {
+                        #(#from_patch_acc)*
+                        #(#extra_fields: Default::default(),)*
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl OptionalFieldVisitor for GenerateStructPatchConversions {
+    fn visit(&mut self, global_options: &GlobalOptions, old_field: &Field, _new_field: &mut Field, field_options: &FieldOptions) {
+        if global_options.struct_patch.is_none() { return; }
+
+        let ident = &field_options.field_ident;
+        let new_ident = &field_options.new_field_ident;
+        let cfg_attr = &field_options.cfg_attribute;
+
+        let is_wrapped = field_options.wrapping_behavior;
+        let is_nested = field_options.new_type.is_some();
+        let is_base_opt = field_options.is_base_opt;
+        // An explicit `Some(None)` (i.e. "clear the value") has no equivalent in the optional
+        // struct, and struct-patch has no way to leave a non-wrapped field untouched.
+        let (to_patch, from_patch) = match (is_base_opt, is_wrapped, is_nested) {
+            (_, true, false) if field_options.convert.is_some() || field_options.wrapper.is_some() => {
+                let field = field_options.unwrap(quote! { v.#new_ident });
+                let to_patch = match field_options.convert {
+                    Some(_) => {
+                        let value = field_options.convert_from_patch(quote! { value });
+                        quote! { #field.map(|value| #value) }
+                    }
+                    None => field,
+                };
+                let value = field_options.wrap(field_options.convert_to_patch(quote! { value }));
+                let from_patch = match field_options.wrapper {
+                    Some(_) => quote! { v.#ident.map(|value| #value).unwrap_or_default() },
+                    None => quote! { v.#ident.map(|value| #value) },
+                };
+                (to_patch, from_patch)
+            }
+            (_, true, false) => (quote! { v.#new_ident }, quote! { v.#ident }),
+            (_, true, true) => (quote! { v.#new_ident.map(Into::into) }, quote! { v.#ident.map(Into::into) }),
+            (true, false, false) if field_options.convert.is_some() => {
+                let to_patch = field_options.convert_from_patch(quote! { v.#new_ident });
+                let from_patch = field_options.convert_to_patch(quote! { v.#ident.flatten() });
+                (quote! { Some(#to_patch).filter(Option::is_some) }, from_patch)
+            }
+            (true, false, false) => (quote! { v.#new_ident.map(Some) }, quote! { v.#ident.flatten() }),
+            (_, false, true) => (quote! { Some(v.#new_ident.into()) }, quote! { v.#ident.map(Into::into).unwrap_or_default() }),
+            // Only an array whose elements are all set has an equivalent in the patch
+            (false, false, false) if field_options.elementwise => {
+                let old_ty = &old_field.ty;
+                (
+                    quote! {
+                        <#old_ty>::try_from(v.#new_ident.into_iter().flatten().collect::<::optional_struct::__private::Vec<_>>()).ok()
+                    },
+                    quote! { v.#ident.map_or_else(Default::default, |value| value.map(Some)) },
+                )
+            }
+            (false, false, false) => {
+                let to_patch = field_options.convert_from_patch(quote! { v.#new_ident });
+                let from_patch = field_options.convert_to_patch(quote! { v.#ident.unwrap_or_default() });
+                (quote! { Some(#to_patch) }, from_patch)
+            }
+        };
+
+        self.to_patch_acc.push(quote! {
+            #cfg_attr
+            #ident: #to_patch,
+        });
+
+        self.from_patch_acc.push(quote! {
+            #cfg_attr
+            #new_ident: #from_patch,
+        });
+    }
+}
+
+// Follows merge's convention for `Option`s: values already set in `self` take precedence.
+fn get_merge_impl(global_options: &GlobalOptions, derive_input: &DeriveInput, new: &DeriveInput) -> TokenStream {
+    if !global_options.merge {
+        return quote! {};
+    }
+
+    let (impl_generics, ty_generics, _) = derive_input.generics.split_for_impl();
+    let new_name = &new.ident;
+    let where_clause = where_clause_with_bounds(&derive_input.generics, std::iter::once(quote! { #new_name #ty_generics: Default }));
+    quote! {
+        impl #impl_generics ::merge::Merge for #new_name #ty_generics #where_clause {
+            fn merge(&mut self, mut other: Self) {
+                core::mem::take(self).apply_to_opt(&mut other);
+                *self = other;
+            }
+        }
+    }
+}
+
+// Applies a copy of the patch to each target, only generated when the optional struct derives `Clone`
+fn get_apply_to_many_impl(derive_input: &DeriveInput, new: &DeriveInput) -> TokenStream {
+    let (impl_generics, ty_generics, where_clause) = derive_input.generics.split_for_impl();
+    let orig_name = &derive_input.ident;
+    let new_name = &new.ident;
+    quote! {
+        impl #impl_generics #new_name #ty_generics #where_clause {
+            fn apply_to_many<'__a>(&self, targets: impl IntoIterator<Item = &'__a mut #orig_name #ty_generics>)
+            where
+                Self: Clone,
+                #orig_name #ty_generics: '__a,
+            {
+                for t in targets {
+                    self.clone().apply_to(t);
+                }
+            }
+        }
+    }
+}
+
+// The lowest priority patches are applied first, so that the first one setting a field wins
+fn get_resolve_impl(derive_input: &DeriveInput, new: &DeriveInput) -> TokenStream {
+    let (impl_generics, ty_generics, where_clause) = derive_input.generics.split_for_impl();
+    let orig_name = &derive_input.ident;
+    let new_name = &new.ident;
+    quote! {
+        impl #impl_generics #new_name #ty_generics #where_clause {
+            fn resolve(&self, fallbacks: &[&Self], base: &#orig_name #ty_generics) -> #orig_name #ty_generics
+            where
+                Self: Clone,
+                #orig_name #ty_generics: Clone,
+            {
+                let mut t = base.clone();
+                for patch in fallbacks.iter().rev() {
+                    (*patch).clone().apply_to(&mut t);
+                }
+                self.clone().apply_to(&mut t);
+                t
+            }
+        }
+    }
+}
+
+// The changes are those `apply_report` gives for a copy of `before`, all of them sharing the
+// same timestamp
+fn get_audit_impl(derive_input: &DeriveInput, new: &DeriveInput) -> TokenStream {
+    let (impl_generics, ty_generics, where_clause) = derive_input.generics.split_for_impl();
+    let orig_name = &derive_input.ident;
+    let new_name = &new.ident;
+    quote! {
+        impl #impl_generics #new_name #ty_generics #where_clause {
+            fn to_audit_rows(&self, before: &#orig_name #ty_generics, entity_id: &str) -> ::optional_struct::__private::Vec<::optional_struct::AuditRow>
+            where
+                Self: Clone,
+                #orig_name #ty_generics: Clone,
+            {
+                let timestamp = ::std::time::SystemTime::now()
+                    .duration_since(::std::time::UNIX_EPOCH)
+                    .unwrap_or_default();
+                let mut after = before.clone();
+                self.clone()
+                    .apply_report(&mut after)
+                    .into_iter()
+                    .map(|change| ::optional_struct::AuditRow::new(entity_id, change, timestamp))
+                    .collect()
+            }
+        }
+    }
+}
+
+// Layers are applied in order onto an empty patch, the last one setting a field winning
+fn get_try_from_layers_impl(derive_input: &DeriveInput, new: &DeriveInput) -> TokenStream {
+    let (impl_generics, ty_generics, where_clause) = derive_input.generics.split_for_impl();
+    let orig_name = &derive_input.ident;
+    let new_name = &new.ident;
+    quote! {
+        impl #impl_generics #orig_name #ty_generics #where_clause {
+            fn try_from_layers(layers: impl IntoIterator<Item = #new_name #ty_generics>) -> Result<Self, ::optional_struct::MissingFieldsError>
+            where
+                #new_name #ty_generics: Default,
+            {
+                let mut merged = #new_name::default();
+                for patch in layers {
+                    patch.apply_to_opt(&mut merged);
+                }
+                Self::try_from(merged).map_err(|merged| ::optional_struct::MissingFieldsError { fields: merged.missing_fields() })
+            }
+        }
+    }
+}
+
+// Profiles are resolved by applying copies of their patches, which are kept for the next time
+fn get_profiles_impl(global_options: &GlobalOptions, derive_input: &DeriveInput, new: &DeriveInput, resolvable: bool) -> TokenStream {
+    let (impl_generics, ty_generics, where_clause) = derive_input.generics.split_for_impl();
+    let generics = &derive_input.generics;
+    let vis = &derive_input.vis;
+    let orig_name = &derive_input.ident;
+    let new_name = &new.ident;
+    let profiles_name = format_ident!("{}Profiles", derive_input.ident);
+    let hidden = global_options.hidden_attribute();
+    let must_use = global_options.must_use_attribute();
+    let resolve = emit_if(resolvable, || quote! {
+        // Applies the profiles of `chain` in order onto `base`, nothing being applied if one of
+        // them is unknown
+        fn resolve(&self, chain: &[&str], base: #orig_name #ty_generics) -> Result<#orig_name #ty_generics, ::optional_struct::UnknownProfile>
+        where
+            #new_name #ty_generics: Clone,
+        {
+            let patches = chain
+                .iter()
+                .map(|name| self.profiles.get(*name).ok_or_else(|| ::optional_struct::UnknownProfile(::optional_struct::__private::String::from(*name))))
+                .collect::<Result<::optional_struct::__private::Vec<_>, _>>()?;
+            let mut t = base;
+            for patch in patches {
+                patch.clone().apply_to(&mut t);
+            }
+            Ok(t)
+        }
+    });
+    quote! {
+        #hidden
+        #vis struct #profiles_name #generics #where_clause {
+            profiles: ::optional_struct::__private::BTreeMap<::optional_struct::__private::String, #new_name #ty_generics>,
+        }
+
+        impl #impl_generics #profiles_name #ty_generics #where_clause {
+            #must_use
+            fn new() -> Self {
+                Self { profiles: ::optional_struct::__private::BTreeMap::new() }
+            }
+
+            // Gives back the profile previously stored under this name
+            fn insert(&mut self, name: impl Into<::optional_struct::__private::String>, patch: #new_name #ty_generics) -> Option<#new_name #ty_generics> {
+                self.profiles.insert(name.into(), patch)
+            }
+
+            #must_use
+            fn get(&self, name: &str) -> Option<&#new_name #ty_generics> {
+                self.profiles.get(name)
+            }
+
+            #resolve
+        }
+
+        impl #impl_generics Default for #profiles_name #ty_generics #where_clause {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl #impl_generics From<::optional_struct::__private::BTreeMap<::optional_struct::__private::String, #new_name #ty_generics>> for #profiles_name #ty_generics #where_clause {
+            fn from(profiles: ::optional_struct::__private::BTreeMap<::optional_struct::__private::String, #new_name #ty_generics>) -> Self {
+                Self { profiles }
+            }
+        }
+    }
+}
+
+// Like `try_from_layers`, the layers being named so that errors tell where values were looked for
+fn get_finalize_impl(derive_input: &DeriveInput, new: &DeriveInput) -> TokenStream {
+    let (impl_generics, ty_generics, where_clause) = derive_input.generics.split_for_impl();
+    let orig_name = &derive_input.ident;
+    let new_name = &new.ident;
+    quote! {
+        impl #impl_generics #orig_name #ty_generics #where_clause {
+            fn finalize(layers: &[(&str, #new_name #ty_generics)]) -> Result<Self, ::optional_struct::FinalizeError>
+            where
+                #new_name #ty_generics: Default + Clone,
+            {
+                let mut merged = #new_name::default();
+                for (_, patch) in layers {
+                    patch.clone().apply_to_opt(&mut merged);
+                }
+                Self::try_from(merged).map_err(|merged| ::optional_struct::FinalizeError {
+                    fields: merged.missing_field_paths(),
+                    layers: layers.iter().map(|(name, _)| ::optional_struct::__private::String::from(*name)).collect(),
+                })
+            }
+        }
+    }
+}
+
+// The body is read by the framework's own JSON extractor, so that requests are rejected the same
+// way (wrong content type, malformed JSON, unknown fields with `deny_unknown_fields`...)
+fn get_axum_impl(derive_input: &DeriveInput, new: &DeriveInput) -> TokenStream {
+    let mut generics = derive_input.generics.clone();
+    generics.params.push(parse_quote! { __S });
+    let (impl_generics, _, _) = generics.split_for_impl();
+    let (_, ty_generics, _) = derive_input.generics.split_for_impl();
+    let where_clause = where_clause_with_bounds(&derive_input.generics, [
+        quote! { __S: Send + Sync },
+        quote! { Self: ::optional_struct::__private::DeserializeOwned },
+    ].into_iter());
+    let new_name = &new.ident;
+    quote! {
+        impl #impl_generics ::axum::extract::FromRequest<__S> for #new_name #ty_generics #where_clause {
+            type Rejection = ::axum::extract::rejection::JsonRejection;
+
+            async fn from_request(request: ::axum::extract::Request, state: &__S) -> Result<Self, Self::Rejection> {
+                let ::axum::Json(patch) = <::axum::Json<Self> as ::axum::extract::FromRequest<__S>>::from_request(request, state).await?;
+                Ok(patch)
+            }
+        }
+    }
+}
+
+fn get_actix_web_impl(derive_input: &DeriveInput, new: &DeriveInput) -> TokenStream {
+    let (impl_generics, ty_generics, _) = derive_input.generics.split_for_impl();
+    let where_clause = where_clause_with_bounds(&derive_input.generics, [
+        quote! { Self: ::optional_struct::__private::DeserializeOwned + 'static },
+    ].into_iter());
+    let new_name = &new.ident;
+    quote! {
+        impl #impl_generics ::actix_web::FromRequest for #new_name #ty_generics #where_clause {
+            type Error = ::actix_web::Error;
+            type Future = ::core::pin::Pin<::optional_struct::__private::Box<dyn ::core::future::Future<Output = Result<Self, Self::Error>>>>;
+
+            fn from_request(request: &::actix_web::HttpRequest, payload: &mut ::actix_web::dev::Payload) -> Self::Future {
+                let patch = <::actix_web::web::Json<Self> as ::actix_web::FromRequest>::from_request(request, payload);
+                ::optional_struct::__private::Box::pin(async move { patch.await.map(::actix_web::web::Json::into_inner) })
+            }
+        }
+    }
+}
+
+// Fields are generated one after the other, starting from an empty patch
+struct GenerateProptestImpl {
+    steps: Vec<TokenStream>,
+    bounds: Vec<TokenStream>,
+}
+
+impl GenerateProptestImpl {
+    fn new() -> Self {
+        GenerateProptestImpl {
+            steps: vec![],
+            bounds: vec![],
+        }
+    }
+
+    fn step(&mut self, cfg_attr: &Option<Attribute>, ident: impl ToTokens, ty: &Type) {
+        self.bounds.push(quote! { #ty: ::proptest::arbitrary::Arbitrary });
+        self.steps.push(quote! {
+            #cfg_attr
+            let strategy = (strategy, ::proptest::arbitrary::any::<#ty>()).prop_map(|(mut patch, value)| {
+                patch.#ident = value;
+                patch
+            });
+        });
+    }
+
+    fn get_implementation(mut self, global_options: &GlobalOptions, orig: &DeriveInput, new: &DeriveInput) -> TokenStream {
+        if !global_options.proptest {
+            return quote! {};
+        }
+
+        for field in &global_options.extra_fields {
+            self.step(&None, &field.ident, &field.ty);
+        }
+        let (impl_generics, ty_generics, _) = orig.generics.split_for_impl();
+        let new_name = &new.ident;
+        let self_bound = quote! { #new_name #ty_generics: Default + core::fmt::Debug + 'static };
+        let where_clause = where_clause_with_bounds(&orig.generics, std::iter::once(self_bound).chain(self.bounds));
+        let steps = self.steps;
+        quote! {
+            impl #impl_generics ::proptest::arbitrary::Arbitrary for #new_name #ty_generics #where_clause {
+                type Parameters = ();
+                type Strategy = ::proptest::strategy::BoxedStrategy<Self>;
+
+                fn arbitrary_with(_: ()) -> Self::Strategy {
+                    use ::proptest::strategy::Strategy as _;
+                    let strategy = ::proptest::strategy::LazyJust::new(<Self as Default>::default);
+                    #(#steps)*
+                    strategy.boxed()
+                }
+            }
+        }
+    }
+}
+
+impl OptionalFieldVisitor for GenerateProptestImpl {
+    fn visit(&mut self, global_options: &GlobalOptions, _old_field: &Field, new_field: &mut Field, field_options: &FieldOptions) {
+        // Boxed patches may be recursive, and are left empty
+        if !global_options.proptest || field_options.boxed {
+            return;
+        }
+        self.step(&field_options.cfg_attribute, &field_options.new_field_ident, &new_field.ty);
+    }
+}
+
+struct SetNewFieldVisibilityVisitor;
+
+impl OptionalFieldVisitor for SetNewFieldVisibilityVisitor {
+    fn visit(&mut self, global_options: &GlobalOptions, _old_field: &Field, new_field: &mut Field, _field_options: &FieldOptions) {
+        if global_options.make_fields_public {
+            new_field.vis = Visibility::Public(syn::token::Pub(new_field.vis.span()))
+        }
+    }
+}
+
+struct SetNewFieldNameVisitor;
+
+impl OptionalFieldVisitor for SetNewFieldNameVisitor {
+    fn visit(&mut self, _global_options: &GlobalOptions, _old_field: &Field, new_field: &mut Field, field_options: &FieldOptions) {
+        if new_field.ident.is_some() {
+            let new_ident = &field_options.new_field_ident;
+            new_field.ident = Some(parse_quote! { #new_ident });
+        }
+    }
+}
+
+struct SetNewFieldTypeVisitor;
+
+impl OptionalFieldVisitor for SetNewFieldTypeVisitor {
+    fn visit(&mut self, _global_options: &GlobalOptions, old_field: &Field, new_field: &mut Field, field_options: &FieldOptions) {
+        let mut new_type = field_options.value_type(old_field);
+
+        if field_options.wrapping_behavior {
+            new_type = field_options.wrapped_type(new_type);
+        };
+        new_field.ty = Type::Verbatim(new_type);
+    }
+}
+
+// Without the `serde_rename` option, the generated fields use their own name as serde key instead
+// of the one the original field was renamed to
+struct RemoveSerdeRenameVisitor;
+
+impl OptionalFieldVisitor for RemoveSerdeRenameVisitor {
+    fn visit(&mut self, global_options: &GlobalOptions, _old_field: &Field, new_field: &mut Field, _field_options: &FieldOptions) {
+        if !global_options.serde_rename {
+            remove_serde_renames(&mut new_field.attrs);
+        }
+    }
+}
+
+// Removes the `rename`, `rename_all` and `alias` arguments of `#[serde]` attributes, and the
+// attributes left empty
+fn remove_serde_renames(attrs: &mut Vec<Attribute>) {
+    attrs.retain_mut(|attribute| {
+        if !attribute.path().is_ident("serde") {
+            return true;
+        }
+        let Ok(metas) = attribute.parse_args_with(Punctuated::<syn::Meta, Token![,]>::parse_terminated) else {
+            return true;
+        };
+        let kept = metas
+            .into_iter()
+            .filter(|m| !["rename", "rename_all", "alias"].iter().any(|r| m.path().is_ident(r)))
             .collect::<Vec<_>>();
+        *attribute = parse_quote! { #[serde(#(#kept),*)] };
+        !kept.is_empty()
+    });
+}
+
+struct AddSerdeSkipAttribute;
+
+impl OptionalFieldVisitor for AddSerdeSkipAttribute {
+    fn visit(&mut self, _global_options: &GlobalOptions, _old_field: &Field, new_field: &mut Field, field_options: &FieldOptions) {
+        if !field_options.serde_skip { return; }
+
+        let attribute : Attribute = match field_options.wrapper {
+            Some(_) => parse_quote!{ #[serde(skip_serializing_if = "::optional_struct::__private::is_unset")] },
+            None => parse_quote!{ #[serde(skip_serializing_if = "Option::is_none")] },
+        };
+        new_field.attrs.push(attribute);
+    }
+}
+
+// Deserializing a `null` as `Some(None)`, the field being unset only when it is missing
+struct AddSerdeExplicitNullAttribute;
+
+impl OptionalFieldVisitor for AddSerdeExplicitNullAttribute {
+    fn visit(&mut self, global_options: &GlobalOptions, _old_field: &Field, new_field: &mut Field, field_options: &FieldOptions) {
+        if !global_options.explicit_null || !field_options.is_double_wrapped() || field_options.wrapper.is_some() || field_options.option_like {
+            return;
+        }
 
-        // Don't forget to reverse so the indices are removed without being shifted!
-        for i in indexes_to_remove.into_iter().rev() {
-            old_field.attrs.swap_remove(i);
-            new_field.attrs.swap_remove(i);
+        let attribute : Attribute = parse_quote!{ #[serde(default, deserialize_with = "::optional_struct::__private::deserialize_some")] };
+        new_field.attrs.push(attribute);
+    }
+}
+
+// An unset field is a missing property rather than a `null` one, which only clears double
+// wrapped fields
+struct AddSchemarsAttribute;
+
+impl OptionalFieldVisitor for AddSchemarsAttribute {
+    fn visit(&mut self, global_options: &GlobalOptions, old_field: &Field, new_field: &mut Field, field_options: &FieldOptions) {
+        let is_plain = field_options.wrapper.is_none()
+            && field_options.new_type.is_none()
+            && !field_options.elementwise
+            && !field_options.option_like;
+        if !global_options.schemars || !is_plain {
+            return;
         }
+
+        let value_ty = match (field_options.wrapping_behavior, field_options.is_base_opt) {
+            (true, false) => field_options.value_type(old_field),
+            (false, true) if field_options.convert.is_none() => {
+                let inner_ty = first_type_argument(&old_field.ty).unwrap_or(&old_field.ty);
+                quote! { #inner_ty }
+            }
+            _ => return,
+        };
+        let with = value_ty.to_string();
+        let attribute : Attribute = parse_quote!{ #[schemars(with = #with, default, skip_serializing_if = "Option::is_none")] };
+        new_field.attrs.push(attribute);
+    }
+}
+
+struct AddPyo3AccessorsAttribute;
+
+impl OptionalFieldVisitor for AddPyo3AccessorsAttribute {
+    fn visit(&mut self, global_options: &GlobalOptions, _old_field: &Field, new_field: &mut Field, _field_options: &FieldOptions) {
+        // pyo3 can only generate getters and setters for named fields
+        if !global_options.is_pyclass || new_field.ident.is_none() { return; }
+
+        let attribute : Attribute = parse_quote!{ #[pyo3(get, set)] };
+        new_field.attrs.push(attribute);
+    }
+}
+
+struct AddFieldDocumentation {
+    orig_name: Ident,
+}
+
+impl OptionalFieldVisitor for AddFieldDocumentation {
+    fn visit(&mut self, global_options: &GlobalOptions, _old_field: &Field, new_field: &mut Field, field_options: &FieldOptions) {
+        if !global_options.doc { return; }
+
+        let orig_name = &self.orig_name;
+        let ident = &field_options.field_ident;
+        let doc = match &new_field.ident {
+            Some(_) => format!("Optional mirror of [`{orig_name}::{ident}`]."),
+            None => format!("Optional mirror of field `{ident}` of [`{orig_name}`]."),
+        };
+        prepend_documentation(&mut new_field.attrs, &doc);
+    }
+}
+
+// The original documentation is kept, in its own paragraph
+fn prepend_documentation(attrs: &mut Vec<Attribute>, doc: &str) {
+    let has_documentation = attrs.iter().any(|a| a.path().is_ident("doc"));
+    if has_documentation {
+        attrs.insert(0, parse_quote! { #[doc = ""] });
+    }
+    attrs.insert(0, parse_quote! { #[doc = #doc] });
+}
+
+// https://github.com/rust-lang/rust/issues/65823 :(
+// Only `optional_*` helpers are removed, the other attributes are kept as they are
+fn strip_helper_attributes(attrs: &mut Vec<Attribute>) {
+    attrs.retain(|a| !HELPER_ATTRIBUTES.iter().any(|h| a.path().is_ident(h)));
+}
+
+// A unit struct simply has no field
+fn struct_fields(derive_input: &DeriveInput) -> syn::punctuated::Iter<'_, Field> {
+    match &derive_input.data {
+        Data::Struct(data_struct) => data_struct.fields.iter(),
+        _ => panic!("OptionalStruct only works for structs :)"),
     }
 }
 
@@ -365,82 +5271,475 @@ fn borrow_fields(derive_input: &mut DeriveInput) -> &mut Punctuated<Field, Comma
     match &mut data_struct.fields {
         Fields::Unnamed(f) => &mut f.unnamed,
         Fields::Named(f) => &mut f.named,
+        // Only the original struct can be a unit struct, and its fields are never borrowed
         Fields::Unit => unreachable!("A struct cannot have simply a unit field?"),
     }
 }
 
-fn visit_fields(visitors: &mut [&mut dyn OptionalFieldVisitor], global_options: &GlobalOptions, derive_input: &DeriveInput) -> (DeriveInput, DeriveInput) {
-    let mut new = derive_input.clone();
-    let mut orig = derive_input.clone();
-    let old_fields = borrow_fields(&mut orig);
-    let new_fields = borrow_fields(&mut new);
+// `#[optional_skip]` or `#[optional_skip(default = "expression")]`
+fn parse_skip_default(attribute: &Attribute) -> TokenStream {
+    let mut default = quote! { ::core::default::Default::default() };
+    if let syn::Meta::List(_) = attribute.meta {
+        attribute
+            .parse_nested_meta(|meta| {
+                if !meta.path.is_ident("default") {
+                    return Err(meta.error("expected 'default'"));
+                }
+                default = user_tokens(meta.value()?.parse::<syn::LitStr>()?.parse::<TokenStream>()?);
+                Ok(())
+            })
+            .unwrap_or_else(|e| panic!("Invalid '{SKIP_ATTRIBUTE}' attribute: {e}"));
+    }
+    default
+}
+
+fn parse_field_conversion(attribute: &Attribute) -> FieldConversion {
+    let mut ty = None;
+    let mut into = None;
+    let mut from = None;
+    attribute
+        .parse_nested_meta(|meta| {
+            let value = meta.value()?.parse::<syn::LitStr>()?;
+            if meta.path.is_ident("ty") {
+                ty = Some(value.parse::<Type>()?);
+            } else if meta.path.is_ident("into") {
+                into = Some(value.parse::<Path>()?);
+            } else if meta.path.is_ident("from") {
+                from = Some(value.parse::<Path>()?);
+            } else {
+                return Err(meta.error("expected 'ty', 'into' or 'from'"));
+            }
+            Ok(())
+        })
+        .unwrap_or_else(|e| panic!("Invalid '{CONVERT_ATTRIBUTE}' attribute: {e}"));
+
+    match (ty, into, from) {
+        (Some(ty), Some(into), Some(from)) => FieldConversion { ty, into, from },
+        _ => panic!("'{CONVERT_ATTRIBUTE}' attribute expects the stored type and both conversions, e.g. {CONVERT_ATTRIBUTE}(ty = \"String\", into = \"to_string\", from = \"from_string\")"),
+    }
+}
 
-    for (struct_index, (old_field, new_field)) in old_fields.iter_mut().zip(new_fields.iter_mut()).enumerate() {
-        let mut wrapping_behavior = !is_type_option(&old_field.ty) && global_options.default_wrapping_behavior;
+// Builds the optional struct field by field, the original one being left untouched
+fn visit_fields(visitors: &mut [&mut dyn OptionalFieldVisitor], global_options: &GlobalOptions, derive_input: &DeriveInput) -> DeriveInput {
+    let old_fields = struct_fields(derive_input);
+    let mut new_fields = Punctuated::<Field, Comma>::new();
+
+    for (struct_index, old_field) in old_fields.enumerate() {
+        // Explicit wrapping attributes win over the default, whatever their order
+        let mut wrap = false;
+        let mut skip_wrap = false;
         let mut cfg_attribute = None;
         let mut new_type = None;
+        // Patch type of an enum field, merged variant by variant
+        let mut enum_merge = None;
+        // Replaced as a whole, even if its type has an optional version
+        let mut atomic = false;
+        let mut replace_only = false;
+        let mut elementwise = false;
+        let mut secret = false;
+        let mut proto_name = None;
+        let mut column = None;
+        let mut redis_with = None;
+        let mut convert = None;
+        let mut transparent = false;
+        let mut set_if = None;
+        let mut wrapper = None;
+        let mut new_name = None;
+        let mut skip = None;
+        let mut merge_op = None;
+        let mut group = None;
+        let mut read_only = false;
+        let mut immutable = false;
         let mut serde_skip = false;
+        // Overrides the detection of `Option` types
+        let mut option_override = None;
         old_field.attrs
             .iter()
             .for_each(|a| {
                 if a.path().is_ident(RENAME_ATTRIBUTE) {
                     let args = a
-                        .parse_args()
-                        .expect(&format!("'{RENAME_ATTRIBUTE}' attribute expects one and only one argument (the new type to use)"));
+                        .parse_args::<Type>()
+                        .unwrap_or_else(|_| panic!("'{RENAME_ATTRIBUTE}' attribute expects one and only one argument (the new type to use)"));
                     new_type = Some(args);
-                    wrapping_behavior = false;
+                } else if a.path().is_ident(ENUM_MERGE_ATTRIBUTE) {
+                    let args = match &a.meta {
+                        syn::Meta::Path(_) => None,
+                        _ => Some(a.parse_args::<Type>().unwrap_or_else(|_| panic!("'{ENUM_MERGE_ATTRIBUTE}' attribute expects at most one argument (the optional enum to use)"))),
+                    };
+                    enum_merge = Some(args);
+                } else if a.path().is_ident(ATOMIC_ATTRIBUTE) {
+                    atomic = true;
+                } else if a.path().is_ident(REPLACE_ONLY_ATTRIBUTE) {
+                    replace_only = true;
+                } else if a.path().is_ident(ELEMENTWISE_ATTRIBUTE) {
+                    elementwise = true;
+                } else if a.path().is_ident(SECRET_ATTRIBUTE) {
+                    secret = true;
+                } else if a.path().is_ident(PROTO_NAME_ATTRIBUTE) {
+                    let args = a
+                        .parse_args::<Ident>()
+                        .unwrap_or_else(|_| panic!("'{PROTO_NAME_ATTRIBUTE}' attribute expects one and only one argument (the name of the field in the message)"));
+                    proto_name = Some(args);
+                } else if a.path().is_ident(COLUMN_ATTRIBUTE) {
+                    let args = a
+                        .parse_args::<Ident>()
+                        .unwrap_or_else(|_| panic!("'{COLUMN_ATTRIBUTE}' attribute expects one and only one argument (the name of the column)"));
+                    column = Some(args);
+                } else if a.path().is_ident(REDIS_WITH_ATTRIBUTE) {
+                    let args = a
+                        .parse_args::<Path>()
+                        .unwrap_or_else(|_| panic!("'{REDIS_WITH_ATTRIBUTE}' attribute expects one and only one argument (the function turning a value into a string)"));
+                    redis_with = Some(args);
                 } else if a.path().is_ident(SKIP_WRAP_ATTRIBUTE) {
-                    wrapping_behavior = false;
+                    skip_wrap = true;
                 } else if a.path().is_ident(WRAP_ATTRIBUTE) {
-                    wrapping_behavior = true;
+                    wrap = true;
+                } else if a.path().is_ident(WRAP_WITH_ATTRIBUTE) {
+                    let args = a
+                        .parse_args()
+                        .unwrap_or_else(|_| panic!("'{WRAP_WITH_ATTRIBUTE}' attribute expects one and only one argument (the wrapper type to use)"));
+                    wrapper = Some(args);
                 } else if a.path().is_ident(SERDE_SKIP_SERIALIZING_NONE) {
                     serde_skip = true;
+                } else if a.path().is_ident(MERGE_OP_ATTRIBUTE) {
+                    let args = a
+                        .parse_args::<Ident>()
+                        .unwrap_or_else(|_| panic!("'{MERGE_OP_ATTRIBUTE}' attribute expects one and only one argument (the operation to use)"));
+                    merge_op = Some(args);
+                } else if a.path().is_ident(GROUP_ATTRIBUTE) {
+                    let args = a
+                        .parse_args::<Ident>()
+                        .unwrap_or_else(|_| panic!("'{GROUP_ATTRIBUTE}' attribute expects one and only one argument (the name of the group)"));
+                    group = Some(args);
+                } else if a.path().is_ident(READ_ONLY_ATTRIBUTE) {
+                    read_only = true;
+                } else if a.path().is_ident(IMMUTABLE_ATTRIBUTE) {
+                    read_only = true;
+                    immutable = true;
+                } else if a.path().is_ident(NOT_OPTION_ATTRIBUTE) || a.path().is_ident(OPTION_LIKE_ATTRIBUTE) {
+                    let option_like = a.path().is_ident(OPTION_LIKE_ATTRIBUTE);
+                    if option_override.is_some_and(|o| o != option_like) {
+                        panic!("'{NOT_OPTION_ATTRIBUTE}' and '{OPTION_LIKE_ATTRIBUTE}' can't be used on the same field");
+                    }
+                    option_override = Some(option_like);
+                } else if a.path().is_ident(SKIP_ATTRIBUTE) {
+                    skip = Some(parse_skip_default(a));
+                } else if a.path().is_ident(FIELD_NAME_ATTRIBUTE) {
+                    let args = a
+                        .parse_args::<Ident>()
+                        .unwrap_or_else(|_| panic!("'{FIELD_NAME_ATTRIBUTE}' attribute expects one and only one argument (the new name of the field)"));
+                    new_name = Some(args);
+                } else if a.path().is_ident(SET_IF_ATTRIBUTE) {
+                    let args = a
+                        .parse_args::<TokenStream>()
+                        .unwrap_or_else(|_| panic!("'{SET_IF_ATTRIBUTE}' attribute expects a predicate, e.g. {SET_IF_ATTRIBUTE}(|s: &String| !s.is_empty())"));
+                    set_if = Some(user_tokens(args));
+                } else if a.path().is_ident(CONVERT_ATTRIBUTE) {
+                    if convert.is_some() {
+                        panic!("'{CONVERT_ATTRIBUTE}' and '{TRANSPARENT_ATTRIBUTE}' can't be used on the same field");
+                    }
+                    convert = Some(parse_field_conversion(a));
+                } else if a.path().is_ident(TRANSPARENT_ATTRIBUTE) {
+                    if convert.is_some() {
+                        panic!("'{CONVERT_ATTRIBUTE}' and '{TRANSPARENT_ATTRIBUTE}' can't be used on the same field");
+                    }
+                    // A newtype is stored as its inner type, converted with `From`/`Into` both ways
+                    let ty = a
+                        .parse_args::<Type>()
+                        .unwrap_or_else(|_| panic!("'{TRANSPARENT_ATTRIBUTE}' attribute expects one and only one argument (the inner type of the newtype)"));
+                    transparent = true;
+                    convert = Some(FieldConversion {
+                        ty,
+                        into: parse_quote! { ::core::convert::Into::into },
+                        from: parse_quote! { ::core::convert::Into::into },
+                    });
                 } else if a.path().is_ident(CFG_ATTRIBUTE) {
                     cfg_attribute = Some(a.clone());
                 }
             });
+        let is_base_opt = option_override.unwrap_or_else(|| is_type_option(&old_field.ty));
+        if atomic {
+            if new_type.is_some() || enum_merge.is_some() || skip_wrap {
+                panic!("'{ATOMIC_ATTRIBUTE}' can't be used on a field with '{RENAME_ATTRIBUTE}', '{ENUM_MERGE_ATTRIBUTE}' or '{SKIP_WRAP_ATTRIBUTE}'");
+            }
+            wrap = true;
+        }
+        if replace_only {
+            if new_type.is_some() || enum_merge.is_some() || skip_wrap || merge_op.is_some() {
+                panic!("'{REPLACE_ONLY_ATTRIBUTE}' can't be used on a field with '{RENAME_ATTRIBUTE}', '{ENUM_MERGE_ATTRIBUTE}', '{SKIP_WRAP_ATTRIBUTE}' or '{MERGE_OP_ATTRIBUTE}'");
+            }
+            wrap = true;
+        }
+        if transparent && is_base_opt {
+            panic!("'{TRANSPARENT_ATTRIBUTE}' can't be used on an Option field");
+        }
+        // The patch of the enum is always optional, so that `None` leaves the field untouched
+        let is_enum_merge = enum_merge.is_some();
+        if let Some(patch) = enum_merge {
+            if new_type.is_some() || skip_wrap || wrapper.is_some() {
+                panic!("'{ENUM_MERGE_ATTRIBUTE}' can't be used on a field with '{RENAME_ATTRIBUTE}', '{SKIP_WRAP_ATTRIBUTE}' or '{WRAP_WITH_ATTRIBUTE}'");
+            }
+            new_type = Some(patch.unwrap_or_else(|| enum_patch_type(&old_field.ty, is_base_opt)));
+            wrap = true;
+        }
+        if elementwise {
+            if !matches!(old_field.ty, Type::Array(_)) {
+                panic!("'{ELEMENTWISE_ATTRIBUTE}' can only be used on array fields");
+            }
+            // `optional_enum_merge`, `optional_atomic` and `optional_replace_only` all set `wrap`
+            if wrap || wrapper.is_some() || new_type.is_some() || convert.is_some() || merge_op.is_some() || set_if.is_some() {
+                panic!("'{ELEMENTWISE_ATTRIBUTE}' can't be used on a field that is wrapped, nested, converted or merged");
+            }
+        }
+        if skip_wrap && (wrap || wrapper.is_some()) {
+            panic!("'{SKIP_WRAP_ATTRIBUTE}' can't be used on a field with '{WRAP_ATTRIBUTE}' or '{WRAP_WITH_ATTRIBUTE}'");
+        }
+        // The nested patch of an `Option` is itself optional, `None` leaving the field untouched
+        if skip_wrap && new_type.is_some() && is_base_opt {
+            panic!("'{SKIP_WRAP_ATTRIBUTE}' can't be used on an Option field with '{RENAME_ATTRIBUTE}'");
+        }
+        // A flattened patch has no key of its own, so `None` could not be told apart from a patch
+        // leaving all of its fields unset: it is stored as is, its fields being optional already
+        if new_type.is_some() && has_serde_flatten(&old_field.attrs) && (wrap || is_base_opt) {
+            panic!("A nested field with '#[serde(flatten)]' can't be wrapped, nor be an Option");
+        }
+        let boxed = new_type.is_some() && is_boxed_type(&old_field.ty, is_base_opt);
+        let wrapping_behavior = if wrap || wrapper.is_some() {
+            true
+        } else if skip_wrap || elementwise {
+            false
+        } else if new_type.is_some() {
+            is_base_opt
+        } else {
+            !is_base_opt && global_options.default_wrapping_behavior
+        };
+        if set_if.is_some() && (wrapping_behavior || new_type.is_some() || is_base_opt) {
+            panic!("'{SET_IF_ATTRIBUTE}' can only be used on fields that are neither wrapped, nested nor an Option");
+        }
+        if new_type.is_some() && wrapper.is_some() {
+            panic!("'{WRAP_WITH_ATTRIBUTE}' can't be used on a field with '{RENAME_ATTRIBUTE}'");
+        }
+        // Nested fields keep using `Option`, whatever the default wrapper
+        if wrapping_behavior && new_type.is_none() && wrapper.is_none() {
+            wrapper = global_options.wrapper.clone();
+        }
+        if merge_op.is_some() && (new_type.is_some() || wrapper.is_some() || (!wrapping_behavior && is_base_opt)) {
+            panic!("'{MERGE_OP_ATTRIBUTE}' can only be used on fields that are neither nested, an Option nor in a custom wrapper");
+        }
+        if new_type.is_some() && convert.is_some() {
+            panic!("'{CONVERT_ATTRIBUTE}' and '{TRANSPARENT_ATTRIBUTE}' can't be used on a field with '{RENAME_ATTRIBUTE}'");
+        }
+        // Mapped types are converted with `Into` both ways, unless the field says otherwise
+        if new_type.is_none() && convert.is_none() && !elementwise {
+            convert = global_options
+                .type_mappings
+                .iter()
+                .find(|m| m.matches(&old_field.ty))
+                .map(|m| FieldConversion {
+                    ty: m.to.clone(),
+                    into: parse_quote! { ::core::convert::Into::into },
+                    from: parse_quote! { ::core::convert::Into::into },
+                });
+        }
         let field_ident = if let Some(ident) = &old_field.ident {
             quote! {#ident}
         } else {
             let i = syn::Index::from(struct_index);
             quote! {#i}
         };
-        let field_options = FieldOptions { wrapping_behavior, cfg_attribute, new_type, field_ident, serde_skip };
+        // Skipped fields shift the fields following them in a tuple struct
+        let new_field_ident = match (new_name, &old_field.ident) {
+            (Some(_), None) => panic!("'{FIELD_NAME_ATTRIBUTE}' can't be used on the fields of a tuple struct"),
+            (Some(new_name), Some(_)) => quote! {#new_name},
+            (None, Some(_)) => field_ident.clone(),
+            (None, None) => {
+                let i = syn::Index::from(new_fields.len());
+                quote! {#i}
+            }
+        };
+        let field_name = field_ident.to_string();
+        let new_field_name = new_field_ident.to_string();
+        let field_options = FieldOptions {
+            wrapping_behavior,
+            cfg_attribute,
+            new_type,
+            convert,
+            set_if,
+            wrapper,
+            merge_op,
+            group,
+            read_only,
+            immutable,
+            replace_only,
+            elementwise,
+            enum_merge: is_enum_merge,
+            secret,
+            proto_name,
+            column,
+            redis_with,
+            boxed,
+            is_base_opt,
+            option_like: option_override == Some(true),
+            field_ident,
+            new_field_ident,
+            field_name,
+            new_field_name,
+            serde_skip,
+        };
+        if let Some(default) = skip {
+            for v in &mut *visitors {
+                v.visit_skipped(global_options, old_field, &field_options, &default);
+            }
+            continue;
+        }
+
+        let mut new_field = old_field.clone();
+        strip_helper_attributes(&mut new_field.attrs);
         for v in &mut *visitors {
-            v.visit(&global_options, old_field, new_field, &field_options);
+            v.visit(global_options, old_field, &mut new_field, &field_options);
         }
+        new_fields.push(new_field);
+    }
+
+    let Data::Struct(data_struct) = &derive_input.data else { unreachable!() };
+    let fields = match &data_struct.fields {
+        Fields::Named(f) => Fields::Named(FieldsNamed { brace_token: f.brace_token, named: new_fields }),
+        Fields::Unnamed(f) => Fields::Unnamed(FieldsUnnamed { paren_token: f.paren_token, unnamed: new_fields }),
+        // The optional version of a unit struct is an empty braced struct
+        Fields::Unit => Fields::Named(FieldsNamed { brace_token: Default::default(), named: new_fields }),
+    };
+    let semi_token = if let Fields::Named(_) = fields { None } else { data_struct.semi_token };
+    DeriveInput {
+        attrs: derive_input.attrs.clone(),
+        vis: derive_input.vis.clone(),
+        ident: derive_input.ident.clone(),
+        generics: derive_input.generics.clone(),
+        data: Data::Struct(DataStruct { struct_token: data_struct.struct_token, fields, semi_token }),
     }
-    (orig, new)
+}
+
+// Traits are told apart by their name only, so that e.g. `core::fmt::Debug` on the original struct
+// isn't derived a second time as `Debug`
+fn derived_trait_name(path: &Path) -> String {
+    path.segments.last().map(|ps| ps.ident.to_string()).unwrap_or_default()
 }
 
 fn get_derive_macros(
     new: &DeriveInput,
     extra_derive: &[String],
+    skip_derive: &[String],
 ) -> TokenStream {
-    let mut extra_derive = extra_derive.iter().collect::<HashSet<_>>();
-    for attributes in &new.attrs {
+    // Kept in the order they were given, so that the output doesn't change from one build to the next
+    let mut left_to_derive = Vec::<Path>::new();
+    for extra in extra_derive {
+        let extra = syn::parse_str::<Path>(extra).unwrap();
+        let name = derived_trait_name(&extra);
+        if !skip_derive.contains(&name) && !left_to_derive.iter().any(|t| derived_trait_name(t) == name) {
+            left_to_derive.push(extra);
+        }
+    }
+    for attributes in new.attrs.iter().filter(|a| a.path().is_ident("derive")) {
         let _ = attributes.parse_nested_meta(|derived_trait|
             {
-                let derived_trait = derived_trait.path;
-                let full_path = quote! { #derived_trait };
-                extra_derive.remove(&full_path.to_string());
+                let name = derived_trait_name(&derived_trait.path);
+                left_to_derive.retain(|t| derived_trait_name(t) != name);
                 Ok(())
             });
     }
 
+    quote! { #[derive(#(#left_to_derive),*)] }
+}
+
+// Removes the traits of `skip_derive` from the derives the generated struct copies from the original
+fn remove_skipped_derives(attrs: &mut Vec<Attribute>, skip_derive: &[String]) {
+    if skip_derive.is_empty() {
+        return;
+    }
+    attrs.retain_mut(|attribute| {
+        if !attribute.path().is_ident("derive") {
+            return true;
+        }
+        let Ok(derived) = attribute.parse_args_with(Punctuated::<Path, Token![,]>::parse_terminated) else {
+            return true;
+        };
+        let kept = derived.into_iter().filter(|t| !skip_derive.contains(&derived_trait_name(t))).collect::<Vec<_>>();
+        *attribute = parse_quote! { #[derive(#(#kept),*)] };
+        !kept.is_empty()
+    });
+}
+
+fn where_clause_with_bounds(generics: &Generics, bounds: impl Iterator<Item = TokenStream>) -> TokenStream {
+    let mut where_clause = generics.where_clause.clone().unwrap_or_else(|| parse_quote! { where });
+    for bound in bounds {
+        where_clause.predicates.push(parse_quote! { #bound });
+    }
+    quote! { #where_clause }
+}
+
+fn has_derive(derive_input: &DeriveInput, trait_name: &str) -> bool {
+    let mut found = false;
+    for attribute in derive_input.attrs.iter().filter(|a| a.path().is_ident("derive")) {
+        let _ = attribute.parse_nested_meta(|derived_trait| {
+            if derived_trait.path.segments.last().map(|ps| ps.ident == trait_name).unwrap_or(false) {
+                found = true;
+            }
+            Ok(())
+        });
+    }
+    found
+}
+
+// The traits of the original struct can only be told from its derives
+fn require_derives(item: &str, derive_input: &DeriveInput, trait_names: &[&str]) {
+    if let Some(missing) = trait_names.iter().find(|t| !has_derive(derive_input, t)) {
+        panic!("'{item}' needs '{}' to derive `{missing}`", derive_input.ident);
+    }
+}
+
+// The `#[validate]` field attributes are copied as-is on the generated struct: since validator
+// skips `None` values, validating the patch only checks the fields it actually sets.
+fn get_validate_set_fields_impl(derive_input: &DeriveInput, new: &DeriveInput) -> TokenStream {
+    if !has_derive(derive_input, "Validate") {
+        return quote! {};
+    }
+
+    let (impl_generics, ty_generics, where_clause) = derive_input.generics.split_for_impl();
+    let new_name = &new.ident;
+    quote! {
+        impl #impl_generics #new_name #ty_generics #where_clause {
+            fn validate_set_fields(&self) -> Result<(), ::validator::ValidationErrors> {
+                ::validator::Validate::validate(self)
+            }
+        }
+    }
+}
+
+// Options given as `#[optional_struct(Name, false, option, option = value, option(values))]`
+// Bare options have to be listed here so they are not mistaken for the new struct name.
+const FLAG_OPTIONS: &[&str] = &["actix_web", "arbitrary", "audit", "axum", "builder", "clap", "derive_copy", "doc", "dynamodb", "explicit_null", "hidden", "inline", "merge", "must_use", "proptest", "redis", "rusqlite", "schemars", "serde_json", "serde_rename", "sparse_json", "split", "sea_query", "struct_patch", "tokio_postgres", "tokio_watch", "track_source", "tracing", "unknown_keys", "zeroize"];
 
-    let mut acc = quote! {};
-    for left_trait_to_derive in extra_derive {
-        let left_trait_to_derive = format_ident!("{left_trait_to_derive}");
-        acc = quote! { # left_trait_to_derive, # acc};
-    }
+// Items that are only generated when listed in the `impls(...)` option. The other ones are either
+// needed by the rest of the generated code, or enabled by their own option.
+const SELECTABLE_IMPLS: &[&str] = &["apply_boxed", "apply_report", "apply_to_many", "assert_applies_to", "change_enum", "construction_macro", "diff", "display", "empty", "env_vars", "field_enum", "finalize", "invert", "iter_set_fields", "merge3", "merge_patch", "missing_fields", "patch_log", "profiles", "reload_from", "resolve", "schema_hash", "set_columns", "set_count", "set_operations", "setters", "strip_defaults", "tracked", "try_from_layers"];
+
+struct MacroOption {
+    name: Ident,
+    value: Option<TokenStream>,
+}
 
-    quote! { #[derive(#acc)] }
+impl MacroOption {
+    fn value_as<T: syn::parse::Parse>(&self) -> Option<T> {
+        let name = &self.name;
+        self.value.as_ref().map(|v| {
+            syn::parse2::<T>(v.clone()).unwrap_or_else(|_| panic!("Invalid value for option '{name}'"))
+        })
+    }
 }
 
 struct ParsedMacroParameters {
     new_struct_name: Option<String>,
     default_wrapping: bool,
+    options: Vec<MacroOption>,
 }
 
 impl Parse for ParsedMacroParameters {
@@ -448,34 +5747,103 @@ impl Parse for ParsedMacroParameters {
         let mut out = ParsedMacroParameters {
             new_struct_name: None,
             default_wrapping: true,
+            options: vec![],
         };
 
-        if let Ok(struct_name) = Ident::parse(input) {
-            out.new_struct_name = Some(struct_name.to_string());
-        } else {
-            return Ok(out);
-        };
-
-        if input.parse::<Token![,]>().is_err() {
-            return Ok(out);
-        };
+        while !input.is_empty() {
+            if input.peek(syn::LitBool) {
+                out.default_wrapping = input.parse::<syn::LitBool>()?.value;
+            } else {
+                let name = input.parse::<Ident>()?;
+                if input.peek(Token![=]) {
+                    input.parse::<Token![=]>()?;
+                    let mut value = TokenStream::new();
+                    while !input.is_empty() && !input.peek(Token![,]) {
+                        value.extend(std::iter::once(input.parse::<TokenTree>()?));
+                    }
+                    out.options.push(MacroOption { name, value: Some(value) });
+                } else if input.peek(syn::token::Paren) {
+                    let content;
+                    syn::parenthesized!(content in input);
+                    out.options.push(MacroOption { name, value: Some(content.parse()?) });
+                } else if out.new_struct_name.is_some() || FLAG_OPTIONS.iter().any(|o| name == o) {
+                    out.options.push(MacroOption { name, value: None });
+                } else {
+                    out.new_struct_name = Some(name.to_string());
+                }
+            }
 
-        if let Ok(wrapping) = syn::LitBool::parse(input) {
-            out.default_wrapping = wrapping.value;
-        } else {
-            return Ok(out);
-        };
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<Token![,]>()?;
+        }
 
         Ok(out)
     }
 }
 
-// TODO this breaks for e.g. yolo::my::Option
+// Either a bare `Option`, or the one of `std`/`core` named by its full path: other paths ending in
+// `Option` name some other type. Fields can override this with `optional_option_like`.
+// First generic argument of a type like `Option<T>` or `Box<T>`
+fn first_type_argument(ty: &Type) -> Option<&Type> {
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+    match &type_path.path.segments.last()?.arguments {
+        syn::PathArguments::AngleBracketed(args) => match args.args.first()? {
+            syn::GenericArgument::Type(inner) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// Whether the value held by a field (in its `Option` if any) is a `Box`
+fn is_boxed_type(ty: &Type, is_base_opt: bool) -> bool {
+    let ty = if is_base_opt { first_type_argument(ty).unwrap_or(ty) } else { ty };
+    match ty {
+        Type::Path(type_path) => {
+            let segments = type_path.path.segments.iter().map(|ps| ps.ident.to_string()).collect::<Vec<_>>();
+            matches!(
+                segments.iter().map(String::as_str).collect::<Vec<_>>().as_slice(),
+                ["Box"] | ["std" | "alloc", "boxed", "Box"]
+            )
+        }
+        _ => false,
+    }
+}
+
+// Type of the elements of a `Vec`
+fn vec_element_type(ty: &Type) -> Option<&Type> {
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+    let segments = type_path.path.segments.iter().map(|ps| ps.ident.to_string()).collect::<Vec<_>>();
+    match segments.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["Vec"] | ["std" | "alloc", "vec", "Vec"] => first_type_argument(ty),
+        _ => None,
+    }
+}
+
+// `Optional<Enum>`, keeping the path and the generic arguments of the enum
+fn enum_patch_type(ty: &Type, is_base_opt: bool) -> Type {
+    let ty = if is_base_opt { first_type_argument(ty).unwrap_or(ty) } else { ty };
+    let Type::Path(type_path) = ty else {
+        panic!("'{ENUM_MERGE_ATTRIBUTE}' expects the optional enum to use when the field isn't a path to an enum");
+    };
+    let mut path = type_path.clone();
+    let last = path.path.segments.last_mut().unwrap();
+    last.ident = format_ident!("Optional{}", last.ident);
+    Type::Path(path)
+}
+
 fn is_path_option(p: &Path) -> bool {
-    p.segments
-        .last()
-        .map(|ps| ps.ident == "Option")
-        .unwrap_or(false)
+    let segments = p.segments.iter().map(|ps| ps.ident.to_string()).collect::<Vec<_>>();
+    matches!(
+        segments.iter().map(String::as_str).collect::<Vec<_>>().as_slice(),
+        ["Option"] | ["std" | "core", "option", "Option"]
+    )
 }
 
 fn is_type_option(t: &Type) -> bool {
@@ -518,27 +5886,499 @@ fn is_type_option(t: &Type) -> bool {
     }
 }
 
+// Attributes that only make sense on the generated struct, e.g. #[optional_struct_attr(pyclass)]
+fn take_passthrough_attributes(derive_input: &mut DeriveInput) -> Vec<Attribute> {
+    let (passthrough, kept) = derive_input
+        .attrs
+        .drain(..)
+        .partition::<Vec<_>, _>(|a| a.path().is_ident(PASSTHROUGH_ATTRIBUTE));
+    derive_input.attrs = kept;
+
+    passthrough
+        .into_iter()
+        .map(|a| {
+            let meta = a
+                .parse_args::<syn::Meta>()
+                .unwrap_or_else(|_| panic!("'{PASSTHROUGH_ATTRIBUTE}' attribute expects the attribute to forward to the generated struct"));
+            parse_quote! { #[#meta] }
+        })
+        .collect()
+}
+
+struct TypeMapping {
+    from: Type,
+    to: Type,
+}
+
+impl Parse for TypeMapping {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let from = input.parse()?;
+        input.parse::<Token![=>]>()?;
+        let to = input.parse()?;
+        Ok(TypeMapping { from, to })
+    }
+}
+
+impl TypeMapping {
+    // `Duration` and `std::time::Duration` are considered to be the same type
+    fn matches(&self, ty: &Type) -> bool {
+        match (&self.from, ty) {
+            (Type::Path(from), Type::Path(ty)) if from.qself.is_none() && ty.qself.is_none() => {
+                let from = from.path.segments.iter().rev();
+                let ty = ty.path.segments.iter().rev();
+                from.zip(ty).all(|(a, b)| quote! { #a }.to_string() == quote! { #b }.to_string())
+            }
+            (from, ty) => quote! { #from }.to_string() == quote! { #ty }.to_string(),
+        }
+    }
+}
+
+// e.g. #[optional_version_field(version)]
+fn take_version_field(derive_input: &mut DeriveInput) -> Option<Ident> {
+    let (attributes, kept) = derive_input
+        .attrs
+        .drain(..)
+        .partition::<Vec<_>, _>(|a| a.path().is_ident(VERSION_FIELD_ATTRIBUTE));
+    derive_input.attrs = kept;
+
+    match attributes.as_slice() {
+        [] => None,
+        [attribute] => Some(
+            attribute
+                .parse_args::<Ident>()
+                .unwrap_or_else(|_| panic!("'{VERSION_FIELD_ATTRIBUTE}' attribute expects the name of a field, e.g. {VERSION_FIELD_ATTRIBUTE}(version)"))
+        ),
+        _ => panic!("'{VERSION_FIELD_ATTRIBUTE}' attribute can only be used once"),
+    }
+}
+
+// Another struct the patch can be applied to, through the fields sharing their name (and type)
+// with the original struct, either all of them or the ones listed
+struct ApplyTarget {
+    path: Path,
+    fields: Option<Vec<Ident>>,
+}
+
+impl Parse for ApplyTarget {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let path = input.parse()?;
+        let fields = if input.peek(syn::token::Paren) {
+            let content;
+            syn::parenthesized!(content in input);
+            Some(Punctuated::<Ident, Token![,]>::parse_terminated(&content)?.into_iter().collect())
+        } else {
+            None
+        };
+        Ok(ApplyTarget { path, fields })
+    }
+}
+
+impl ApplyTarget {
+    fn includes(&self, field: &Ident) -> bool {
+        self.fields.as_ref().is_none_or(|fields| fields.contains(field))
+    }
+}
+
+// e.g. #[optional_targets(DbUser, ApiUser(name, email))]
+fn take_apply_targets(derive_input: &mut DeriveInput) -> Vec<ApplyTarget> {
+    let (targets, kept) = derive_input
+        .attrs
+        .drain(..)
+        .partition::<Vec<_>, _>(|a| a.path().is_ident(TARGETS_ATTRIBUTE));
+    derive_input.attrs = kept;
+
+    targets
+        .into_iter()
+        .flat_map(|a| {
+            a.parse_args_with(Punctuated::<ApplyTarget, Token![,]>::parse_terminated)
+                .unwrap_or_else(|_| panic!("'{TARGETS_ATTRIBUTE}' attribute expects a list of structs, e.g. {TARGETS_ATTRIBUTE}(DbUser, ApiUser(name, email))"))
+        })
+        .collect()
+}
+
+// e.g. #[optional_map_type(Duration => humantime_serde::Serde<Duration>)]
+fn take_type_mappings(derive_input: &mut DeriveInput) -> Vec<TypeMapping> {
+    let (mappings, kept) = derive_input
+        .attrs
+        .drain(..)
+        .partition::<Vec<_>, _>(|a| a.path().is_ident(MAP_TYPE_ATTRIBUTE));
+    derive_input.attrs = kept;
+
+    mappings
+        .into_iter()
+        .flat_map(|a| {
+            a.parse_args_with(Punctuated::<TypeMapping, Token![,]>::parse_terminated)
+                .unwrap_or_else(|_| panic!("'{MAP_TYPE_ATTRIBUTE}' attribute expects a list of type mappings, e.g. {MAP_TYPE_ATTRIBUTE}(Url => String)"))
+        })
+        .collect()
+}
+
+fn has_serde_flatten(attrs: &[Attribute]) -> bool {
+    attrs
+        .iter()
+        .filter(|a| a.path().is_ident("serde"))
+        .filter_map(|a| a.parse_args_with(Punctuated::<syn::Meta, Token![,]>::parse_terminated).ok())
+        .any(|metas| metas.iter().any(|m| m.path().is_ident("flatten")))
+}
+
+fn is_pyclass_attribute(attribute: &Attribute) -> bool {
+    attribute
+        .path()
+        .segments
+        .last()
+        .map(|ps| ps.ident == "pyclass")
+        .unwrap_or(false)
+}
+
 struct GlobalOptions {
     new_struct_name: String,
     extra_derive: Vec<String>,
+    // Names of the traits the generated struct must not derive
+    skip_derive: Vec<String>,
     default_wrapping_behavior: bool,
     make_fields_public: bool,
+    passthrough_attributes: Vec<Attribute>,
+    type_mappings: Vec<TypeMapping>,
+    // Checked and bumped by `try_apply_to` instead of being applied
+    version_field: Option<Ident>,
+    // Other structs the patch also implements `Applyable` for
+    targets: Vec<ApplyTarget>,
+    is_pyclass: bool,
+    merge: bool,
+    // Also generates `update_from_arg_matches`, reading the arguments given on the command line
+    clap: bool,
+    proptest: bool,
+    struct_patch: Option<Ident>,
+    tracing_level: Option<Ident>,
+    serde_json: bool,
+    // Also generates `to_value_sparse` and `from_value_sparse`, which nested patches need as well
+    sparse_json: bool,
+    // Also generates `bind_set_fields`, the parameters matching `set_columns`
+    tokio_postgres: bool,
+    // Also generates `to_sea_query_values`, the column/value pairs matching `set_columns`
+    sea_query: bool,
+    // Also generates `to_named_params` and `set_clause`, matching `set_columns`
+    rusqlite: bool,
+    // Also generates `to_redis_hset` and `to_redis_hdel`, refreshing a hash field by field
+    redis: bool,
+    // Also generates `to_dynamo_update`, an update expression setting and removing attributes
+    dynamodb: bool,
+    // Also generates `to_audit_rows`, which needs `std` for its timestamps
+    audit: bool,
+    tokio_watch: bool,
+    // Implement the request extractor of these web frameworks, deserializing a JSON body
+    axum: bool,
+    actix_web: bool,
+    // A `null` value clears a double wrapped field instead of leaving it unset
+    explicit_null: bool,
+    // Also derives `JsonSchema`, only double wrapped fields being nullable
+    schemars: bool,
+    // The prost message that `from_proto` copies the masked fields of
+    prost: Option<Path>,
+    // Also generates the `SourcedStruct` wrapper, recording the layer which set each field
+    track_source: bool,
+    // Some(true) when the builder uses typestate
+    builder: Option<bool>,
+    must_use: bool,
+    inline: bool,
+    doc: bool,
+    // Copies the `#[serde(rename)]`-like attributes of the original struct to the generated one
+    serde_rename: bool,
+    hidden: bool,
+    wrapper: Option<Path>,
+    // Prepended to the names of the environment variables of the fields
+    env_prefix: Option<String>,
+    // Implements `Zeroize` for the secret fields
+    zeroize: bool,
+    // Fields that only exist in the generated struct
+    extra_fields: Vec<Field>,
+    // One of the extra fields collects the keys that aren't fields when deserializing
+    unknown_keys: bool,
+    // Wraps each generated impl in its own `const _: () = { ... };` block
+    split: bool,
+    // Items of `SELECTABLE_IMPLS` to generate
+    impls: Vec<String>,
+    // Also generates the compact `OptionalStructMask` version of the struct
+    mask_repr: bool,
 }
 
 impl GlobalOptions {
-    fn new(attr: ParsedMacroParameters, struct_definition: &DeriveInput) -> Self {
+    fn new(attr: ParsedMacroParameters, struct_definition: &DeriveInput, passthrough_attributes: Vec<Attribute>, type_mappings: Vec<TypeMapping>, version_field: Option<Ident>, targets: Vec<ApplyTarget>) -> Self {
         let new_struct_name = attr.new_struct_name.unwrap_or_else(|| "Optional".to_owned() + &struct_definition.ident.to_string());
         let default_wrapping_behavior = attr.default_wrapping;
+        let is_pyclass = passthrough_attributes.iter().any(is_pyclass_attribute);
+        let mut merge = false;
+        let mut clap = false;
+        let mut proptest = false;
+        let mut struct_patch = None;
+        let mut tracing_level = None;
+        let mut serde_json = false;
+        let mut sparse_json = false;
+        let mut tokio_postgres = false;
+        let mut sea_query = false;
+        let mut rusqlite = false;
+        let mut redis = false;
+        let mut dynamodb = false;
+        let mut audit = false;
+        let mut tokio_watch = false;
+        let mut axum = false;
+        let mut actix_web = false;
+        let mut explicit_null = false;
+        let mut schemars = false;
+        let mut prost = None;
+        let mut track_source = false;
+        let mut builder = None;
+        let mut must_use = true;
+        let mut inline = true;
+        let mut doc = true;
+        let mut serde_rename = true;
+        let mut hidden = false;
+        let mut wrapper = None;
+        let mut env_prefix = None;
+        let mut zeroize = false;
+        let mut extra_fields = vec![];
+        let mut unknown_keys = false;
+        let mut split = false;
+        let mut impls = vec![];
+        let mut mask_repr = false;
+        let mut skip_derive = vec![];
+        let mut extra_derive = vec!["Clone", "PartialEq", "Default", "Debug"]
+            .into_iter()
+            .map(|s| s.to_owned())
+            .collect::<Vec<_>>();
+        for option in &attr.options {
+            match option.name.to_string().as_str() {
+                "arbitrary" => extra_derive.push("::arbitrary::Arbitrary".to_owned()),
+                "schemars" => {
+                    schemars = true;
+                    extra_derive.push("::schemars::JsonSchema".to_owned());
+                }
+                "builder" => builder = Some(match option.value_as::<Ident>() {
+                    None => false,
+                    Some(mode) if mode == "typestate" => true,
+                    Some(mode) => panic!("Unknown builder mode '{mode}', the only supported one is 'typestate'"),
+                }),
+                "derive_copy" => extra_derive.push("Copy".to_owned()),
+                "derive" => {
+                    let traits = option
+                        .value
+                        .as_ref()
+                        .and_then(|v| Punctuated::<Path, Token![,]>::parse_terminated.parse2(v.clone()).ok())
+                        .unwrap_or_else(|| panic!("'derive' option expects a list of traits, e.g. derive(Eq, Hash)"));
+                    extra_derive.extend(traits.iter().map(|t| quote! { #t }.to_string()));
+                }
+                "skip_derive" => {
+                    let traits = option
+                        .value
+                        .as_ref()
+                        .and_then(|v| Punctuated::<Path, Token![,]>::parse_terminated.parse2(v.clone()).ok())
+                        .unwrap_or_else(|| panic!("'skip_derive' option expects a list of traits, e.g. skip_derive(Default)"));
+                    skip_derive.extend(traits.iter().map(derived_trait_name));
+                }
+                "extra_fields" => {
+                    let fields = option
+                        .value
+                        .as_ref()
+                        .and_then(|v| {
+                            let parser = |input: ParseStream| Punctuated::<Field, Token![,]>::parse_terminated_with(input, Field::parse_named);
+                            parser.parse2(v.clone()).ok()
+                        })
+                        .unwrap_or_else(|| panic!("'extra_fields' option expects a list of fields, e.g. extra_fields(reason: Option<String>)"));
+                    extra_fields.extend(fields);
+                }
+                "unknown_keys" => {
+                    unknown_keys = true;
+                    let ident = option.value_as::<Ident>().unwrap_or_else(|| format_ident!("unknown_keys"));
+                    let field = quote! {
+                        #[serde(flatten)]
+                        #ident: ::serde_json::Map<::optional_struct::__private::String, ::serde_json::Value>
+                    };
+                    extra_fields.push(Field::parse_named.parse2(field).expect("the unknown keys field is valid"));
+                }
+                "env_prefix" => env_prefix = Some(
+                    option
+                        .value_as::<syn::LitStr>()
+                        .map(|p| p.value())
+                        .unwrap_or_else(|| panic!("'env_prefix' option expects a string, e.g. env_prefix = \"APP_\""))
+                ),
+                "merge" => merge = true,
+                "zeroize" => zeroize = true,
+                "clap" => clap = true,
+                "proptest" => proptest = true,
+                "must_use" => must_use = option.value_as::<syn::LitBool>().map(|b| b.value).unwrap_or(true),
+                "doc" => doc = option.value_as::<syn::LitBool>().map(|b| b.value).unwrap_or(true),
+                "serde_rename" => serde_rename = option.value_as::<syn::LitBool>().map(|b| b.value).unwrap_or(true),
+                "hidden" => hidden = true,
+                "inline" => inline = option.value_as::<syn::LitBool>().map(|b| b.value).unwrap_or(true),
+                "serde_json" => serde_json = true,
+                "sparse_json" => sparse_json = true,
+                "split" => split = true,
+                "repr" => mask_repr = match option.value_as::<syn::LitStr>().map(|r| r.value()).as_deref() {
+                    Some("option") => false,
+                    Some("mask") => true,
+                    _ => panic!("'repr' option expects either \"option\" or \"mask\""),
+                },
+                "impls" => {
+                    let names = option
+                        .value
+                        .as_ref()
+                        .and_then(|v| Punctuated::<Ident, Token![,]>::parse_terminated.parse2(v.clone()).ok())
+                        .unwrap_or_else(|| panic!("'impls' option expects a list of generated items, e.g. impls(display, setters)"));
+                    let names = names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+                    if let Some(unknown) = names.iter().find(|n| !SELECTABLE_IMPLS.contains(&n.as_str())) {
+                        panic!("Unknown generated item '{unknown}' in 'impls' option, expected some of {}", SELECTABLE_IMPLS.join(", "));
+                    }
+                    impls = names;
+                }
+                "tokio_postgres" => tokio_postgres = true,
+                "sea_query" => sea_query = true,
+                "rusqlite" => rusqlite = true,
+                "redis" => redis = true,
+                "dynamodb" => dynamodb = true,
+                "audit" => audit = true,
+                "tokio_watch" => tokio_watch = true,
+                "prost" => prost = Some(
+                    option
+                        .value_as::<Path>()
+                        .unwrap_or_else(|| panic!("'prost' option expects the type of the message, e.g. prost = proto::Config"))
+                ),
+                "axum" => axum = true,
+                "actix_web" => actix_web = true,
+                "explicit_null" => explicit_null = true,
+                "track_source" => track_source = true,
+                "wrapper" => wrapper = Some(
+                    option
+                        .value_as::<Path>()
+                        .unwrap_or_else(|| panic!("'wrapper' option expects a type, e.g. wrapper = MyMaybe"))
+                ),
+                "struct_patch" => struct_patch = Some(
+                    option
+                        .value_as::<Ident>()
+                        .unwrap_or_else(|| format_ident!("{}Patch", struct_definition.ident))
+                ),
+                "tracing" => tracing_level = Some(
+                    option
+                        .value_as::<Ident>()
+                        .map(|level| format_ident!("{}", level.to_string().to_uppercase()))
+                        .unwrap_or_else(|| format_ident!("INFO"))
+                ),
+                unknown => panic!("Unknown optional_struct option '{unknown}'"),
+            }
+        }
         GlobalOptions {
             new_struct_name,
-            extra_derive: vec!["Clone", "PartialEq", "Default", "Debug"]
-                .into_iter()
-                .map(|s| s.to_owned())
-                .collect(),
+            extra_derive,
+            skip_derive,
             default_wrapping_behavior,
             make_fields_public: true,
+            passthrough_attributes,
+            type_mappings,
+            version_field,
+            targets,
+            is_pyclass,
+            merge,
+            clap,
+            proptest,
+            struct_patch,
+            tracing_level,
+            serde_json,
+            sparse_json,
+            tokio_postgres,
+            sea_query,
+            rusqlite,
+            redis,
+            dynamodb,
+            audit,
+            tokio_watch,
+            axum,
+            actix_web,
+            explicit_null,
+            schemars,
+            prost,
+            track_source,
+            builder,
+            must_use,
+            inline,
+            doc,
+            serde_rename,
+            hidden,
+            wrapper,
+            env_prefix,
+            zeroize,
+            extra_fields,
+            unknown_keys,
+            split,
+            impls,
+            mask_repr,
+        }
+    }
+
+    fn emits(&self, item: &str) -> bool {
+        self.impls.iter().any(|i| i == item)
+    }
+
+    // Only used for items that define nothing but impls, which still apply outside of the block
+    fn isolate(&self, tokens: TokenStream) -> TokenStream {
+        if self.split && !tokens.is_empty() {
+            quote! { const _: () = { #tokens }; }
+        } else {
+            tokens
         }
     }
+
+    fn must_use_attribute(&self) -> TokenStream {
+        if self.must_use { quote! { #[must_use] } } else { quote! {} }
+    }
+
+    fn inline_attribute(&self) -> TokenStream {
+        if self.inline { quote! { #[inline] } } else { quote! {} }
+    }
+
+    fn hidden_attribute(&self) -> TokenStream {
+        if self.hidden { quote! { #[doc(hidden)] } } else { quote! {} }
+    }
+}
+
+// Names the generated code binds values to
+const GENERATED_BINDINGS: &[&str] = &[
+    "after", "base", "base_value", "before", "body", "c", "chain", "change", "changes", "column", "columns", "conflicts", "count", "current", "e", "entity_id", "existing", "expected", "f", "field",
+    "fields", "group", "hash", "i", "inner", "layer", "m", "mask", "matches", "merged", "mismatches", "msg", "name", "nested", "old", "other", "ours", "params", "patch",
+    "patches", "path", "paths", "payload", "parent", "pending", "reference", "reported", "request", "separator", "set", "state", "strategy", "t", "theirs", "timestamp", "total", "tx", "update", "v",
+    "value", "values",
+];
+
+// Expressions given by the user are kept in an invisible group, which `hygienic_bindings` leaves as
+// it is
+fn user_tokens(tokens: impl ToTokens) -> TokenStream {
+    TokenTree::Group(Group::new(Delimiter::None, tokens.into_token_stream())).into()
+}
+
+// The bindings of the generated code get a mixed-site span, like those of a `macro_rules!`: they
+// can't shadow the functions or constants the user's expressions refer to, even when
+// `optional_struct` is itself used from another macro.
+fn hygienic_bindings(tokens: TokenStream) -> TokenStream {
+    tokens
+        .into_iter()
+        .map(|token| match token {
+            TokenTree::Ident(mut ident) => {
+                let name = ident.to_string();
+                if GENERATED_BINDINGS.contains(&name.as_str()) || (name.starts_with("__") && name != "__private") {
+                    ident.set_span(ident.span().resolved_at(Span::mixed_site()));
+                }
+                TokenTree::Ident(ident)
+            }
+            TokenTree::Group(group) if group.delimiter() != Delimiter::None => {
+                let mut respanned = Group::new(group.delimiter(), hygienic_bindings(group.stream()));
+                respanned.set_span(group.span());
+                TokenTree::Group(respanned)
+            }
+            token => token,
+        })
+        .collect()
+}
+
+fn emit_if(condition: bool, generate: impl FnOnce() -> TokenStream) -> TokenStream {
+    if condition { generate() } else { quote! {} }
 }
 
 pub struct OptionalStructOutput {
@@ -550,43 +6390,935 @@ pub fn opt_struct(
     attr: TokenStream,
     input: TokenStream,
 ) -> OptionalStructOutput {
-    let derive_input = syn::parse2::<DeriveInput>(input).unwrap();
-    let macro_params = GlobalOptions::new(syn::parse2::<_>(attr).unwrap(), &derive_input);
+    let mut derive_input = syn::parse2::<DeriveInput>(input).unwrap();
+    let passthrough_attributes = take_passthrough_attributes(&mut derive_input);
+    let type_mappings = take_type_mappings(&mut derive_input);
+    let version_field = take_version_field(&mut derive_input);
+    let targets = take_apply_targets(&mut derive_input);
+    let macro_params = GlobalOptions::new(syn::parse2::<_>(attr).unwrap(), &derive_input, passthrough_attributes, type_mappings, version_field, targets);
+    if let Data::Enum(_) = derive_input.data {
+        return opt_enum(&macro_params, derive_input);
+    }
 
     let mut apply_fn_generator = GenerateApplyFnVisitor::new();
     let mut try_from_generator = GenerateTryFromImpl::new();
     let mut can_convert_generator = GenerateCanConvertImpl::new();
+    let mut struct_patch_generator = GenerateStructPatchConversions::new();
+    let mut traced_apply_generator = GenerateTracedApplyFnVisitor::new();
+    let mut apply_report_generator = GenerateApplyReportFnVisitor::new();
+    let mut apply_to_watch_generator = GenerateApplyToWatchFnVisitor::new();
+    let mut display_generator = GenerateDisplayImpl::new();
+    let mut iter_set_fields_generator = GenerateIterSetFieldsFn::new();
+    let mut set_count_generator = GenerateSetCountFns::new();
+    let mut field_groups_generator = GenerateFieldGroupsFns::new();
+    let mut versioned_apply_generator = GenerateVersionedApplyFn::new();
+    let mut ignored_fields_generator = GenerateIgnoredFieldsFn::new();
+    let mut set_columns_generator = GenerateSetColumnsFn::new();
+    let mut fields_by_name_generator = GenerateFieldsByNameFns::new();
+    let mut arg_matches_generator = GenerateArgMatchesFn::new();
+    let mut from_proto_generator = GenerateFromProtoFn::new();
+    let mut redis_hash_generator = GenerateRedisHashFns::new();
+    let mut dynamo_update_generator = GenerateDynamoUpdateFn::new();
+    let mut change_enum_generator = GenerateChangeEnum::new(&derive_input);
+    let mut field_enum_generator = GenerateFieldEnum::new(&derive_input);
+    let mut set_operations_generator = GenerateSetOperationsFns::new();
+    let mut strip_defaults_generator = GenerateStripDefaultsFn::new();
+    let mut assert_applies_to_generator = GenerateAssertAppliesToFn::new();
+    let mut proptest_generator = GenerateProptestImpl::new();
+    let mut tracked_wrapper_generator = GenerateTrackedWrapper::new();
+    let mut sourced_wrapper_generator = GenerateSourcedWrapper::new(&derive_input);
+    let mut schema_hash_generator = GenerateSchemaHashConst::new();
+    let mut env_vars_generator = GenerateEnvVarsConst::new();
+    let mut zeroize_generator = GenerateZeroizeImpl::new();
+    let mut diff_generator = GenerateDiffImpl::new();
+    let mut merge3_generator = GenerateMerge3Fn::new();
+    let mut invert_generator = GenerateInvertFn::new();
+    let mut builder_generator = GenerateBuilder::new();
+    let mut setters_generator = GenerateSettersFns::new();
+    let mut empty_generator = GenerateEmptyConst::new();
+    let mut mask_repr_generator = GenerateMaskRepr::new();
+    let mut construction_macro_generator = GenerateConstructionMacro::new();
+    let mut comparison_generator = GenerateComparisonImpls::new();
+    let mut field_documentation = AddFieldDocumentation { orig_name: derive_input.ident.clone() };
 
     let mut visitors = [
-        &mut RemoveHelperAttributesVisitor as &mut dyn OptionalFieldVisitor,
-        &mut SetNewFieldVisibilityVisitor,
+        &mut SetNewFieldVisibilityVisitor as &mut dyn OptionalFieldVisitor,
+        &mut SetNewFieldNameVisitor,
         &mut SetNewFieldTypeVisitor,
+        &mut RemoveSerdeRenameVisitor,
         &mut AddSerdeSkipAttribute,
+        &mut AddSerdeExplicitNullAttribute,
+        &mut AddSchemarsAttribute,
+        &mut AddPyo3AccessorsAttribute,
+        &mut field_documentation,
         &mut apply_fn_generator,
         &mut try_from_generator,
         &mut can_convert_generator,
+        &mut struct_patch_generator,
+        &mut traced_apply_generator,
+        &mut apply_report_generator,
+        &mut apply_to_watch_generator,
+        &mut display_generator,
+        &mut iter_set_fields_generator,
+        &mut set_count_generator,
+        &mut field_groups_generator,
+        &mut versioned_apply_generator,
+        &mut ignored_fields_generator,
+        &mut set_columns_generator,
+        &mut fields_by_name_generator,
+        &mut arg_matches_generator,
+        &mut from_proto_generator,
+        &mut redis_hash_generator,
+        &mut dynamo_update_generator,
+        &mut change_enum_generator,
+        &mut field_enum_generator,
+        &mut set_operations_generator,
+        &mut strip_defaults_generator,
+        &mut assert_applies_to_generator,
+        &mut proptest_generator,
+        &mut tracked_wrapper_generator,
+        &mut sourced_wrapper_generator,
+        &mut schema_hash_generator,
+        &mut env_vars_generator,
+        &mut zeroize_generator,
+        &mut diff_generator,
+        &mut merge3_generator,
+        &mut invert_generator,
+        &mut builder_generator,
+        &mut setters_generator,
+        &mut empty_generator,
+        &mut mask_repr_generator,
+        &mut construction_macro_generator,
+        &mut comparison_generator,
     ];
 
-    let (orig, mut new) = visit_fields(&mut visitors, &macro_params, &derive_input);
+    let mut new = visit_fields(&mut visitors, &macro_params, &derive_input);
+    if let Data::Struct(data_struct) = &mut derive_input.data {
+        for field in data_struct.fields.iter_mut() {
+            strip_helper_attributes(&mut field.attrs);
+        }
+    }
 
     new.ident = Ident::new(&macro_params.new_struct_name, new.ident.span());
+    // Attributes given with `optional_struct_attr` are meant for the generated struct, and kept as is
+    if !macro_params.serde_rename {
+        remove_serde_renames(&mut new.attrs);
+    }
+    new.attrs.extend(macro_params.passthrough_attributes.iter().cloned());
+    if !macro_params.extra_fields.is_empty() {
+        let new_fields = borrow_fields(&mut new);
+        if new_fields.iter().any(|f| f.ident.is_none()) {
+            panic!("'extra_fields' option can't be used on a tuple struct");
+        }
+        for field in &macro_params.extra_fields {
+            let mut field = field.clone();
+            if macro_params.make_fields_public {
+                field.vis = parse_quote! { pub };
+            }
+            new_fields.push(field);
+        }
+    }
+    if macro_params.doc {
+        prepend_documentation(&mut new.attrs, &format!("Optional version of [`{}`], where fields can be left unset.", derive_input.ident));
+    }
+    if macro_params.hidden {
+        new.attrs.push(syn::parse_quote! { #[doc(hidden)] });
+    }
+
+    let apply_fn_impl = macro_params.isolate(apply_fn_generator.get_implementation(&macro_params, &derive_input, &new));
+    let try_from_impl = macro_params.isolate(try_from_generator.get_implementation(&macro_params, &derive_input, &new));
+    // Layers report the fields none of them set
+    let missing_fields = macro_params.emits("missing_fields") || macro_params.emits("try_from_layers") || macro_params.emits("finalize");
+    let can_convert_impl = macro_params.isolate(can_convert_generator.get_implementation(&macro_params, &derive_input, &new, missing_fields));
 
-    let apply_fn_impl = apply_fn_generator.get_implementation(&derive_input, &new);
-    let try_from_impl = try_from_generator.get_implementation(&derive_input, &new);
-    let can_convert_impl = can_convert_generator.get_implementation(&derive_input, &new);
+    let validate_impl = macro_params.isolate(get_validate_set_fields_impl(&derive_input, &new));
+    let merge_impl = macro_params.isolate(get_merge_impl(&macro_params, &derive_input, &new));
+    let proptest_impl = macro_params.isolate(proptest_generator.get_implementation(&macro_params, &derive_input, &new));
+    let struct_patch_impl = macro_params.isolate(struct_patch_generator.get_implementation(&macro_params, &derive_input, &new));
+    let traced_apply_impl = macro_params.isolate(traced_apply_generator.get_implementation(&macro_params, &derive_input, &new));
+    // Audit rows are made of the changes `apply_report` gives
+    let apply_report_impl = emit_if(macro_params.emits("apply_report") || macro_params.audit, || macro_params.isolate(apply_report_generator.get_implementation(&derive_input, &new)));
+    let apply_to_watch_impl = emit_if(macro_params.tokio_watch, || macro_params.isolate(apply_to_watch_generator.get_implementation(&derive_input, &new)));
+    let display_impl = emit_if(macro_params.emits("display"), || macro_params.isolate(display_generator.get_implementation(&derive_input, &new)));
+    let iter_set_fields_impl = emit_if(macro_params.emits("iter_set_fields"), || macro_params.isolate(iter_set_fields_generator.get_implementation(&macro_params, &derive_input, &new)));
+    let field_groups_impl = macro_params.isolate(field_groups_generator.get_implementation(&macro_params, &derive_input, &new));
+    let versioned_apply_impl = macro_params.isolate(versioned_apply_generator.get_implementation(&macro_params, &derive_input, &new));
+    let ignored_fields_impl = macro_params.isolate(ignored_fields_generator.get_implementation(&macro_params, &derive_input, &new));
+    // Parameters and values are given in the order of `set_columns`
+    let set_columns = macro_params.emits("set_columns") || macro_params.tokio_postgres || macro_params.sea_query || macro_params.rusqlite;
+    let set_columns_impl = emit_if(set_columns, || macro_params.isolate(set_columns_generator.get_implementation(&macro_params, &derive_input, &new)));
+    // Groups, read-only fields and columns check whether nested patches are set with `set_count`
+    let needs_set_count = !field_groups_impl.is_empty() || !ignored_fields_impl.is_empty() || !set_columns_impl.is_empty();
+    let set_count_impl = emit_if(needs_set_count || macro_params.emits("set_count"), || macro_params.isolate(set_count_generator.get_implementation(&macro_params, &derive_input, &new)));
+    let arg_matches_impl = macro_params.isolate(arg_matches_generator.get_implementation(&macro_params, &derive_input, &new));
+    let from_proto_impl = macro_params.isolate(from_proto_generator.get_implementation(&macro_params, &derive_input, &new));
+    let redis_hash_impl = macro_params.isolate(redis_hash_generator.get_implementation(&macro_params, &derive_input, &new));
+    let dynamo_update_impl = macro_params.isolate(dynamo_update_generator.get_implementation(&macro_params, &derive_input, &new));
+    let change_enum_impl = emit_if(macro_params.emits("change_enum"), || change_enum_generator.get_implementation(&macro_params, &derive_input, &new));
+    // The sourced wrapper refers to fields through the field enum
+    let field_enum_impl = emit_if(macro_params.emits("field_enum") || macro_params.track_source, || {
+        field_enum_generator.get_implementation(&macro_params, &derive_input, &new, macro_params.emits("field_enum"))
+    });
+    let set_operations_impl = emit_if(macro_params.emits("set_operations"), || macro_params.isolate(set_operations_generator.get_implementation(&macro_params, &derive_input, &new)));
+    let strip_defaults_impl = emit_if(macro_params.emits("strip_defaults"), || macro_params.isolate(strip_defaults_generator.get_implementation(&derive_input, &new)));
+    let tracked_wrapper_impl = emit_if(macro_params.emits("tracked"), || tracked_wrapper_generator.get_implementation(&macro_params, &derive_input, &new));
+    let sourced_wrapper_impl = sourced_wrapper_generator.get_implementation(&macro_params, &derive_input, &new);
+    let schema_hash_impl = emit_if(macro_params.emits("schema_hash"), || macro_params.isolate(schema_hash_generator.get_implementation(&derive_input, &new)));
+    let zeroize_impl = macro_params.isolate(zeroize_generator.get_implementation(&macro_params, &derive_input, &new));
+    // The prefix is only used by `ENV_VARS`
+    let env_vars_impl = emit_if(macro_params.emits("env_vars") || macro_params.env_prefix.is_some(), || macro_params.isolate(env_vars_generator.get_implementation(&derive_input, &new)));
+    let merge3_impl = emit_if(macro_params.emits("merge3"), || macro_params.isolate(merge3_generator.get_implementation(&macro_params, &derive_input, &new)));
+    let invert_impl = emit_if(macro_params.emits("invert"), || macro_params.isolate(invert_generator.get_implementation(&macro_params, &derive_input, &new)));
+    let builder_impl = builder_generator.get_implementation(&macro_params, &derive_input, &new);
+    let generated_setters = [("set_count", !set_count_impl.is_empty()), ("set_columns", set_columns), ("set_clause", macro_params.rusqlite)];
+    let generated_setters: Vec<_> = generated_setters.iter().filter(|(_, generated)| *generated).map(|(name, _)| *name).collect();
+    let setters_impl = emit_if(macro_params.emits("setters"), || macro_params.isolate(setters_generator.get_implementation(&derive_input, &new, &generated_setters)));
+    let empty_impl = emit_if(macro_params.emits("empty"), || macro_params.isolate(empty_generator.get_implementation(&macro_params, &derive_input, &new)));
+    let mask_repr_impl = mask_repr_generator.get_implementation(&macro_params, &derive_input, &new);
+    let construction_macro = emit_if(macro_params.emits("construction_macro"), || construction_macro_generator.get_implementation(&new));
 
-    let derives = get_derive_macros(&new, &macro_params.extra_derive);
+    // Clone can't be implemented at all with a replace-only field
+    let mut skip_derive = macro_params.skip_derive.clone();
+    let implements = |skip_derive: &[String], trait_name: &str| {
+        !skip_derive.iter().any(|t| t == trait_name)
+            && (macro_params.extra_derive.iter().any(|t| t == trait_name) || has_derive(&new, trait_name))
+    };
+    let comparison_impls = if comparison_generator.has_replace_only {
+        let (eq, debug) = (implements(&skip_derive, "PartialEq"), implements(&skip_derive, "Debug"));
+        skip_derive.extend(["Clone", "PartialEq", "Debug"].map(str::to_owned));
+        macro_params.isolate(comparison_generator.get_implementation(&derive_input, &new, eq, debug))
+    } else if comparison_generator.has_secret {
+        let debug = implements(&skip_derive, "Debug");
+        skip_derive.push("Debug".to_owned());
+        macro_params.isolate(comparison_generator.get_implementation(&derive_input, &new, false, debug))
+    } else {
+        quote! {}
+    };
+    let fields_by_name_impl = macro_params.isolate(fields_by_name_generator.get_implementation(&macro_params, &derive_input, &new, implements(&skip_derive, "Default")));
+    let apply_to_many_impl = emit_if(macro_params.emits("apply_to_many") && implements(&skip_derive, "Clone"), || macro_params.isolate(get_apply_to_many_impl(&derive_input, &new)));
+    let audit_impl = emit_if(macro_params.audit, || macro_params.isolate(get_audit_impl(&derive_input, &new)));
+    let require_clone = |item: &str| {
+        if !implements(&skip_derive, "Clone") {
+            panic!("'{item}' needs '{}' to implement `Clone`, which 'skip_derive(Clone)' and replace-only fields prevent", new.ident);
+        }
+    };
+    let resolve_impl = emit_if(macro_params.emits("resolve"), || {
+        require_derives("resolve", &derive_input, &["Clone"]);
+        require_clone("resolve");
+        macro_params.isolate(get_resolve_impl(&derive_input, &new))
+    });
+    let profiles_impl = emit_if(macro_params.emits("profiles"), || get_profiles_impl(&macro_params, &derive_input, &new, implements(&skip_derive, "Clone")));
+    let try_from_layers_impl = emit_if(macro_params.emits("try_from_layers") && implements(&skip_derive, "Default"), || macro_params.isolate(get_try_from_layers_impl(&derive_input, &new)));
+    let finalize_impl = emit_if(macro_params.emits("finalize") && implements(&skip_derive, "Default") && implements(&skip_derive, "Clone"), || {
+        macro_params.isolate(get_finalize_impl(&derive_input, &new))
+    });
+    let merge_patch = macro_params.emits("merge_patch");
+    if merge_patch {
+        require_derives("merge_patch", &derive_input, &["Clone", "Deserialize"]);
+        if !implements(&skip_derive, "Deserialize") {
+            panic!("'merge_patch' needs '{}' to implement `Deserialize`, which 'skip_derive(Deserialize)' prevents", new.ident);
+        }
+    }
+    // Merge patches report the fields they changed with `reload_from`, which needs the diff
+    let reload_from = macro_params.emits("reload_from") || merge_patch;
+    let diff_impl = emit_if(macro_params.emits("diff") || reload_from, || {
+        macro_params.isolate(diff_generator.get_implementation(&macro_params, &derive_input, &new, reload_from, merge_patch))
+    });
+    let axum_impl = emit_if(macro_params.axum, || macro_params.isolate(get_axum_impl(&derive_input, &new)));
+    let actix_web_impl = emit_if(macro_params.actix_web, || macro_params.isolate(get_actix_web_impl(&derive_input, &new)));
+    // Only for tests, the original struct has to be cloned, compared and printed
+    let assert_applies_to_impl = emit_if(macro_params.emits("assert_applies_to"), || {
+        require_derives("assert_applies_to", &derive_input, &["Clone", "PartialEq", "Debug"]);
+        require_clone("assert_applies_to");
+        macro_params.isolate(assert_applies_to_generator.get_implementation(&derive_input, &new))
+    });
+    remove_skipped_derives(&mut new.attrs, &skip_derive);
+    let derives = get_derive_macros(&new, &macro_params.extra_derive, &skip_derive);
 
     let generated = quote! {
         #derives
         #new
+        #comparison_impls
         #apply_fn_impl
+        #apply_to_many_impl
+        #resolve_impl
+        #audit_impl
+        #profiles_impl
+        #try_from_layers_impl
+        #finalize_impl
+        #axum_impl
+        #actix_web_impl
+        #assert_applies_to_impl
+        #try_from_impl
+        #can_convert_impl
+        #validate_impl
+        #merge_impl
+        #proptest_impl
+        #struct_patch_impl
+        #traced_apply_impl
+        #apply_report_impl
+        #apply_to_watch_impl
+        #display_impl
+        #iter_set_fields_impl
+        #set_count_impl
+        #field_groups_impl
+        #versioned_apply_impl
+        #ignored_fields_impl
+        #set_columns_impl
+        #fields_by_name_impl
+        #arg_matches_impl
+        #from_proto_impl
+        #redis_hash_impl
+        #dynamo_update_impl
+        #change_enum_impl
+        #field_enum_impl
+        #set_operations_impl
+        #strip_defaults_impl
+        #tracked_wrapper_impl
+        #sourced_wrapper_impl
+        #schema_hash_impl
+        #env_vars_impl
+        #zeroize_impl
+        #diff_impl
+        #merge3_impl
+        #invert_impl
+        #builder_impl
+        #setters_impl
+        #empty_impl
+        #mask_repr_impl
+        #construction_macro
+    };
+
+    OptionalStructOutput {
+        original: quote! { #derive_input },
+        generated: hygienic_bindings(generated),
+    }
+}
+
+// Mirrors the fields of an enum variant, bound to `__patch_<field>` in the optional enum and to
+// `__target_<field>` in the original one (or in another patch)
+struct GenerateVariantImpls {
+    variant: Ident,
+    patch_pattern: Vec<TokenStream>,
+    target_pattern: Vec<TokenStream>,
+    // Fields that have to be checked by `can_convert`, and how
+    checked_pattern: Vec<TokenStream>,
+    checks: CfgStatements,
+    missing_paths: CfgStatements,
+    convert: Vec<TokenStream>,
+    // Fields that can be missing are moved like those of a struct, their binding being mutable
+    try_from_pattern: Vec<TokenStream>,
+    takes: Vec<(Option<Attribute>, TokenStream, TokenStream)>,
+    // Nested patches that aren't wrapped, the last one being moved rather than taken
+    nested_takes: Vec<(Option<Attribute>, TokenStream, TokenStream, Ident, TokenStream)>,
+    apply: CfgStatements,
+    apply_opt: CfgStatements,
+    report: CfgStatements,
+    display: CfgStatements,
+    set_count: CfgStatements,
+    field_count: CfgStatements,
+    schema_hash: Vec<TokenStream>,
+    debug_bounds: Vec<TokenStream>,
+    display_bounds: Vec<TokenStream>,
+}
+
+impl GenerateVariantImpls {
+    fn new(variant: Ident) -> Self {
+        GenerateVariantImpls {
+            variant,
+            patch_pattern: vec![],
+            target_pattern: vec![],
+            checked_pattern: vec![],
+            checks: CfgStatements::default(),
+            missing_paths: CfgStatements::default(),
+            convert: vec![],
+            try_from_pattern: vec![],
+            takes: vec![],
+            nested_takes: vec![],
+            apply: CfgStatements::default(),
+            apply_opt: CfgStatements::default(),
+            report: CfgStatements::default(),
+            display: CfgStatements::default(),
+            set_count: CfgStatements::default(),
+            field_count: CfgStatements::default(),
+            schema_hash: vec![],
+            debug_bounds: vec![],
+            display_bounds: vec![],
+        }
+    }
+}
+
+impl OptionalFieldVisitor for GenerateVariantImpls {
+    fn visit_skipped(&mut self, _global_options: &GlobalOptions, _old_field: &Field, _field_options: &FieldOptions, _default: &TokenStream) {
+        panic!("'{SKIP_ATTRIBUTE}' can't be used on the fields of an enum");
+    }
+
+    fn visit(&mut self, _global_options: &GlobalOptions, old_field: &Field, new_field: &mut Field, field_options: &FieldOptions) {
+        let FieldOptions { convert, set_if, wrapper, merge_op, group, read_only, secret, proto_name, column, redis_with, .. } = field_options;
+        if convert.is_some() || set_if.is_some() || wrapper.is_some() || merge_op.is_some() || group.is_some() || *read_only || *secret || proto_name.is_some() || column.is_some() || redis_with.is_some() {
+            panic!("Only '{RENAME_ATTRIBUTE}', '{ENUM_MERGE_ATTRIBUTE}', '{WRAP_ATTRIBUTE}' and '{SKIP_WRAP_ATTRIBUTE}' can be used on the fields of an enum");
+        }
+        if field_options.new_field_ident.to_string() != field_options.field_ident.to_string() {
+            panic!("'{FIELD_NAME_ATTRIBUTE}' can't be used on the fields of an enum");
+        }
+        let ident = &field_options.field_ident;
+        let name = &field_options.field_name;
+        let cfg_attr = &field_options.cfg_attribute;
+        let patch = format_ident!("__patch_{}", name.trim_start_matches("r#"));
+        let target = format_ident!("__target_{}", name.trim_start_matches("r#"));
+        self.patch_pattern.push(quote! { #cfg_attr #ident: #patch });
+        self.target_pattern.push(quote! { #cfg_attr #ident: #target });
+
+        let old_ty = &old_field.ty;
+        let new_ty = &new_field.ty;
+        let field_hash = schema_hash(format!("{}:{}:{}:{}", self.variant, self.schema_hash.len(), ident, quote! { #new_ty }).as_bytes());
+        let mut hash = quote! { ::optional_struct::__private::combine_schema_hash(hash, #field_hash) };
+        match &field_options.new_type {
+            // Boxed patches may refer back to this enum, so they are counted and hashed as a leaf
+            Some(_) if field_options.boxed => {
+                self.field_count.push(cfg_attr, quote! { count += 1; });
+            }
+            Some(new_type) => {
+                self.display_bounds.push(quote! { #new_type: core::fmt::Display });
+                self.field_count.push(cfg_attr, quote! { count += <#new_type>::field_count(); });
+                hash = quote! { ::optional_struct::__private::combine_schema_hash(#hash, <#new_type>::PATCH_SCHEMA_HASH) };
+            }
+            None => {
+                self.display_bounds.push(quote! { #old_ty: core::fmt::Debug });
+                self.field_count.push(cfg_attr, quote! { count += 1; });
+            }
+        }
+        self.schema_hash.push(quote! {
+            #cfg_attr
+            let hash = #hash;
+        });
+
+        let report = |value: TokenStream| quote! {
+            let old = ::core::mem::replace(#target, #value);
+            changes.extend(::optional_struct::FieldChange::from_debug(#name, &old, #target));
+        };
+        let write = |format: &str, value: TokenStream| {
+            let format = format!("{{}}{name}: {format}");
+            quote! {
+                write!(f, #format, separator, #value)?;
+                separator = ", ";
+            }
+        };
+        let is_wrapped = field_options.wrapping_behavior;
+        let is_nested = field_options.new_type.is_some();
+        let is_base_opt = field_options.is_base_opt;
+        if !is_nested || is_base_opt {
+            self.debug_bounds.push(quote! { #old_ty: core::fmt::Debug });
+        }
+        let nested_value = field_options.nested_try_into(quote! { nested });
+        let nested_count = if field_options.boxed {
+            quote! { usize::from(nested.set_count() != 0) }
+        } else {
+            quote! { nested.set_count() }
+        };
+        // Same semantics as the fields of a struct, `can_convert` being checked before converting
+        let (missing, apply, report, count) = match (is_base_opt, is_wrapped, is_nested) {
+            (true, true, false) if field_options.is_double_wrapped() => (
+                None,
+                quote! { if let Some(value) = #patch { *#target = value; } },
+                {
+                    let report = report(quote! { value });
+                    quote! { if let Some(value) = #patch { #report } }
+                },
+                quote! { usize::from(#patch.is_some()) },
+            ),
+            (_, true, false) => (
+                Some(quote! { #patch.is_none() }),
+                quote! { if let Some(value) = #patch { *#target = value; } },
+                {
+                    let report = report(quote! { value });
+                    quote! { if let Some(value) = #patch { #report } }
+                },
+                quote! { usize::from(#patch.is_some()) },
+            ),
+            (true, true, true) => (
+                Some(quote! { #patch.as_ref().is_some_and(|nested| !nested.can_convert()) }),
+                quote! {
+                    match (#target, #patch) {
+                        (Some(existing), Some(nested)) => nested.apply_to(existing),
+                        (existing, Some(nested)) => *existing = #nested_value.ok(),
+                        (_, None) => {},
+                    }
+                },
+                quote! {
+                    match (#target, #patch) {
+                        (Some(existing), Some(nested)) => {
+                            changes.extend(nested.apply_report(existing).into_iter().map(|c| c.nested_in(#name)));
+                        }
+                        (existing, Some(nested)) => {
+                            let value = #nested_value.ok();
+                            changes.extend(::optional_struct::FieldChange::from_debug(#name, existing, &value));
+                            *existing = value;
+                        }
+                        (_, None) => {}
+                    }
+                },
+                quote! { #patch.as_ref().map_or(0, |nested| #nested_count) },
+            ),
+            (_, true, true) => (
+                Some(quote! { #patch.as_ref().map_or(true, |nested| !nested.can_convert()) }),
+                quote! { if let Some(nested) = #patch { nested.apply_to(#target); } },
+                quote! {
+                    if let Some(nested) = #patch {
+                        changes.extend(nested.apply_report(#target).into_iter().map(|c| c.nested_in(#name)));
+                    }
+                },
+                quote! { #patch.as_ref().map_or(0, |nested| #nested_count) },
+            ),
+            (_, false, true) => (
+                Some(quote! { !#patch.can_convert() }),
+                quote! { #patch.apply_to(#target); },
+                quote! { changes.extend(#patch.apply_report(#target).into_iter().map(|c| c.nested_in(#name))); },
+                quote! { { let nested = &#patch; #nested_count } },
+            ),
+            (true, false, false) => (
+                None,
+                quote! { if #patch.is_some() { *#target = #patch; } },
+                {
+                    let report = report(quote! { #patch });
+                    quote! { if #patch.is_some() { #report } }
+                },
+                quote! { usize::from(#patch.is_some()) },
+            ),
+            (false, false, false) => (None, quote! { *#target = #patch; }, report(quote! { #patch }), quote! { 1 }),
+        };
+        // Patches of the same variant are merged field by field
+        let apply_opt = match (is_wrapped, is_nested) {
+            (true, true) => quote! {
+                match (#target, #patch) {
+                    (Some(existing), Some(nested)) => nested.apply_to_opt(existing),
+                    (existing, Some(nested)) => *existing = Some(nested),
+                    (_, None) => {},
+                }
+            },
+            (false, true) => quote! { #patch.apply_to_opt(#target); },
+            (_, false) if is_wrapped || is_base_opt => quote! { if #patch.is_some() { *#target = #patch; } },
+            (_, false) => quote! { *#target = #patch; },
+        };
+        let display = match (is_wrapped || is_base_opt, is_nested) {
+            (true, nested) => {
+                let write = write(if nested { "{}" } else { "{:?}" }, quote! { value });
+                quote! { if let Some(value) = #patch { #write } }
+            }
+            (false, true) => write("{}", quote! { #patch }),
+            (false, false) => write("{:?}", quote! { #patch }),
+        };
+
+        if let Some(missing) = missing {
+            self.checked_pattern.push(quote! { #cfg_attr #ident: #patch });
+            self.checks.push(cfg_attr, quote! {
+                if #missing {
+                    return false;
+                }
+            });
+            self.missing_paths.push(cfg_attr, quote! {
+                if #missing {
+                    missing.push(::optional_struct::__private::String::from(#name));
+                }
+            });
+        }
+        // The patch is converted like a struct
+        let local = format_ident!("__value_{}", name.trim_start_matches("r#"));
+        let value = match field_options.take(old_field, quote! { #patch }, &local) {
+            Some((pattern, taken)) => {
+                self.try_from_pattern.push(quote! { #cfg_attr #ident: mut #patch });
+                if field_options.is_moved_nested() {
+                    let moved = field_options.nested_try_into(quote! { #patch });
+                    self.nested_takes.push((cfg_attr.clone(), pattern, taken, patch.clone(), quote! { let #local = match #moved }));
+                } else {
+                    self.takes.push((cfg_attr.clone(), pattern, taken));
+                }
+                quote! { #local }
+            }
+            None => {
+                self.try_from_pattern.push(quote! { #cfg_attr #ident: #patch });
+                if field_options.is_double_wrapped() { quote! { #patch.flatten() } } else { quote! { #patch } }
+            }
+        };
+        self.convert.push(quote! { #cfg_attr #ident: #value });
+        self.apply.push(cfg_attr, apply);
+        self.apply_opt.push(cfg_attr, apply_opt);
+        self.report.push(cfg_attr, quote! { { #report } });
+        self.display.push(cfg_attr, quote! { { #display } });
+        self.set_count.push(cfg_attr, quote! { count += #count; });
+    }
+}
+
+// An enum is mirrored variant by variant. The fields of the active variant are applied like those
+// of a struct, and a patch for another variant replaces the value if it is complete.
+fn opt_enum(macro_params: &GlobalOptions, mut derive_input: DeriveInput) -> OptionalStructOutput {
+    let unsupported = [
+        ("actix_web", macro_params.actix_web),
+        ("audit", macro_params.audit),
+        ("axum", macro_params.axum),
+        ("builder", macro_params.builder.is_some()),
+        ("clap", macro_params.clap),
+        ("dynamodb", macro_params.dynamodb),
+        ("env_prefix", macro_params.env_prefix.is_some()),
+        ("explicit_null", macro_params.explicit_null),
+        // The unknown keys are collected in an extra field of their own
+        ("extra_fields", macro_params.extra_fields.len() > usize::from(macro_params.unknown_keys)),
+        ("merge", macro_params.merge),
+        ("proptest", macro_params.proptest),
+        ("prost", macro_params.prost.is_some()),
+        ("redis", macro_params.redis),
+        ("repr", macro_params.mask_repr),
+        ("rusqlite", macro_params.rusqlite),
+        ("schemars", macro_params.schemars),
+        ("sea_query", macro_params.sea_query),
+        ("serde_json", macro_params.serde_json),
+        ("sparse_json", macro_params.sparse_json),
+        ("struct_patch", macro_params.struct_patch.is_some()),
+        ("tokio_postgres", macro_params.tokio_postgres),
+        ("tokio_watch", macro_params.tokio_watch),
+        ("track_source", macro_params.track_source),
+        ("tracing", macro_params.tracing_level.is_some()),
+        ("unknown_keys", macro_params.unknown_keys),
+        ("wrapper", macro_params.wrapper.is_some()),
+        ("zeroize", macro_params.zeroize),
+    ];
+    if let Some((option, _)) = unsupported.iter().find(|(_, used)| *used) {
+        panic!("'{option}' option can't be used on an enum");
+    }
+    if macro_params.version_field.is_some() {
+        panic!("'{VERSION_FIELD_ATTRIBUTE}' attribute can't be used on an enum");
+    }
+    if !macro_params.targets.is_empty() {
+        panic!("'{TARGETS_ATTRIBUTE}' attribute can't be used on an enum");
+    }
+
+    let generics = derive_input.generics.clone();
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let old_name = derive_input.ident.clone();
+    let new_name = Ident::new(&macro_params.new_struct_name, old_name.span());
+    let must_use = macro_params.must_use_attribute();
+    let inline = macro_params.inline_attribute();
+    let hidden = macro_params.hidden_attribute();
+
+    let mut new = derive_input.clone();
+    new.ident = new_name.clone();
+    let Data::Enum(old_enum) = &mut derive_input.data else { unreachable!() };
+    let Data::Enum(new_enum) = &mut new.data else { unreachable!() };
+    let mut variants = vec![];
+    for (old_variant, new_variant) in old_enum.variants.iter_mut().zip(new_enum.variants.iter_mut()) {
+        let mut generator = GenerateVariantImpls::new(old_variant.ident.clone());
+        if !matches!(old_variant.fields, Fields::Unit) {
+            let variant_struct = DeriveInput {
+                attrs: vec![],
+                vis: Visibility::Inherited,
+                ident: old_variant.ident.clone(),
+                generics: generics.clone(),
+                data: Data::Struct(DataStruct { struct_token: Default::default(), fields: old_variant.fields.clone(), semi_token: None }),
+            };
+            let mut visitors = [
+                &mut SetNewFieldTypeVisitor as &mut dyn OptionalFieldVisitor,
+                &mut RemoveSerdeRenameVisitor,
+                &mut generator,
+            ];
+            let Data::Struct(visited) = visit_fields(&mut visitors, macro_params, &variant_struct).data else { unreachable!() };
+            new_variant.fields = visited.fields;
+            for field in old_variant.fields.iter_mut() {
+                strip_helper_attributes(&mut field.attrs);
+            }
+        }
+        variants.push(generator);
+    }
+
+    let mut can_convert_arms = vec![];
+    let mut missing_paths_arms = vec![];
+    let mut try_from_arms = vec![];
+    let mut apply_arms = vec![];
+    let mut apply_opt_arms = vec![];
+    let mut report_arms = vec![];
+    let mut display_arms = vec![];
+    let mut set_count_arms = vec![];
+    let mut field_counts = vec![];
+    let mut schema_hashes = vec![];
+    let mut debug_bounds = vec![quote! { #old_name #ty_generics: core::fmt::Debug }];
+    let mut display_bounds = vec![];
+    for generator in variants {
+        let GenerateVariantImpls { variant, patch_pattern, target_pattern, checked_pattern, checks, missing_paths, convert, try_from_pattern, mut takes, nested_takes, apply, apply_opt, report, display, set_count, field_count, schema_hash: hash, debug_bounds: debug, display_bounds: displayed } = generator;
+        let variant_hash = schema_hash(variant.to_string().as_bytes());
+        let name = variant.to_string();
+        let (open, close) = if patch_pattern.is_empty() { ("", "") } else { (" {", "}") };
+        // Like for a struct, only the nested patches taken before the last one have to implement
+        // `Default`
+        let moved = nested_takes.split_last().map(|((cfg_attr, _, _, patch, moved), taken)| {
+            takes.extend(taken.iter().map(|(cfg_attr, pattern, taken, ..)| (cfg_attr.clone(), pattern.clone(), taken.clone())));
+            quote! {
+                #cfg_attr
+                #moved {
+                    Ok(value) => value,
+                    Err(#patch) => return Err(#new_name::#variant { #(#patch_pattern),* }),
+                };
+            }
+        });
+        let takes = takes.iter().map(|(cfg_attr, pattern, taken)| quote! {
+            #cfg_attr
+            let #pattern = #taken else {
+                return Err(#new_name::#variant { #(#patch_pattern),* });
+            };
+        }).chain(moved);
+        can_convert_arms.push(quote! {
+            #new_name::#variant { #(#checked_pattern,)* .. } => {
+                #checks
+                true
+            }
+        });
+        missing_paths_arms.push(quote! {
+            #new_name::#variant { #(#checked_pattern,)* .. } => {
+                #missing_paths
+            }
+        });
+        try_from_arms.push(quote! {
+            #new_name::#variant { #(#try_from_pattern),* } => {
+                #(#takes)*
+                Ok(#old_name::#variant { #(#convert),* })
+            }
+        });
+        apply_arms.push(quote! {
+            (#new_name::#variant { #(#patch_pattern),* }, #old_name::#variant { #(#target_pattern),* }) => {
+                #apply
+            }
+        });
+        apply_opt_arms.push(quote! {
+            (#new_name::#variant { #(#patch_pattern),* }, #new_name::#variant { #(#target_pattern),* }) => {
+                #apply_opt
+            }
+        });
+        report_arms.push(quote! {
+            (#new_name::#variant { #(#patch_pattern),* }, #old_name::#variant { #(#target_pattern),* }) => {
+                #report
+            }
+        });
+        display_arms.push(quote! {
+            #new_name::#variant { #(#patch_pattern),* } => {
+                f.write_str(#name)?;
+                f.write_str(#open)?;
+                #display
+                f.write_str(#close)
+            }
+        });
+        set_count_arms.push(quote! {
+            #new_name::#variant { #(#patch_pattern),* } => {
+                #set_count
+            }
+        });
+        field_counts.push(quote! {
+            {
+                let mut count = 0;
+                #field_count
+                if count > max {
+                    max = count;
+                }
+            }
+        });
+        schema_hashes.push(quote! {
+            let hash = ::optional_struct::__private::combine_schema_hash(hash, #variant_hash);
+            #(#hash)*
+        });
+        debug_bounds.extend(debug);
+        display_bounds.extend(displayed);
+    }
+    let report_where_clause = where_clause_with_bounds(&generics, debug_bounds.into_iter());
+    let display_where_clause = where_clause_with_bounds(&generics, display_bounds.into_iter());
+    let seed = schema_hash(new_name.to_string().as_bytes());
+
+    if !macro_params.serde_rename {
+        remove_serde_renames(&mut new.attrs);
+    }
+    new.attrs.extend(macro_params.passthrough_attributes.iter().cloned());
+    if macro_params.doc {
+        prepend_documentation(&mut new.attrs, &format!("Optional version of [`{old_name}`], where the fields of its variants can be left unset."));
+    }
+    if macro_params.hidden {
+        new.attrs.push(syn::parse_quote! { #[doc(hidden)] });
+    }
+    // An enum can only derive `Default` along with a `#[default]` variant
+    let mut skip_derive = macro_params.skip_derive.clone();
+    skip_derive.push("Default".to_owned());
+    let extra_derive = macro_params.extra_derive.iter().filter(|t| *t != "Default").cloned().collect::<Vec<_>>();
+    remove_skipped_derives(&mut new.attrs, &macro_params.skip_derive);
+    let derives = get_derive_macros(&new, &extra_derive, &skip_derive);
+
+    let patch_log = emit_if(macro_params.emits("patch_log"), || quote! {
+        fn patch_log() -> ::optional_struct::PatchLog<#old_name #ty_generics, Self> {
+            ::optional_struct::PatchLog::new()
+        }
+    });
+    let apply_impl = macro_params.isolate(quote! {
+        impl #impl_generics #new_name #ty_generics #where_clause {
+            #must_use
+            fn build(self, mut t: #old_name #ty_generics) -> #old_name #ty_generics {
+                self.apply_to(&mut t);
+                t
+            }
+
+            fn apply_to(self, t: &mut #old_name #ty_generics) {
+                match (self, t) {
+                    #(#apply_arms)*
+                    #[allow(unreachable_patterns)]
+                    (patch, t) => {
+                        if let Ok(value) = #old_name::try_from(patch) {
+                            *t = value;
+                        }
+                    }
+                }
+            }
+
+            fn try_build(self) -> Result<#old_name #ty_generics, Self> {
+                self.try_into()
+            }
+
+            // A patch for another variant replaces the pending one
+            fn apply_to_opt(self, t: &mut Self) {
+                match (self, t) {
+                    #(#apply_opt_arms)*
+                    #[allow(unreachable_patterns)]
+                    (patch, t) => *t = patch,
+                }
+            }
+
+            #must_use
+            fn apply(mut self, t: Self) -> Self {
+                t.apply_to_opt(&mut self);
+                self
+            }
+
+            #patch_log
+        }
+
+        impl #impl_generics ::optional_struct::Applyable<#old_name #ty_generics> for #new_name #ty_generics #where_clause {
+            fn apply_to(self, t: &mut #old_name #ty_generics) {
+                Self::apply_to(self, t)
+            }
+        }
+    });
+    let missing_field_paths = emit_if(macro_params.emits("missing_fields"), || quote! {
+        // Fields of the active variant preventing the conversion
+        #must_use
+        fn missing_field_paths(&self) -> ::optional_struct::__private::Vec<::optional_struct::__private::String> {
+            #[allow(unused_mut)]
+            let mut missing = ::optional_struct::__private::Vec::new();
+            match self {
+                #(#missing_paths_arms)*
+            }
+            missing
+        }
+    });
+    let can_convert_impl = macro_params.isolate(quote! {
+        impl #impl_generics #new_name #ty_generics #where_clause {
+            // Whether the fields of the active variant are all set
+            #must_use
+            #inline
+            fn can_convert(&self) -> bool {
+                match self {
+                    #(#can_convert_arms)*
+                }
+            }
+
+            #missing_field_paths
+        }
+    });
+    let try_from_impl = macro_params.isolate(quote! {
+        #hidden
+        impl #impl_generics TryFrom<#new_name #ty_generics> for #old_name #ty_generics #where_clause {
+            type Error = #new_name #ty_generics;
+
+            // The bindings of the last nested patches are moved rather than taken
+            #[allow(unused_mut)]
+            fn try_from(v: Self::Error) -> Result<Self, Self::Error> {
+                if !v.can_convert() {
+                    return Err(v);
+                }
+                match v {
+                    #(#try_from_arms)*
+                }
+            }
+        }
+    });
+    let set_count_impl = emit_if(macro_params.emits("set_count"), || macro_params.isolate(quote! {
+        impl #impl_generics #new_name #ty_generics #where_clause {
+            // Fields of the active variant which are set
+            #must_use
+            fn set_count(&self) -> usize {
+                #[allow(unused_mut)]
+                let mut count = 0;
+                match self {
+                    #(#set_count_arms)*
+                }
+                count
+            }
+
+            // Number of fields of the largest variant
+            #must_use
+            const fn field_count() -> usize {
+                #[allow(unused_mut)]
+                let mut max = 0;
+                #(#field_counts)*
+                max
+            }
+        }
+    }));
+    let schema_hash_impl = emit_if(macro_params.emits("schema_hash"), || macro_params.isolate(quote! {
+        impl #impl_generics #new_name #ty_generics #where_clause {
+            pub const PATCH_SCHEMA_HASH: u64 = {
+                let hash = #seed;
+                #(#schema_hashes)*
+                hash
+            };
+        }
+    }));
+    let apply_report_impl = emit_if(macro_params.emits("apply_report"), || macro_params.isolate(quote! {
+        impl #impl_generics #new_name #ty_generics #report_where_clause {
+            // A change of variant is reported as a change of the whole value
+            fn apply_report(self, t: &mut #old_name #ty_generics) -> ::optional_struct::__private::Vec<::optional_struct::FieldChange> {
+                let mut changes = ::optional_struct::__private::Vec::new();
+                match (self, t) {
+                    #(#report_arms)*
+                    #[allow(unreachable_patterns)]
+                    (patch, t) => {
+                        if let Ok(value) = #old_name::try_from(patch) {
+                            let old = ::core::mem::replace(t, value);
+                            changes.extend(::optional_struct::FieldChange::from_debug("", &old, t));
+                        }
+                    }
+                }
+                changes
+            }
+        }
+    }));
+    let display_impl = emit_if(macro_params.emits("display"), || macro_params.isolate(quote! {
+        impl #impl_generics core::fmt::Display for #new_name #ty_generics #display_where_clause {
+            #[allow(unused_assignments, unused_mut, unused_variables)]
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                let mut separator = "";
+                match self {
+                    #(#display_arms)*
+                }
+            }
+        }
+    }));
+
+    let generated = quote! {
+        #derives
+        #new
+        #apply_impl
         #try_from_impl
         #can_convert_impl
+        #set_count_impl
+        #schema_hash_impl
+        #apply_report_impl
+        #display_impl
     };
 
     OptionalStructOutput {
-        original: quote! { #orig },
-        generated,
+        original: quote! { #derive_input },
+        generated: hygienic_bindings(generated),
     }
 }
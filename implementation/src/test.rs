@@ -1,4 +1,4 @@
-use quote::quote;
+use quote::{format_ident, quote};
 
 use crate::opt_struct;
 
@@ -75,4 +75,1496 @@ fn with_cfg_attributes() {
             baz: u8,
         }
     ));
-}
\ No newline at end of file
+}
+#[test]
+fn with_pyclass_passthrough() {
+    let out = opt_struct(quote!(), quote!(
+        #[optional_struct_attr(pyclass)]
+        struct Foo {
+           bar: u8,
+        }
+    ));
+    assert!(!out.original.to_string().contains("pyclass"));
+    let generated = out.generated.to_string();
+    assert!(generated.contains("# [pyclass]"));
+    assert!(generated.contains("# [pyo3 (get , set)]"));
+}
+
+#[test]
+fn with_options() {
+    opt_struct(quote!(merge), quote!(
+        struct Foo {
+           bar: u8,
+        }
+    ));
+    opt_struct(quote!(OptionalFoo, false, merge, struct_patch = FooPatch), quote!(
+        struct Foo {
+           bar: u8,
+        }
+    ));
+}
+
+#[test]
+#[should_panic(expected = "Unknown optional_struct option 'not_an_option'")]
+fn with_unknown_option() {
+    opt_struct(quote!(OptionalFoo, not_an_option), quote!(
+        struct Foo {
+           bar: u8,
+        }
+    ));
+}
+
+#[test]
+fn with_tracing() {
+    let out = opt_struct(quote!(tracing), quote!(
+        struct Foo<T> {
+            bar: T,
+            #[optional_rename(OptionalBaz)]
+            baz: Baz,
+        }
+    ));
+    let generated = out.generated.to_string();
+    assert!(generated.contains("fn apply_to_traced"));
+    assert!(generated.contains(":: tracing :: Level :: INFO"));
+    assert!(generated.contains("T : core :: fmt :: Debug"));
+}
+
+#[test]
+fn with_tuple_struct_changes() {
+    let out = opt_struct(quote!(impls(change_enum)), quote!(
+        struct Foo(u8, String);
+    ));
+    let generated = out.generated.to_string();
+    assert!(generated.contains("enum FooChange { Field0 (u8) , Field1 (String) , }"));
+}
+
+#[test]
+fn with_typestate_builder() {
+    let out = opt_struct(quote!(builder = typestate), quote!(
+        struct Foo<T: Clone> {
+            bar: T,
+            baz: Option<u8>,
+        }
+    ));
+    let generated = out.generated.to_string();
+    assert!(generated.contains("struct FooBuilder < T : Clone , S0 ,"));
+    assert!(generated.contains("FooBuilder < T , :: optional_struct :: typestate :: Set ,"));
+}
+
+#[test]
+fn without_empty_const() {
+    let out = opt_struct(quote!(), quote!(
+        struct Foo {
+            bar: u8,
+            #[optional_skip_wrap]
+            baz: u8,
+        }
+    ));
+    assert!(!out.generated.to_string().contains("EMPTY"));
+}
+
+#[test]
+#[should_panic(expected = "'empty' can't be generated: 'baz' isn't an `Option`")]
+fn with_empty_const_and_unwrapped_field() {
+    opt_struct(quote!(impls(empty)), quote!(
+        struct Foo {
+            bar: u8,
+            #[optional_skip_wrap]
+            baz: u8,
+        }
+    ));
+}
+
+#[test]
+#[should_panic(expected = "'empty' can't be generated: 'reason' isn't an `Option`")]
+fn with_empty_const_and_required_extra_field() {
+    opt_struct(quote!(impls(empty), extra_fields(reason: String)), quote!(
+        struct Foo {
+            bar: u8,
+        }
+    ));
+}
+
+#[test]
+fn with_must_use_and_inline() {
+    let foo = quote!(
+        struct Foo {
+            bar: u8,
+        }
+    );
+    let generated = opt_struct(quote!(impls(setters)), foo.clone()).generated.to_string();
+    assert!(generated.contains("# [must_use] fn apply"));
+    assert!(generated.contains("# [inline] fn set_bar"));
+
+    let generated = opt_struct(quote!(must_use = false, inline = false, impls(setters)), foo).generated.to_string();
+    assert!(!generated.contains("must_use"));
+    assert!(!generated.contains("inline"));
+}
+
+#[test]
+#[should_panic(expected = "The setter of 'count' would clash with the generated `set_count`")]
+fn with_setter_clashing_with_set_count() {
+    opt_struct(quote!(impls(set_count, setters)), quote!(
+        struct Foo {
+            count: u8,
+        }
+    ));
+}
+
+#[test]
+#[should_panic(expected = "The setter of 'columns' would clash with the generated `set_columns`")]
+fn with_setter_clashing_with_set_columns() {
+    opt_struct(quote!(impls(set_columns, setters)), quote!(
+        struct Foo {
+            columns: u8,
+        }
+    ));
+}
+
+#[test]
+#[should_panic(expected = "The setter of 'clause' would clash with the generated `set_clause`")]
+fn with_setter_clashing_with_set_clause() {
+    opt_struct(quote!(rusqlite, impls(setters)), quote!(
+        struct Foo {
+            clause: u8,
+        }
+    ));
+}
+
+#[test]
+fn with_renamed_setter() {
+    let generated = opt_struct(quote!(impls(set_count, setters)), quote!(
+        struct Foo {
+            #[optional_field_name(item_count)]
+            count: u8,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("fn set_item_count"));
+
+    // Without `set_count`, nothing clashes
+    let generated = opt_struct(quote!(impls(setters)), quote!(
+        struct Foo {
+            count: u8,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("fn set_count"));
+}
+
+#[test]
+fn with_documentation() {
+    let foo = quote!(
+        /// The foo
+        struct Foo {
+            /// The bar
+            bar: u8,
+        }
+    );
+    let out = opt_struct(quote!(), foo.clone());
+    let generated = out.generated.to_string();
+    assert!(generated.contains("# [doc = \"Optional version of [`Foo`], where fields can be left unset.\"] # [doc = \"\"] # [doc = r\" The foo\"]"));
+    assert!(generated.contains("# [doc = \"Optional mirror of [`Foo::bar`].\"] # [doc = \"\"] # [doc = r\" The bar\"]"));
+    assert!(!out.original.to_string().contains("Optional"));
+
+    let generated = opt_struct(quote!(doc = false), foo).generated.to_string();
+    assert!(!generated.contains("Optional version"));
+    assert!(!generated.contains("Optional mirror"));
+}
+
+#[test]
+fn with_hidden() {
+    let foo = quote!(
+        struct Foo {
+            bar: u8,
+        }
+    );
+    let generated = opt_struct(quote!(hidden), foo.clone()).generated.to_string();
+    assert!(generated.contains("# [doc (hidden)] struct OptionalFoo"));
+    assert!(generated.contains("# [doc (hidden)] impl TryFrom < OptionalFoo > for Foo"));
+
+    let generated = opt_struct(quote!(), foo).generated.to_string();
+    assert!(!generated.contains("hidden"));
+}
+
+#[test]
+fn with_many_fields() {
+    let fields = (0..300usize).map(|i| {
+        let ident = format_ident!("field_{}", i);
+        quote! { #ident: u32, }
+    });
+    let foo = quote!(
+        struct Foo {
+            #(#fields)*
+        }
+    );
+    let generated = opt_struct(quote!(), foo).generated.to_string();
+    assert!(generated.contains("field_299 : Option < u32 >"));
+}
+
+#[test]
+fn with_split_impls() {
+    let foo = quote!(
+        struct Foo {
+            bar: u8,
+        }
+    );
+    let generated = opt_struct(quote!(split, impls(display)), foo.clone()).generated.to_string();
+    assert!(generated.contains("const _ : () = { impl TryFrom < OptionalFoo > for Foo"));
+    assert!(generated.contains("impl core :: fmt :: Display for OptionalFoo"));
+    assert!(!generated.contains("enum FooChange"));
+    assert!(!generated.contains("fn iter_set_fields"));
+
+    let generated = opt_struct(quote!(impls(change_enum, iter_set_fields)), foo).generated.to_string();
+    assert!(!generated.contains("const _"));
+    assert!(generated.contains("enum FooChange"));
+    assert!(generated.contains("fn iter_set_fields"));
+}
+
+#[test]
+fn try_from_without_unwrap() {
+    let generated = opt_struct(quote!(), quote!(
+        struct Foo {
+            bar: u8,
+            baz: Option<String>,
+            #[optional_rename(OptionalQux)]
+            qux: Qux,
+            #[optional_rename(OptionalQux)]
+            #[optional_wrap]
+            quux: Qux,
+        }
+    )).generated.to_string();
+    let try_from = &generated[generated.find("impl TryFrom").unwrap()..];
+    let try_from = &try_from[..try_from.find("impl OptionalFoo").unwrap()];
+    assert!(!try_from.contains("unwrap"));
+}
+
+#[test]
+fn shared_missing_field_check() {
+    let generated = opt_struct(quote!(), quote!(
+        struct Foo {
+            bar: u8,
+            baz: Option<String>,
+        }
+    )).generated.to_string();
+    assert_eq!(generated.matches("fn __missing_field").count(), 1);
+    assert!(generated.contains("if v . __missing_field () . is_some () { return Err (v) ; }"));
+    assert!(generated.contains("fn can_convert (& self) -> bool { self . __missing_field () . is_none () }"));
+}
+
+#[test]
+fn with_grouped_cfg_fields() {
+    let generated = opt_struct(quote!(), quote!(
+        struct Foo {
+            #[cfg(feature = "a")]
+            bar: u8,
+            #[cfg(feature = "a")]
+            baz: u8,
+            qux: u8,
+        }
+    )).generated.to_string();
+    let can_convert = &generated[generated.find("fn __missing_field").unwrap()..];
+    let can_convert = &can_convert[..can_convert.find("fn can_convert").unwrap()];
+    assert_eq!(can_convert.matches("# [cfg (feature = \"a\")]").count(), 1);
+}
+
+#[test]
+fn can_convert_wrapped_nested() {
+    let generated = opt_struct(quote!(), quote!(
+        struct Foo {
+            #[optional_rename(OptionalBar)]
+            #[optional_wrap]
+            bar: Bar,
+            #[optional_rename(OptionalBar)]
+            baz: Bar,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("if if let Some (i) = & self . bar { ! i . can_convert () } else { true } { return Some (\"bar\") ; }"));
+    assert!(generated.contains("if ! self . baz . can_convert () { return Some (\"baz\") ; }"));
+}
+
+#[test]
+fn with_wrap_before_rename() {
+    let wrap_first = opt_struct(quote!(), quote!(
+        struct Foo {
+            #[optional_wrap]
+            #[optional_rename(OptionalBar)]
+            bar: Bar,
+        }
+    )).generated.to_string();
+    let rename_first = opt_struct(quote!(), quote!(
+        struct Foo {
+            #[optional_rename(OptionalBar)]
+            #[optional_wrap]
+            bar: Bar,
+        }
+    )).generated.to_string();
+    assert!(wrap_first.contains("bar : Option < OptionalBar >"));
+    assert_eq!(wrap_first, rename_first);
+}
+
+#[test]
+#[should_panic(expected = "'optional_skip_wrap' can't be used on a field with 'optional_wrap' or 'optional_wrap_with'")]
+fn with_wrap_and_skip_wrap() {
+    opt_struct(quote!(), quote!(
+        struct Foo {
+            #[optional_wrap]
+            #[optional_skip_wrap]
+            bar: u8,
+        }
+    ));
+}
+
+#[test]
+#[should_panic(expected = "'optional_skip_wrap' can't be used on an Option field with 'optional_rename'")]
+fn with_skip_wrap_renamed_option() {
+    opt_struct(quote!(), quote!(
+        struct Foo {
+            #[optional_rename(OptionalBar)]
+            #[optional_skip_wrap]
+            bar: Option<Bar>,
+        }
+    ));
+}
+
+#[test]
+fn original_keeps_foreign_attributes() {
+    let out = opt_struct(quote!(), quote!(
+        #[derive(Debug, sqlx::FromRow)]
+        #[optional_struct_attr(derive(Hash))]
+        #[serde(rename_all = "camelCase")]
+        #[sqlx(rename_all = "snake_case")]
+        struct Foo {
+            #[sqlx(rename = "bar_id")]
+            #[optional_wrap]
+            #[serde(default)]
+            /// The bar
+            #[optional_field_name(baz)]
+            #[sqlx(default)]
+            bar: u8,
+        }
+    ));
+    let expected = quote!(
+        #[derive(Debug, sqlx::FromRow)]
+        #[serde(rename_all = "camelCase")]
+        #[sqlx(rename_all = "snake_case")]
+        struct Foo {
+            #[sqlx(rename = "bar_id")]
+            #[serde(default)]
+            /// The bar
+            #[sqlx(default)]
+            bar: u8,
+        }
+    );
+    assert_eq!(out.original.to_string(), expected.to_string());
+}
+
+#[test]
+fn with_stable_derive_order() {
+    let generated = opt_struct(quote!(OptionalFoo, true, derive(Hash, Eq, Hash)), quote!(
+        #[derive(Debug)]
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(generated.starts_with("# [derive (Clone , PartialEq , Default , Hash , Eq)]"));
+}
+
+#[test]
+#[should_panic(expected = "A nested field with '#[serde(flatten)]' can't be wrapped, nor be an Option")]
+fn with_wrapped_flattened_nested() {
+    opt_struct(quote!(), quote!(
+        struct Foo {
+            #[serde(default, flatten)]
+            #[optional_rename(OptionalBar)]
+            #[optional_wrap]
+            bar: Bar,
+        }
+    ));
+}
+
+#[test]
+fn with_option_paths() {
+    let generated = opt_struct(quote!(), quote!(
+        struct Foo {
+            bar: std::option::Option<u8>,
+            baz: ::core::option::Option<u8>,
+            qux: dsl::Option<u8>,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("pub bar : std :: option :: Option < u8 > ,"));
+    assert!(generated.contains("pub baz : :: core :: option :: Option < u8 > ,"));
+    assert!(generated.contains("pub qux : Option < dsl :: Option < u8 > >"));
+}
+
+#[test]
+#[should_panic(expected = "'optional_not_option' and 'optional_option_like' can't be used on the same field")]
+fn with_conflicting_option_overrides() {
+    opt_struct(quote!(), quote!(
+        struct Foo {
+            #[optional_not_option]
+            #[optional_option_like]
+            bar: Setting<u8>,
+        }
+    ));
+}
+
+#[test]
+fn with_derive_by_full_path() {
+    let generated = opt_struct(quote!(), quote!(
+        #[derive(core::fmt::Debug, std::clone::Clone)]
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(generated.starts_with("# [derive (PartialEq , Default)]"));
+    assert!(generated.contains("# [derive (core :: fmt :: Debug , std :: clone :: Clone)] struct OptionalFoo"));
+}
+
+#[test]
+fn with_skip_derive() {
+    let generated = opt_struct(quote!(OptionalFoo, true, skip_derive(Default, core::fmt::Debug)), quote!(
+        #[derive(Debug, Eq)]
+        #[derive(Default)]
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(generated.starts_with("# [derive (Clone , PartialEq)]"));
+    assert!(generated.contains("# [derive (Eq)] struct OptionalFoo"));
+    let header = &generated[..generated.find("struct OptionalFoo").unwrap()];
+    assert_eq!(header.matches("# [derive").count(), 2);
+}
+
+#[test]
+fn with_enum() {
+    let generated = opt_struct(quote!(), quote!(
+        enum Foo {
+            Bar,
+            Baz(u8, #[optional_skip_wrap] u8),
+            Qux { quux: Option<u8> },
+        }
+    )).generated.to_string();
+    assert!(generated.contains("enum OptionalFoo { Bar , Baz (Option < u8 > , u8) , Qux { quux : Option < u8 > } , }"));
+    assert!(generated.contains("OptionalFoo :: Baz { 0 : __patch_0 , .. } => { if __patch_0 . is_none () { return false ; } true }"));
+    assert!(generated.contains("OptionalFoo :: Qux { .. } => { true }"));
+    assert!(generated.contains("let Some (__value_0) = :: core :: mem :: take (& mut __patch_0) else { return Err (OptionalFoo :: Baz { 0 : __patch_0 , 1 : __patch_1 }) ; } ;"));
+    assert!(!generated.contains("unreachable !"));
+}
+
+#[test]
+fn with_enum_split_impls() {
+    let foo = quote!(
+        enum Foo {
+            Bar(u8),
+        }
+    );
+    let generated = opt_struct(quote!(split, impls(display)), foo).generated.to_string();
+    assert!(generated.contains("const _ : () = { impl core :: fmt :: Display for OptionalFoo"));
+    assert!(generated.contains("const _ : () = { impl TryFrom < OptionalFoo > for Foo"));
+    assert!(!generated.contains("fn apply_report"));
+    assert!(!generated.contains("fn patch_log"));
+    assert!(!generated.contains("fn set_count"));
+    assert!(!generated.contains("PATCH_SCHEMA_HASH"));
+}
+
+#[test]
+fn with_enum_merge() {
+    let generated = opt_struct(quote!(), quote!(
+        struct Foo {
+            #[optional_enum_merge]
+            bar: crate::Bar<u8>,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("bar : Option < crate :: OptionalBar < u8 > >"));
+}
+
+#[test]
+#[should_panic(expected = "'optional_enum_merge' can't be used on a field with 'optional_rename'")]
+fn with_enum_merge_renamed() {
+    opt_struct(quote!(), quote!(
+        struct Foo {
+            #[optional_enum_merge]
+            #[optional_rename(OptionalBar)]
+            bar: Bar,
+        }
+    ));
+}
+
+#[test]
+#[should_panic(expected = "'builder' option can't be used on an enum")]
+fn with_enum_builder() {
+    opt_struct(quote!(OptionalFoo, true, builder), quote!(
+        enum Foo {
+            Bar,
+        }
+    ));
+}
+
+#[test]
+fn with_transparent() {
+    let generated = opt_struct(quote!(), quote!(
+        struct Foo {
+            #[optional_transparent(u64)]
+            bar: Bar,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("bar : Option < u64 >"));
+}
+
+#[test]
+#[should_panic(expected = "'optional_convert' and 'optional_transparent' can't be used on the same field")]
+fn with_transparent_and_convert() {
+    opt_struct(quote!(), quote!(
+        struct Foo {
+            #[optional_transparent(u64)]
+            #[optional_convert(ty = "u64", into = "into", from = "from")]
+            bar: Bar,
+        }
+    ));
+}
+
+#[test]
+fn with_tuple_struct() {
+    let generated = opt_struct(quote!(impls(missing_fields, setters)), quote!(
+        struct Foo(u8, #[optional_skip_wrap] u16);
+    )).generated.to_string();
+    assert!(generated.contains("fn with_0 (mut self , value : impl Into < u8 >) -> Self"));
+    assert!(generated.contains("fn get_0 (& self) -> Option < & u8 > { self . 0 . as_ref () }"));
+    assert!(generated.contains("fn get_1 (& self) -> & u16 { & self . 1 }"));
+    assert!(generated.contains("missing . push (\".0\")"));
+}
+
+#[test]
+fn with_unit_struct() {
+    let output = opt_struct(quote!(), quote!(
+        struct Foo;
+    ));
+    assert_eq!(output.original.to_string(), "struct Foo ;");
+    assert!(output.generated.to_string().contains("struct OptionalFoo { }"));
+}
+
+#[test]
+#[should_panic(expected = "'optional_atomic' can't be used on a field with 'optional_rename', 'optional_enum_merge' or 'optional_skip_wrap'")]
+fn with_atomic_renamed() {
+    opt_struct(quote!(), quote!(
+        struct Foo {
+            #[optional_atomic]
+            #[optional_rename(OptionalBar)]
+            bar: Bar,
+        }
+    ));
+}
+
+#[test]
+fn with_replace_only() {
+    let generated = opt_struct(quote!(), quote!(
+        struct Foo {
+            bar: u8,
+            #[optional_replace_only]
+            baz: Box<dyn Baz>,
+        }
+    )).generated.to_string();
+    assert!(generated.starts_with("# [derive (Default)] # [doc"));
+    assert!(generated.contains("impl PartialEq for OptionalFoo"));
+    assert!(!generated.contains("self . baz != other . baz"));
+}
+
+#[test]
+fn with_elementwise() {
+    let generated = opt_struct(quote!(), quote!(
+        struct Foo {
+            #[optional_elementwise]
+            bar: [u8; 4],
+        }
+    )).generated.to_string();
+    assert!(generated.contains("pub bar : [Option < u8 > ; 4]"));
+    assert!(generated.contains("let Ok (__bar) = < [u8 ; 4] > :: try_from (:: core :: mem :: replace (& mut v . bar , :: core :: array :: from_fn (| _ | None))"));
+    assert!(!generated.contains("unreachable !"));
+}
+
+#[test]
+#[should_panic(expected = "'optional_elementwise' can't be used on a field that is wrapped, nested, converted or merged")]
+fn with_elementwise_wrapped() {
+    opt_struct(quote!(), quote!(
+        struct Foo {
+            #[optional_elementwise]
+            #[optional_wrap]
+            bar: [u8; 4],
+        }
+    ));
+}
+
+#[test]
+fn with_generic_rename() {
+    let generated = opt_struct(quote!(), quote!(
+        struct Foo<T> {
+            #[optional_rename(crate::OptionalBar<T, u8>)]
+            bar: Bar<T, u8>,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("pub bar : crate :: OptionalBar < T , u8 >"));
+}
+
+#[test]
+fn with_boxed_rename() {
+    let generated = opt_struct(quote!(), quote!(
+        struct Node {
+            value: u32,
+            #[optional_rename(OptionalNode)]
+            next: Option<Box<Node>>,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("pub next : Option < :: optional_struct :: __private :: Box < OptionalNode > >"));
+    assert!(!generated.contains("< OptionalNode > :: field_count ()"));
+}
+
+#[test]
+fn with_diff() {
+    // `Foo` may already implement `Diffable` by hand
+    let generated = opt_struct(quote!(), quote!(
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(!generated.contains("Diffable"));
+
+    let generated = opt_struct(quote!(impls(diff)), quote!(
+        struct Foo {
+            bar: u8,
+            #[optional_merge_op(add)]
+            total: u8,
+        }
+    )).generated.to_string();
+    assert!(!generated.contains("Diffable"));
+
+    let generated = opt_struct(quote!(impls(diff)), quote!(
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("impl :: optional_struct :: Diffable for Foo where u8 : PartialEq + Clone"));
+
+    let generated = opt_struct(quote!(impls(diff)), quote!(
+        struct Foo {
+            bar: u8,
+            #[optional_replace_only]
+            baz: Box<dyn Fn()>,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("impl :: optional_struct :: Diffable for Foo where u8 : PartialEq + Clone {"));
+    assert!(generated.contains("baz : :: core :: default :: Default :: default () ,"));
+}
+
+#[test]
+fn with_merge3() {
+    let generated = opt_struct(quote!(impls(merge3)), quote!(
+        struct Foo {
+            bar: u8,
+            #[optional_rename(OptionalBaz)]
+            baz: Baz,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("impl :: optional_struct :: __private :: Merge3 < Foo > for OptionalFoo where u8 : PartialEq , OptionalBaz : :: optional_struct :: __private :: Merge3 < Baz >"));
+    assert!(generated.contains("fn merge3 (base : & Foo , ours : Self , theirs : Self) -> Result < Self , :: optional_struct :: Conflicts >"));
+}
+
+#[test]
+fn with_invert() {
+    let generated = opt_struct(quote!(impls(invert)), quote!(
+        struct Foo {
+            bar: u8,
+            #[optional_rename(OptionalBaz)]
+            baz: Option<Box<Baz>>,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("fn invert (& self , base : & Foo) -> Self"));
+    assert!(generated.contains("Some (:: optional_struct :: __private :: Box :: new (nested . invert (current)))"));
+}
+
+#[test]
+fn with_apply_to_many() {
+    let generated = opt_struct(quote!(impls(apply_to_many)), quote!(
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("fn apply_to_many"));
+
+    let generated = opt_struct(quote!(skip_derive(Clone), impls(apply_to_many)), quote!(
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(!generated.contains("fn apply_to_many"));
+}
+
+#[test]
+fn with_resolve() {
+    let generated = opt_struct(quote!(impls(resolve)), quote!(
+        #[derive(Clone)]
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("fn resolve (& self , fallbacks : & [& Self] , base : & Foo) -> Foo"));
+}
+
+#[test]
+#[should_panic(expected = "'resolve' needs 'Foo' to derive `Clone`")]
+fn with_resolve_without_clone() {
+    opt_struct(quote!(impls(resolve)), quote!(
+        struct Foo {
+            bar: u8,
+        }
+    ));
+}
+
+#[test]
+#[should_panic(expected = "'resolve' needs 'OptionalFoo' to implement `Clone`")]
+fn with_resolve_and_skipped_clone() {
+    opt_struct(quote!(impls(resolve), skip_derive(Clone)), quote!(
+        #[derive(Clone)]
+        struct Foo {
+            bar: u8,
+        }
+    ));
+}
+
+#[test]
+fn with_field_enum() {
+    // `FooField` may already be defined next to `Foo`
+    let generated = opt_struct(quote!(), quote!(
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(!generated.contains("enum FooField"));
+
+    let generated = opt_struct(quote!(impls(field_enum)), quote!(
+        struct Foo {
+            bar_baz: u8,
+            #[optional_skip_wrap]
+            qux: u8,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("enum FooField { BarBaz , Qux , }"));
+    assert!(!generated.contains("fn retain_fields"));
+}
+
+#[test]
+fn with_tracked_wrapper() {
+    // `TrackedFoo` may already be defined next to `Foo`
+    let generated = opt_struct(quote!(), quote!(
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(!generated.contains("struct TrackedFoo"));
+
+    let generated = opt_struct(quote!(impls(tracked)), quote!(
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("struct TrackedFoo"));
+    assert!(generated.contains("fn set_bar (& mut self , value : u8)"));
+}
+
+#[test]
+fn with_set_operations() {
+    let generated = opt_struct(quote!(impls(set_operations)), quote!(
+        struct Foo {
+            bar: u8,
+            #[optional_rename(OptionalBaz)]
+            baz: Option<Baz>,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("where Option < u8 > : Clone + Default"));
+    assert!(generated.contains("(Some (nested) , Some (value)) => Some (nested . subtract (value))"));
+}
+
+#[test]
+fn with_strip_defaults() {
+    let generated = opt_struct(quote!(impls(strip_defaults)), quote!(
+        struct Foo<T> {
+            bar: T,
+            #[optional_skip_wrap]
+            total: u8,
+            #[optional_rename(OptionalBaz<T>)]
+            baz: Baz<T>,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("where T : PartialEq , OptionalBaz < T > : :: optional_struct :: __private :: StripDefaults < Baz < T > >"));
+    assert!(!generated.contains("reference . total"));
+}
+
+#[test]
+fn with_assert_applies_to() {
+    let generated = opt_struct(quote!(impls(assert_applies_to)), quote!(
+        #[derive(Clone, Debug, PartialEq)]
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("# [cfg (test)] impl OptionalFoo where Foo : Clone , OptionalFoo : Clone , u8 : PartialEq + core :: fmt :: Debug"));
+}
+
+#[test]
+#[should_panic(expected = "'assert_applies_to' needs 'Foo' to derive `PartialEq`")]
+fn with_assert_applies_to_without_partial_eq() {
+    opt_struct(quote!(impls(assert_applies_to)), quote!(
+        #[derive(Clone, Debug)]
+        struct Foo {
+            bar: u8,
+        }
+    ));
+}
+
+#[test]
+fn with_arbitrary_and_proptest() {
+    let generated = opt_struct(quote!(arbitrary, proptest), quote!(
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(generated.contains(":: arbitrary :: Arbitrary"));
+    assert!(generated.contains("impl :: proptest :: arbitrary :: Arbitrary for OptionalFoo"));
+    assert!(generated.contains("patch . bar = value"));
+}
+
+#[test]
+fn with_patch_log() {
+    let generated = opt_struct(quote!(impls(patch_log)), quote!(
+        struct Foo<T> {
+            bar: T,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("fn patch_log () -> :: optional_struct :: PatchLog < Foo < T > , Self >"));
+    assert!(generated.contains("impl < T > :: optional_struct :: Applyable < Foo < T > > for OptionalFoo < T >"));
+}
+
+#[test]
+fn with_apply_boxed() {
+    let foo = quote!(
+        struct Foo {
+            bar: u8,
+        }
+    );
+    let generated = opt_struct(quote!(), foo.clone()).generated.to_string();
+    assert!(!generated.contains("fn apply_boxed"));
+
+    let generated = opt_struct(quote!(impls(apply_boxed)), foo).generated.to_string();
+    assert!(generated.contains("fn apply_boxed (self : :: optional_struct :: __private :: Box < Self > , t : & mut Foo)"));
+}
+
+#[test]
+fn with_track_source() {
+    let generated = opt_struct(quote!(track_source, impls(display)), quote!(
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("struct SourcedFoo"));
+    assert!(generated.contains("enum FooField"));
+    assert!(generated.contains("fn source_of (& self , field : FooField) -> Option < & str >"));
+    assert!(generated.contains("self . record_source (FooField :: Bar , layer)"));
+}
+
+#[test]
+fn with_reload_from() {
+    // `Foo` may already have a `reload_from` function
+    let generated = opt_struct(quote!(), quote!(
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(!generated.contains("fn reload_from"));
+
+    let generated = opt_struct(quote!(impls(reload_from)), quote!(
+        struct Foo {
+            #[optional_skip_wrap]
+            bar: u8,
+            baz: u8,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("fn reload_from (& mut self , other : Self) -> :: optional_struct :: __private :: Vec < & 'static str >"));
+    assert!(generated.contains("if self . bar != other . bar { changes . push (\"bar\") ; }"));
+    assert!(generated.contains("if patch . baz . as_ref () . is_some () { changes . push (\"baz\") ; }"));
+}
+
+#[test]
+fn with_unknown_keys() {
+    let generated = opt_struct(quote!(unknown_keys), quote!(
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("# [serde (flatten)] pub unknown_keys : :: serde_json :: Map < :: optional_struct :: __private :: String , :: serde_json :: Value >"));
+
+    let generated = opt_struct(quote!(unknown_keys = extra), quote!(
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("pub extra : :: serde_json :: Map"));
+}
+
+#[test]
+#[should_panic(expected = "'unknown_keys' option can't be used on an enum")]
+fn with_enum_unknown_keys() {
+    opt_struct(quote!(unknown_keys), quote!(
+        enum Foo {
+            Bar,
+        }
+    ));
+}
+
+#[test]
+fn with_try_from_layers() {
+    // `Foo` may already have a `try_from_layers` function
+    let generated = opt_struct(quote!(), quote!(
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(!generated.contains("fn try_from_layers"));
+
+    let generated = opt_struct(quote!(impls(try_from_layers)), quote!(
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("fn try_from_layers (layers : impl IntoIterator < Item = OptionalFoo >) -> Result < Self , :: optional_struct :: MissingFieldsError >"));
+
+    let generated = opt_struct(quote!(skip_derive(Default), impls(try_from_layers)), quote!(
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(!generated.contains("fn try_from_layers"));
+}
+
+#[test]
+fn with_clap() {
+    let generated = opt_struct(quote!(clap), quote!(
+        struct Foo {
+            bar: u8,
+            baz: Option<String>,
+            tags: Vec<String>,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("fn update_from_arg_matches (& mut self , matches : & :: clap :: ArgMatches)"));
+    assert!(generated.contains("match matches . try_get_one :: < u8 > (\"bar\")"));
+    assert!(generated.contains("match matches . try_get_many :: < String > (\"tags\")"));
+    assert!(generated.contains("self . tags = Some (value . cloned () . collect ())"));
+    assert!(generated.contains("Err (:: clap :: parser :: MatchesError :: UnknownArgument { .. }) => { }"));
+    assert!(generated.contains("matches . value_source (\"baz\") != Some (:: clap :: parser :: ValueSource :: DefaultValue)"));
+    assert!(generated.contains("self . baz = Some (value . clone ())"));
+}
+
+#[test]
+fn with_env_vars() {
+    let generated = opt_struct(quote!(env_prefix = "APP_"), quote!(
+        struct Foo {
+            bar_baz: u8,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("pub const ENV_VARS : & 'static [(& 'static str , & 'static str)] = & [(\"bar_baz\" , \"APP_BAR_BAZ\") ,] ;"));
+
+    let generated = opt_struct(quote!(), quote!(
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(!generated.contains("ENV_VARS"));
+}
+
+#[test]
+fn with_secret() {
+    let generated = opt_struct(quote!(zeroize, impls(apply_report, display)), quote!(
+        struct Foo {
+            bar: u8,
+            #[optional_secret]
+            password: String,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("# [derive (Clone , PartialEq , Default)]"));
+    assert!(generated.contains("fields . field (\"password\" , & :: optional_struct :: __private :: Redacted (self . password . as_ref () . is_some ()))"));
+    assert!(generated.contains("password: <redacted>"));
+    assert!(generated.contains("changes [reported ..] . iter_mut () . for_each (:: optional_struct :: FieldChange :: redact)"));
+    assert!(generated.contains("impl :: zeroize :: Zeroize for OptionalFoo where String : :: zeroize :: Zeroize"));
+    assert!(generated.contains(":: zeroize :: Zeroize :: zeroize (& mut self . password) ;"));
+    assert!(!generated.contains(":: zeroize :: Zeroize :: zeroize (& mut self . bar) ;"));
+}
+
+#[test]
+#[should_panic(expected = "can be used on the fields of an enum")]
+fn with_enum_secret() {
+    opt_struct(quote!(), quote!(
+        enum Foo {
+            Bar {
+                #[optional_secret]
+                password: String,
+            },
+        }
+    ));
+}
+
+#[test]
+fn with_finalize() {
+    // `Foo` may already have a `finalize` function
+    let generated = opt_struct(quote!(), quote!(
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(!generated.contains("fn finalize"));
+
+    let generated = opt_struct(quote!(impls(finalize)), quote!(
+        struct Foo {
+            bar: u8,
+            #[optional_rename(OptionalBaz)]
+            baz: Baz,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("fn finalize (layers : & [(& str , OptionalFoo)]) -> Result < Self , :: optional_struct :: FinalizeError >"));
+    assert!(generated.contains("fn missing_field_paths (& self)"));
+    assert!(generated.contains("missing . extend (self . baz . missing_field_paths () . into_iter () . map (| path | :: optional_struct :: __private :: format ! (\"{}.{}\" , \"baz\" , path)))"));
+}
+
+#[test]
+fn with_construction_macro() {
+    // `optional_foo!` may already be defined in the module of `Foo`
+    let generated = opt_struct(quote!(), quote!(
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(!generated.contains("macro_rules !"));
+
+    let generated = opt_struct(quote!(impls(construction_macro)), quote!(
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("macro_rules ! optional_foo"));
+    assert!(!generated.contains("macro_export"));
+}
+
+#[test]
+fn with_profiles() {
+    // `FooProfiles` may already be defined next to `Foo`
+    let generated = opt_struct(quote!(), quote!(
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(!generated.contains("struct FooProfiles"));
+
+    let generated = opt_struct(quote!(impls(profiles)), quote!(
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("struct FooProfiles { profiles : :: optional_struct :: __private :: BTreeMap < :: optional_struct :: __private :: String , OptionalFoo > , }"));
+    assert!(generated.contains("fn resolve (& self , chain : & [& str] , base : Foo) -> Result < Foo , :: optional_struct :: UnknownProfile > where OptionalFoo : Clone"));
+
+    let generated = opt_struct(quote!(skip_derive(Clone), impls(profiles)), quote!(
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("struct FooProfiles"));
+    assert!(!generated.contains("fn resolve (& self , fallbacks"));
+}
+
+#[test]
+fn with_web_extractors() {
+    let generated = opt_struct(quote!(axum, actix_web), quote!(
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("impl < __S > :: axum :: extract :: FromRequest < __S > for OptionalFoo where __S : Send + Sync , Self : :: optional_struct :: __private :: DeserializeOwned"));
+    assert!(generated.contains("impl :: actix_web :: FromRequest for OptionalFoo where Self : :: optional_struct :: __private :: DeserializeOwned + 'static"));
+
+    let generated = opt_struct(quote!(), quote!(
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(!generated.contains("FromRequest"));
+}
+
+#[test]
+fn with_explicit_null() {
+    let generated = opt_struct(quote!(explicit_null), quote!(
+        struct Foo {
+            #[optional_wrap]
+            bar: Option<u8>,
+            baz: Option<u8>,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("# [serde (default , deserialize_with = \"::optional_struct::__private::deserialize_some\")] pub bar : Option < Option < u8 > >"));
+    assert!(generated.contains("pub baz : Option < u8 >"));
+    assert_eq!(generated.matches("deserialize_some").count(), 1);
+}
+
+#[test]
+fn with_merge_patch() {
+    let foo = quote!(
+        #[derive(Clone, Deserialize)]
+        struct Foo {
+            bar: u8,
+        }
+    );
+    let generated = opt_struct(quote!(impls(merge_patch)), foo.clone()).generated.to_string();
+    assert!(generated.contains("fn apply_merge_patch (& mut self , body : & [u8]) -> Result < :: optional_struct :: __private :: Vec < & 'static str > , :: serde_json :: Error >"));
+    assert!(generated.contains("Ok (self . reload_from (merged))"));
+
+    let generated = opt_struct(quote!(serde_json), foo).generated.to_string();
+    assert!(!generated.contains("apply_merge_patch"));
+}
+
+#[test]
+#[should_panic(expected = "'merge_patch' needs 'Foo' to derive `Clone`")]
+fn with_merge_patch_without_clone() {
+    opt_struct(quote!(impls(merge_patch)), quote!(
+        #[derive(Deserialize)]
+        struct Foo {
+            bar: u8,
+        }
+    ));
+}
+
+#[test]
+#[should_panic(expected = "'merge_patch' needs 'OptionalFoo' to implement `Deserialize`")]
+fn with_merge_patch_and_skipped_deserialize() {
+    opt_struct(quote!(impls(merge_patch), skip_derive(Deserialize)), quote!(
+        #[derive(Clone, Deserialize)]
+        struct Foo {
+            bar: u8,
+        }
+    ));
+}
+
+#[test]
+fn with_version_field() {
+    let output = opt_struct(quote!(), quote!(
+        #[optional_version_field(version)]
+        struct Foo {
+            bar: u8,
+            version: u32,
+        }
+    ));
+    assert!(!output.original.to_string().contains("optional_version_field"));
+    let generated = output.generated.to_string();
+    assert!(generated.contains("fn try_apply_to (mut self , t : & mut Foo) -> Result < () , :: optional_struct :: VersionConflict < u32 >>"));
+    assert!(generated.contains("t . version += 1 ;"));
+}
+
+#[test]
+#[should_panic(expected = "'optional_version_field' attribute expects the name of a field, found 'revision'")]
+fn with_unknown_version_field() {
+    opt_struct(quote!(), quote!(
+        #[optional_version_field(revision)]
+        struct Foo {
+            version: u32,
+        }
+    ));
+}
+
+#[test]
+#[should_panic(expected = "'optional_version_field' attribute can't be used along with 'optional_group'")]
+fn with_version_field_and_group() {
+    opt_struct(quote!(), quote!(
+        #[optional_version_field(version)]
+        struct Foo {
+            #[optional_group(bar)]
+            bar: u8,
+            version: u32,
+        }
+    ));
+}
+
+#[test]
+fn with_project() {
+    let generated = opt_struct(quote!(impls(field_enum)), quote!(
+        struct Foo {
+            bar: u8,
+            #[optional_rename(OptionalBaz)]
+            baz: Baz,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("fn project (value : & Foo , fields : & [FooField]) -> Self"));
+    assert!(generated.contains("bar : if fields . contains (& FooField :: Bar) { Some (value . bar . clone ()) } else { Default :: default () } ,"));
+    assert!(generated.contains("baz : if fields . contains (& FooField :: Baz) { < OptionalBaz > :: snapshot (& value . baz) } else { Default :: default () } ,"));
+
+    let generated = opt_struct(quote!(impls(field_enum)), quote!(
+        struct Foo {
+            #[optional_skip_wrap]
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("fn snapshot (value : & Foo) -> Self"));
+    assert!(!generated.contains("fn project"));
+}
+
+#[test]
+fn with_schemars() {
+    let generated = opt_struct(quote!(schemars), quote!(
+        struct Foo {
+            bar: u8,
+            baz: Option<u8>,
+            #[optional_wrap]
+            qux: Option<u8>,
+        }
+    )).generated.to_string();
+    assert!(generated.starts_with("# [derive (Clone , PartialEq , Default , Debug , :: schemars :: JsonSchema)]"));
+    assert!(generated.contains("# [schemars (with = \"u8\" , default , skip_serializing_if = \"Option::is_none\")] pub bar : Option < u8 >"));
+    assert!(generated.contains("# [schemars (with = \"u8\" , default , skip_serializing_if = \"Option::is_none\")] pub baz : Option < u8 >"));
+    assert!(generated.contains("pub qux : Option < Option < u8 > >"));
+    assert_eq!(generated.matches("# [schemars").count(), 2);
+}
+
+#[test]
+fn with_prost() {
+    let generated = opt_struct(quote!(prost = proto::Foo), quote!(
+        struct Foo {
+            #[optional_proto_name(display_name)]
+            name: String,
+            #[optional_rename(OptionalBaz)]
+            baz: Baz,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("fn from_proto (msg : & proto :: Foo , mask : & :: prost_types :: FieldMask) -> Self"));
+    assert!(generated.contains("name : match :: optional_struct :: __private :: masked_paths (& mask . paths , \"display_name\") { Some (_) => Some (:: core :: convert :: Into :: < String > :: into (msg . display_name . clone ())) , None => Default :: default () , } ,"));
+    assert!(generated.contains("(Some (paths) , Some (nested)) => < OptionalBaz > :: from_proto (nested , & :: prost_types :: FieldMask { paths }) ,"));
+}
+
+#[test]
+#[should_panic(expected = "'prost' option can't be used along with fields that are enum-merged, elementwise or always set, such as 'bar'")]
+fn with_prost_always_set() {
+    opt_struct(quote!(prost = proto::Foo), quote!(
+        struct Foo {
+            #[optional_skip_wrap]
+            bar: u8,
+        }
+    ));
+}
+
+#[test]
+fn with_sparse_value() {
+    let generated = opt_struct(quote!(sparse_json), quote!(
+        struct Foo {
+            bar: u8,
+            #[optional_rename(OptionalBaz)]
+            baz: Baz,
+        }
+    )).generated.to_string();
+    assert!(!generated.contains("fn set ("));
+    assert!(generated.contains("impl OptionalFoo where Option < u8 > : :: serde :: Serialize { # [must_use] fn to_value_sparse (& self) -> :: serde_json :: Value"));
+    assert!(generated.contains("\"baz\" => patch . baz = < OptionalBaz > :: from_value_sparse (value) ? ,"));
+
+    let generated = opt_struct(quote!(sparse_json, skip_derive(Default)), quote!(
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("fn to_value_sparse"));
+    assert!(!generated.contains("fn from_value_sparse"));
+
+    let generated = opt_struct(quote!(serde_json), quote!(
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(!generated.contains("sparse"));
+}
+
+#[test]
+fn with_targets() {
+    let generated = opt_struct(quote!(), quote!(
+        #[optional_targets(DbFoo, ApiFoo(bar))]
+        struct Foo {
+            bar: u8,
+            baz: u8,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("impl :: optional_struct :: Applyable < DbFoo > for OptionalFoo { # [allow (unused_variables)] fn apply_to (self , t : & mut DbFoo) { if let Some (inner) = self . bar { t . bar = inner ; } if let Some (inner) = self . baz { t . baz = inner ; } } }"));
+    assert!(generated.contains("impl :: optional_struct :: Applyable < ApiFoo > for OptionalFoo { # [allow (unused_variables)] fn apply_to (self , t : & mut ApiFoo) { if let Some (inner) = self . bar { t . bar = inner ; } } }"));
+    assert!(!generated.contains("optional_targets"));
+}
+
+#[test]
+#[should_panic(expected = "'optional_targets' attribute lists 'qux', which isn't a field of the struct")]
+fn with_targets_unknown_field() {
+    opt_struct(quote!(), quote!(
+        #[optional_targets(ApiFoo(qux))]
+        struct Foo {
+            bar: u8,
+        }
+    ));
+}
+
+#[test]
+fn with_set_columns() {
+    let generated = opt_struct(quote!(impls(set_columns)), quote!(
+        struct Foo {
+            bar: u8,
+            #[optional_column(baz_id)]
+            baz: u8,
+            #[optional_read_only]
+            created: u8,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("fn set_columns (& self) -> :: optional_struct :: __private :: Vec < & 'static str > { # [allow (unused_mut)] let mut columns = :: optional_struct :: __private :: Vec :: new () ; if self . bar . as_ref () . is_some () { columns . push (\"bar\") ; } if self . baz . as_ref () . is_some () { columns . push (\"baz_id\") ; } columns }"));
+
+    let generated = opt_struct(quote!(impls(diff)), quote!(
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(!generated.contains("fn set_columns"));
+}
+
+#[test]
+fn with_tokio_postgres() {
+    let generated = opt_struct(quote!(tokio_postgres), quote!(
+        struct Foo {
+            bar: u8,
+            #[optional_skip_wrap]
+            baz: Option<u8>,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("impl OptionalFoo where u8 : :: tokio_postgres :: types :: ToSql + Sync , Option < u8 > : :: tokio_postgres :: types :: ToSql + Sync { # [allow (unused_variables)] fn bind_set_fields < '__a > (& '__a self , params : & mut :: optional_struct :: __private :: Vec < & '__a (dyn :: tokio_postgres :: types :: ToSql + Sync) >) { if let Some (value) = self . bar . as_ref () { params . push (value) ; } if self . baz . is_some () { params . push (& self . baz) ; } } }"));
+
+    let generated = opt_struct(quote!(), quote!(
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(!generated.contains("bind_set_fields"));
+}
+
+#[test]
+fn with_sea_query() {
+    let generated = opt_struct(quote!(sea_query), quote!(
+        struct Foo {
+            #[optional_column(bar_id)]
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("impl OptionalFoo where u8 : Clone + Into < :: sea_query :: SimpleExpr > { # [must_use] fn to_sea_query_values (& self) -> :: optional_struct :: __private :: Vec < (:: sea_query :: Alias , :: sea_query :: SimpleExpr) > { # [allow (unused_mut)] let mut values = :: optional_struct :: __private :: Vec :: new () ; if let Some (value) = self . bar . as_ref () { values . push ((:: sea_query :: Alias :: new (\"bar_id\") , Clone :: clone (value) . into ())) ; } values } }"));
+
+    let generated = opt_struct(quote!(), quote!(
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(!generated.contains("to_sea_query_values"));
+}
+
+#[test]
+fn with_rusqlite() {
+    let generated = opt_struct(quote!(rusqlite), quote!(
+        struct Foo {
+            #[optional_column(bar_id)]
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("impl OptionalFoo where u8 : :: rusqlite :: ToSql { # [must_use] fn to_named_params (& self) -> :: optional_struct :: __private :: Vec < (& 'static str , & dyn :: rusqlite :: ToSql) > { # [allow (unused_mut)] let mut params = :: optional_struct :: __private :: Vec :: new () ; if let Some (value) = self . bar . as_ref () { params . push ((\":bar_id\" , value as & dyn :: rusqlite :: ToSql)) ; } params } }"));
+    assert!(generated.contains("fn set_clause (& self) -> :: optional_struct :: __private :: String"));
+
+    let generated = opt_struct(quote!(), quote!(
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(!generated.contains("to_named_params"));
+}
+
+#[test]
+fn with_redis() {
+    let generated = opt_struct(quote!(redis), quote!(
+        struct Foo {
+            bar: u8,
+            #[optional_redis_with(to_hex)]
+            baz: u8,
+            #[optional_wrap]
+            qux: Option<u8>,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("impl OptionalFoo where u8 : :: core :: fmt :: Display , u8 : :: core :: fmt :: Display {"));
+    assert!(generated.contains("if let Some (value) = self . bar . as_ref () { fields . push ((:: optional_struct :: __private :: String :: from (\"bar\") , :: optional_struct :: __private :: ToString :: to_string (value))) ; }"));
+    assert!(generated.contains("if let Some (value) = self . baz . as_ref () { fields . push ((:: optional_struct :: __private :: String :: from (\"baz\") , to_hex (value))) ; }"));
+    assert!(generated.contains("if let Some (None) = self . qux . as_ref () { fields . push (:: optional_struct :: __private :: String :: from (\"qux\")) ; }"));
+}
+
+#[test]
+#[should_panic(expected = "'redis' option can't be used along with fields that are enum-merged or elementwise, such as 'bar'")]
+fn with_redis_elementwise() {
+    opt_struct(quote!(redis), quote!(
+        struct Foo {
+            #[optional_elementwise]
+            bar: [u8; 2],
+        }
+    ));
+}
+
+#[test]
+fn with_dynamodb() {
+    let generated = opt_struct(quote!(dynamodb), quote!(
+        struct Foo {
+            bar: u8,
+            #[optional_wrap]
+            baz: Option<u8>,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("impl OptionalFoo where u8 : :: serde :: Serialize , u8 : :: serde :: Serialize {"));
+    assert!(generated.contains("if let Some (value) = self . bar . as_ref () { let path = :: optional_struct :: __private :: dynamo_path (parent , \"bar\") ; update . set (path , :: serde_dynamo :: to_attribute_value (value) ?) ; }"));
+    assert!(generated.contains("Some (None) => { let path = :: optional_struct :: __private :: dynamo_path (parent , \"baz\") ; update . remove (path) ; }"));
+    assert!(generated.contains("fn to_dynamo_update (& self)"));
+}
+
+#[test]
+#[should_panic(expected = "'dynamodb' option can't be used along with fields that are enum-merged or elementwise, such as 'bar'")]
+fn with_dynamodb_elementwise() {
+    opt_struct(quote!(dynamodb), quote!(
+        struct Foo {
+            #[optional_elementwise]
+            bar: [u8; 2],
+        }
+    ));
+}
+
+#[test]
+fn with_audit() {
+    let generated = opt_struct(quote!(audit, impls(diff)), quote!(
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("fn to_audit_rows (& self , before : & Foo , entity_id : & str) -> :: optional_struct :: __private :: Vec < :: optional_struct :: AuditRow > where Self : Clone , Foo : Clone ,"));
+    assert!(generated.contains("fn apply_report"));
+
+    let generated = opt_struct(quote!(), quote!(
+        struct Foo {
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(!generated.contains("fn to_audit_rows"));
+}
+
+#[test]
+fn with_immutable() {
+    let generated = opt_struct(quote!(serde_json), quote!(
+        struct Foo {
+            #[optional_immutable]
+            id: u64,
+            bar: u8,
+        }
+    )).generated.to_string();
+    assert!(generated.contains("\"id\" => return Err (:: optional_struct :: SetError :: Immutable (\"id\")) ,"));
+    assert!(generated.contains("fn ignored_fields"));
+    assert!(!generated.contains("optional_immutable"));
+}
@@ -1,2 +1,528 @@
 #![no_std]
-pub use optional_struct_export::optional_struct;
\ No newline at end of file
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Display, Formatter};
+use core::marker::PhantomData;
+use core::time::Duration;
+use serde::{Deserialize, Serialize};
+
+pub use optional_struct_export::optional_struct;
+
+/// A field modified when applying an optional struct, as reported by the generated
+/// `apply_report` function.
+///
+/// Values are stored as their `Debug` rendering, and fields of nested structs are
+/// named after their full path, e.g. `log_config.log_file`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: String,
+    pub new: String,
+}
+
+impl FieldChange {
+    /// Returns `None` if both values have the same rendering, i.e. nothing changed.
+    pub fn from_debug(field: &str, old: &dyn Debug, new: &dyn Debug) -> Option<Self> {
+        let old = format!("{old:?}");
+        let new = format!("{new:?}");
+        if old == new {
+            None
+        } else {
+            Some(FieldChange { field: field.into(), old, new })
+        }
+    }
+
+    /// Hides the values, e.g. of a secret field.
+    pub fn redact(&mut self) {
+        self.old = "<redacted>".into();
+        self.new = "<redacted>".into();
+    }
+
+    /// Prefixes the field name with the name of the field containing it, a change without a
+    /// field name (e.g. another variant of an enum) becoming a change of the parent itself.
+    pub fn nested_in(self, parent: &str) -> Self {
+        FieldChange {
+            field: if self.field.is_empty() { parent.into() } else { format!("{parent}.{}", self.field) },
+            ..self
+        }
+    }
+}
+
+/// A field changed by a patch, as recorded for compliance logging by the generated
+/// `to_audit_rows` function of the `audit` option.
+///
+/// Values are stored as their `Debug` rendering, like those of [`FieldChange`], and the
+/// timestamp is the time elapsed since the Unix epoch.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct AuditRow {
+    pub entity: String,
+    pub field: String,
+    pub old: String,
+    pub new: String,
+    pub timestamp: Duration,
+}
+
+impl AuditRow {
+    /// Records a change of the entity identified by `entity`, e.g. `user:42`.
+    pub fn new(entity: &str, change: FieldChange, timestamp: Duration) -> Self {
+        let FieldChange { field, old, new } = change;
+        AuditRow { entity: entity.into(), field, old, new, timestamp }
+    }
+}
+
+/// Fields changed to different values on both sides of a three-way merge, as reported by the
+/// generated `merge3` function.
+///
+/// Fields of nested structs are named after their full path, e.g. `log_config.log_file`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Conflicts {
+    pub fields: Vec<String>,
+}
+
+impl Conflicts {
+    /// Prefixes the names of the conflicting fields with the name of the field containing them.
+    pub fn nested_in(self, parent: &str) -> Self {
+        Conflicts {
+            fields: self.fields.into_iter().map(|field| format!("{parent}.{field}")).collect(),
+        }
+    }
+}
+
+impl Display for Conflicts {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "conflicting changes to {}", self.fields.join(", "))
+    }
+}
+
+impl core::error::Error for Conflicts {}
+
+/// Required fields that none of the layers given to the generated `try_from_layers` function
+/// set.
+///
+/// Nested structs are named after the field containing them.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MissingFieldsError {
+    pub fields: Vec<&'static str>,
+}
+
+impl Display for MissingFieldsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "missing required fields: {}", self.fields.join(", "))
+    }
+}
+
+impl core::error::Error for MissingFieldsError {}
+
+/// Required fields that none of the layers given to the generated `finalize` function set,
+/// along with the names of those layers.
+///
+/// Fields of nested structs are named after their full path, e.g. `database.url`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FinalizeError {
+    pub fields: Vec<String>,
+    pub layers: Vec<String>,
+}
+
+impl Display for FinalizeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} not set (checked: {})", self.fields.join(", "), self.layers.join(", "))
+    }
+}
+
+impl core::error::Error for FinalizeError {}
+
+/// Profile missing from the generated `StructProfiles`, when resolving a chain of profiles.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct UnknownProfile(pub String);
+
+impl Display for UnknownProfile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown profile '{}'", self.0)
+    }
+}
+
+impl core::error::Error for UnknownProfile {}
+
+/// Patch made for another version of the struct than its current one, as reported by the
+/// generated `try_apply_to` of structs with a version field (see `optional_version_field`).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct VersionConflict<V> {
+    /// The version the patch expects, `None` if it doesn't tell.
+    pub expected: Option<V>,
+    pub actual: V,
+}
+
+impl<V: Display> Display for VersionConflict<V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.expected {
+            Some(expected) => write!(f, "expected version {expected}, found {}", self.actual),
+            None => write!(f, "no expected version, found {}", self.actual),
+        }
+    }
+}
+
+impl<V: Debug + Display> core::error::Error for VersionConflict<V> {}
+
+/// Patches which can be applied to a value of type `T`, implemented by the generated
+/// optional structs and enums (and by their mask version, see the `repr = "mask"` option).
+pub trait Applyable<T> {
+    /// Sets the fields of `target` which are set in the patch.
+    fn apply_to(self, target: &mut T);
+}
+
+/// Patches recorded one after the other, which can be replayed onto a value of type `T`.
+///
+/// It serializes as the sequence of its patches, e.g. to store the history of a value or
+/// send it to another process.
+#[derive(Serialize, Deserialize)]
+#[serde(transparent, bound(serialize = "P: Serialize", deserialize = "P: Deserialize<'de>"))]
+pub struct PatchLog<T, P: Applyable<T>> {
+    patches: Vec<P>,
+    #[serde(skip)]
+    target: PhantomData<fn(&mut T)>,
+}
+
+impl<T, P: Applyable<T>> PatchLog<T, P> {
+    pub fn new() -> Self {
+        PatchLog { patches: Vec::new(), target: PhantomData }
+    }
+
+    /// Adds a patch at the end of the log.
+    pub fn record(&mut self, patch: P) {
+        self.patches.push(patch);
+    }
+
+    /// Applies every patch of the log, in the order they were recorded.
+    pub fn replay(&self, onto: &mut T)
+    where
+        P: Clone,
+    {
+        for patch in &self.patches {
+            patch.clone().apply_to(onto);
+        }
+    }
+
+    pub fn patches(&self) -> &[P] {
+        &self.patches
+    }
+
+    pub fn len(&self) -> usize {
+        self.patches.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patches.is_empty()
+    }
+}
+
+impl<T, P: Applyable<T>> Default for PatchLog<T, P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, P: Applyable<T> + Clone> Clone for PatchLog<T, P> {
+    fn clone(&self) -> Self {
+        PatchLog { patches: self.patches.clone(), target: PhantomData }
+    }
+}
+
+impl<T, P: Applyable<T> + Debug> Debug for PatchLog<T, P> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("PatchLog").field(&self.patches).finish()
+    }
+}
+
+impl<T, P: Applyable<T> + PartialEq> PartialEq for PatchLog<T, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.patches == other.patches
+    }
+}
+
+impl<T, P: Applyable<T>> From<Vec<P>> for PatchLog<T, P> {
+    fn from(patches: Vec<P>) -> Self {
+        PatchLog { patches, target: PhantomData }
+    }
+}
+
+impl<T, P: Applyable<T>> Extend<P> for PatchLog<T, P> {
+    fn extend<I: IntoIterator<Item = P>>(&mut self, patches: I) {
+        self.patches.extend(patches);
+    }
+}
+
+/// Error returned by the generated `set` and `from_value_sparse` functions (see the `serde_json`
+/// option).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SetError {
+    /// The struct has no field with this name.
+    UnknownField(String),
+    /// The value could not be deserialized into the type of the field.
+    InvalidValue { field: &'static str, reason: String },
+    /// The value given to `from_value_sparse` isn't a JSON object.
+    NotAnObject,
+    /// The field is marked with `optional_immutable`, and can't be set by name.
+    Immutable(&'static str),
+}
+
+impl SetError {
+    pub fn invalid_value(field: &'static str, reason: &dyn Display) -> Self {
+        SetError::InvalidValue { field, reason: format!("{reason}") }
+    }
+}
+
+impl Display for SetError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SetError::UnknownField(field) => write!(f, "unknown field '{field}'"),
+            SetError::InvalidValue { field, reason } => write!(f, "invalid value for field '{field}': {reason}"),
+            SetError::NotAnObject => write!(f, "expected a JSON object"),
+            SetError::Immutable(field) => write!(f, "field '{field}' is immutable"),
+        }
+    }
+}
+
+impl core::error::Error for SetError {}
+
+/// Type wrapping the fields of an optional struct, in place of `Option` (see the `wrapper`
+/// option and the `optional_wrap_with` attribute).
+///
+/// A wrapper is either unset (its `Default` value) or holds a value, and may carry extra
+/// information, e.g. which configuration layer set the value.
+pub trait Wrapper: Default {
+    type Value;
+
+    /// Wraps a value which is set.
+    fn wrap(value: Self::Value) -> Self;
+
+    /// Returns the value, if it is set.
+    fn as_option(&self) -> Option<&Self::Value>;
+
+    /// Unwraps the value, if it is set.
+    fn into_option(self) -> Option<Self::Value>;
+}
+
+impl<T> Wrapper for Option<T> {
+    type Value = T;
+
+    fn wrap(value: T) -> Self {
+        Some(value)
+    }
+
+    fn as_option(&self) -> Option<&T> {
+        self.as_ref()
+    }
+
+    fn into_option(self) -> Option<T> {
+        self
+    }
+}
+
+/// Types for which the patch turning one value into another can be computed.
+///
+/// It is implemented for the structs annotated with `#[optional_struct]`, whose patch is
+/// the generated optional struct, and for common leaf types, whose patch is an `Option`
+/// holding the new value if it differs.
+pub trait Diffable {
+    type Patch;
+
+    /// Returns the patch which, applied to `self`, gives `other`, the parts that are
+    /// equal being left unset.
+    fn diff(&self, other: &Self) -> Self::Patch;
+}
+
+macro_rules! impl_leaf_diffable {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl Diffable for $ty {
+                type Patch = Option<$ty>;
+
+                fn diff(&self, other: &Self) -> Option<$ty> {
+                    (self != other).then(|| other.clone())
+                }
+            }
+        )*
+    };
+}
+
+impl_leaf_diffable!(bool, char, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64, String);
+
+impl<T: Clone + PartialEq> Diffable for Option<T> {
+    type Patch = Option<Option<T>>;
+
+    fn diff(&self, other: &Self) -> Option<Option<T>> {
+        (self != other).then(|| other.clone())
+    }
+}
+
+impl<T: Clone + PartialEq> Diffable for Vec<T> {
+    type Patch = Option<Vec<T>>;
+
+    fn diff(&self, other: &Self) -> Option<Vec<T>> {
+        (self != other).then(|| other.clone())
+    }
+}
+
+impl<T: Clone + PartialEq, const N: usize> Diffable for [T; N] {
+    type Patch = Option<[T; N]>;
+
+    fn diff(&self, other: &Self) -> Option<[T; N]> {
+        (self != other).then(|| other.clone())
+    }
+}
+
+/// Markers used by the builders generated with the `builder = typestate` option.
+pub mod typestate {
+    /// The field has been given a value.
+    pub struct Set;
+    /// The field is still missing.
+    pub struct Unset;
+}
+
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::boxed::Box;
+    pub use alloc::collections::BTreeMap;
+    pub use alloc::format;
+    pub use alloc::string::{String, ToString};
+    pub use alloc::vec::Vec;
+    pub use serde::de::{DeserializeOwned, Error as DeError};
+
+    // Used by the generated construction macros: unlike `Into`, it keeps type inference working
+    // for literals, while still accepting `&str`s for `String` fields.
+    pub trait IntoField<T> {
+        fn into_field(self) -> T;
+    }
+
+    impl<T> IntoField<T> for T {
+        fn into_field(self) -> T {
+            self
+        }
+    }
+
+    impl IntoField<String> for &str {
+        fn into_field(self) -> String {
+            self.into()
+        }
+    }
+
+    // Calls the `optional_set_if` predicate of a field, which lets closures infer their argument type
+    #[inline]
+    pub fn is_set<T>(value: &T, predicate: impl FnOnce(&T) -> bool) -> bool {
+        predicate(value)
+    }
+
+    // Used as `skip_serializing_if` for fields with a custom wrapper
+    pub fn is_unset<W: crate::Wrapper>(wrapper: &W) -> bool {
+        wrapper.as_option().is_none()
+    }
+
+    // Printed in place of the value of a secret field, or as `None` when it is unset
+    pub struct Redacted(pub bool);
+
+    impl core::fmt::Debug for Redacted {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            f.write_str(if self.0 { "<redacted>" } else { "None" })
+        }
+    }
+
+    // Paths of a field mask below `field`, as read by `from_proto`: `None` if the mask doesn't
+    // include the field, no paths if it includes it as a whole. An empty mask includes everything.
+    pub fn masked_paths(paths: &[String], field: &str) -> Option<Vec<String>> {
+        if paths.is_empty() || paths.iter().any(|p| p == field) {
+            return Some(Vec::new());
+        }
+        let below = paths
+            .iter()
+            .filter_map(|p| p.strip_prefix(field)?.strip_prefix('.'))
+            .map(String::from)
+            .collect::<Vec<_>>();
+        (!below.is_empty()).then_some(below)
+    }
+
+    // Used as `deserialize_with` by the `explicit_null` option: a present value is set, even when
+    // it is `null`, while missing ones are left to `#[serde(default)]`
+    pub fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        T: serde::Deserialize<'de>,
+        D: serde::Deserializer<'de>,
+    {
+        T::deserialize(deserializer).map(Some)
+    }
+
+    // Document path of an attribute in a DynamoDB expression, below `parent` for the fields of
+    // nested patches
+    pub fn dynamo_path(parent: &str, name: &str) -> String {
+        if parent.is_empty() { format!("#{name}") } else { format!("{parent}.#{name}") }
+    }
+
+    // Update expression built by the `dynamodb` option. Attributes are always referred to through
+    // name placeholders, since many common names are reserved words, and values are numbered.
+    pub struct DynamoUpdate<V> {
+        set: Vec<String>,
+        remove: Vec<String>,
+        pub names: Vec<(String, String)>,
+        pub values: Vec<(String, V)>,
+    }
+
+    impl<V> Default for DynamoUpdate<V> {
+        fn default() -> Self {
+            DynamoUpdate { set: Vec::new(), remove: Vec::new(), names: Vec::new(), values: Vec::new() }
+        }
+    }
+
+    impl<V> DynamoUpdate<V> {
+        pub fn set(&mut self, path: String, value: V) {
+            self.add_names(&path);
+            let placeholder = format!(":v{}", self.values.len());
+            self.set.push(format!("{path} = {placeholder}"));
+            self.values.push((placeholder, value));
+        }
+
+        pub fn remove(&mut self, path: String) {
+            self.add_names(&path);
+            self.remove.push(path);
+        }
+
+        // Only the names of the attributes in the expression are given, DynamoDB rejecting unused ones
+        fn add_names(&mut self, path: &str) {
+            for placeholder in path.split('.') {
+                if !self.names.iter().any(|(p, _)| p == placeholder) {
+                    self.names.push((String::from(placeholder), String::from(&placeholder[1..])));
+                }
+            }
+        }
+
+        // e.g. `SET #a = :v0, #b = :v1 REMOVE #c`, empty if nothing is set
+        pub fn expression(&self) -> String {
+            let mut clauses = Vec::new();
+            if !self.set.is_empty() {
+                clauses.push(format!("SET {}", self.set.join(", ")));
+            }
+            if !self.remove.is_empty() {
+                clauses.push(format!("REMOVE {}", self.remove.join(", ")));
+            }
+            clauses.join(" ")
+        }
+    }
+
+    // Three-way merge of two patches of a same base value, conflicting fields being recorded
+    // rather than failing right away so that nested patches can be merged as a whole
+    pub trait Merge3<T>: Sized {
+        fn merge3(base: &T, ours: Self, theirs: Self, conflicts: &mut crate::Conflicts) -> Self;
+    }
+
+    // Implemented along with the generated `strip_defaults`, so that the bounds of nested
+    // patches can be required by their parent
+    pub trait StripDefaults<T> {
+        fn strip_defaults(&mut self, reference: &T);
+    }
+
+    // FNV-1a step, used to mix the hashes making up `PATCH_SCHEMA_HASH`
+    pub const fn combine_schema_hash(hash: u64, value: u64) -> u64 {
+        (hash ^ value).wrapping_mul(0x100000001b3)
+    }
+}
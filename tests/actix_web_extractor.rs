@@ -0,0 +1,33 @@
+use actix_web::http::header::ContentType;
+use actix_web::test::TestRequest;
+use actix_web::FromRequest;
+use optional_struct::*;
+use serde::Deserialize;
+
+#[optional_struct(actix_web, explicit_null)]
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct User {
+    name: String,
+    #[optional_wrap]
+    nickname: Option<String>,
+}
+
+#[actix_web::test]
+async fn test_extracts_patch() {
+    let (request, mut payload) = TestRequest::patch()
+        .insert_header(ContentType::json())
+        .set_payload(r#"{"name": "John", "nickname": null}"#)
+        .to_http_parts();
+    let patch = OptionalUser::from_request(&request, &mut payload).await.unwrap();
+    assert_eq!(patch, OptionalUser { name: Some("John".to_owned()), nickname: Some(None) });
+}
+
+#[actix_web::test]
+async fn test_rejects_unknown_fields() {
+    let (request, mut payload) = TestRequest::patch()
+        .insert_header(ContentType::json())
+        .set_payload(r#"{"nmae": "John"}"#)
+        .to_http_parts();
+    assert!(OptionalUser::from_request(&request, &mut payload).await.is_err());
+}
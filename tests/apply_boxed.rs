@@ -0,0 +1,53 @@
+use optional_struct::*;
+
+#[optional_struct(impls(apply_boxed))]
+#[derive(Debug, PartialEq)]
+struct Buffer {
+    data: [u8; 4096],
+    len: Option<usize>,
+    name: String,
+
+    #[optional_rename(OptionalHeader)]
+    header: Header,
+}
+
+#[optional_struct]
+#[derive(Debug, PartialEq)]
+struct Header {
+    version: u32,
+    flags: u32,
+}
+
+#[test]
+fn test_apply_boxed() {
+    let mut buffer = Buffer {
+        data: [0; 4096],
+        len: None,
+        name: "foo".to_owned(),
+        header: Header {
+            version: 1,
+            flags: 0,
+        },
+    };
+
+    let patch = Box::new(OptionalBuffer {
+        data: Some([1; 4096]),
+        len: Some(12),
+        name: None,
+        header: OptionalHeader {
+            version: Some(2),
+            flags: None,
+        },
+    });
+    patch.apply_boxed(&mut buffer);
+
+    assert_eq!(buffer, Buffer {
+        data: [1; 4096],
+        len: Some(12),
+        name: "foo".to_owned(),
+        header: Header {
+            version: 2,
+            flags: 0,
+        },
+    });
+}
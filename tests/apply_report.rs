@@ -0,0 +1,55 @@
+use optional_struct::*;
+
+#[optional_struct(impls(apply_report))]
+struct Config {
+    timeout: Option<u32>,
+    name: String,
+
+    #[optional_rename(OptionalLogConfig)]
+    #[optional_wrap]
+    log_config: LogConfig,
+}
+
+#[optional_struct(impls(apply_report))]
+struct LogConfig {
+    log_file: String,
+    log_level: usize,
+}
+
+#[test]
+fn test_apply_report() {
+    let mut config = Config {
+        timeout: Some(2),
+        name: "foo".to_owned(),
+        log_config: LogConfig {
+            log_file: "/var/log/foobar.log".to_owned(),
+            log_level: 3,
+        },
+    };
+
+    let opt_config = OptionalConfig {
+        timeout: Some(5),
+        name: Some("foo".to_owned()),
+        log_config: Some(OptionalLogConfig {
+            log_file: Some("/tmp/bar.log".to_owned()),
+            log_level: None,
+        }),
+    };
+
+    let changes = opt_config.apply_report(&mut config);
+
+    assert_eq!(config.timeout, Some(5));
+    assert_eq!(config.log_config.log_file, "/tmp/bar.log");
+    assert_eq!(changes, vec![
+        FieldChange {
+            field: "timeout".to_owned(),
+            old: "Some(2)".to_owned(),
+            new: "Some(5)".to_owned(),
+        },
+        FieldChange {
+            field: "log_config.log_file".to_owned(),
+            old: "\"/var/log/foobar.log\"".to_owned(),
+            new: "\"/tmp/bar.log\"".to_owned(),
+        },
+    ]);
+}
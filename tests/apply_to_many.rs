@@ -0,0 +1,48 @@
+use optional_struct::*;
+
+#[optional_struct(impls(apply_to_many))]
+#[derive(Clone, Debug, PartialEq)]
+struct Session {
+    user: String,
+    theme: String,
+    volume: u8,
+}
+
+fn session(user: &str) -> Session {
+    Session {
+        user: user.to_owned(),
+        theme: "light".to_owned(),
+        volume: 5,
+    }
+}
+
+#[test]
+fn test_apply_to_many() {
+    let mut sessions = vec![session("a"), session("b")];
+    let patch = OptionalSession {
+        theme: Some("dark".to_owned()),
+        ..Default::default()
+    };
+    patch.apply_to_many(&mut sessions);
+    assert!(sessions.iter().all(|s| s.theme == "dark"));
+    assert_eq!(sessions[1].user, "b");
+
+    // Any iterator of mutable references works, and the patch can still be used
+    let mut other = session("c");
+    patch.apply_to_many(sessions.iter_mut().take(1).chain([&mut other]));
+    assert_eq!(other.theme, "dark");
+    assert_eq!(patch.theme, Some("dark".to_owned()));
+}
+
+#[optional_struct(impls(apply_to_many))]
+#[derive(Clone, Debug, PartialEq)]
+struct Wrapper<T> {
+    value: T,
+}
+
+#[test]
+fn test_apply_to_many_generic() {
+    let mut values = [Wrapper { value: 1 }, Wrapper { value: 2 }];
+    OptionalWrapper { value: Some(3) }.apply_to_many(&mut values);
+    assert_eq!(values, [Wrapper { value: 3 }, Wrapper { value: 3 }]);
+}
@@ -0,0 +1,40 @@
+use arbitrary::{Arbitrary, Unstructured};
+use optional_struct::*;
+
+#[optional_struct(arbitrary)]
+#[derive(Debug, PartialEq)]
+struct Config {
+    name: String,
+    port: u16,
+    #[optional_wrap]
+    proxy: Option<String>,
+}
+
+#[test]
+fn test_arbitrary_patches() {
+    // Any bytes will do, e.g. those of a simple LCG
+    let mut seed = 1u32;
+    let data = (0..4096)
+        .map(|_| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            (seed >> 16) as u8
+        })
+        .collect::<Vec<_>>();
+    let mut u = Unstructured::new(&data);
+    let mut proxies = [false; 3];
+    while !u.is_empty() {
+        let patch = OptionalConfig::arbitrary(&mut u).unwrap();
+        // Tri-state fields can be left untouched, cleared or set
+        match patch.proxy {
+            None => proxies[0] = true,
+            Some(None) => proxies[1] = true,
+            Some(Some(_)) => proxies[2] = true,
+        }
+
+        let mut config = Config { name: "a".to_owned(), port: 80, proxy: None };
+        let expected_port = patch.port.unwrap_or(config.port);
+        patch.apply_to(&mut config);
+        assert_eq!(config.port, expected_port);
+    }
+    assert_eq!(proxies, [true; 3]);
+}
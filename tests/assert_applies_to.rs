@@ -0,0 +1,27 @@
+use optional_struct::*;
+
+#[optional_struct(impls(assert_applies_to))]
+#[derive(Clone, Debug, PartialEq)]
+struct Config {
+    name: String,
+    port: u16,
+    #[optional_skip(default = "1")]
+    revision: u32,
+}
+
+fn config() -> Config {
+    Config { name: "a".to_owned(), port: 80, revision: 1 }
+}
+
+#[test]
+fn test_assert_applies_to() {
+    let patch = OptionalConfig { name: None, port: Some(8080) };
+    patch.assert_applies_to(&config(), &Config { port: 8080, ..config() });
+}
+
+#[test]
+#[should_panic(expected = "the patch didn't give the expected value:\n  port: expected 8080, got 8000\n  revision: expected 2, got 1")]
+fn test_assert_applies_to_mismatch() {
+    let patch = OptionalConfig { name: None, port: Some(8000) };
+    patch.assert_applies_to(&config(), &Config { port: 8080, revision: 2, ..config() });
+}
@@ -0,0 +1,59 @@
+use optional_struct::*;
+
+#[optional_struct]
+#[derive(Clone, Debug, PartialEq)]
+struct Range {
+    start: u32,
+    end: u32,
+}
+
+#[optional_struct(OptionalWindow, false)]
+#[derive(Debug, PartialEq)]
+struct Window {
+    title: String,
+    #[optional_atomic]
+    range: Range,
+    #[optional_atomic]
+    limit: Option<Range>,
+}
+
+#[test]
+fn test_atomic_apply() {
+    let mut window = Window {
+        title: "main".to_owned(),
+        range: Range { start: 0, end: 10 },
+        limit: Some(Range { start: 0, end: 100 }),
+    };
+
+    // The value is replaced as a whole, even though `Range` has an optional version
+    let patch = OptionalWindow {
+        title: "main".to_owned(),
+        range: Some(Range { start: 5, end: 6 }),
+        limit: Some(None),
+    };
+    patch.apply_to(&mut window);
+    assert_eq!(window.range, Range { start: 5, end: 6 });
+    assert_eq!(window.limit, None);
+
+    let patch = OptionalWindow {
+        title: "other".to_owned(),
+        range: None,
+        limit: None,
+    };
+    patch.apply_to(&mut window);
+    assert_eq!(window.title, "other");
+    assert_eq!(window.range, Range { start: 5, end: 6 });
+}
+
+#[test]
+fn test_atomic_try_from() {
+    let patch = OptionalWindow {
+        title: "main".to_owned(),
+        range: None,
+        limit: None,
+    };
+    assert!(!patch.can_convert());
+
+    let window: Window = OptionalWindow { range: Some(Range { start: 1, end: 2 }), ..patch }.try_into().unwrap();
+    assert_eq!(window.limit, None);
+}
@@ -0,0 +1,64 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use optional_struct::*;
+
+#[optional_struct(audit)]
+#[derive(Clone, Debug, PartialEq)]
+struct Account {
+    owner: String,
+    balance: u64,
+    #[optional_secret]
+    pin: u16,
+    #[optional_rename(OptionalLimits)]
+    limits: Limits,
+}
+
+#[optional_struct(impls(apply_report))]
+#[derive(Clone, Debug, PartialEq)]
+struct Limits {
+    daily: u64,
+}
+
+fn account() -> Account {
+    Account {
+        owner: "Alice".to_owned(),
+        balance: 100,
+        pin: 1234,
+        limits: Limits { daily: 50 },
+    }
+}
+
+#[test]
+fn test_to_audit_rows() {
+    let before = account();
+    let patch = OptionalAccount {
+        owner: Some("Alice".to_owned()),
+        balance: Some(80),
+        pin: Some(4321),
+        limits: OptionalLimits { daily: Some(20) },
+    };
+    let start = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let rows = patch.to_audit_rows(&before, "account:7");
+    let end = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+
+    // Unchanged fields aren't recorded, and secret ones are redacted
+    let fields = rows
+        .iter()
+        .map(|row| (row.entity.as_str(), row.field.as_str(), row.old.as_str(), row.new.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(fields, vec![
+        ("account:7", "balance", "100", "80"),
+        ("account:7", "pin", "<redacted>", "<redacted>"),
+        ("account:7", "limits.daily", "50", "20"),
+    ]);
+    assert!(rows.iter().all(|row| start <= row.timestamp && row.timestamp <= end && row.timestamp == rows[0].timestamp));
+
+    // Neither the value nor the patch are modified
+    assert_eq!(before, account());
+    assert_eq!(patch.balance, Some(80));
+}
+
+#[test]
+fn test_nothing_changed() {
+    assert!(OptionalAccount::default().to_audit_rows(&account(), "account:7").is_empty());
+}
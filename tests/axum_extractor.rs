@@ -0,0 +1,46 @@
+use axum::body::Body;
+use axum::extract::FromRequest;
+use axum::http::{header, Request, StatusCode};
+use axum::response::IntoResponse;
+use optional_struct::*;
+use serde::Deserialize;
+
+#[optional_struct(axum, explicit_null)]
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct User {
+    name: String,
+    #[optional_wrap]
+    nickname: Option<String>,
+}
+
+fn json_request(body: &'static str) -> Request<Body> {
+    Request::builder()
+        .method("PATCH")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_extracts_patch() {
+    let patch = OptionalUser::from_request(json_request(r#"{"nickname": null}"#), &()).await.unwrap();
+    assert_eq!(patch, OptionalUser { name: None, nickname: Some(None) });
+
+    let mut user = User { name: "Jane".to_owned(), nickname: Some("JD".to_owned()) };
+    patch.apply_to(&mut user);
+    assert_eq!(user, User { name: "Jane".to_owned(), nickname: None });
+}
+
+#[tokio::test]
+async fn test_rejects_unknown_fields() {
+    let rejection = OptionalUser::from_request(json_request(r#"{"nmae": "John"}"#), &()).await.unwrap_err();
+    assert_eq!(rejection.into_response().status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn test_rejects_other_content_types() {
+    let request = Request::builder().method("PATCH").body(Body::from(r#"{"name": "John"}"#)).unwrap();
+    let rejection = OptionalUser::from_request(request, &()).await.unwrap_err();
+    assert_eq!(rejection.into_response().status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+}
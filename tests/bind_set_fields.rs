@@ -0,0 +1,47 @@
+use optional_struct::*;
+use tokio_postgres::types::ToSql;
+
+#[optional_struct(tokio_postgres)]
+#[allow(dead_code)]
+struct User {
+    name: String,
+    #[optional_column(email_address)]
+    email: String,
+    #[optional_wrap]
+    nickname: Option<String>,
+    #[optional_read_only]
+    created_at: i64,
+}
+
+fn update_statement(patch: &OptionalUser) -> String {
+    let assignments = patch
+        .set_columns()
+        .iter()
+        .enumerate()
+        .map(|(i, column)| format!("{column} = ${}", i + 2))
+        .collect::<Vec<_>>();
+    format!("UPDATE users SET {} WHERE id = $1", assignments.join(", "))
+}
+
+#[test]
+fn test_bind_set_fields() {
+    let id = 7i32;
+    let patch = OptionalUser {
+        name: None,
+        email: Some("alice@example.com".to_owned()),
+        nickname: Some(None),
+        created_at: Some(0),
+    };
+    let mut params: Vec<&(dyn ToSql + Sync)> = vec![&id];
+    patch.bind_set_fields(&mut params);
+    assert_eq!(update_statement(&patch), "UPDATE users SET email_address = $2, nickname = $3 WHERE id = $1");
+    assert_eq!(format!("{params:?}"), r#"[7, "alice@example.com", None]"#);
+}
+
+#[test]
+fn test_nothing_to_bind() {
+    let patch = OptionalUser::default();
+    let mut params: Vec<&(dyn ToSql + Sync)> = vec![];
+    patch.bind_set_fields(&mut params);
+    assert!(params.is_empty());
+}
@@ -0,0 +1,62 @@
+use optional_struct::*;
+
+#[optional_struct(builder)]
+#[derive(Debug, PartialEq)]
+struct Config {
+    timeout: Option<u32>,
+    path: String,
+    percentage: f32,
+}
+
+#[optional_struct(builder = typestate)]
+#[derive(Debug, PartialEq)]
+struct StrictConfig {
+    timeout: Option<u32>,
+    path: String,
+    #[optional_rename(OptionalLogConfig)]
+    #[optional_wrap]
+    log_config: LogConfig,
+}
+
+#[optional_struct]
+#[derive(Debug, PartialEq)]
+struct LogConfig {
+    log_file: String,
+    log_level: usize,
+}
+
+#[test]
+fn test_builder_fn() {
+    let config = Config::builder()
+        .path("/tmp/bar.log".to_owned())
+        .percentage(0.5)
+        .build();
+    assert_eq!(config, Ok(Config {
+        timeout: None,
+        path: "/tmp/bar.log".to_owned(),
+        percentage: 0.5,
+    }));
+
+    let incomplete = Config::builder().timeout(Some(2)).build();
+    assert!(incomplete.is_err());
+}
+
+#[test]
+fn test_typestate_builder_fn() {
+    // Forgetting .path() or .log_config() would not compile
+    let config = StrictConfig::builder()
+        .log_config(OptionalLogConfig {
+            log_file: Some("/tmp/bar.log".to_owned()),
+            log_level: Some(3),
+        })
+        .path("/tmp/bar.log".to_owned())
+        .build();
+    assert_eq!(config, Ok(StrictConfig {
+        timeout: None,
+        path: "/tmp/bar.log".to_owned(),
+        log_config: LogConfig {
+            log_file: "/tmp/bar.log".to_owned(),
+            log_level: 3,
+        },
+    }));
+}
@@ -0,0 +1,36 @@
+use optional_struct::*;
+
+#[optional_struct(impls(change_enum))]
+#[allow(dead_code)]
+struct Config {
+    timeout: Option<u32>,
+    log_file: String,
+    #[optional_skip_wrap]
+    port: u16,
+
+    #[optional_rename(OptionalLogConfig)]
+    #[optional_wrap]
+    log_config: LogConfig,
+}
+
+#[optional_struct]
+#[allow(dead_code)]
+struct LogConfig {
+    log_level: usize,
+}
+
+#[test]
+fn test_into_changes() {
+    let opt_config = OptionalConfig {
+        timeout: Some(3),
+        log_file: None,
+        port: 8080,
+        log_config: Some(OptionalLogConfig { log_level: Some(2) }),
+    };
+
+    assert_eq!(opt_config.into_changes(), vec![
+        ConfigChange::Timeout(Some(3)),
+        ConfigChange::Port(8080),
+        ConfigChange::LogConfig(OptionalLogConfig { log_level: Some(2) }),
+    ]);
+}
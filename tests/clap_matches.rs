@@ -0,0 +1,109 @@
+use clap::{Args, Parser};
+use optional_struct::*;
+
+#[optional_struct(clap, impls(try_from_layers))]
+#[derive(Debug, PartialEq)]
+struct Config {
+    host: String,
+    port: u16,
+    verbose: bool,
+    tags: Vec<String>,
+    // Only set in configuration files
+    log_file: Option<String>,
+    #[optional_rename(OptionalLimits)]
+    limits: Limits,
+}
+
+#[optional_struct(clap, impls(missing_fields))]
+#[derive(Debug, PartialEq)]
+struct Limits {
+    timeout: u32,
+}
+
+#[derive(Parser)]
+struct Cli {
+    #[arg(long)]
+    host: Option<String>,
+    #[arg(long, default_value_t = 80)]
+    port: u16,
+    #[arg(long)]
+    verbose: bool,
+    #[arg(long)]
+    tags: Vec<String>,
+    #[command(flatten)]
+    limits: CliLimits,
+}
+
+#[derive(Args)]
+struct CliLimits {
+    #[arg(long, default_value_t = 30)]
+    timeout: u32,
+}
+
+fn cli_layer(args: &[&str]) -> OptionalConfig {
+    let matches = <Cli as clap::CommandFactory>::command().get_matches_from(args);
+    let mut patch = OptionalConfig::default();
+    patch.update_from_arg_matches(&matches);
+    patch
+}
+
+fn file_layer() -> OptionalConfig {
+    OptionalConfig {
+        host: Some("localhost".to_owned()),
+        port: Some(8080),
+        verbose: Some(true),
+        tags: Some(vec!["file".to_owned()]),
+        log_file: Some("app.log".to_owned()),
+        limits: OptionalLimits { timeout: Some(5) },
+    }
+}
+
+#[test]
+fn test_defaults_are_not_set() {
+    let patch = cli_layer(&["app"]);
+    assert_eq!(patch, OptionalConfig::default());
+
+    let config = Config::try_from_layers([file_layer(), patch]).unwrap();
+    assert_eq!(config.port, 8080);
+    assert!(config.verbose);
+    assert_eq!(config.limits.timeout, 5);
+}
+
+#[test]
+fn test_given_arguments_are_set() {
+    let patch = cli_layer(&["app", "--host", "example.com", "--port", "80", "--timeout", "1"]);
+    assert_eq!(patch, OptionalConfig {
+        host: Some("example.com".to_owned()),
+        port: Some(80),
+        limits: OptionalLimits { timeout: Some(1) },
+        ..Default::default()
+    });
+
+    let config = Config::try_from_layers([file_layer(), patch]).unwrap();
+    assert_eq!(config, Config {
+        host: "example.com".to_owned(),
+        port: 80,
+        verbose: true,
+        tags: vec!["file".to_owned()],
+        log_file: Some("app.log".to_owned()),
+        limits: Limits { timeout: 1 },
+    });
+}
+
+#[test]
+fn test_repeated_arguments_are_collected() {
+    let patch = cli_layer(&["app", "--tags", "t1", "--tags", "t2"]);
+    assert_eq!(patch.tags, Some(vec!["t1".to_owned(), "t2".to_owned()]));
+}
+
+#[optional_struct(clap)]
+struct Mismatch {
+    port: String,
+}
+
+#[test]
+#[should_panic(expected = "argument 'port' doesn't have the type of the field of the same name")]
+fn test_mismatched_type_panics() {
+    let matches = <Cli as clap::CommandFactory>::command().get_matches_from(["app"]);
+    OptionalMismatch::default().update_from_arg_matches(&matches);
+}
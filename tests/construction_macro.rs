@@ -0,0 +1,50 @@
+use optional_struct::*;
+
+#[optional_struct(impls(construction_macro))]
+#[allow(dead_code)]
+struct Config {
+    timeout: Option<u32>,
+    name: String,
+    port: u16,
+    #[optional_skip_wrap]
+    percentage: f32,
+
+    #[optional_rename(OptionalLogConfig)]
+    #[optional_wrap]
+    log_config: LogConfig,
+}
+
+#[optional_struct(impls(construction_macro))]
+#[allow(dead_code)]
+struct LogConfig {
+    log_file: String,
+    log_level: usize,
+}
+
+#[optional_struct(impls(construction_macro))]
+#[allow(dead_code)]
+struct Pair(u8, String);
+
+#[test]
+fn test_construction_macro() {
+    let opt_config = optional_config! {
+        name: "x",
+        timeout: 3,
+        percentage: 0.5,
+        log_config: optional_log_config! { log_level: 2 },
+    };
+
+    assert_eq!(opt_config, OptionalConfig {
+        timeout: Some(3),
+        name: Some("x".to_owned()),
+        port: None,
+        percentage: 0.5,
+        log_config: Some(OptionalLogConfig {
+            log_file: None,
+            log_level: Some(2),
+        }),
+    });
+
+    assert_eq!(optional_pair! { 1: "y" }, OptionalPair(None, Some("y".to_owned())));
+    assert_eq!(optional_pair! {}, OptionalPair::default());
+}
@@ -0,0 +1,66 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use optional_struct::*;
+
+fn path_to_string(path: PathBuf) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+fn millis_to_duration(millis: u64) -> Duration {
+    Duration::from_millis(millis)
+}
+
+fn duration_to_millis(duration: Duration) -> u64 {
+    duration.as_millis() as u64
+}
+
+#[optional_struct(impls(tracked))]
+#[derive(Debug, PartialEq)]
+struct Config {
+    #[optional_convert(ty = "String", into = "path_to_string", from = "PathBuf::from")]
+    path: PathBuf,
+    #[optional_skip_wrap]
+    #[optional_convert(ty = "u64", into = "duration_to_millis", from = "millis_to_duration")]
+    timeout: Duration,
+}
+
+#[test]
+fn test_convert_apply() {
+    let mut config = Config {
+        path: PathBuf::from("/etc/foo"),
+        timeout: Duration::from_secs(1),
+    };
+
+    let opt_config = OptionalConfig {
+        path: Some("/etc/bar".to_owned()),
+        timeout: 500,
+    };
+    opt_config.apply_to(&mut config);
+    assert_eq!(config.path, PathBuf::from("/etc/bar"));
+    assert_eq!(config.timeout, Duration::from_millis(500));
+}
+
+#[test]
+fn test_convert_try_from() {
+    let opt_config = OptionalConfig {
+        path: None,
+        timeout: 500,
+    };
+    assert!(opt_config.clone().try_build().is_err());
+
+    let config: Config = OptionalConfig { path: Some("/etc/bar".to_owned()), ..opt_config }.try_into().unwrap();
+    assert_eq!(config, Config { path: PathBuf::from("/etc/bar"), timeout: Duration::from_millis(500) });
+}
+
+#[test]
+fn test_convert_tracked() {
+    let mut tracked = TrackedConfig::new(Config {
+        path: PathBuf::from("/etc/foo"),
+        timeout: Duration::from_secs(1),
+    });
+    tracked.set_path(PathBuf::from("/etc/bar"));
+    tracked.set_timeout(Duration::from_secs(2));
+    assert_eq!(tracked.get().path, PathBuf::from("/etc/bar"));
+    assert_eq!(tracked.flush(), OptionalConfig { path: Some("/etc/bar".to_owned()), timeout: 2000 });
+}
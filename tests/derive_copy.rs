@@ -0,0 +1,25 @@
+use optional_struct::*;
+
+#[optional_struct(derive_copy)]
+#[allow(dead_code)]
+struct Position {
+    x: f32,
+    y: f32,
+    layer: Option<u8>,
+}
+
+fn consume(patch: OptionalPosition) -> OptionalPosition {
+    patch
+}
+
+#[test]
+fn test_derive_copy() {
+    let patch = OptionalPosition {
+        x: Some(1.0),
+        y: None,
+        layer: Some(2),
+    };
+
+    let copy = consume(patch);
+    assert_eq!(copy, patch);
+}
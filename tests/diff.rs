@@ -0,0 +1,89 @@
+use optional_struct::*;
+
+#[optional_struct(impls(diff))]
+#[derive(Clone, Debug, PartialEq)]
+struct Config {
+    name: String,
+    port: u16,
+    comment: Option<String>,
+    #[optional_rename(OptionalLimits)]
+    limits: Limits,
+    #[optional_rename(OptionalLimits)]
+    fallback: Option<Limits>,
+}
+
+#[optional_struct(impls(diff))]
+#[derive(Clone, Debug, PartialEq)]
+struct Limits {
+    soft: u8,
+    hard: u8,
+}
+
+fn config() -> Config {
+    Config {
+        name: "a".to_owned(),
+        port: 80,
+        comment: None,
+        limits: Limits { soft: 1, hard: 2 },
+        fallback: Some(Limits { soft: 3, hard: 4 }),
+    }
+}
+
+// Generic code computing and applying patches, knowing nothing about the concrete type
+fn sync<T: Diffable>(local: &T, remote: &T) -> T::Patch {
+    local.diff(remote)
+}
+
+#[test]
+fn test_diff_equal() {
+    let patch = sync(&config(), &config());
+    assert_eq!(patch.name, None);
+    assert_eq!(patch.port, None);
+    assert_eq!(patch.comment, None);
+    assert_eq!(patch.limits.soft, None);
+    assert_eq!(patch.fallback, Some(OptionalLimits { soft: None, hard: None }));
+}
+
+#[test]
+fn test_diff_apply() {
+    let mut local = config();
+    let remote = Config {
+        port: 8080,
+        comment: Some("remote".to_owned()),
+        limits: Limits { soft: 1, hard: 5 },
+        fallback: Some(Limits { soft: 6, hard: 4 }),
+        ..config()
+    };
+    let patch = sync(&local, &remote);
+    assert_eq!(patch.name, None);
+    assert_eq!(patch.port, Some(8080));
+    assert_eq!(patch.limits, OptionalLimits { soft: None, hard: Some(5) });
+    assert_eq!(patch.fallback, Some(OptionalLimits { soft: Some(6), hard: None }));
+
+    patch.apply_to(&mut local);
+    assert_eq!(local, remote);
+}
+
+#[test]
+fn test_diff_leaves() {
+    assert_eq!(1u8.diff(&1), None);
+    assert_eq!(1u8.diff(&2), Some(2));
+    assert_eq!("a".to_owned().diff(&"b".to_owned()), Some("b".to_owned()));
+    assert_eq!(Some(1).diff(&None), Some(None));
+    assert_eq!(vec![1, 2].diff(&vec![1, 2]), None);
+    assert_eq!([1, 2].diff(&[1, 3]), Some([1, 3]));
+}
+
+#[optional_struct(impls(diff))]
+#[derive(Clone, Debug, PartialEq)]
+struct Point<T> {
+    x: T,
+    y: T,
+}
+
+#[test]
+fn test_diff_generic() {
+    let patch = Point { x: 1.0, y: 2.0 }.diff(&Point { x: 1.0, y: 3.0 });
+    assert_eq!(patch.x, None);
+    assert_eq!(patch.y, Some(3.0));
+}
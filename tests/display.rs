@@ -0,0 +1,35 @@
+use optional_struct::*;
+
+#[optional_struct(impls(display))]
+#[allow(dead_code)]
+struct Config {
+    timeout: Option<u32>,
+    name: String,
+    port: u16,
+
+    #[optional_rename(OptionalLogConfig)]
+    #[optional_wrap]
+    log_config: LogConfig,
+}
+
+#[optional_struct(impls(display))]
+#[allow(dead_code)]
+struct LogConfig {
+    log_file: String,
+    log_level: usize,
+}
+
+#[test]
+fn test_display() {
+    let opt_config = OptionalConfig {
+        timeout: None,
+        name: Some("x".to_owned()),
+        port: Some(8080),
+        log_config: Some(OptionalLogConfig {
+            log_file: None,
+            log_level: Some(3),
+        }),
+    };
+    assert_eq!(opt_config.to_string(), r#"{name: "x", port: 8080, log_config: {log_level: 3}}"#);
+    assert_eq!(OptionalConfig::default().to_string(), "{}");
+}
@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+
+use aws_sdk_dynamodb::types::AttributeValue;
+use optional_struct::*;
+
+#[optional_struct(dynamodb)]
+struct User {
+    name: String,
+    #[optional_wrap]
+    nickname: Option<String>,
+    #[optional_wrap]
+    status: Option<String>,
+    #[optional_rename(OptionalLimits)]
+    limits: Limits,
+}
+
+#[optional_struct(dynamodb)]
+struct Limits {
+    uploads: u32,
+    #[optional_wrap]
+    name: Option<String>,
+}
+
+fn names(names: &[&str]) -> HashMap<String, String> {
+    names.iter().map(|n| (format!("#{n}"), n.to_string())).collect()
+}
+
+#[test]
+fn test_set_and_remove() {
+    let patch = OptionalUser {
+        name: Some("Alice".to_owned()),
+        nickname: Some(None),
+        status: None,
+        limits: OptionalLimits {
+            uploads: Some(3),
+            name: Some(None),
+        },
+    };
+    let (expression, attribute_names, values) = patch.to_dynamo_update().unwrap();
+    assert_eq!(expression, "SET #name = :v0, #limits.#uploads = :v1 REMOVE #nickname, #limits.#name");
+    assert_eq!(attribute_names, names(&["name", "nickname", "limits", "uploads"]));
+    assert_eq!(values, HashMap::from([
+        (":v0".to_owned(), AttributeValue::S("Alice".to_owned())),
+        (":v1".to_owned(), AttributeValue::N("3".to_owned())),
+    ]));
+}
+
+#[test]
+fn test_nothing_set() {
+    let (expression, attribute_names, values) = OptionalUser::default().to_dynamo_update().unwrap();
+    assert_eq!(expression, "");
+    assert!(attribute_names.is_empty());
+    assert!(values.is_empty());
+}
@@ -0,0 +1,90 @@
+use optional_struct::*;
+
+#[optional_struct(impls(apply_report, display, missing_fields, set_count, tracked))]
+#[derive(Debug, PartialEq)]
+struct Calibration {
+    name: String,
+    #[optional_elementwise]
+    offsets: [i16; 4],
+    gains: [u8; 2],
+}
+
+fn calibration() -> Calibration {
+    Calibration {
+        name: "default".to_owned(),
+        offsets: [0, 1, 2, 3],
+        gains: [1, 1],
+    }
+}
+
+#[test]
+fn test_elementwise_apply() {
+    let mut calibration = calibration();
+    let patch = OptionalCalibration {
+        name: None,
+        offsets: [None, Some(-5), None, Some(7)],
+        gains: Some([2, 3]),
+    };
+    patch.apply_to(&mut calibration);
+    assert_eq!(calibration.offsets, [0, -5, 2, 7]);
+    assert_eq!(calibration.gains, [2, 3]);
+}
+
+#[test]
+fn test_elementwise_apply_patches() {
+    let first = OptionalCalibration {
+        offsets: [Some(1), Some(2), None, None],
+        ..Default::default()
+    };
+    let second = OptionalCalibration {
+        offsets: [None, Some(5), Some(6), None],
+        ..Default::default()
+    };
+    assert_eq!(first.apply(second).offsets, [Some(1), Some(5), Some(6), None]);
+}
+
+#[test]
+fn test_elementwise_try_from() {
+    let patch = OptionalCalibration {
+        name: Some("custom".to_owned()),
+        offsets: [Some(1), None, Some(3), Some(4)],
+        gains: Some([1, 2]),
+    };
+    assert_eq!(patch.missing_fields(), vec!["offsets"]);
+    let patch = patch.try_build().unwrap_err();
+
+    let calibration: Calibration = OptionalCalibration { offsets: [Some(1), Some(2), Some(3), Some(4)], ..patch }.try_into().unwrap();
+    assert_eq!(calibration.offsets, [1, 2, 3, 4]);
+}
+
+#[test]
+fn test_elementwise_report() {
+    let mut calibration = calibration();
+    let changes = OptionalCalibration {
+        offsets: [None, Some(1), Some(9), None],
+        ..Default::default()
+    }
+    .apply_report(&mut calibration);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].field, "offsets[2]");
+    assert_eq!(changes[0].new, "9");
+}
+
+#[test]
+fn test_elementwise_set_fields() {
+    let patch = OptionalCalibration {
+        offsets: [None, Some(1), None, None],
+        ..Default::default()
+    };
+    assert_eq!(patch.set_count(), 1);
+    assert_eq!(patch.to_string(), "{offsets: [None, Some(1), None, None]}");
+    assert_eq!(OptionalCalibration::default().to_string(), "{}");
+}
+
+#[test]
+fn test_elementwise_tracked() {
+    let mut tracked = TrackedCalibration::new(calibration());
+    tracked.set_offsets([4, 3, 2, 1]);
+    assert_eq!(tracked.get().offsets, [4, 3, 2, 1]);
+    assert_eq!(tracked.flush().offsets, [Some(4), Some(3), Some(2), Some(1)]);
+}
@@ -0,0 +1,27 @@
+use optional_struct::*;
+
+#[optional_struct(impls(empty))]
+#[allow(dead_code)]
+struct Config {
+    timeout: Option<u32>,
+    path: String,
+
+    #[optional_rename(OptionalLogConfig)]
+    #[optional_wrap]
+    log_config: LogConfig,
+}
+
+#[optional_struct(impls(empty))]
+#[allow(dead_code)]
+struct LogConfig {
+    log_file: String,
+    log_level: usize,
+}
+
+static NO_CHANGES: OptionalConfig = OptionalConfig::EMPTY;
+
+#[test]
+fn test_empty() {
+    assert_eq!(NO_CHANGES, OptionalConfig::default());
+    assert_eq!(OptionalConfig::empty(), OptionalConfig::default());
+}
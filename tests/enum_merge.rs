@@ -0,0 +1,113 @@
+use optional_struct::*;
+
+#[optional_struct(impls(apply_report, set_count))]
+#[derive(Debug, PartialEq)]
+enum Mode {
+    Off,
+    Fixed(u32),
+    Adaptive { min: u32, max: u32 },
+}
+
+#[optional_struct(impls(apply_report, set_count))]
+#[derive(Debug, PartialEq)]
+struct Config {
+    name: String,
+    #[optional_enum_merge]
+    mode: Mode,
+    #[optional_enum_merge(OptionalMode)]
+    fallback: Option<Mode>,
+}
+
+fn config() -> Config {
+    Config {
+        name: "cfg".to_owned(),
+        mode: Mode::Adaptive { min: 1, max: 10 },
+        fallback: None,
+    }
+}
+
+#[test]
+fn test_enum_merge_same_variant() {
+    let mut config = config();
+    let patch = OptionalConfig {
+        name: None,
+        mode: Some(OptionalMode::Adaptive { min: None, max: Some(20) }),
+        fallback: None,
+    };
+    assert_eq!(patch.set_count(), 1);
+    patch.apply_to(&mut config);
+    assert_eq!(config.mode, Mode::Adaptive { min: 1, max: 20 });
+    assert_eq!(config.fallback, None);
+}
+
+#[test]
+fn test_enum_merge_other_variant() {
+    let mut config = config();
+
+    // An incomplete patch for another variant can't replace the value
+    let patch = OptionalConfig {
+        name: None,
+        mode: Some(OptionalMode::Fixed(None)),
+        fallback: Some(OptionalMode::Adaptive { min: Some(2), max: None }),
+    };
+    patch.apply_to(&mut config);
+    assert_eq!(config.mode, Mode::Adaptive { min: 1, max: 10 });
+    assert_eq!(config.fallback, None);
+
+    let patch = OptionalConfig {
+        name: None,
+        mode: Some(OptionalMode::Fixed(Some(3))),
+        fallback: Some(OptionalMode::Off),
+    };
+    patch.apply_to(&mut config);
+    assert_eq!(config.mode, Mode::Fixed(3));
+    assert_eq!(config.fallback, Some(Mode::Off));
+}
+
+#[test]
+fn test_enum_merge_patches() {
+    let first = OptionalConfig {
+        name: Some("first".to_owned()),
+        mode: Some(OptionalMode::Adaptive { min: Some(1), max: None }),
+        fallback: None,
+    };
+    let second = OptionalConfig {
+        name: None,
+        mode: Some(OptionalMode::Adaptive { min: None, max: Some(5) }),
+        fallback: None,
+    };
+    let config: Config = first.apply(second).try_into().unwrap();
+    assert_eq!(config.mode, Mode::Adaptive { min: 1, max: 5 });
+
+    // The last variant wins
+    let third = OptionalConfig {
+        name: None,
+        mode: Some(OptionalMode::Off),
+        fallback: None,
+    };
+    let patch = OptionalConfig { name: None, mode: Some(OptionalMode::Fixed(None)), fallback: None }.apply(third);
+    assert_eq!(patch.mode, Some(OptionalMode::Off));
+}
+
+#[test]
+fn test_enum_merge_report() {
+    let mut config = config();
+    let changes = OptionalConfig {
+        name: None,
+        mode: Some(OptionalMode::Adaptive { min: None, max: Some(20) }),
+        fallback: None,
+    }
+    .apply_report(&mut config);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].field, "mode.max");
+
+    let changes = OptionalConfig {
+        name: None,
+        mode: Some(OptionalMode::Off),
+        fallback: None,
+    }
+    .apply_report(&mut config);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].field, "mode");
+    assert_eq!(config.mode, Mode::Off);
+}
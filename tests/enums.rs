@@ -0,0 +1,120 @@
+use optional_struct::*;
+
+#[optional_struct(impls(missing_fields))]
+#[derive(Debug, PartialEq)]
+enum Mode {
+    Off,
+    Fixed(u32, #[optional_skip_wrap] bool),
+    Adaptive {
+        min: u32,
+        max: u32,
+        label: Option<String>,
+        #[optional_rename(OptionalLimits)]
+        limits: Limits,
+    },
+}
+
+#[optional_struct]
+#[derive(Debug, PartialEq)]
+struct Limits {
+    soft: u8,
+    hard: u8,
+}
+
+#[test]
+fn test_enum_apply_same_variant() {
+    let mut mode = Mode::Adaptive {
+        min: 1,
+        max: 10,
+        label: None,
+        limits: Limits { soft: 1, hard: 2 },
+    };
+    let patch = OptionalMode::Adaptive {
+        min: None,
+        max: Some(20),
+        label: Some("auto".to_owned()),
+        limits: OptionalLimits { soft: None, hard: Some(5) },
+    };
+    assert!(!patch.can_convert());
+    patch.apply_to(&mut mode);
+    assert_eq!(mode, Mode::Adaptive {
+        min: 1,
+        max: 20,
+        label: Some("auto".to_owned()),
+        limits: Limits { soft: 1, hard: 5 },
+    });
+}
+
+#[test]
+fn test_enum_apply_other_variant() {
+    let mut mode = Mode::Off;
+
+    // Incomplete patches for another variant are ignored
+    OptionalMode::Fixed(None, true).apply_to(&mut mode);
+    assert_eq!(mode, Mode::Off);
+
+    OptionalMode::Fixed(Some(3), true).apply_to(&mut mode);
+    assert_eq!(mode, Mode::Fixed(3, true));
+
+    OptionalMode::Fixed(None, false).apply_to(&mut mode);
+    assert_eq!(mode, Mode::Fixed(3, false));
+
+    OptionalMode::Off.apply_to(&mut mode);
+    assert_eq!(mode, Mode::Off);
+}
+
+#[test]
+fn test_enum_try_from() {
+    assert_eq!(OptionalMode::Off.try_build(), Ok(Mode::Off));
+    assert!(OptionalMode::Fixed(Some(1), false).can_convert());
+    assert_eq!(OptionalMode::Fixed(None, false).try_build(), Err(OptionalMode::Fixed(None, false)));
+
+    let incomplete = OptionalMode::Adaptive {
+        min: Some(1),
+        max: Some(2),
+        label: None,
+        limits: OptionalLimits { soft: Some(1), hard: None },
+    };
+    assert!(incomplete.clone().try_build().is_err());
+
+    let complete = OptionalMode::Adaptive {
+        min: Some(1),
+        max: Some(2),
+        label: None,
+        limits: OptionalLimits { soft: Some(1), hard: Some(2) },
+    };
+    assert_eq!(complete.try_build(), Ok(Mode::Adaptive {
+        min: 1,
+        max: 2,
+        label: None,
+        limits: Limits { soft: 1, hard: 2 },
+    }));
+}
+
+#[optional_struct]
+#[derive(Debug, PartialEq)]
+enum Wrapper<T> {
+    Value {
+        value: T,
+    },
+}
+
+#[test]
+fn test_generic_enum() {
+    let mut wrapper = Wrapper::Value { value: 1u8 };
+    OptionalWrapper::Value { value: Some(2) }.apply_to(&mut wrapper);
+    assert_eq!(wrapper, Wrapper::Value { value: 2 });
+}
+
+#[test]
+fn test_enum_missing_field_paths() {
+    let patch = OptionalMode::Adaptive {
+        min: None,
+        max: Some(20),
+        label: None,
+        limits: OptionalLimits { soft: None, hard: Some(5) },
+    };
+    assert_eq!(patch.missing_field_paths(), ["min", "limits"]);
+    assert_eq!(OptionalMode::Fixed(None, true).missing_field_paths(), ["0"]);
+    assert!(OptionalMode::Off.missing_field_paths().is_empty());
+}
@@ -0,0 +1,38 @@
+use optional_struct::*;
+
+#[optional_struct(env_prefix = "APP_")]
+struct Config {
+    host: String,
+    max_connections: u32,
+    log_file: Option<String>,
+    #[optional_rename(OptionalLimits)]
+    limits: Limits,
+    #[optional_skip]
+    #[allow(dead_code)]
+    internal: bool,
+}
+
+#[optional_struct(env_prefix = "APP_LIMITS_")]
+struct Limits {
+    timeout: u32,
+}
+
+#[optional_struct(impls(env_vars))]
+struct Plain {
+    r#type: String,
+}
+
+#[test]
+fn test_env_vars() {
+    assert_eq!(OptionalConfig::ENV_VARS, [
+        ("host", "APP_HOST"),
+        ("max_connections", "APP_MAX_CONNECTIONS"),
+        ("log_file", "APP_LOG_FILE"),
+    ]);
+    assert_eq!(OptionalLimits::ENV_VARS, [("timeout", "APP_LIMITS_TIMEOUT")]);
+}
+
+#[test]
+fn test_env_vars_without_prefix() {
+    assert_eq!(OptionalPlain::ENV_VARS, [("type", "TYPE")]);
+}
@@ -0,0 +1,50 @@
+use optional_struct::*;
+use serde::Deserialize;
+use serde_json::json;
+
+#[optional_struct(explicit_null)]
+#[derive(Deserialize, Debug, PartialEq)]
+struct Profile {
+    name: String,
+    #[optional_wrap]
+    nickname: Option<String>,
+    email: Option<String>,
+}
+
+fn profile() -> Profile {
+    Profile {
+        name: "Jane".to_owned(),
+        nickname: Some("JD".to_owned()),
+        email: Some("jane@example.com".to_owned()),
+    }
+}
+
+#[test]
+fn test_null_clears_double_wrapped_field() {
+    let patch: OptionalProfile = serde_json::from_value(json!({ "nickname": null })).unwrap();
+    assert_eq!(patch.nickname, Some(None));
+
+    let mut profile = profile();
+    patch.apply_to(&mut profile);
+    assert_eq!(profile.nickname, None);
+    assert_eq!(profile.name, "Jane");
+}
+
+#[test]
+fn test_missing_field_stays_unset() {
+    let patch: OptionalProfile = serde_json::from_value(json!({ "name": "John" })).unwrap();
+    assert_eq!(patch.nickname, None);
+
+    let mut profile = profile();
+    patch.apply_to(&mut profile);
+    assert_eq!(profile.nickname, Some("JD".to_owned()));
+    assert_eq!(profile.name, "John");
+}
+
+#[test]
+fn test_null_leaves_other_fields_unset() {
+    // Not double wrapped, a `null` can only mean unset
+    let patch: OptionalProfile = serde_json::from_value(json!({ "email": null, "nickname": "J" })).unwrap();
+    assert_eq!(patch.email, None);
+    assert_eq!(patch.nickname, Some(Some("J".to_owned())));
+}
@@ -0,0 +1,26 @@
+use std::collections::HashSet;
+
+use optional_struct::*;
+
+#[optional_struct(derive(Eq, Hash, PartialOrd, Ord))]
+#[allow(dead_code)]
+struct Config {
+    timeout: Option<u32>,
+    path: String,
+}
+
+#[test]
+fn test_extra_derive_option() {
+    let first = OptionalConfig {
+        timeout: Some(1),
+        path: None,
+    };
+    let second = OptionalConfig {
+        timeout: Some(2),
+        path: None,
+    };
+
+    let pending = [first.clone(), second.clone(), first.clone()].into_iter().collect::<HashSet<_>>();
+    assert_eq!(pending.len(), 2);
+    assert!(first < second);
+}
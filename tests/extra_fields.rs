@@ -0,0 +1,32 @@
+use optional_struct::*;
+
+#[derive(Clone, Debug, PartialEq)]
+struct UserId(u32);
+
+#[optional_struct(extra_fields(updated_by: Option<UserId>, reason: Option<String>), impls(empty, set_count))]
+#[derive(Debug, PartialEq)]
+struct Config {
+    name: String,
+    port: u16,
+}
+
+#[test]
+fn test_extra_fields() {
+    let mut config = Config {
+        name: "foo".to_owned(),
+        port: 80,
+    };
+
+    let opt_config = OptionalConfig {
+        name: None,
+        port: Some(8080),
+        updated_by: Some(UserId(3)),
+        reason: Some("migration".to_owned()),
+    };
+    assert_eq!(opt_config.updated_by, Some(UserId(3)));
+    assert_eq!(opt_config.set_count(), 1);
+    opt_config.apply_to(&mut config);
+    assert_eq!(config, Config { name: "foo".to_owned(), port: 8080 });
+
+    assert_eq!(OptionalConfig::EMPTY.reason, None);
+}
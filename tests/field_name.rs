@@ -0,0 +1,44 @@
+use optional_struct::*;
+
+#[optional_struct(builder, impls(iter_set_fields, setters, tracked))]
+#[derive(Debug, Clone, PartialEq)]
+struct Config {
+    #[optional_field_name(name)]
+    display_name: String,
+    #[optional_field_name(port)]
+    #[optional_skip_wrap]
+    listen_port: u16,
+}
+
+#[test]
+fn test_field_name_apply() {
+    let mut config = Config {
+        display_name: "foo".to_owned(),
+        listen_port: 80,
+    };
+
+    let mut opt_config = OptionalConfig {
+        name: None,
+        port: 8080,
+    };
+    assert!(opt_config.clone().try_build().is_err());
+    opt_config.clone().apply_to(&mut config);
+    assert_eq!(config, Config { display_name: "foo".to_owned(), listen_port: 8080 });
+
+    opt_config.set_name("bar");
+    let names = opt_config.iter_set_fields().map(|(name, _)| name).collect::<Vec<_>>();
+    assert_eq!(names, ["name", "port"]);
+    let config = Config { display_name: "bar".to_owned(), listen_port: 8080 };
+    assert_eq!(opt_config.try_build(), Ok(config.clone()));
+    assert_eq!(Config::builder().name("bar".to_owned()).port(8080).build(), Ok(config));
+}
+
+#[test]
+fn test_field_name_tracked() {
+    let mut tracked = TrackedConfig::new(Config {
+        display_name: "foo".to_owned(),
+        listen_port: 80,
+    });
+    tracked.set_display_name("bar".to_owned());
+    assert_eq!(tracked.flush().name, Some("bar".to_owned()));
+}
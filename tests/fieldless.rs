@@ -0,0 +1,44 @@
+use optional_struct::*;
+
+#[optional_struct(impls(set_count))]
+#[derive(Debug, PartialEq)]
+struct Marker;
+
+#[optional_struct]
+#[derive(Debug, PartialEq)]
+struct Empty {}
+
+#[optional_struct]
+#[derive(Debug, PartialEq)]
+struct Holder {
+    value: u32,
+    #[optional_rename(OptionalMarker)]
+    marker: Marker,
+}
+
+#[test]
+fn test_unit_struct() {
+    let patch = OptionalMarker::default();
+    assert!(patch.can_convert());
+    assert_eq!(patch.set_count(), 0);
+    assert_eq!(OptionalMarker::field_count(), 0);
+
+    let mut marker = Marker;
+    patch.clone().apply_to(&mut marker);
+    assert_eq!(marker, Marker);
+    assert_eq!(Marker::try_from(patch), Ok(Marker));
+}
+
+#[test]
+fn test_empty_struct() {
+    let patch = OptionalEmpty {};
+    assert!(patch.can_convert());
+    let empty: Empty = patch.try_into().unwrap();
+    assert_eq!(empty, Empty {});
+}
+
+#[test]
+fn test_nested_unit_struct() {
+    let holder: Holder = OptionalHolder { value: Some(1), marker: OptionalMarker {} }.try_into().unwrap();
+    assert_eq!(holder, Holder { value: 1, marker: Marker });
+}
@@ -0,0 +1,46 @@
+use optional_struct::*;
+use serde_json::json;
+
+#[optional_struct(serde_json)]
+#[allow(dead_code)]
+struct Config {
+    timeout: Option<u32>,
+    name: String,
+    #[optional_wrap]
+    description: Option<String>,
+}
+
+#[test]
+fn test_set_by_name() {
+    let mut opt_config = OptionalConfig::default();
+
+    opt_config.set("timeout", json!(3)).unwrap();
+    opt_config.set("name", json!("foo")).unwrap();
+    opt_config.set("description", json!(null)).unwrap();
+
+    assert_eq!(opt_config, OptionalConfig {
+        timeout: Some(3),
+        name: Some("foo".to_owned()),
+        description: Some(None),
+    });
+
+    assert_eq!(opt_config.set("port", json!(8080)), Err(SetError::UnknownField("port".to_owned())));
+    assert!(matches!(
+        opt_config.set("timeout", json!("soon")),
+        Err(SetError::InvalidValue { field: "timeout", .. })
+    ));
+}
+
+#[test]
+fn test_get_by_name() {
+    let opt_config = OptionalConfig {
+        timeout: None,
+        name: Some("foo".to_owned()),
+        description: Some(None),
+    };
+
+    assert_eq!(opt_config.get("timeout"), None);
+    assert_eq!(opt_config.get("name"), Some(json!("foo")));
+    assert_eq!(opt_config.get("description"), Some(json!(null)));
+    assert_eq!(opt_config.get("port"), None);
+}
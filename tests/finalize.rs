@@ -0,0 +1,66 @@
+use optional_struct::*;
+
+#[optional_struct(impls(finalize))]
+#[derive(Debug, PartialEq)]
+struct Config {
+    name: String,
+    #[optional_rename(OptionalDatabase)]
+    database: Database,
+    #[optional_rename(OptionalDatabase)]
+    replica: Option<Database>,
+}
+
+#[optional_struct(impls(missing_fields))]
+#[derive(Debug, PartialEq)]
+struct Database {
+    url: String,
+    pool_size: u32,
+}
+
+fn defaults() -> OptionalConfig {
+    OptionalConfig {
+        database: OptionalDatabase { pool_size: Some(4), ..Default::default() },
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_finalize() {
+    let file = OptionalConfig {
+        name: Some("app".to_owned()),
+        database: OptionalDatabase { url: Some("postgres://localhost".to_owned()), ..Default::default() },
+        ..Default::default()
+    };
+    let config = Config::finalize(&[("defaults", defaults()), ("/etc/app.toml", file)]).unwrap();
+    assert_eq!(config, Config {
+        name: "app".to_owned(),
+        database: Database { url: "postgres://localhost".to_owned(), pool_size: 4 },
+        replica: None,
+    });
+}
+
+#[test]
+fn test_missing_nested_field() {
+    let env = OptionalConfig {
+        name: Some("app".to_owned()),
+        replica: Some(OptionalDatabase { pool_size: Some(2), ..Default::default() }),
+        ..Default::default()
+    };
+    let error = Config::finalize(&[
+        ("defaults", defaults()),
+        ("/etc/app.toml", OptionalConfig::default()),
+        ("env", env),
+    ]).unwrap_err();
+    assert_eq!(error.fields, ["database.url", "replica.url"]);
+    assert_eq!(error.to_string(), "database.url, replica.url not set (checked: defaults, /etc/app.toml, env)");
+}
+
+#[test]
+fn test_missing_field_paths() {
+    let patch = OptionalConfig {
+        database: OptionalDatabase { url: Some("postgres://localhost".to_owned()), ..Default::default() },
+        ..Default::default()
+    };
+    assert_eq!(patch.missing_field_paths(), ["name", "database.pool_size"]);
+    assert_eq!(patch.missing_fields(), ["name", "database"]);
+}
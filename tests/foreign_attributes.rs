@@ -0,0 +1,30 @@
+use optional_struct::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[optional_struct]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Foo {
+    #[serde(rename = "file")]
+    #[optional_wrap]
+    /// Where to write the logs
+    #[serde(default)]
+    log_file: String,
+    #[optional_field_name(level)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    log_level: Option<u8>,
+}
+
+#[test]
+fn test_foreign_attributes_on_original() {
+    let foo = Foo {
+        log_file: "/tmp/foo.log".to_owned(),
+        log_level: None,
+    };
+    assert_eq!(serde_json::to_value(&foo).unwrap(), json!({ "file": "/tmp/foo.log" }));
+
+    let foo: Foo = serde_json::from_value(json!({ "logLevel": 3 })).unwrap();
+    assert_eq!(foo.log_file, "");
+    assert_eq!(foo.log_level, Some(3));
+}
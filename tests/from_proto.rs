@@ -0,0 +1,100 @@
+use optional_struct::*;
+use prost_types::FieldMask;
+
+mod proto {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Server {
+        #[prost(string, tag = "1")]
+        pub display_name: String,
+        #[prost(uint32, tag = "2")]
+        pub port: u32,
+        #[prost(string, optional, tag = "3")]
+        pub description: Option<String>,
+        #[prost(message, optional, tag = "4")]
+        pub limits: Option<Limits>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Limits {
+        #[prost(uint32, tag = "1")]
+        pub connections: u32,
+        #[prost(uint32, tag = "2")]
+        pub timeout: u32,
+    }
+}
+
+#[optional_struct(prost = proto::Server)]
+#[derive(Debug, PartialEq)]
+struct Server {
+    #[optional_proto_name(display_name)]
+    name: String,
+    port: u32,
+    #[optional_wrap]
+    description: Option<String>,
+    #[optional_rename(OptionalLimits)]
+    limits: Limits,
+}
+
+#[optional_struct(prost = proto::Limits)]
+#[derive(Debug, PartialEq)]
+struct Limits {
+    connections: u32,
+    timeout: u32,
+}
+
+fn message() -> proto::Server {
+    proto::Server {
+        display_name: "web".to_owned(),
+        port: 8080,
+        description: None,
+        limits: Some(proto::Limits { connections: 100, timeout: 30 }),
+    }
+}
+
+fn mask(paths: &[&str]) -> FieldMask {
+    FieldMask { paths: paths.iter().map(|p| p.to_string()).collect() }
+}
+
+#[test]
+fn test_from_proto() {
+    let patch = OptionalServer::from_proto(&message(), &mask(&["display_name", "limits.timeout"]));
+    assert_eq!(patch, OptionalServer {
+        name: Some("web".to_owned()),
+        port: None,
+        description: None,
+        limits: OptionalLimits { connections: None, timeout: Some(30) },
+    });
+}
+
+#[test]
+fn test_masked_unset_field_is_cleared() {
+    let patch = OptionalServer::from_proto(&message(), &mask(&["description"]));
+    assert_eq!(patch.description, Some(None));
+
+    let mut server = Server {
+        name: "web".to_owned(),
+        port: 80,
+        description: Some("Frontend".to_owned()),
+        limits: Limits { connections: 10, timeout: 5 },
+    };
+    patch.apply_to(&mut server);
+    assert_eq!(server.description, None);
+}
+
+#[test]
+fn test_whole_nested_message() {
+    let patch = OptionalServer::from_proto(&message(), &mask(&["limits"]));
+    assert_eq!(patch.limits, OptionalLimits { connections: Some(100), timeout: Some(30) });
+    assert_eq!(patch.port, None);
+}
+
+#[test]
+fn test_empty_mask() {
+    let patch = OptionalServer::from_proto(&message(), &FieldMask::default());
+    assert_eq!(patch, OptionalServer {
+        name: Some("web".to_owned()),
+        port: Some(8080),
+        description: Some(None),
+        limits: OptionalLimits { connections: Some(100), timeout: Some(30) },
+    });
+}
@@ -23,3 +23,49 @@ fn test_apply_options() {
     assert_eq!(config.value_t, 3.0);
     assert_eq!(config.value_v, "bar");
 }
+
+#[optional_struct]
+#[derive(Debug, PartialEq)]
+struct Limits {
+    low: u8,
+    high: u8,
+}
+
+#[optional_struct]
+#[derive(Debug, PartialEq)]
+struct Inner<T> {
+    value: T,
+    total: u32,
+}
+
+// The derives and impls of the nested optional struct need those bounds
+#[optional_struct]
+#[derive(Debug, PartialEq)]
+struct Outer<T: Clone + Default + std::fmt::Debug> {
+    #[optional_rename(OptionalInner<T>)]
+    inner: Inner<T>,
+    #[optional_rename(crate::OptionalLimits)]
+    limits: Limits,
+}
+
+#[test]
+fn test_generic_nested() {
+    let mut outer = Outer {
+        inner: Inner { value: "a", total: 1 },
+        limits: Limits { low: 0, high: 10 },
+    };
+    let patch = OptionalOuter {
+        inner: OptionalInner { value: Some("b"), total: None },
+        limits: OptionalLimits { low: None, high: Some(20) },
+    };
+    patch.apply_to(&mut outer);
+    assert_eq!(outer.inner, Inner { value: "b", total: 1 });
+    assert_eq!(outer.limits.high, 20);
+
+    let patch = OptionalOuter {
+        inner: OptionalInner { value: Some(1.5), total: Some(2) },
+        limits: OptionalLimits { low: Some(1), high: Some(2) },
+    };
+    let outer: Outer<f64> = patch.try_into().unwrap();
+    assert_eq!(outer.inner, Inner { value: 1.5, total: 2 });
+}
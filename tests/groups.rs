@@ -0,0 +1,94 @@
+use optional_struct::*;
+
+#[optional_struct]
+#[derive(Debug, PartialEq)]
+struct Server {
+    #[optional_group(endpoint)]
+    host: String,
+    #[optional_group(endpoint)]
+    port: u16,
+    #[optional_group(tls)]
+    cert: Option<String>,
+    #[optional_group(tls)]
+    key: Option<String>,
+    name: String,
+}
+
+fn server() -> Server {
+    Server {
+        host: "localhost".to_owned(),
+        port: 80,
+        cert: None,
+        key: None,
+        name: "foo".to_owned(),
+    }
+}
+
+#[test]
+fn test_groups_incomplete() {
+    let patch = OptionalServer {
+        port: Some(8080),
+        ..Default::default()
+    };
+    assert_eq!(patch.incomplete_group(), Some("endpoint"));
+
+    let patch = OptionalServer {
+        host: Some("example.com".to_owned()),
+        port: Some(8080),
+        key: Some("key".to_owned()),
+        ..Default::default()
+    };
+    assert_eq!(patch.incomplete_group(), Some("tls"));
+
+    let patch = OptionalServer {
+        name: Some("bar".to_owned()),
+        ..Default::default()
+    };
+    assert_eq!(patch.incomplete_group(), None);
+}
+
+#[test]
+fn test_groups_try_apply() {
+    let mut s = server();
+    let patch = OptionalServer {
+        port: Some(8080),
+        name: Some("bar".to_owned()),
+        ..Default::default()
+    };
+    let patch = patch.try_apply_to(&mut s).unwrap_err();
+    assert_eq!(s, server());
+    assert_eq!(patch.port, Some(8080));
+
+    let patch = OptionalServer {
+        host: Some("example.com".to_owned()),
+        port: Some(8080),
+        ..Default::default()
+    };
+    assert!(patch.try_apply_to(&mut s).is_ok());
+    assert_eq!(s.host, "example.com");
+    assert_eq!(s.port, 8080);
+    assert_eq!(s.name, "foo");
+}
+
+#[test]
+fn test_groups_try_build() {
+    let patch = OptionalServer {
+        host: Some("example.com".to_owned()),
+        port: Some(8080),
+        cert: Some("cert".to_owned()),
+        key: None,
+        name: Some("bar".to_owned()),
+    };
+    assert!(!patch.can_convert());
+    assert!(patch.try_build().is_err());
+
+    let patch = OptionalServer {
+        host: Some("example.com".to_owned()),
+        port: Some(8080),
+        cert: Some("cert".to_owned()),
+        key: Some("key".to_owned()),
+        name: Some("bar".to_owned()),
+    };
+    assert!(patch.can_convert());
+    assert!(patch.try_build().is_ok());
+}
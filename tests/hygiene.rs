@@ -0,0 +1,93 @@
+use std::time::Duration;
+
+use optional_struct::*;
+
+// Named like the bindings of the generated code
+fn v() -> u32 {
+    7
+}
+
+fn count(level: &u8) -> bool {
+    *level != 0
+}
+
+fn value(millis: u64) -> Duration {
+    Duration::from_millis(millis)
+}
+
+fn inner(duration: Duration) -> u64 {
+    duration.as_millis() as u64
+}
+
+#[optional_struct(impls(set_count, tracked))]
+#[derive(Debug, PartialEq)]
+struct Config {
+    #[optional_skip(default = "v()")]
+    retries: u32,
+    #[optional_skip_wrap]
+    #[optional_set_if(count)]
+    level: u8,
+    #[optional_convert(ty = "u64", into = "inner", from = "value")]
+    timeout: Duration,
+}
+
+macro_rules! define_config {
+    ($name:ident, $($field:ident),*) => {
+        #[optional_struct(impls(set_count))]
+        #[derive(Debug, PartialEq)]
+        struct $name {
+            $($field: u32,)*
+        }
+    };
+}
+
+define_config!(Bindings, v, t, value, inner, total);
+
+#[test]
+fn test_user_functions_named_like_bindings() {
+    let opt_config = OptionalConfig {
+        level: 2,
+        timeout: Some(1500),
+    };
+    assert_eq!(opt_config.set_count(), 2);
+    assert_eq!(opt_config.try_build().unwrap(), Config {
+        retries: 7,
+        level: 2,
+        timeout: Duration::from_millis(1500),
+    });
+
+    let mut config = Config {
+        retries: 1,
+        level: 3,
+        timeout: Duration::from_secs(1),
+    };
+    OptionalConfig { level: 0, timeout: None }.apply_to(&mut config);
+    assert_eq!(config.level, 3);
+}
+
+#[test]
+fn test_fields_named_like_bindings() {
+    let opt = OptionalBindings {
+        v: Some(1),
+        t: Some(2),
+        value: None,
+        inner: Some(4),
+        total: Some(5),
+    };
+    let mut bindings = Bindings { v: 0, t: 0, value: 3, inner: 0, total: 0 };
+    opt.clone().apply_to(&mut bindings);
+    assert_eq!(bindings, Bindings { v: 1, t: 2, value: 3, inner: 4, total: 5 });
+    assert_eq!(opt.set_count(), 4);
+    assert!(opt.try_build().is_err());
+}
+
+#[test]
+fn test_tracked_conversion() {
+    let mut tracked = TrackedConfig::new(Config {
+        retries: 1,
+        level: 3,
+        timeout: Duration::from_secs(1),
+    });
+    tracked.set_timeout(Duration::from_millis(20));
+    assert_eq!(tracked.flush().timeout, Some(20));
+}
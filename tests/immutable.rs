@@ -0,0 +1,53 @@
+use optional_struct::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[optional_struct(serde_json, sparse_json, impls(set_columns))]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct User {
+    #[optional_immutable]
+    id: u64,
+    #[optional_immutable]
+    created_at: u64,
+    email: String,
+}
+
+fn user() -> User {
+    User {
+        id: 1,
+        created_at: 1700000000,
+        email: "alice@example.com".to_owned(),
+    }
+}
+
+#[test]
+fn test_not_applied() {
+    let mut user = user();
+    let patch = OptionalUser {
+        id: Some(2),
+        created_at: None,
+        email: Some("alice@example.org".to_owned()),
+    };
+    assert_eq!(patch.ignored_fields(), vec!["id"]);
+    assert_eq!(patch.set_columns(), vec!["email"]);
+    patch.apply_to(&mut user);
+    assert_eq!(user, User {
+        id: 1,
+        created_at: 1700000000,
+        email: "alice@example.org".to_owned(),
+    });
+}
+
+#[test]
+fn test_not_set_by_name() {
+    let mut patch = OptionalUser::default();
+    assert_eq!(patch.set("id", json!(2)), Err(SetError::Immutable("id")));
+    assert_eq!(patch.set("email", json!("alice@example.org")), Ok(()));
+    assert_eq!(patch.id, None);
+    assert_eq!(SetError::Immutable("id").to_string(), "field 'id' is immutable");
+
+    assert_eq!(
+        OptionalUser::from_value_sparse(json!({ "created_at": 0 })),
+        Err(SetError::Immutable("created_at")),
+    );
+}
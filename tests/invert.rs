@@ -0,0 +1,62 @@
+use optional_struct::*;
+
+#[optional_struct(impls(invert))]
+#[derive(Clone, Debug, PartialEq)]
+struct Document {
+    title: String,
+    body: String,
+    tags: Option<Vec<String>>,
+    #[optional_rename(OptionalLayout)]
+    layout: Layout,
+}
+
+#[optional_struct(impls(invert))]
+#[derive(Clone, Debug, PartialEq)]
+struct Layout {
+    width: u16,
+    margin: u16,
+}
+
+fn document() -> Document {
+    Document {
+        title: "Draft".to_owned(),
+        body: "Hello".to_owned(),
+        tags: Some(vec!["a".to_owned()]),
+        layout: Layout { width: 80, margin: 2 },
+    }
+}
+
+#[test]
+fn test_invert_undo_redo() {
+    let mut document = document();
+    let patch = OptionalDocument {
+        title: Some("Title".to_owned()),
+        tags: Some(vec![]),
+        layout: OptionalLayout { width: Some(100), margin: None },
+        ..Default::default()
+    };
+
+    // Only the fields set in the patch are captured
+    let undo = patch.invert(&document);
+    assert_eq!(undo.title, Some("Draft".to_owned()));
+    assert_eq!(undo.body, None);
+    assert_eq!(undo.tags, Some(vec!["a".to_owned()]));
+    assert_eq!(undo.layout, OptionalLayout { width: Some(80), margin: None });
+
+    let redo = patch.clone();
+    patch.apply_to(&mut document);
+    let applied = document.clone();
+    undo.apply_to(&mut document);
+    assert_eq!(document, self::document());
+
+    redo.apply_to(&mut document);
+    assert_eq!(document, applied);
+}
+
+#[test]
+fn test_invert_unset_option() {
+    // `None` can't be restored by a patch, the field is left unset
+    let base = Document { tags: None, ..document() };
+    let patch = OptionalDocument { tags: Some(vec![]), ..Default::default() };
+    assert_eq!(patch.invert(&base).tags, None);
+}
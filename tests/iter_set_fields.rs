@@ -0,0 +1,25 @@
+use optional_struct::*;
+
+#[optional_struct(impls(iter_set_fields))]
+#[allow(dead_code)]
+struct Config {
+    timeout: Option<u32>,
+    name: String,
+    port: u16,
+}
+
+#[test]
+fn test_iter_set_fields() {
+    let opt_config = OptionalConfig {
+        timeout: Some(3),
+        name: None,
+        port: Some(8080),
+    };
+
+    let fields = opt_config.iter_set_fields().collect::<Vec<_>>();
+    assert_eq!(fields.len(), 2);
+    assert_eq!(fields[0].0, "timeout");
+    assert_eq!(fields[0].1.downcast_ref::<u32>(), Some(&3));
+    assert_eq!(fields[1].0, "port");
+    assert_eq!(fields[1].1.downcast_ref::<u16>(), Some(&8080));
+}
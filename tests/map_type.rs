@@ -0,0 +1,49 @@
+use std::time::Duration;
+
+use optional_struct::*;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Millis(u64);
+
+impl From<Duration> for Millis {
+    fn from(duration: Duration) -> Self {
+        Millis(duration.as_millis() as u64)
+    }
+}
+
+impl From<Millis> for Duration {
+    fn from(millis: Millis) -> Self {
+        Duration::from_millis(millis.0)
+    }
+}
+
+#[optional_struct]
+#[optional_map_type(std::time::Duration => Millis)]
+#[derive(Debug, PartialEq)]
+struct Config {
+    timeout: Duration,
+    #[optional_skip_wrap]
+    retry_delay: Duration,
+    name: String,
+}
+
+#[test]
+fn test_map_type() {
+    let mut config = Config {
+        timeout: Duration::from_secs(1),
+        retry_delay: Duration::from_secs(1),
+        name: "foo".to_owned(),
+    };
+
+    let opt_config = OptionalConfig {
+        timeout: Some(Millis(500)),
+        retry_delay: Millis(200),
+        name: None,
+    };
+    opt_config.apply_to(&mut config);
+    assert_eq!(config, Config {
+        timeout: Duration::from_millis(500),
+        retry_delay: Duration::from_millis(200),
+        name: "foo".to_owned(),
+    });
+}
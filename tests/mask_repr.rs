@@ -0,0 +1,61 @@
+use optional_struct::*;
+
+#[optional_struct(repr = "mask")]
+#[derive(Debug, PartialEq)]
+struct Config {
+    timeout: u32,
+    name: String,
+    retries: Option<u8>,
+    #[optional_skip_wrap]
+    verbose: bool,
+}
+
+#[test]
+fn test_mask_round_trip() {
+    let patch = OptionalConfig {
+        timeout: Some(5),
+        name: None,
+        retries: Some(3),
+        verbose: true,
+    };
+    let mask = OptionalConfigMask::from(patch.clone());
+    assert_eq!(OptionalConfig::from(mask), patch);
+}
+
+#[test]
+fn test_mask_apply() {
+    let mut config = Config {
+        timeout: 1,
+        name: "foo".to_owned(),
+        retries: None,
+        verbose: false,
+    };
+    let mask = OptionalConfigMask::from(OptionalConfig {
+        timeout: None,
+        name: Some("bar".to_owned()),
+        retries: None,
+        verbose: true,
+    });
+    mask.apply_to(&mut config);
+    assert_eq!(config, Config {
+        timeout: 1,
+        name: "bar".to_owned(),
+        retries: None,
+        verbose: true,
+    });
+}
+
+#[test]
+fn test_mask_patch_log() {
+    let mut log = PatchLog::<Config, OptionalConfigMask>::new();
+    log.record(OptionalConfigMask::from(OptionalConfig { timeout: Some(2), ..Default::default() }));
+    log.record(OptionalConfigMask::from(OptionalConfig { retries: Some(1), ..Default::default() }));
+    let mut config = Config {
+        timeout: 1,
+        name: "foo".to_owned(),
+        retries: None,
+        verbose: false,
+    };
+    log.replay(&mut config);
+    assert_eq!((config.timeout, config.retries), (2, Some(1)));
+}
@@ -0,0 +1,39 @@
+use merge::Merge;
+use optional_struct::*;
+
+#[optional_struct(merge)]
+struct Config {
+    delay: Option<u32>,
+    path: String,
+    percentage: f32,
+}
+
+#[test]
+fn test_merge() {
+    let mut opt_config = OptionalConfig {
+        delay: None,
+        path: Some("/tmp/bar.log".to_owned()),
+        percentage: None,
+    };
+
+    opt_config.merge(OptionalConfig {
+        delay: Some(3),
+        path: Some("/var/log/foo.log".to_owned()),
+        percentage: None,
+    });
+
+    assert_eq!(opt_config, OptionalConfig {
+        delay: Some(3),
+        path: Some("/tmp/bar.log".to_owned()),
+        percentage: None,
+    });
+
+    let config = opt_config.build(Config {
+        delay: None,
+        path: "/var/log/foo.log".to_owned(),
+        percentage: 3.12,
+    });
+    assert_eq!(config.delay, Some(3));
+    assert_eq!(config.path, "/tmp/bar.log");
+    assert_eq!(config.percentage, 3.12);
+}
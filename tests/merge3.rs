@@ -0,0 +1,84 @@
+use optional_struct::*;
+
+#[optional_struct(impls(merge3))]
+#[derive(Clone, Debug, PartialEq)]
+struct Document {
+    title: String,
+    body: String,
+    tags: Option<Vec<String>>,
+    #[optional_merge_op(add)]
+    edits: u32,
+    #[optional_rename(OptionalLayout)]
+    layout: Layout,
+}
+
+#[optional_struct(impls(merge3))]
+#[derive(Clone, Debug, PartialEq)]
+struct Layout {
+    width: u16,
+    margin: u16,
+}
+
+fn base() -> Document {
+    Document {
+        title: "Draft".to_owned(),
+        body: "Hello".to_owned(),
+        tags: None,
+        edits: 0,
+        layout: Layout { width: 80, margin: 2 },
+    }
+}
+
+#[test]
+fn test_merge3_without_conflicts() {
+    let ours = OptionalDocument {
+        title: Some("Title".to_owned()),
+        edits: Some(1),
+        layout: OptionalLayout { width: Some(100), margin: None },
+        ..Default::default()
+    };
+    let theirs = OptionalDocument {
+        // Same change on both sides
+        title: Some("Title".to_owned()),
+        // Changed back to the base value, i.e. no change
+        body: Some("Hello".to_owned()),
+        tags: Some(vec!["a".to_owned()]),
+        edits: Some(2),
+        layout: OptionalLayout { width: None, margin: Some(4) },
+    };
+    let merged = OptionalDocument::merge3(&base(), ours, theirs).unwrap();
+    assert_eq!(merged.title, Some("Title".to_owned()));
+    assert_eq!(merged.body, Some("Hello".to_owned()));
+    assert_eq!(merged.tags, Some(vec!["a".to_owned()]));
+    assert_eq!(merged.edits, Some(3));
+
+    let document = merged.build(base());
+    assert_eq!(document.layout, Layout { width: 100, margin: 4 });
+}
+
+#[test]
+fn test_merge3_base_value_loses() {
+    let ours = OptionalDocument { body: Some("Hello".to_owned()), ..Default::default() };
+    let theirs = OptionalDocument { body: Some("World".to_owned()), ..Default::default() };
+    let merged = OptionalDocument::merge3(&base(), ours, theirs).unwrap();
+    assert_eq!(merged.body, Some("World".to_owned()));
+}
+
+#[test]
+fn test_merge3_conflicts() {
+    let ours = OptionalDocument {
+        title: Some("Ours".to_owned()),
+        body: Some("Ours".to_owned()),
+        layout: OptionalLayout { width: Some(100), margin: None },
+        ..Default::default()
+    };
+    let theirs = OptionalDocument {
+        title: Some("Theirs".to_owned()),
+        body: Some("Ours".to_owned()),
+        layout: OptionalLayout { width: Some(120), margin: None },
+        ..Default::default()
+    };
+    let conflicts = OptionalDocument::merge3(&base(), ours, theirs).unwrap_err();
+    assert_eq!(conflicts.fields, ["title", "layout.width"]);
+    assert_eq!(conflicts.to_string(), "conflicting changes to title, layout.width");
+}
@@ -0,0 +1,89 @@
+use optional_struct::*;
+
+#[optional_struct(impls(apply_report))]
+#[derive(Debug, PartialEq)]
+struct Quota {
+    #[optional_merge_op(add)]
+    requests: u64,
+    #[optional_merge_op(saturating_add)]
+    errors: u8,
+    #[optional_merge_op(min)]
+    min_latency: u32,
+    #[optional_merge_op(max)]
+    #[optional_skip_wrap]
+    max_latency: u32,
+    name: String,
+}
+
+#[test]
+fn test_merge_op_apply() {
+    let mut quota = Quota {
+        requests: 10,
+        errors: 250,
+        min_latency: 20,
+        max_latency: 40,
+        name: "foo".to_owned(),
+    };
+
+    let patch = OptionalQuota {
+        requests: Some(5),
+        errors: Some(10),
+        min_latency: Some(15),
+        max_latency: 30,
+        name: Some("bar".to_owned()),
+    };
+    patch.apply_to(&mut quota);
+    assert_eq!(quota, Quota {
+        requests: 15,
+        errors: 255,
+        min_latency: 15,
+        max_latency: 40,
+        name: "bar".to_owned(),
+    });
+}
+
+#[test]
+fn test_merge_op_combine_patches() {
+    let first = OptionalQuota {
+        requests: Some(5),
+        min_latency: None,
+        max_latency: 30,
+        ..Default::default()
+    };
+    let second = OptionalQuota {
+        requests: Some(3),
+        min_latency: Some(12),
+        max_latency: 50,
+        ..Default::default()
+    };
+
+    let combined = first.apply(second);
+    assert_eq!(combined.requests, Some(8));
+    assert_eq!(combined.min_latency, Some(12));
+    assert_eq!(combined.max_latency, 50);
+}
+
+#[test]
+fn test_merge_op_apply_report() {
+    let mut quota = Quota {
+        requests: 10,
+        errors: 0,
+        min_latency: 20,
+        max_latency: 40,
+        name: "foo".to_owned(),
+    };
+
+    let patch = OptionalQuota {
+        requests: Some(5),
+        max_latency: 30,
+        ..Default::default()
+    };
+    let changes = patch.apply_report(&mut quota);
+    assert_eq!(quota.requests, 15);
+    assert_eq!(quota.max_latency, 40);
+    assert_eq!(changes, vec![FieldChange {
+        field: "requests".to_owned(),
+        old: "10".to_owned(),
+        new: "15".to_owned(),
+    }]);
+}
@@ -0,0 +1,61 @@
+use optional_struct::*;
+use serde::{Deserialize, Serialize};
+
+#[optional_struct(explicit_null, impls(merge_patch))]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+// Nested patches can be left out as well
+#[optional_struct_attr(serde(default))]
+struct Config {
+    host: String,
+    port: u16,
+    #[optional_wrap]
+    proxy: Option<String>,
+    #[optional_rename(OptionalLimits)]
+    limits: Limits,
+}
+
+#[optional_struct(impls(diff, set_count))]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+struct Limits {
+    connections: u32,
+    timeout: u32,
+}
+
+fn config() -> Config {
+    Config {
+        host: "localhost".to_owned(),
+        port: 80,
+        proxy: Some("proxy".to_owned()),
+        limits: Limits { connections: 10, timeout: 30 },
+    }
+}
+
+#[test]
+fn test_apply_merge_patch() {
+    let mut config = config();
+    let changed = config.apply_merge_patch(br#"{"port": 8080, "host": "localhost", "limits": {"timeout": 60}}"#).unwrap();
+    assert_eq!(changed, ["port", "limits"]);
+    assert_eq!(config, Config {
+        port: 8080,
+        limits: Limits { connections: 10, timeout: 60 },
+        ..self::config()
+    });
+}
+
+#[test]
+fn test_null_removes_member() {
+    let mut config = config();
+    let changed = config.apply_merge_patch(br#"{"proxy": null}"#).unwrap();
+    assert_eq!(changed, ["proxy"]);
+    assert_eq!(config.proxy, None);
+}
+
+#[test]
+fn test_invalid_patch_applies_nothing() {
+    let mut config = config();
+    assert!(config.apply_merge_patch(br#"{"port": "http", "host": "example.com"}"#).is_err());
+    assert!(config.apply_merge_patch(br#"{"port": 8080"#).is_err());
+    let err = config.apply_merge_patch(b"[8080]").unwrap_err();
+    assert_eq!(err.to_string(), "a merge patch must be a JSON object");
+    assert_eq!(config, self::config());
+}
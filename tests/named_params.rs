@@ -0,0 +1,66 @@
+use optional_struct::*;
+use rusqlite::Connection;
+
+#[optional_struct(rusqlite)]
+#[derive(Debug, PartialEq)]
+struct User {
+    name: String,
+    #[optional_column(email_address)]
+    email: String,
+    #[optional_wrap]
+    nickname: Option<String>,
+    #[optional_read_only]
+    created_at: i64,
+}
+
+fn database() -> Connection {
+    let connection = Connection::open_in_memory().unwrap();
+    connection.execute_batch("
+        CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, email_address TEXT, nickname TEXT, created_at INTEGER);
+        INSERT INTO users VALUES (1, 'Alice', 'alice@example.com', 'Al', 0);
+    ").unwrap();
+    connection
+}
+
+fn user(connection: &Connection) -> User {
+    connection
+        .query_row("SELECT name, email_address, nickname, created_at FROM users WHERE id = 1", [], |row| {
+            Ok(User {
+                name: row.get(0)?,
+                email: row.get(1)?,
+                nickname: row.get(2)?,
+                created_at: row.get(3)?,
+            })
+        })
+        .unwrap()
+}
+
+#[test]
+fn test_partial_update() {
+    let connection = database();
+    let patch = OptionalUser {
+        name: None,
+        email: Some("alice@example.org".to_owned()),
+        nickname: Some(None),
+        created_at: Some(42),
+    };
+    assert_eq!(patch.set_clause(), "email_address = :email_address, nickname = :nickname");
+
+    let mut params = patch.to_named_params();
+    params.push((":id", &1));
+    let query = format!("UPDATE users SET {} WHERE id = :id", patch.set_clause());
+    assert_eq!(connection.execute(&query, params.as_slice()).unwrap(), 1);
+    assert_eq!(user(&connection), User {
+        name: "Alice".to_owned(),
+        email: "alice@example.org".to_owned(),
+        nickname: None,
+        created_at: 0,
+    });
+}
+
+#[test]
+fn test_nothing_set() {
+    let patch = OptionalUser::default();
+    assert!(patch.to_named_params().is_empty());
+    assert_eq!(patch.set_clause(), "");
+}
@@ -39,3 +39,33 @@ fn test_apply_options() {
     assert_eq!(config.log_config.log_file, "/tmp/bar.log");
     assert_eq!(config.log_config.log_level, 3);
 }
+
+#[test]
+fn test_can_convert_wrapped_nested() {
+    let unset = OptionalConfig {
+        timeout: None,
+        log_config: None,
+    };
+    assert!(!unset.can_convert());
+
+    let incomplete = OptionalConfig {
+        timeout: None,
+        log_config: Some(OptionalLogConfig {
+            log_file: Some("/tmp/bar.log".to_owned()),
+            log_level: None,
+        }),
+    };
+    assert!(!incomplete.can_convert());
+    assert!(incomplete.try_build().is_err());
+
+    let complete = OptionalConfig {
+        timeout: None,
+        log_config: Some(OptionalLogConfig {
+            log_file: Some("/tmp/bar.log".to_owned()),
+            log_level: Some(2),
+        }),
+    };
+    assert!(complete.can_convert());
+    let config = complete.try_build().unwrap();
+    assert_eq!(config.log_config.log_level, 2);
+}
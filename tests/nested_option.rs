@@ -0,0 +1,96 @@
+use optional_struct::*;
+
+#[optional_struct(impls(apply_report))]
+#[derive(Debug, PartialEq)]
+struct Config {
+    timeout: u32,
+
+    #[optional_rename(OptionalLogConfig)]
+    log_config: Option<LogConfig>,
+}
+
+#[optional_struct(impls(apply_report))]
+#[derive(Debug, PartialEq)]
+struct LogConfig {
+    log_file: String,
+    log_level: usize,
+}
+
+fn log_config() -> LogConfig {
+    LogConfig {
+        log_file: "/var/log/foobar.log".to_owned(),
+        log_level: 3,
+    }
+}
+
+#[test]
+fn test_nested_option_apply_to_existing() {
+    let mut config = Config {
+        timeout: 2,
+        log_config: Some(log_config()),
+    };
+    let patch = OptionalConfig {
+        timeout: None,
+        log_config: Some(OptionalLogConfig {
+            log_file: None,
+            log_level: Some(1),
+        }),
+    };
+    patch.apply_to(&mut config);
+    assert_eq!(config.log_config, Some(LogConfig {
+        log_level: 1,
+        ..log_config()
+    }));
+}
+
+#[test]
+fn test_nested_option_apply_to_missing() {
+    let mut config = Config {
+        timeout: 2,
+        log_config: None,
+    };
+    let incomplete = OptionalConfig {
+        timeout: None,
+        log_config: Some(OptionalLogConfig {
+            log_file: None,
+            log_level: Some(1),
+        }),
+    };
+    incomplete.apply_to(&mut config);
+    assert_eq!(config.log_config, None);
+
+    let complete = OptionalConfig {
+        timeout: None,
+        log_config: Some(OptionalLogConfig {
+            log_file: Some("/var/log/foobar.log".to_owned()),
+            log_level: Some(3),
+        }),
+    };
+    let changes = complete.apply_report(&mut config);
+    assert_eq!(config.log_config, Some(log_config()));
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].field, "log_config");
+}
+
+#[test]
+fn test_nested_option_try_build() {
+    let unset = OptionalConfig {
+        timeout: Some(1),
+        log_config: None,
+    };
+    assert!(unset.can_convert());
+    assert_eq!(unset.try_build().unwrap(), Config {
+        timeout: 1,
+        log_config: None,
+    });
+
+    let incomplete = OptionalConfig {
+        timeout: Some(1),
+        log_config: Some(OptionalLogConfig {
+            log_file: None,
+            log_level: Some(1),
+        }),
+    };
+    assert!(!incomplete.can_convert());
+    assert!(incomplete.try_build().is_err());
+}
@@ -0,0 +1,101 @@
+use optional_struct::*;
+
+mod dsl {
+    #[derive(Clone, Debug, Default, PartialEq)]
+    pub struct Option<T>(pub Vec<T>);
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Setting<T> {
+    #[default]
+    Inherit,
+    Value(T),
+}
+
+impl<T> Setting<T> {
+    pub fn is_some(&self) -> bool {
+        matches!(self, Setting::Value(_))
+    }
+
+    pub fn as_ref(&self) -> Option<&T> {
+        match self {
+            Setting::Inherit => None,
+            Setting::Value(v) => Some(v),
+        }
+    }
+}
+
+#[optional_struct(impls(set_count))]
+#[derive(Debug, PartialEq)]
+struct Config {
+    tags: dsl::Option<String>,
+    delay: ::core::option::Option<u32>,
+    #[optional_option_like]
+    level: Setting<u8>,
+}
+
+#[optional_struct(impls(set_count))]
+struct Options {
+    #[optional_not_option]
+    inner: Option<u8>,
+}
+
+#[test]
+fn test_path_aware_detection() {
+    let mut config = Config {
+        tags: dsl::Option(vec!["a".to_owned()]),
+        delay: Some(1),
+        level: Setting::Value(2),
+    };
+
+    // `dsl::Option` is wrapped like any other type, `core::option::Option` is not
+    let opt_config = OptionalConfig {
+        tags: Some(dsl::Option(vec![])),
+        delay: None,
+        level: Setting::Inherit,
+    };
+    opt_config.apply_to(&mut config);
+    assert_eq!(config, Config {
+        tags: dsl::Option(vec![]),
+        delay: Some(1),
+        level: Setting::Value(2),
+    });
+}
+
+#[test]
+fn test_option_like() {
+    let mut config = Config {
+        tags: dsl::Option(vec![]),
+        delay: None,
+        level: Setting::Value(2),
+    };
+
+    let opt_config = OptionalConfig {
+        tags: None,
+        delay: None,
+        level: Setting::Value(3),
+    };
+    assert_eq!(opt_config.set_count(), 1);
+    opt_config.apply_to(&mut config);
+    assert_eq!(config.level, Setting::Value(3));
+
+    let opt_config = OptionalConfig {
+        tags: Some(dsl::Option(vec![])),
+        delay: None,
+        level: Setting::Inherit,
+    };
+    assert!(opt_config.can_convert());
+    assert_eq!(opt_config.try_build().unwrap().level, Setting::Inherit);
+}
+
+#[test]
+fn test_not_option() {
+    let mut options = Options { inner: Some(1) };
+
+    // Handled like any other type, i.e. wrapped
+    OptionalOptions { inner: Some(None) }.apply_to(&mut options);
+    assert_eq!(options.inner, None);
+    OptionalOptions { inner: None }.apply_to(&mut options);
+    assert_eq!(options.inner, None);
+    assert!(!OptionalOptions { inner: None }.can_convert());
+}
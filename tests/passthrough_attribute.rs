@@ -0,0 +1,26 @@
+use serde_json::json;
+use optional_struct::*;
+use serde::Serialize;
+
+#[optional_struct]
+#[derive(Serialize)]
+#[optional_struct_attr(serde(rename_all = "camelCase"))]
+struct Foo {
+    log_file: String,
+    log_level: u8,
+}
+
+#[test]
+fn test_passthrough_attribute() {
+    let foo = Foo {
+        log_file: "/tmp/foo.log".to_owned(),
+        log_level: 3,
+    };
+    let opt = OptionalFoo {
+        log_file: Some("/tmp/foo.log".to_owned()),
+        log_level: None,
+    };
+
+    assert_eq!(serde_json::to_value(&foo).unwrap(), json!({ "log_file": "/tmp/foo.log", "log_level": 3 }));
+    assert_eq!(serde_json::to_value(&opt).unwrap(), json!({ "logFile": "/tmp/foo.log", "logLevel": null }));
+}
@@ -0,0 +1,83 @@
+use optional_struct::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[optional_struct(impls(patch_log))]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct Account {
+    name: String,
+    balance: i64,
+}
+
+#[optional_struct(impls(patch_log))]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+enum Status {
+    Active { since: u32 },
+    Closed,
+}
+
+fn new_account() -> Account {
+    Account { name: "main".to_owned(), balance: 0 }
+}
+
+#[test]
+fn test_replay_in_order() {
+    let mut log = OptionalAccount::patch_log();
+    assert!(log.is_empty());
+    log.record(OptionalAccount { balance: Some(10), ..Default::default() });
+    log.record(OptionalAccount { name: Some("savings".to_owned()), ..Default::default() });
+    log.record(OptionalAccount { balance: Some(25), ..Default::default() });
+    assert_eq!(log.len(), 3);
+
+    let mut account = new_account();
+    log.replay(&mut account);
+    assert_eq!(account, Account { name: "savings".to_owned(), balance: 25 });
+
+    // The log is left untouched and can be replayed again
+    let mut other = new_account();
+    log.replay(&mut other);
+    assert_eq!(other, account);
+}
+
+#[test]
+fn test_serde_round_trip() {
+    let log = PatchLog::<Account, OptionalAccount>::from(vec![
+        OptionalAccount { balance: Some(10), ..Default::default() },
+        OptionalAccount { name: Some("savings".to_owned()), ..Default::default() },
+    ]);
+    let value = serde_json::to_value(&log).unwrap();
+    assert_eq!(value, json!([
+        { "name": null, "balance": 10 },
+        { "name": "savings", "balance": null },
+    ]));
+
+    let deserialized: PatchLog<Account, OptionalAccount> = serde_json::from_value(value).unwrap();
+    assert_eq!(deserialized, log);
+    let mut account = new_account();
+    deserialized.replay(&mut account);
+    assert_eq!(account, Account { name: "savings".to_owned(), balance: 10 });
+}
+
+#[test]
+fn test_enum_log() {
+    let mut log = OptionalStatus::patch_log();
+    log.extend([OptionalStatus::Closed, OptionalStatus::Active { since: Some(3) }]);
+
+    let mut status = Status::Active { since: 1 };
+    log.replay(&mut status);
+    assert_eq!(status, Status::Active { since: 3 });
+    assert_eq!(log.patches()[0], OptionalStatus::Closed);
+}
+
+fn apply_all<T, P: Applyable<T>>(patches: Vec<P>, target: &mut T) {
+    for patch in patches {
+        patch.apply_to(target);
+    }
+}
+
+#[test]
+fn test_applyable_is_generic() {
+    let mut account = new_account();
+    apply_all(vec![OptionalAccount { balance: Some(-5), ..Default::default() }], &mut account);
+    assert_eq!(account.balance, -5);
+}
@@ -0,0 +1,49 @@
+use optional_struct::*;
+use std::collections::BTreeMap;
+
+#[optional_struct(impls(profiles))]
+#[derive(Debug, PartialEq)]
+struct Config {
+    name: String,
+    workers: u32,
+    debug: bool,
+}
+
+fn base() -> Config {
+    Config { name: "app".to_owned(), workers: 1, debug: false }
+}
+
+fn profiles() -> ConfigProfiles {
+    let mut profiles = ConfigProfiles::new();
+    profiles.insert("default", OptionalConfig { debug: Some(true), ..Default::default() });
+    profiles.insert("production", OptionalConfig { workers: Some(8), debug: Some(false), ..Default::default() });
+    profiles.insert("canary", OptionalConfig { name: Some("app-canary".to_owned()), ..Default::default() });
+    profiles
+}
+
+#[test]
+fn test_resolve_in_order() {
+    let profiles = profiles();
+    assert_eq!(profiles.resolve(&["default"], base()).unwrap(), Config { name: "app".to_owned(), workers: 1, debug: true });
+    assert_eq!(
+        profiles.resolve(&["default", "production", "canary"], base()).unwrap(),
+        Config { name: "app-canary".to_owned(), workers: 8, debug: false },
+    );
+    assert_eq!(profiles.resolve(&[], base()).unwrap(), base());
+}
+
+#[test]
+fn test_unknown_profile() {
+    let error = profiles().resolve(&["default", "staging"], base()).unwrap_err();
+    assert_eq!(error, UnknownProfile("staging".to_owned()));
+    assert_eq!(error.to_string(), "unknown profile 'staging'");
+}
+
+#[test]
+fn test_from_map() {
+    let map = BTreeMap::from([("test".to_owned(), OptionalConfig { workers: Some(2), ..Default::default() })]);
+    let mut profiles = ConfigProfiles::from(map);
+    assert_eq!(profiles.get("test").and_then(|p| p.workers), Some(2));
+    let previous = profiles.insert("test", OptionalConfig::default());
+    assert_eq!(previous.and_then(|p| p.workers), Some(2));
+}
@@ -0,0 +1,71 @@
+use optional_struct::*;
+use serde::Serialize;
+use serde_json::json;
+
+#[optional_struct(impls(field_enum))]
+#[derive(Serialize, Debug, PartialEq)]
+struct User {
+    #[optional_serde_skip_none]
+    name: String,
+    #[optional_serde_skip_none]
+    email: Option<String>,
+    #[optional_serde_skip_none]
+    #[optional_wrap]
+    nickname: Option<String>,
+    #[optional_rename(OptionalAddress)]
+    address: Address,
+}
+
+#[optional_struct(impls(field_enum))]
+#[derive(Serialize, Debug, PartialEq)]
+struct Address {
+    #[optional_serde_skip_none]
+    city: String,
+    #[optional_serde_skip_none]
+    zip: String,
+}
+
+fn user() -> User {
+    User {
+        name: "Jane".to_owned(),
+        email: Some("jane@example.com".to_owned()),
+        nickname: None,
+        address: Address { city: "Paris".to_owned(), zip: "75001".to_owned() },
+    }
+}
+
+#[test]
+fn test_project() {
+    let patch = OptionalUser::project(&user(), &[UserField::Name, UserField::Nickname]);
+    assert_eq!(patch, OptionalUser {
+        name: Some("Jane".to_owned()),
+        email: None,
+        nickname: Some(None),
+        address: OptionalAddress { city: None, zip: None },
+    });
+}
+
+#[test]
+fn test_project_nested() {
+    let patch = OptionalUser::project(&user(), &[UserField::Address]);
+    assert_eq!(serde_json::to_value(&patch).unwrap(), json!({
+        "address": { "city": "Paris", "zip": "75001" },
+    }));
+}
+
+#[test]
+fn test_snapshot() {
+    let snapshot = OptionalUser::snapshot(&user());
+    assert_eq!(snapshot.email, Some("jane@example.com".to_owned()));
+    assert_eq!(snapshot.address.zip, Some("75001".to_owned()));
+
+    // Applying a snapshot gives the same value back
+    let mut other = User {
+        name: "John".to_owned(),
+        email: None,
+        nickname: Some("J".to_owned()),
+        address: Address { city: "Lyon".to_owned(), zip: "69001".to_owned() },
+    };
+    snapshot.apply_to(&mut other);
+    assert_eq!(other, user());
+}
@@ -0,0 +1,52 @@
+use optional_struct::*;
+use proptest::prelude::*;
+
+#[optional_struct(proptest)]
+#[derive(Clone, Debug, PartialEq)]
+struct Config {
+    name: String,
+    port: u16,
+    #[optional_wrap]
+    proxy: Option<String>,
+    #[optional_rename(OptionalLimits)]
+    limits: Limits,
+}
+
+#[optional_struct(proptest)]
+#[derive(Clone, Debug, PartialEq)]
+struct Limits {
+    soft: u8,
+    hard: u8,
+}
+
+fn config() -> Config {
+    Config {
+        name: "a".to_owned(),
+        port: 80,
+        proxy: Some("proxy".to_owned()),
+        limits: Limits { soft: 1, hard: 2 },
+    }
+}
+
+proptest! {
+    #[test]
+    fn test_apply_is_idempotent(patch in any::<OptionalConfig>()) {
+        let mut once = config();
+        patch.clone().apply_to(&mut once);
+        let mut twice = once.clone();
+        patch.apply_to(&mut twice);
+        prop_assert_eq!(once, twice);
+    }
+
+    #[test]
+    fn test_apply_sets_given_fields(patch in any::<OptionalConfig>()) {
+        let mut config = config();
+        patch.clone().apply_to(&mut config);
+        if let Some(proxy) = patch.proxy {
+            prop_assert_eq!(config.proxy, proxy);
+        }
+        if let Some(soft) = patch.limits.soft {
+            prop_assert_eq!(config.limits.soft, soft);
+        }
+    }
+}
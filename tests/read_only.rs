@@ -0,0 +1,48 @@
+use optional_struct::*;
+
+#[optional_struct]
+#[derive(Debug, PartialEq)]
+struct Account {
+    #[optional_read_only]
+    id: u64,
+    #[optional_read_only]
+    created_at: Option<u64>,
+    email: String,
+}
+
+#[test]
+fn test_read_only_not_applied() {
+    let mut account = Account {
+        id: 1,
+        created_at: Some(10),
+        email: "foo@example.com".to_owned(),
+    };
+    let patch = OptionalAccount {
+        id: Some(2),
+        created_at: Some(20),
+        email: Some("bar@example.com".to_owned()),
+    };
+    assert_eq!(patch.ignored_fields(), vec!["id", "created_at"]);
+
+    patch.apply_to(&mut account);
+    assert_eq!(account, Account {
+        id: 1,
+        created_at: Some(10),
+        email: "bar@example.com".to_owned(),
+    });
+}
+
+#[test]
+fn test_read_only_ignored_fields() {
+    let patch = OptionalAccount {
+        email: Some("bar@example.com".to_owned()),
+        ..Default::default()
+    };
+    assert!(patch.ignored_fields().is_empty());
+
+    let patch = OptionalAccount {
+        created_at: Some(20),
+        ..Default::default()
+    };
+    assert_eq!(patch.ignored_fields(), vec!["created_at"]);
+}
@@ -0,0 +1,134 @@
+use optional_struct::*;
+
+#[optional_struct(impls(apply_report, set_count))]
+#[derive(Debug, PartialEq)]
+struct Node {
+    value: u32,
+    #[optional_rename(OptionalNode)]
+    next: Option<Box<Node>>,
+}
+
+#[optional_struct(impls(display, set_count))]
+#[derive(Debug, PartialEq)]
+struct Table {
+    name: String,
+    #[optional_rename(OptionalRows)]
+    rows: Box<Rows>,
+}
+
+#[optional_struct(impls(display, set_count))]
+#[derive(Debug, PartialEq)]
+struct Rows {
+    first: u8,
+    last: u8,
+}
+
+fn list() -> Node {
+    Node {
+        value: 1,
+        next: Some(Box::new(Node { value: 2, next: None })),
+    }
+}
+
+#[test]
+fn test_recursive_apply() {
+    let mut node = list();
+    let patch = OptionalNode {
+        value: None,
+        next: Some(Box::new(OptionalNode {
+            value: Some(20),
+            next: None,
+        })),
+    };
+    patch.apply_to(&mut node);
+    assert_eq!(node, Node { value: 1, next: Some(Box::new(Node { value: 20, next: None })) });
+}
+
+#[test]
+fn test_recursive_apply_below_leaf() {
+    let mut node = list();
+    // The patch goes one level deeper than the list, and is complete there
+    let patch = OptionalNode {
+        value: None,
+        next: Some(Box::new(OptionalNode {
+            value: None,
+            next: Some(Box::new(OptionalNode { value: Some(3), next: None })),
+        })),
+    };
+    assert_eq!(patch.set_count(), 1);
+    patch.apply_to(&mut node);
+    assert_eq!(node.next.as_ref().unwrap().next, Some(Box::new(Node { value: 3, next: None })));
+}
+
+#[test]
+fn test_recursive_try_from() {
+    let patch = OptionalNode {
+        value: Some(1),
+        next: Some(Box::new(OptionalNode { value: None, next: None })),
+    };
+    assert!(!patch.can_convert());
+    let patch = patch.try_build().unwrap_err();
+
+    let patch = OptionalNode { next: Some(Box::new(OptionalNode { value: Some(2), next: None })), ..patch };
+    assert_eq!(Node::try_from(patch), Ok(list()));
+}
+
+#[test]
+fn test_recursive_apply_patches() {
+    let first = OptionalNode {
+        value: Some(1),
+        next: Some(Box::new(OptionalNode { value: Some(2), next: None })),
+    };
+    let second = OptionalNode {
+        value: None,
+        next: Some(Box::new(OptionalNode { value: None, next: Some(Box::new(OptionalNode { value: Some(3), next: None })) })),
+    };
+    let node: Node = first.apply(second).try_into().unwrap();
+    assert_eq!(node.next.unwrap().next.unwrap().value, 3);
+}
+
+#[test]
+fn test_recursive_report() {
+    let mut node = list();
+    let changes = OptionalNode {
+        value: None,
+        next: Some(Box::new(OptionalNode { value: Some(5), next: None })),
+    }
+    .apply_report(&mut node);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].field, "next.value");
+    assert_eq!(OptionalNode::field_count(), 2);
+}
+
+#[test]
+fn test_boxed_nested() {
+    let mut table = Table { name: "t".to_owned(), rows: Box::new(Rows { first: 0, last: 10 }) };
+    let patch = OptionalTable {
+        name: None,
+        rows: Box::new(OptionalRows { first: None, last: Some(20) }),
+    };
+    assert_eq!(patch.to_string(), "{rows: {last: 20}}");
+    patch.apply_to(&mut table);
+    assert_eq!(table.rows, Box::new(Rows { first: 0, last: 20 }));
+    assert_eq!(OptionalTable::field_count(), 2);
+}
+
+#[optional_struct(impls(set_count))]
+#[derive(Debug, PartialEq)]
+enum Expr {
+    Literal(i64),
+    Neg(#[optional_rename(OptionalExpr)] Box<Expr>),
+}
+
+#[test]
+fn test_recursive_enum() {
+    let mut expr = Expr::Neg(Box::new(Expr::Literal(1)));
+    let patch = OptionalExpr::Neg(Box::new(OptionalExpr::Literal(Some(2))));
+    assert_eq!(patch.set_count(), 1);
+    patch.apply_to(&mut expr);
+    assert_eq!(expr, Expr::Neg(Box::new(Expr::Literal(2))));
+
+    let patch = OptionalExpr::Neg(Box::new(OptionalExpr::Neg(Box::new(OptionalExpr::Literal(Some(3))))));
+    assert_eq!(patch.try_build(), Ok(Expr::Neg(Box::new(Expr::Neg(Box::new(Expr::Literal(3)))))));
+    assert!(!OptionalExpr::Neg(Box::new(OptionalExpr::Literal(None))).can_convert());
+}
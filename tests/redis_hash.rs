@@ -0,0 +1,63 @@
+use optional_struct::*;
+use std::time::Duration;
+
+fn seconds(value: &Duration) -> String {
+    value.as_secs().to_string()
+}
+
+// Named like a binding of the generated code
+fn value(uploads: &u32) -> String {
+    format!("{uploads:03}")
+}
+
+#[optional_struct(redis)]
+struct Session {
+    user: String,
+    #[optional_redis_with(seconds)]
+    ttl: Duration,
+    #[optional_wrap]
+    theme: Option<String>,
+    #[optional_rename(OptionalLimits)]
+    limits: Limits,
+}
+
+#[optional_struct(redis)]
+struct Limits {
+    #[optional_redis_with(value)]
+    uploads: u32,
+    #[optional_wrap]
+    quota: Option<u64>,
+}
+
+#[test]
+fn test_to_redis_hset() {
+    let patch = OptionalSession {
+        user: None,
+        ttl: Some(Duration::from_secs(90)),
+        theme: Some(Some("dark".to_owned())),
+        limits: OptionalLimits {
+            uploads: Some(3),
+            quota: None,
+        },
+    };
+    assert_eq!(patch.to_redis_hset(), vec![
+        ("ttl".to_owned(), "90".to_owned()),
+        ("theme".to_owned(), "dark".to_owned()),
+        ("limits.uploads".to_owned(), "003".to_owned()),
+    ]);
+    assert!(patch.to_redis_hdel().is_empty());
+}
+
+#[test]
+fn test_to_redis_hdel() {
+    let patch = OptionalSession {
+        theme: Some(None),
+        limits: OptionalLimits {
+            uploads: None,
+            quota: Some(None),
+        },
+        ..Default::default()
+    };
+    assert!(patch.to_redis_hset().is_empty());
+    assert_eq!(patch.to_redis_hdel(), vec!["theme".to_owned(), "limits.quota".to_owned()]);
+}
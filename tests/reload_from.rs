@@ -0,0 +1,64 @@
+use optional_struct::*;
+
+#[optional_struct(impls(reload_from))]
+#[derive(Clone, Debug, PartialEq)]
+struct Config {
+    host: String,
+    port: u16,
+    #[optional_wrap]
+    proxy: Option<String>,
+    #[optional_rename(OptionalLimits)]
+    limits: Limits,
+}
+
+#[optional_struct(impls(diff, set_count))]
+#[derive(Clone, Debug, PartialEq)]
+struct Limits {
+    connections: u32,
+    timeout: u32,
+}
+
+fn config() -> Config {
+    Config {
+        host: "localhost".to_owned(),
+        port: 80,
+        proxy: Some("proxy".to_owned()),
+        limits: Limits { connections: 10, timeout: 30 },
+    }
+}
+
+#[test]
+fn test_reload_reports_changed_fields() {
+    let mut current = config();
+    let mut new = config();
+    new.port = 8080;
+    new.proxy = None;
+    new.limits.timeout = 5;
+
+    let changed = current.reload_from(new.clone());
+    assert_eq!(changed, vec!["port", "proxy", "limits"]);
+    assert_eq!(current, new);
+}
+
+#[test]
+fn test_reload_same_config() {
+    let mut current = config();
+    assert!(current.reload_from(config()).is_empty());
+    assert_eq!(current, config());
+}
+
+#[optional_struct(impls(reload_from))]
+#[derive(Clone, Debug, PartialEq)]
+struct Logging {
+    file: Option<String>,
+}
+
+#[test]
+fn test_reload_removed_value() {
+    let mut current = Logging { file: Some("app.log".to_owned()) };
+    assert_eq!(current.reload_from(Logging { file: None }), vec!["file"]);
+    assert_eq!(current, Logging { file: None });
+
+    assert_eq!(current.reload_from(Logging { file: Some("other.log".to_owned()) }), vec!["file"]);
+    assert_eq!(current, Logging { file: Some("other.log".to_owned()) });
+}
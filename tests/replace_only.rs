@@ -0,0 +1,78 @@
+use optional_struct::*;
+
+trait Handler {
+    fn handle(&self) -> u32;
+}
+
+struct Constant(u32);
+
+impl Handler for Constant {
+    fn handle(&self) -> u32 {
+        self.0
+    }
+}
+
+#[optional_struct(impls(apply_report, diff, display, tracked))]
+struct Service {
+    name: String,
+    #[optional_replace_only]
+    handler: Box<dyn Handler>,
+}
+
+fn service() -> Service {
+    Service { name: "a".to_owned(), handler: Box::new(Constant(1)) }
+}
+
+#[test]
+fn test_replace_only_apply() {
+    let mut service = service();
+    let patch = OptionalService { name: None, handler: Some(Box::new(Constant(2))) };
+    patch.apply_to(&mut service);
+    assert_eq!(service.name, "a");
+    assert_eq!(service.handler.handle(), 2);
+
+    OptionalService { name: Some("b".to_owned()), handler: None }.apply_to(&mut service);
+    assert_eq!(service.handler.handle(), 2);
+}
+
+#[test]
+fn test_replace_only_try_from() {
+    assert!(!OptionalService { name: Some("a".to_owned()), handler: None }.can_convert());
+    let service: Service = OptionalService { name: Some("a".to_owned()), handler: Some(Box::new(Constant(3))) }.try_into().unwrap();
+    assert_eq!(service.handler.handle(), 3);
+}
+
+#[test]
+fn test_replace_only_comparison() {
+    // Replace-only fields are left out of the comparison and of the debug output
+    let first = OptionalService { name: Some("a".to_owned()), handler: Some(Box::new(Constant(1))) };
+    let second = OptionalService { name: Some("a".to_owned()), handler: None };
+    assert_eq!(first, second);
+    assert_eq!(format!("{first:?}"), "OptionalService { name: Some(\"a\"), .. }");
+    assert_eq!(first.to_string(), "{name: \"a\"}");
+}
+
+#[test]
+fn test_replace_only_report() {
+    let mut service = service();
+    let changes = OptionalService { name: Some("b".to_owned()), handler: Some(Box::new(Constant(4))) }.apply_report(&mut service);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].field, "name");
+    assert_eq!(service.handler.handle(), 4);
+}
+
+#[test]
+fn test_replace_only_tracked() {
+    let mut tracked = TrackedService::new(service());
+    tracked.set_name("b".to_owned());
+    assert!(tracked.is_dirty());
+    assert_eq!(tracked.flush().name, Some("b".to_owned()));
+}
+
+#[test]
+fn test_replace_only_diff() {
+    // Replace-only fields can't be compared, and are left out of the diff
+    let patch = service().diff(&Service { name: "b".to_owned(), handler: Box::new(Constant(2)) });
+    assert_eq!(patch.name, Some("b".to_owned()));
+    assert!(patch.handler.is_none());
+}
@@ -0,0 +1,58 @@
+use optional_struct::*;
+
+#[optional_struct(impls(resolve))]
+#[derive(Clone, Debug, PartialEq)]
+struct Settings {
+    theme: String,
+    language: String,
+    #[optional_rename(OptionalLimits)]
+    limits: Limits,
+}
+
+#[optional_struct]
+#[derive(Clone, Debug, PartialEq)]
+struct Limits {
+    uploads: u32,
+    storage: u32,
+}
+
+fn default_settings() -> Settings {
+    Settings {
+        theme: "light".to_owned(),
+        language: "en".to_owned(),
+        limits: Limits { uploads: 10, storage: 100 },
+    }
+}
+
+#[test]
+fn test_first_set_value_wins() {
+    let user = OptionalSettings {
+        theme: Some("dark".to_owned()),
+        ..Default::default()
+    };
+    let team = OptionalSettings {
+        theme: Some("blue".to_owned()),
+        language: Some("fr".to_owned()),
+        limits: OptionalLimits { uploads: Some(20), storage: None },
+    };
+    let org = OptionalSettings {
+        language: Some("de".to_owned()),
+        limits: OptionalLimits { uploads: Some(30), storage: Some(500) },
+        ..Default::default()
+    };
+    let base = default_settings();
+    assert_eq!(user.resolve(&[&team, &org], &base), Settings {
+        theme: "dark".to_owned(),
+        language: "fr".to_owned(),
+        limits: Limits { uploads: 20, storage: 500 },
+    });
+    // Neither the patches nor the base are consumed
+    assert_eq!(base, default_settings());
+    assert_eq!(user.theme, Some("dark".to_owned()));
+}
+
+#[test]
+fn test_without_fallbacks() {
+    let base = default_settings();
+    assert_eq!(OptionalSettings::default().resolve(&[], &base), base);
+}
@@ -0,0 +1,39 @@
+use optional_struct::*;
+
+#[optional_struct(impls(field_enum))]
+#[derive(Debug, PartialEq)]
+struct Account {
+    display_name: String,
+    email: String,
+    role: Option<String>,
+    #[optional_rename(OptionalQuota)]
+    quota: Quota,
+}
+
+#[optional_struct(impls(field_enum))]
+#[derive(Debug, PartialEq)]
+struct Quota {
+    files: u32,
+}
+
+#[test]
+fn test_retain_fields() {
+    let mut patch = OptionalAccount {
+        display_name: Some("Alice".to_owned()),
+        email: Some("alice@example.com".to_owned()),
+        role: Some("admin".to_owned()),
+        quota: OptionalQuota { files: Some(10) },
+    };
+
+    // A regular user may only change their name and email
+    patch.retain_fields(&[AccountField::DisplayName, AccountField::Email]);
+    assert_eq!(patch, OptionalAccount {
+        display_name: Some("Alice".to_owned()),
+        email: Some("alice@example.com".to_owned()),
+        role: None,
+        quota: OptionalQuota { files: None },
+    });
+
+    patch.retain_fields(&[]);
+    assert_eq!(patch, OptionalAccount::default());
+}
@@ -0,0 +1,44 @@
+use optional_struct::*;
+
+mod v1 {
+    use optional_struct::*;
+
+    #[optional_struct(impls(schema_hash))]
+    #[allow(dead_code)]
+    pub struct Config {
+        pub timeout: Option<u32>,
+        pub path: String,
+    }
+}
+
+mod v2 {
+    use optional_struct::*;
+
+    #[optional_struct(impls(schema_hash))]
+    #[allow(dead_code)]
+    pub struct Config {
+        pub timeout: Option<u64>,
+        pub path: String,
+    }
+}
+
+#[optional_struct(impls(schema_hash))]
+#[allow(dead_code)]
+struct Config {
+    timeout: Option<u32>,
+    path: String,
+}
+
+#[optional_struct(impls(schema_hash))]
+#[allow(dead_code)]
+struct Outer {
+    #[optional_rename(OptionalConfig)]
+    config: Config,
+}
+
+#[test]
+fn test_schema_hash() {
+    assert_eq!(v1::OptionalConfig::PATCH_SCHEMA_HASH, OptionalConfig::PATCH_SCHEMA_HASH);
+    assert_ne!(v1::OptionalConfig::PATCH_SCHEMA_HASH, v2::OptionalConfig::PATCH_SCHEMA_HASH);
+    assert_ne!(OptionalOuter::PATCH_SCHEMA_HASH, OptionalConfig::PATCH_SCHEMA_HASH);
+}
@@ -0,0 +1,34 @@
+use optional_struct::*;
+use schemars::{schema_for, JsonSchema};
+use serde::Deserialize;
+use serde_json::json;
+
+#[optional_struct(schemars)]
+#[derive(Deserialize, JsonSchema)]
+struct User {
+    name: String,
+    email: Option<String>,
+    #[optional_wrap]
+    nickname: Option<String>,
+    #[optional_skip_wrap]
+    id: u32,
+}
+
+#[test]
+fn test_patch_schema() {
+    let schema = schema_for!(OptionalUser).to_value();
+    assert_eq!(schema["title"], "OptionalUser");
+    assert_eq!(schema["required"], json!(["id"]));
+    assert_eq!(schema["properties"]["name"]["type"], "string");
+    assert_eq!(schema["properties"]["email"]["type"], "string");
+    // Only a tri-state field can be cleared with `null`
+    assert_eq!(schema["properties"]["nickname"]["type"], json!(["string", "null"]));
+}
+
+#[test]
+fn test_original_schema() {
+    let schema = schema_for!(User).to_value();
+    assert_eq!(schema["title"], "User");
+    assert_eq!(schema["required"], json!(["name", "id"]));
+    assert_eq!(schema["properties"]["email"]["type"], json!(["string", "null"]));
+}
@@ -0,0 +1,37 @@
+use optional_struct::*;
+use sea_query::{Alias, Expr, ExprTrait, PostgresQueryBuilder, Query};
+
+#[optional_struct(sea_query)]
+#[allow(dead_code)]
+struct User {
+    name: String,
+    #[optional_column(email_address)]
+    email: String,
+    #[optional_wrap]
+    nickname: Option<String>,
+    #[optional_read_only]
+    created_at: i64,
+}
+
+#[test]
+fn test_update_statement() {
+    let patch = OptionalUser {
+        name: None,
+        email: Some("alice@example.com".to_owned()),
+        nickname: Some(None),
+        created_at: Some(0),
+    };
+    let sql = Query::update()
+        .table(Alias::new("users"))
+        .values(patch.to_sea_query_values())
+        .and_where(Expr::col(Alias::new("id")).eq(7))
+        .to_string(PostgresQueryBuilder);
+    assert_eq!(sql, r#"UPDATE "users" SET "email_address" = 'alice@example.com', "nickname" = NULL WHERE "id" = 7"#);
+    // The patch can still be used
+    assert_eq!(patch.set_columns(), vec!["email_address", "nickname"]);
+}
+
+#[test]
+fn test_nothing_set() {
+    assert!(OptionalUser::default().to_sea_query_values().is_empty());
+}
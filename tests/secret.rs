@@ -0,0 +1,66 @@
+use optional_struct::*;
+use zeroize::Zeroize;
+
+#[optional_struct(zeroize, impls(apply_report, display))]
+#[derive(Debug, PartialEq)]
+struct Credentials {
+    user: String,
+    #[optional_secret]
+    password: String,
+    #[optional_secret]
+    token: Option<String>,
+}
+
+fn patch() -> OptionalCredentials {
+    OptionalCredentials {
+        user: Some("admin".to_owned()),
+        password: Some("hunter2".to_owned()),
+        token: None,
+    }
+}
+
+#[test]
+fn test_debug_is_redacted() {
+    assert_eq!(
+        format!("{:?}", patch()),
+        r#"OptionalCredentials { user: Some("admin"), password: <redacted>, token: None }"#,
+    );
+}
+
+#[test]
+fn test_display_is_redacted() {
+    assert_eq!(patch().to_string(), r#"{user: "admin", password: <redacted>}"#);
+}
+
+#[test]
+fn test_apply_report_is_redacted() {
+    let mut credentials = Credentials {
+        user: "admin".to_owned(),
+        password: "123456".to_owned(),
+        token: Some("abc".to_owned()),
+    };
+    let changes = patch().apply_report(&mut credentials);
+    assert_eq!(changes, vec![FieldChange {
+        field: "password".to_owned(),
+        old: "<redacted>".to_owned(),
+        new: "<redacted>".to_owned(),
+    }]);
+    assert_eq!(credentials.password, "hunter2");
+
+    // Unchanged secrets aren't reported
+    assert!(patch().apply_report(&mut credentials).is_empty());
+}
+
+#[test]
+fn test_zeroize() {
+    let mut patch = OptionalCredentials {
+        token: Some("abc".to_owned()),
+        ..patch()
+    };
+    patch.zeroize();
+    assert_eq!(patch, OptionalCredentials {
+        user: Some("admin".to_owned()),
+        password: None,
+        token: None,
+    });
+}
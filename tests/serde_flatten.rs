@@ -0,0 +1,60 @@
+use optional_struct::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[optional_struct]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Config {
+    name: String,
+
+    #[serde(flatten)]
+    #[optional_rename(OptionalLogConfig)]
+    log_config: LogConfig,
+}
+
+#[optional_struct]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct LogConfig {
+    log_file: String,
+    log_level: usize,
+}
+
+#[test]
+fn test_deserialize_flattened_patch() {
+    let opt_config: OptionalConfig = serde_json::from_value(json!({ "log_level": 2 })).unwrap();
+    assert_eq!(opt_config, OptionalConfig {
+        name: None,
+        log_config: OptionalLogConfig {
+            log_file: None,
+            log_level: Some(2),
+        },
+    });
+
+    let mut config = Config {
+        name: "foo".to_owned(),
+        log_config: LogConfig {
+            log_file: "/var/log/foo.log".to_owned(),
+            log_level: 3,
+        },
+    };
+    opt_config.apply_to(&mut config);
+    assert_eq!(config.log_config.log_level, 2);
+    assert_eq!(config.log_config.log_file, "/var/log/foo.log");
+}
+
+#[test]
+fn test_flattened_patch_round_trip() {
+    let opt_config = OptionalConfig {
+        name: Some("foo".to_owned()),
+        log_config: OptionalLogConfig {
+            log_file: Some("/var/log/foo.log".to_owned()),
+            log_level: None,
+        },
+    };
+    let value = serde_json::to_value(&opt_config).unwrap();
+    assert_eq!(value, json!({ "name": "foo", "log_file": "/var/log/foo.log", "log_level": null }));
+    assert_eq!(serde_json::from_value::<OptionalConfig>(value).unwrap(), opt_config);
+
+    let config: Config = serde_json::from_value(json!({ "name": "foo", "log_file": "/tmp/foo.log", "log_level": 1 })).unwrap();
+    assert_eq!(config.log_config.log_level, 1);
+}
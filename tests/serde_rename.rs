@@ -0,0 +1,52 @@
+use optional_struct::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[optional_struct]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct User {
+    #[serde(rename = "userName", default)]
+    name: String,
+    log_level: u8,
+}
+
+#[optional_struct(OptionalPlainUser, true, serde_rename = false)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PlainUser {
+    #[serde(rename = "userName", default)]
+    name: String,
+    log_level: u8,
+}
+
+#[test]
+fn test_serde_rename_copied() {
+    let user = User {
+        name: "foo".to_owned(),
+        log_level: 2,
+    };
+    let patch = OptionalUser {
+        name: Some("foo".to_owned()),
+        log_level: Some(2),
+    };
+    assert_eq!(serde_json::to_value(&user).unwrap(), json!({ "userName": "foo", "logLevel": 2 }));
+    assert_eq!(serde_json::to_value(&patch).unwrap(), json!({ "userName": "foo", "logLevel": 2 }));
+}
+
+#[test]
+fn test_serde_rename_not_copied() {
+    let patch = OptionalPlainUser {
+        name: Some("foo".to_owned()),
+        log_level: Some(2),
+    };
+    assert_eq!(serde_json::to_value(&patch).unwrap(), json!({ "name": "foo", "log_level": 2 }));
+
+    // The original struct keeps its keys
+    let user: PlainUser = patch.try_into().unwrap();
+    assert_eq!(serde_json::to_value(&user).unwrap(), json!({ "userName": "foo", "logLevel": 2 }));
+
+    // Other serde arguments are kept
+    let patch: OptionalPlainUser = serde_json::from_value(json!({ "log_level": 1 })).unwrap();
+    assert_eq!(patch.name, None);
+}
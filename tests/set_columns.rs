@@ -0,0 +1,54 @@
+use optional_struct::*;
+
+#[optional_struct(impls(set_columns))]
+#[allow(dead_code)]
+struct User {
+    name: String,
+    #[optional_column(email_address)]
+    email: String,
+    #[optional_read_only]
+    created_at: u64,
+    #[optional_rename(OptionalAddress)]
+    address: Address,
+}
+
+#[optional_struct(impls(set_count))]
+struct Address {
+    city: String,
+    zip: String,
+}
+
+fn update_statement(patch: &OptionalUser) -> String {
+    let assignments = patch
+        .set_columns()
+        .iter()
+        .enumerate()
+        .map(|(i, column)| format!("{column} = ${}", i + 1))
+        .collect::<Vec<_>>();
+    format!("UPDATE users SET {} WHERE id = $0", assignments.join(", "))
+}
+
+#[test]
+fn test_set_columns() {
+    let patch = OptionalUser {
+        name: Some("Alice".to_owned()),
+        email: Some("alice@example.com".to_owned()),
+        created_at: Some(0),
+        address: OptionalAddress::default(),
+    };
+    assert_eq!(patch.set_columns(), vec!["name", "email_address"]);
+    assert_eq!(update_statement(&patch), "UPDATE users SET name = $1, email_address = $2 WHERE id = $0");
+}
+
+#[test]
+fn test_nested_patch_is_one_column() {
+    let patch = OptionalUser {
+        address: OptionalAddress {
+            city: Some("Paris".to_owned()),
+            zip: None,
+        },
+        ..Default::default()
+    };
+    assert_eq!(patch.set_columns(), vec!["address"]);
+    assert!(OptionalUser::default().set_columns().is_empty());
+}
@@ -0,0 +1,43 @@
+use optional_struct::*;
+
+#[optional_struct(impls(set_count))]
+#[derive(Debug, Clone, PartialEq, Default)]
+struct Inner {
+    a: u8,
+    b: Option<u8>,
+}
+
+#[optional_struct(impls(set_count))]
+#[allow(dead_code)]
+struct Config {
+    timeout: Option<u32>,
+    name: String,
+    #[optional_skip_wrap]
+    port: u16,
+    #[optional_rename(OptionalInner)]
+    inner: Inner,
+    #[optional_rename(OptionalInner)]
+    #[optional_wrap]
+    wrapped_inner: Inner,
+}
+
+#[test]
+fn test_set_count() {
+    assert_eq!(OptionalConfig::field_count(), 7);
+
+    let mut opt_config = OptionalConfig {
+        timeout: Some(3),
+        name: None,
+        port: 8080,
+        inner: OptionalInner { a: Some(1), b: None },
+        wrapped_inner: None,
+    };
+    assert_eq!(opt_config.set_count(), 3);
+    assert!(!opt_config.is_complete());
+
+    opt_config.name = Some("foo".to_owned());
+    opt_config.inner.b = Some(2);
+    opt_config.wrapped_inner = Some(OptionalInner { a: Some(1), b: Some(2) });
+    assert_eq!(opt_config.set_count(), 7);
+    assert!(opt_config.is_complete());
+}
@@ -0,0 +1,56 @@
+use optional_struct::*;
+
+#[optional_struct(impls(iter_set_fields, set_count))]
+#[derive(Debug, PartialEq)]
+struct Config {
+    #[optional_skip_wrap]
+    #[optional_set_if(|s: &String| !s.is_empty())]
+    name: String,
+    #[optional_skip_wrap]
+    #[optional_set_if(|port| *port != 0)]
+    port: u16,
+    timeout: u32,
+}
+
+#[test]
+fn test_set_if_apply() {
+    let mut config = Config {
+        name: "foo".to_owned(),
+        port: 8080,
+        timeout: 3,
+    };
+
+    let opt_config = OptionalConfig {
+        name: String::new(),
+        port: 0,
+        timeout: None,
+    };
+    assert_eq!(opt_config.set_count(), 0);
+    assert_eq!(opt_config.iter_set_fields().count(), 0);
+    opt_config.apply_to(&mut config);
+    assert_eq!(config, Config { name: "foo".to_owned(), port: 8080, timeout: 3 });
+
+    let opt_config = OptionalConfig {
+        name: "bar".to_owned(),
+        port: 0,
+        timeout: None,
+    };
+    assert_eq!(opt_config.set_count(), 1);
+    opt_config.apply_to(&mut config);
+    assert_eq!(config, Config { name: "bar".to_owned(), port: 8080, timeout: 3 });
+}
+
+#[test]
+fn test_set_if_apply_opt() {
+    let first = OptionalConfig {
+        name: "foo".to_owned(),
+        port: 8080,
+        timeout: None,
+    };
+    let second = OptionalConfig {
+        name: String::new(),
+        port: 9090,
+        timeout: Some(3),
+    };
+    assert_eq!(first.apply(second), OptionalConfig { name: "foo".to_owned(), port: 9090, timeout: Some(3) });
+}
@@ -0,0 +1,61 @@
+use optional_struct::*;
+
+#[optional_struct(impls(set_operations))]
+#[derive(Debug, PartialEq)]
+struct Settings {
+    theme: String,
+    volume: u8,
+    language: Option<String>,
+    #[optional_rename(OptionalWindow)]
+    window: Window,
+}
+
+#[optional_struct(impls(set_operations))]
+#[derive(Debug, PartialEq)]
+struct Window {
+    width: u16,
+    height: u16,
+}
+
+fn pending() -> OptionalSettings {
+    OptionalSettings {
+        theme: Some("dark".to_owned()),
+        volume: Some(3),
+        language: Some("fr".to_owned()),
+        window: OptionalWindow { width: Some(800), height: Some(600) },
+    }
+}
+
+fn acknowledged() -> OptionalSettings {
+    OptionalSettings {
+        theme: Some("light".to_owned()),
+        volume: None,
+        language: Some("fr".to_owned()),
+        window: OptionalWindow { width: None, height: Some(600) },
+    }
+}
+
+#[test]
+fn test_subtract() {
+    // What remains to be sent once the peer acknowledged some of the fields
+    assert_eq!(pending().subtract(&acknowledged()), OptionalSettings {
+        theme: None,
+        volume: Some(3),
+        language: None,
+        window: OptionalWindow { width: Some(800), height: None },
+    });
+    assert_eq!(pending().subtract(&OptionalSettings::default()), pending());
+    assert_eq!(pending().subtract(&pending()), OptionalSettings::default());
+}
+
+#[test]
+fn test_intersect() {
+    // Values are taken from `self`
+    assert_eq!(pending().intersect(&acknowledged()), OptionalSettings {
+        theme: Some("dark".to_owned()),
+        volume: None,
+        language: Some("fr".to_owned()),
+        window: OptionalWindow { width: None, height: Some(600) },
+    });
+    assert_eq!(pending().intersect(&OptionalSettings::default()), OptionalSettings::default());
+}
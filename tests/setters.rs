@@ -0,0 +1,29 @@
+use optional_struct::*;
+
+#[optional_struct(impls(setters))]
+#[allow(dead_code)]
+struct Config {
+    timeout: Option<u32>,
+    path: String,
+    #[optional_wrap]
+    description: Option<String>,
+    #[optional_skip_wrap]
+    percentage: f32,
+}
+
+#[test]
+fn test_setters() {
+    let mut opt_config = OptionalConfig::default();
+    opt_config
+        .set_timeout(3)
+        .set_path("/tmp/bar.log")
+        .set_description(None)
+        .set_percentage(0.5);
+
+    assert_eq!(opt_config, OptionalConfig {
+        timeout: Some(3),
+        path: Some("/tmp/bar.log".to_owned()),
+        description: Some(None),
+        percentage: 0.5,
+    });
+}
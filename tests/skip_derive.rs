@@ -0,0 +1,60 @@
+use optional_struct::*;
+
+#[optional_struct(OptionalConfig, true, skip_derive(Default, Debug), impls(set_count))]
+#[derive(Debug, PartialEq)]
+struct Config {
+    timeout: u32,
+    retries: u8,
+}
+
+// Patches default to a single retry instead of leaving every field unset
+impl Default for OptionalConfig {
+    fn default() -> Self {
+        OptionalConfig {
+            timeout: None,
+            retries: Some(1),
+        }
+    }
+}
+
+impl std::fmt::Debug for OptionalConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "OptionalConfig({}/{})", self.set_count(), OptionalConfig::field_count())
+    }
+}
+
+#[test]
+fn test_skip_derive() {
+    let mut config = Config { timeout: 3, retries: 5 };
+    OptionalConfig::default().apply_to(&mut config);
+    assert_eq!(config, Config { timeout: 3, retries: 1 });
+    assert_eq!(format!("{:?}", OptionalConfig::default()), "OptionalConfig(1/2)");
+}
+
+#[optional_struct(skip_derive(Default))]
+#[derive(Debug, PartialEq)]
+struct Limits {
+    max_connections: u32,
+}
+
+#[optional_struct(skip_derive(Default))]
+#[derive(Debug, PartialEq)]
+struct Service {
+    name: String,
+    #[optional_rename(OptionalLimits)]
+    limits: Limits,
+}
+
+#[test]
+fn test_skip_derive_default_try_from() {
+    let patch = OptionalService {
+        name: Some("api".to_owned()),
+        limits: OptionalLimits { max_connections: None },
+    };
+    let mut patch = Service::try_from(patch).unwrap_err();
+    assert_eq!(patch.name.as_deref(), Some("api"));
+
+    patch.limits.max_connections = Some(64);
+    let service = Service::try_from(patch).unwrap();
+    assert_eq!(service, Service { name: "api".to_owned(), limits: Limits { max_connections: 64 } });
+}
@@ -0,0 +1,61 @@
+use optional_struct::*;
+
+fn now() -> u64 {
+    1234
+}
+
+#[derive(Debug, PartialEq)]
+struct Handle(u32);
+
+#[optional_struct(impls(set_count))]
+#[derive(Debug, PartialEq)]
+struct Session {
+    user: String,
+    #[optional_skip(default = "now()")]
+    created_at: u64,
+    #[optional_skip(default = "Handle(0)")]
+    handle: Handle,
+    #[optional_skip]
+    requests: u32,
+}
+
+#[optional_struct]
+#[derive(Debug, PartialEq)]
+struct Pair(#[optional_skip] u32, String);
+
+#[test]
+fn test_skip_try_from() {
+    let opt_session = OptionalSession { user: Some("foo".to_owned()) };
+    assert_eq!(OptionalSession::field_count(), 1);
+    assert_eq!(opt_session.try_build(), Ok(Session {
+        user: "foo".to_owned(),
+        created_at: 1234,
+        handle: Handle(0),
+        requests: 0,
+    }));
+}
+
+#[test]
+fn test_skip_apply() {
+    let mut session = Session {
+        user: "foo".to_owned(),
+        created_at: 1,
+        handle: Handle(2),
+        requests: 3,
+    };
+    OptionalSession { user: Some("bar".to_owned()) }.apply_to(&mut session);
+    assert_eq!(session, Session {
+        user: "bar".to_owned(),
+        created_at: 1,
+        handle: Handle(2),
+        requests: 3,
+    });
+}
+
+#[test]
+fn test_skip_tuple_struct() {
+    let mut pair = Pair(1, "foo".to_owned());
+    OptionalPair(Some("bar".to_owned())).apply_to(&mut pair);
+    assert_eq!(pair, Pair(1, "bar".to_owned()));
+    assert_eq!(OptionalPair(Some("baz".to_owned())).try_build(), Ok(Pair(0, "baz".to_owned())));
+}
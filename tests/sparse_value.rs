@@ -0,0 +1,66 @@
+use optional_struct::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+// The struct itself doesn't implement `Serialize` nor `Deserialize`
+#[optional_struct(sparse_json)]
+#[allow(dead_code)]
+struct Config {
+    timeout: Option<u32>,
+    name: String,
+    #[optional_wrap]
+    description: Option<String>,
+    #[optional_rename(OptionalLimits)]
+    limits: Limits,
+}
+
+#[optional_struct(sparse_json)]
+#[derive(Serialize, Deserialize)]
+struct Limits {
+    connections: u32,
+    timeout: u32,
+}
+
+#[test]
+fn test_to_value_sparse() {
+    let patch = OptionalConfig {
+        timeout: None,
+        name: Some("foo".to_owned()),
+        description: Some(None),
+        limits: OptionalLimits { connections: None, timeout: Some(30) },
+    };
+    assert_eq!(patch.to_value_sparse(), json!({
+        "name": "foo",
+        "description": null,
+        "limits": { "timeout": 30 },
+    }));
+
+    // Empty nested patches are left out
+    assert_eq!(OptionalConfig::default().to_value_sparse(), json!({}));
+}
+
+#[test]
+fn test_from_value_sparse() {
+    let patch = OptionalConfig::from_value_sparse(json!({
+        "timeout": 3,
+        "description": null,
+        "limits": { "connections": 10 },
+    })).unwrap();
+    assert_eq!(patch, OptionalConfig {
+        timeout: Some(3),
+        name: None,
+        description: Some(None),
+        limits: OptionalLimits { connections: Some(10), timeout: None },
+    });
+    assert_eq!(OptionalConfig::from_value_sparse(patch.to_value_sparse()), Ok(patch));
+}
+
+#[test]
+fn test_from_value_sparse_errors() {
+    assert_eq!(OptionalConfig::from_value_sparse(json!([1, 2])), Err(SetError::NotAnObject));
+    assert_eq!(OptionalConfig::from_value_sparse(json!({ "port": 80 })), Err(SetError::UnknownField("port".to_owned())));
+    assert_eq!(
+        OptionalConfig::from_value_sparse(json!({ "limits": { "timeout": "soon" } })).map_err(|e| e.to_string()),
+        Err("invalid value for field 'timeout': invalid type: string \"soon\", expected u32".to_owned()),
+    );
+}
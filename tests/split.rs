@@ -0,0 +1,52 @@
+use optional_struct::*;
+
+#[optional_struct(split, impls(display, set_count))]
+#[derive(Debug, PartialEq)]
+struct Config {
+    timeout: Option<u32>,
+    name: String,
+
+    #[optional_rename(OptionalLogConfig)]
+    log_config: LogConfig,
+}
+
+#[optional_struct(split, impls(construction_macro, display, set_count))]
+#[derive(Debug, PartialEq)]
+struct LogConfig {
+    log_file: String,
+    log_level: usize,
+}
+
+#[test]
+fn test_split_impls_still_apply() {
+    let mut config = Config {
+        timeout: None,
+        name: "foo".to_owned(),
+        log_config: LogConfig {
+            log_file: "/var/log/foobar.log".to_owned(),
+            log_level: 3,
+        },
+    };
+    let patch = OptionalConfig {
+        timeout: Some(5),
+        name: None,
+        log_config: optional_log_config! { log_level: 1 },
+    };
+    assert_eq!(patch.set_count(), 2);
+    assert_eq!(patch.to_string(), "{timeout: 5, log_config: {log_level: 1}}");
+
+    patch.apply_to(&mut config);
+    assert_eq!(config.timeout, Some(5));
+    assert_eq!(config.log_config.log_level, 1);
+    assert_eq!(config.log_config.log_file, "/var/log/foobar.log");
+}
+
+#[test]
+fn test_split_try_build() {
+    let patch = OptionalLogConfig {
+        log_file: Some("/tmp/bar.log".to_owned()),
+        log_level: None,
+    };
+    assert!(!patch.can_convert());
+    assert!(patch.try_build().is_err());
+}
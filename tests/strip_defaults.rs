@@ -0,0 +1,59 @@
+use optional_struct::*;
+
+#[optional_struct(impls(strip_defaults))]
+#[derive(Debug, Default, PartialEq)]
+struct Config {
+    name: String,
+    port: u16,
+    proxy: Option<String>,
+    #[optional_rename(OptionalLimits)]
+    limits: Option<Limits>,
+    #[optional_rename(OptionalLimits)]
+    #[optional_wrap]
+    defaults: Limits,
+}
+
+#[optional_struct(impls(set_count, strip_defaults))]
+#[derive(Debug, Default, PartialEq)]
+struct Limits {
+    soft: u8,
+    hard: u8,
+}
+
+#[test]
+fn test_strip_defaults() {
+    let reference = Config {
+        name: "server".to_owned(),
+        port: 80,
+        proxy: Some("proxy".to_owned()),
+        limits: Some(Limits { soft: 1, hard: 2 }),
+        defaults: Limits { soft: 0, hard: 0 },
+    };
+    let mut patch = OptionalConfig {
+        name: Some("server".to_owned()),
+        port: Some(8080),
+        proxy: Some("proxy".to_owned()),
+        limits: Some(OptionalLimits { soft: Some(1), hard: Some(3) }),
+        defaults: Some(OptionalLimits { soft: Some(0), hard: None }),
+    };
+    patch.strip_defaults(&reference);
+    assert_eq!(patch, OptionalConfig {
+        name: None,
+        port: Some(8080),
+        proxy: None,
+        limits: Some(OptionalLimits { soft: None, hard: Some(3) }),
+        // Nothing is left in the nested patch
+        defaults: None,
+    });
+}
+
+#[test]
+fn test_strip_defaults_against_default() {
+    let mut patch = OptionalConfig {
+        name: Some(String::new()),
+        port: Some(0),
+        ..Default::default()
+    };
+    patch.strip_defaults(&Config::default());
+    assert_eq!(patch, OptionalConfig::default());
+}
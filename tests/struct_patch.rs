@@ -0,0 +1,42 @@
+use optional_struct::*;
+use struct_patch::Patch;
+
+#[optional_struct(struct_patch)]
+#[derive(Patch, Debug, PartialEq)]
+#[patch(attribute(derive(Clone, Debug, PartialEq)))]
+struct Config {
+    delay: Option<u32>,
+    path: String,
+    #[optional_skip_wrap]
+    percentage: f32,
+}
+
+#[test]
+fn test_struct_patch_conversions() {
+    let opt_config = OptionalConfig {
+        delay: Some(2),
+        path: None,
+        percentage: 0.5,
+    };
+
+    let patch: ConfigPatch = opt_config.clone().into();
+    assert_eq!(patch, ConfigPatch {
+        delay: Some(Some(2)),
+        path: None,
+        percentage: Some(0.5),
+    });
+
+    let mut config = Config {
+        delay: None,
+        path: "/var/log/foo.log".to_owned(),
+        percentage: 3.12,
+    };
+    config.apply(patch.clone());
+    assert_eq!(config, Config {
+        delay: Some(2),
+        path: "/var/log/foo.log".to_owned(),
+        percentage: 0.5,
+    });
+
+    assert_eq!(OptionalConfig::from(patch), opt_config);
+}
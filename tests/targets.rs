@@ -0,0 +1,80 @@
+use optional_struct::*;
+
+#[optional_struct]
+#[optional_targets(DbUser, ApiUser(name, email))]
+#[derive(Debug, PartialEq)]
+struct User {
+    name: String,
+    email: String,
+    password_hash: String,
+}
+
+#[derive(Debug, PartialEq)]
+struct DbUser {
+    id: u32,
+    name: String,
+    email: String,
+    password_hash: String,
+}
+
+#[derive(Debug, PartialEq)]
+struct ApiUser {
+    name: String,
+    email: String,
+}
+
+#[test]
+fn test_applied_to_every_field_of_target() {
+    let mut user = DbUser {
+        id: 1,
+        name: "Alice".to_owned(),
+        email: "alice@example.com".to_owned(),
+        password_hash: "old".to_owned(),
+    };
+    let patch = OptionalUser {
+        name: None,
+        email: Some("alice@example.org".to_owned()),
+        password_hash: Some("new".to_owned()),
+    };
+    Applyable::apply_to(patch, &mut user);
+    assert_eq!(user, DbUser {
+        id: 1,
+        name: "Alice".to_owned(),
+        email: "alice@example.org".to_owned(),
+        password_hash: "new".to_owned(),
+    });
+}
+
+#[test]
+fn test_applied_to_listed_fields_of_target() {
+    let mut user = ApiUser {
+        name: "Alice".to_owned(),
+        email: "alice@example.com".to_owned(),
+    };
+    let patch = OptionalUser {
+        name: Some("Bob".to_owned()),
+        email: None,
+        password_hash: Some("new".to_owned()),
+    };
+    Applyable::apply_to(patch, &mut user);
+    assert_eq!(user, ApiUser {
+        name: "Bob".to_owned(),
+        email: "alice@example.com".to_owned(),
+    });
+}
+
+#[test]
+fn test_still_applied_to_original() {
+    let mut user = User {
+        name: "Alice".to_owned(),
+        email: "alice@example.com".to_owned(),
+        password_hash: "old".to_owned(),
+    };
+    let patch = OptionalUser {
+        name: Some("Bob".to_owned()),
+        email: None,
+        password_hash: None,
+    };
+    patch.apply_to(&mut user);
+    assert_eq!(user.name, "Bob");
+}
@@ -0,0 +1,68 @@
+use optional_struct::*;
+use tokio::sync::watch;
+
+#[optional_struct(tokio_watch)]
+#[derive(Debug, PartialEq)]
+struct Config {
+    timeout: Option<u32>,
+    path: String,
+}
+
+#[test]
+fn test_apply_to_watch() {
+    let (tx, mut rx) = watch::channel(Config {
+        timeout: Some(2),
+        path: "/var/log/foo.log".to_owned(),
+    });
+
+    let unchanged = OptionalConfig {
+        timeout: None,
+        path: Some("/var/log/foo.log".to_owned()),
+    };
+    assert!(!unchanged.apply_to_watch(&tx));
+    assert!(!rx.has_changed().unwrap());
+
+    let changed = OptionalConfig {
+        timeout: Some(3),
+        path: None,
+    };
+    assert!(changed.apply_to_watch(&tx));
+    assert!(rx.has_changed().unwrap());
+    assert_eq!(*rx.borrow_and_update(), Config {
+        timeout: Some(3),
+        path: "/var/log/foo.log".to_owned(),
+    });
+}
+
+// Can't be compared, so setting it always counts as a change
+struct Handler(u32);
+
+#[optional_struct(tokio_watch)]
+struct Service {
+    port: u16,
+    #[optional_replace_only]
+    handler: Handler,
+}
+
+#[test]
+fn test_apply_to_watch_replace_only() {
+    let (tx, mut rx) = watch::channel(Service {
+        port: 80,
+        handler: Handler(1),
+    });
+
+    let replaced = OptionalService {
+        port: None,
+        handler: Some(Handler(2)),
+    };
+    assert!(replaced.apply_to_watch(&tx));
+    assert!(rx.has_changed().unwrap());
+    assert_eq!(rx.borrow_and_update().handler.0, 2);
+
+    let unchanged = OptionalService {
+        port: Some(80),
+        handler: None,
+    };
+    assert!(!unchanged.apply_to_watch(&tx));
+    assert!(!rx.has_changed().unwrap());
+}
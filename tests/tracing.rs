@@ -0,0 +1,69 @@
+use std::sync::{Arc, Mutex};
+
+use optional_struct::*;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+#[optional_struct(tracing = debug)]
+struct Config {
+    delay: Option<u32>,
+    path: String,
+    percentage: f32,
+}
+
+#[derive(Default)]
+struct FieldVisitor(Vec<String>);
+
+impl Visit for FieldVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0.push(format!("{}={:?}", field.name(), value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.push(format!("{}={}", field.name(), value));
+    }
+}
+
+struct Recorder(Arc<Mutex<Vec<String>>>);
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata<'_>) -> bool { true }
+    fn new_span(&self, _: &Attributes<'_>) -> Id { Id::from_u64(1) }
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+    fn enter(&self, _: &Id) {}
+    fn exit(&self, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        assert_eq!(*event.metadata().level(), tracing::Level::DEBUG);
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        self.0.lock().unwrap().push(visitor.0.join(" "));
+    }
+}
+
+#[test]
+fn test_apply_to_traced() {
+    let mut config = Config {
+        delay: Some(2),
+        path: "/var/log/foo.log".to_owned(),
+        percentage: 3.12,
+    };
+
+    let opt_config = OptionalConfig {
+        delay: None,
+        path: Some("/tmp/bar.log".to_owned()),
+        percentage: None,
+    };
+
+    let events = Arc::new(Mutex::new(vec![]));
+    tracing::subscriber::with_default(Recorder(events.clone()), || opt_config.apply_to_traced(&mut config));
+
+    assert_eq!(config.delay, Some(2));
+    assert_eq!(config.path, "/tmp/bar.log");
+    assert_eq!(config.percentage, 3.12);
+    assert_eq!(*events.lock().unwrap(), vec![
+        "message=applying patch field field=path old=\"/var/log/foo.log\" new=\"/tmp/bar.log\"".to_owned(),
+    ]);
+}
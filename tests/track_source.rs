@@ -0,0 +1,72 @@
+use optional_struct::*;
+
+#[optional_struct(track_source)]
+#[derive(Debug, PartialEq)]
+struct Config {
+    host: String,
+    port: u16,
+    verbose: Option<bool>,
+    #[optional_rename(OptionalLimits)]
+    limits: Limits,
+}
+
+#[optional_struct(impls(set_count))]
+#[derive(Debug, PartialEq)]
+struct Limits {
+    connections: u32,
+    timeout: u32,
+}
+
+fn load() -> SourcedConfig {
+    let mut config = SourcedConfig::new();
+    config.apply_layer("defaults", OptionalConfig {
+        host: Some("localhost".to_owned()),
+        port: Some(80),
+        limits: OptionalLimits { connections: Some(10), timeout: Some(30) },
+        ..Default::default()
+    });
+    config.apply_layer("file", OptionalConfig {
+        port: Some(8080),
+        ..Default::default()
+    });
+    config.apply_layer("env", OptionalConfig {
+        verbose: Some(false),
+        limits: OptionalLimits { timeout: Some(5), ..Default::default() },
+        ..Default::default()
+    });
+    config.apply_layer("cli", OptionalConfig {
+        verbose: Some(true),
+        ..Default::default()
+    });
+    config
+}
+
+#[test]
+fn test_source_of_last_layer() {
+    let config = load();
+    assert_eq!(config.source_of(ConfigField::Host), Some("defaults"));
+    assert_eq!(config.source_of(ConfigField::Port), Some("file"));
+    assert_eq!(config.source_of(ConfigField::Verbose), Some("cli"));
+    // Nested fields are attributed to the last layer setting any of their fields
+    assert_eq!(config.source_of(ConfigField::Limits), Some("env"));
+}
+
+#[test]
+fn test_layers_are_merged() {
+    let config = load().into_patch().try_build().unwrap();
+    assert_eq!(config, Config {
+        host: "localhost".to_owned(),
+        port: 8080,
+        verbose: Some(true),
+        limits: Limits { connections: 10, timeout: 5 },
+    });
+}
+
+#[test]
+fn test_unset_field_has_no_source() {
+    let mut config = SourcedConfig::new();
+    config.apply_layer("cli", OptionalConfig { port: Some(1), ..Default::default() });
+    assert_eq!(config.source_of(ConfigField::Host), None);
+    assert_eq!(config.source_of(ConfigField::Limits), None);
+    assert_eq!(config.patch().port, Some(1));
+}
@@ -0,0 +1,60 @@
+use optional_struct::*;
+
+#[optional_struct(impls(tracked))]
+#[derive(Debug, PartialEq)]
+struct Config {
+    timeout: Option<u32>,
+    name: String,
+
+    #[optional_rename(OptionalLogConfig)]
+    #[optional_wrap]
+    log_config: LogConfig,
+}
+
+#[optional_struct]
+#[derive(Debug, PartialEq)]
+struct LogConfig {
+    log_file: String,
+    log_level: usize,
+}
+
+#[test]
+fn test_tracked() {
+    let mut tracked = TrackedConfig::new(Config {
+        timeout: Some(2),
+        name: "foo".to_owned(),
+        log_config: LogConfig {
+            log_file: "/var/log/foobar.log".to_owned(),
+            log_level: 3,
+        },
+    });
+    assert!(!tracked.is_dirty());
+
+    tracked.set_name("bar".to_owned());
+    tracked.set_log_config(OptionalLogConfig { log_file: None, log_level: Some(1) });
+    tracked.set_log_config(OptionalLogConfig { log_file: Some("/tmp/bar.log".to_owned()), log_level: None });
+
+    assert!(tracked.is_dirty());
+    assert_eq!(tracked.name, "bar");
+    assert_eq!(tracked.log_config.log_level, 1);
+
+    let patch = tracked.flush();
+    assert!(!tracked.is_dirty());
+    assert_eq!(patch, OptionalConfig {
+        timeout: None,
+        name: Some("bar".to_owned()),
+        log_config: Some(OptionalLogConfig {
+            log_file: Some("/tmp/bar.log".to_owned()),
+            log_level: Some(1),
+        }),
+    });
+
+    assert_eq!(tracked.into_inner(), Config {
+        timeout: Some(2),
+        name: "bar".to_owned(),
+        log_config: LogConfig {
+            log_file: "/tmp/bar.log".to_owned(),
+            log_level: 1,
+        },
+    });
+}
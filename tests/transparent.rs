@@ -0,0 +1,50 @@
+use optional_struct::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct UserId(u64);
+
+impl From<u64> for UserId {
+    fn from(id: u64) -> Self {
+        UserId(id)
+    }
+}
+
+impl From<UserId> for u64 {
+    fn from(id: UserId) -> Self {
+        id.0
+    }
+}
+
+#[optional_struct(impls(tracked))]
+#[derive(Debug, PartialEq)]
+struct Session {
+    #[optional_transparent(u64)]
+    user: UserId,
+    #[optional_skip_wrap]
+    #[optional_transparent(u64)]
+    owner: UserId,
+}
+
+#[test]
+fn test_transparent_apply() {
+    let mut session = Session { user: UserId(1), owner: UserId(2) };
+    let patch = OptionalSession { user: Some(3), owner: 4 };
+    patch.apply_to(&mut session);
+    assert_eq!(session, Session { user: UserId(3), owner: UserId(4) });
+}
+
+#[test]
+fn test_transparent_try_from() {
+    assert!(OptionalSession { user: None, owner: 4 }.try_build().is_err());
+    let session: Session = OptionalSession { user: Some(3), owner: 4 }.try_into().unwrap();
+    assert_eq!(session, Session { user: UserId(3), owner: UserId(4) });
+}
+
+#[test]
+fn test_transparent_tracked() {
+    let mut tracked = TrackedSession::new(Session { user: UserId(1), owner: UserId(2) });
+    tracked.set_user(UserId(5));
+    tracked.set_owner(UserId(6));
+    assert_eq!(tracked.get().user, UserId(5));
+    assert_eq!(tracked.flush(), OptionalSession { user: Some(5), owner: 6 });
+}
@@ -0,0 +1,65 @@
+use optional_struct::*;
+
+#[optional_struct(impls(try_from_layers))]
+#[derive(Debug, PartialEq)]
+struct Config {
+    host: String,
+    port: u16,
+    verbose: Option<bool>,
+    #[optional_rename(OptionalLimits)]
+    limits: Limits,
+}
+
+#[optional_struct(impls(missing_fields))]
+#[derive(Debug, PartialEq)]
+struct Limits {
+    connections: u32,
+    timeout: u32,
+}
+
+#[test]
+fn test_layers_are_merged_in_order() {
+    let defaults = OptionalConfig {
+        host: Some("localhost".to_owned()),
+        port: Some(80),
+        limits: OptionalLimits { connections: Some(10), timeout: Some(30) },
+        ..Default::default()
+    };
+    let file = OptionalConfig {
+        port: Some(8080),
+        verbose: Some(true),
+        ..Default::default()
+    };
+    let cli = OptionalConfig {
+        port: Some(9000),
+        limits: OptionalLimits { timeout: Some(5), ..Default::default() },
+        ..Default::default()
+    };
+
+    let config = Config::try_from_layers([defaults, file, cli]).unwrap();
+    assert_eq!(config, Config {
+        host: "localhost".to_owned(),
+        port: 9000,
+        verbose: Some(true),
+        limits: Limits { connections: 10, timeout: 5 },
+    });
+}
+
+#[test]
+fn test_missing_fields_are_reported() {
+    let file = OptionalConfig {
+        port: Some(8080),
+        limits: OptionalLimits { timeout: Some(5), ..Default::default() },
+        ..Default::default()
+    };
+
+    let error = Config::try_from_layers([file]).unwrap_err();
+    assert_eq!(error, MissingFieldsError { fields: vec!["host", "limits"] });
+    assert_eq!(error.to_string(), "missing required fields: host, limits");
+}
+
+#[test]
+fn test_no_layers() {
+    let error = Config::try_from_layers(vec![]).unwrap_err();
+    assert_eq!(error.fields, ["host", "port", "limits"]);
+}
@@ -3,6 +3,10 @@ use optional_struct::*;
 #[optional_struct]
 struct Config(Option<u32>, String, f32);
 
+#[optional_struct(impls(missing_fields, setters))]
+#[derive(Debug, PartialEq)]
+struct Point(i32, #[optional_skip_wrap] i32, Option<String>);
+
 #[test]
 fn test_apply_options_tuple_struct() {
     let opt_config = OptionalConfig::default();
@@ -10,3 +14,35 @@ fn test_apply_options_tuple_struct() {
     assert_eq!(opt_config.1, None);
     assert_eq!(opt_config.2, None);
 }
+
+#[test]
+fn test_tuple_struct_setters() {
+    let patch = OptionalPoint::default().with_0(1).with_1(2);
+    assert_eq!(patch.get_0(), Some(&1));
+    assert_eq!(patch.get_1(), &2);
+    assert_eq!(patch.get_2(), &None);
+
+    let mut patch = patch;
+    patch.set_2(Some("origin".to_owned()));
+    assert_eq!(patch, OptionalPoint(Some(1), 2, Some("origin".to_owned())));
+}
+
+#[test]
+fn test_tuple_struct_apply_to() {
+    let mut point = Point(1, 2, None);
+    OptionalPoint(None, 3, Some("a".to_owned())).apply_to(&mut point);
+    assert_eq!(point, Point(1, 3, Some("a".to_owned())));
+    OptionalPoint(Some(4), 5, None).apply_to(&mut point);
+    assert_eq!(point, Point(4, 5, Some("a".to_owned())));
+}
+
+#[test]
+fn test_tuple_struct_try_from() {
+    let patch = OptionalPoint(None, 2, None);
+    assert_eq!(patch.missing_fields(), vec![".0"]);
+    assert_eq!(patch.try_build(), Err(OptionalPoint(None, 2, None)));
+
+    let point: Point = OptionalPoint(Some(1), 2, None).try_into().unwrap();
+    assert_eq!(point, Point(1, 2, None));
+    assert!(OptionalPoint(Some(1), 2, None).missing_fields().is_empty());
+}
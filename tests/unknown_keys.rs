@@ -0,0 +1,48 @@
+use optional_struct::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[optional_struct(unknown_keys)]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Config {
+    host: String,
+    port: u16,
+}
+
+#[optional_struct(unknown_keys = ignored)]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Limits {
+    timeout: u32,
+}
+
+#[test]
+fn test_unknown_keys_are_collected() {
+    let patch: OptionalConfig = serde_json::from_value(json!({
+        "port": 8080,
+        "prot": 80,
+        "debug": true,
+    })).unwrap();
+    assert_eq!(patch.port, Some(8080));
+    assert_eq!(patch.host, None);
+    let mut unknown = patch.unknown_keys.keys().collect::<Vec<_>>();
+    unknown.sort();
+    assert_eq!(unknown, ["debug", "prot"]);
+
+    // Unknown keys don't prevent building the original struct
+    let config = patch.build(Config { host: "localhost".to_owned(), port: 80 });
+    assert_eq!(config.port, 8080);
+}
+
+#[test]
+fn test_no_unknown_keys() {
+    let patch: OptionalConfig = serde_json::from_value(json!({ "host": "example.com" })).unwrap();
+    assert!(patch.unknown_keys.is_empty());
+    assert_eq!(serde_json::to_value(&patch).unwrap(), json!({ "host": "example.com", "port": null }));
+}
+
+#[test]
+fn test_renamed_field() {
+    let patch: OptionalLimits = serde_json::from_value(json!({ "timeout": 5, "retries": 3 })).unwrap();
+    assert_eq!(patch.timeout, Some(5));
+    assert_eq!(patch.ignored.get("retries"), Some(&json!(3)));
+}
@@ -0,0 +1,34 @@
+use optional_struct::*;
+use validator::Validate;
+
+#[optional_struct]
+#[derive(Validate)]
+struct Config {
+    #[validate(length(min = 1))]
+    name: String,
+    #[validate(range(min = 1, max = 100))]
+    percentage: u8,
+}
+
+#[test]
+fn test_validate_set_fields() {
+    let config = Config {
+        name: "".to_owned(),
+        percentage: 50,
+    };
+    assert!(config.validate().is_err());
+
+    let opt_config = OptionalConfig {
+        name: None,
+        percentage: Some(42),
+    };
+    assert!(opt_config.validate_set_fields().is_ok());
+
+    let opt_config = OptionalConfig {
+        name: Some("".to_owned()),
+        percentage: None,
+    };
+    let errors = opt_config.validate_set_fields().unwrap_err();
+    assert!(errors.field_errors().contains_key("name"));
+    assert!(!errors.field_errors().contains_key("percentage"));
+}
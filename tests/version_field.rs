@@ -0,0 +1,60 @@
+use optional_struct::*;
+
+#[optional_struct]
+#[optional_version_field(version)]
+#[derive(Debug, PartialEq)]
+struct Document {
+    title: String,
+    body: String,
+    version: u64,
+}
+
+fn document() -> Document {
+    Document {
+        title: "Draft".to_owned(),
+        body: "...".to_owned(),
+        version: 3,
+    }
+}
+
+#[test]
+fn test_matching_version_is_applied() {
+    let mut document = document();
+    let patch = OptionalDocument {
+        title: Some("Final".to_owned()),
+        body: None,
+        version: Some(3),
+    };
+    assert_eq!(patch.try_apply_to(&mut document), Ok(()));
+    assert_eq!(document, Document {
+        title: "Final".to_owned(),
+        body: "...".to_owned(),
+        version: 4,
+    });
+}
+
+#[test]
+fn test_stale_version_is_rejected() {
+    let mut document = document();
+    let patch = OptionalDocument {
+        title: Some("Final".to_owned()),
+        body: None,
+        version: Some(2),
+    };
+    let conflict = patch.try_apply_to(&mut document).unwrap_err();
+    assert_eq!(conflict, VersionConflict { expected: Some(2), actual: 3 });
+    assert_eq!(conflict.to_string(), "expected version 2, found 3");
+    assert_eq!(document, self::document());
+}
+
+#[test]
+fn test_missing_version_is_rejected() {
+    let mut document = document();
+    let patch = OptionalDocument {
+        title: Some("Final".to_owned()),
+        body: None,
+        version: None,
+    };
+    assert_eq!(patch.try_apply_to(&mut document), Err(VersionConflict { expected: None, actual: 3 }));
+    assert_eq!(document, self::document());
+}
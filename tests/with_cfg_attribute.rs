@@ -1,4 +1,5 @@
 //#![feature(stmt_expr_attributes)]
+#![allow(clippy::non_minimal_cfg)]
 use optional_struct::*;
 
 #[optional_struct]
@@ -1,6 +1,6 @@
 use optional_struct::*;
 
-#[optional_struct]
+#[optional_struct(impls(apply_report, set_count, tracked))]
 struct Config {
     #[optional_wrap]
     delay: Option<u32>,
@@ -28,3 +28,107 @@ fn test_apply_double_wrap() {
     assert_eq!(config.path, "/tmp/bar.log");
     assert_eq!(config.percentage, 42.24);
 }
+
+#[test]
+fn test_apply_double_wrap_unset() {
+    let mut config = Config {
+        delay: Some(2),
+        path: "/var/log/foo.log".to_owned(),
+        percentage: 3.12,
+    };
+
+    let opt_config = OptionalConfig {
+        delay: None,
+        path: None,
+        percentage: None,
+    };
+
+    opt_config.apply_to(&mut config);
+    assert_eq!(config.delay, Some(2));
+
+    let opt_config = OptionalConfig {
+        delay: Some(Some(5)),
+        path: None,
+        percentage: None,
+    };
+
+    opt_config.apply_to(&mut config);
+    assert_eq!(config.delay, Some(5));
+}
+
+#[test]
+fn test_apply_to_opt_double_wrap() {
+    let mut first = OptionalConfig {
+        delay: Some(Some(2)),
+        path: None,
+        percentage: None,
+    };
+
+    OptionalConfig { delay: None, path: None, percentage: None }.apply_to_opt(&mut first);
+    assert_eq!(first.delay, Some(Some(2)));
+
+    OptionalConfig { delay: Some(None), path: None, percentage: None }.apply_to_opt(&mut first);
+    assert_eq!(first.delay, Some(None));
+}
+
+#[test]
+fn test_try_build_double_wrap() {
+    let unset = OptionalConfig {
+        delay: None,
+        path: Some("/tmp/bar.log".to_owned()),
+        percentage: Some(42.24),
+    };
+    assert!(unset.can_convert());
+    assert_eq!(unset.try_build().unwrap().delay, None);
+
+    let cleared = OptionalConfig {
+        delay: Some(None),
+        path: Some("/tmp/bar.log".to_owned()),
+        percentage: Some(42.24),
+    };
+    assert_eq!(cleared.try_build().unwrap().delay, None);
+
+    let set = OptionalConfig {
+        delay: Some(Some(3)),
+        path: Some("/tmp/bar.log".to_owned()),
+        percentage: Some(42.24),
+    };
+    assert_eq!(set.set_count(), 3);
+    assert_eq!(set.try_build().unwrap().delay, Some(3));
+}
+
+#[test]
+fn test_report_double_wrap() {
+    let mut config = Config {
+        delay: Some(2),
+        path: "/var/log/foo.log".to_owned(),
+        percentage: 3.12,
+    };
+
+    let opt_config = OptionalConfig {
+        delay: Some(None),
+        path: None,
+        percentage: None,
+    };
+    assert_eq!(opt_config.set_count(), 1);
+
+    let changes = opt_config.apply_report(&mut config);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].field, "delay");
+    assert_eq!(changes[0].old, "Some(2)");
+    assert_eq!(changes[0].new, "None");
+    assert_eq!(config.delay, None);
+}
+
+#[test]
+fn test_tracked_double_wrap() {
+    let mut tracked = TrackedConfig::new(Config {
+        delay: Some(2),
+        path: "/var/log/foo.log".to_owned(),
+        percentage: 3.12,
+    });
+
+    tracked.set_delay(None);
+    assert_eq!(tracked.delay, None);
+    assert_eq!(tracked.flush().delay, Some(None));
+}
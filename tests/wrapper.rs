@@ -0,0 +1,94 @@
+use optional_struct::*;
+
+// Remembers which configuration layer set the value
+#[derive(Clone, Debug, PartialEq)]
+struct FromLayer<T> {
+    value: Option<T>,
+    layer: &'static str,
+}
+
+impl<T> Default for FromLayer<T> {
+    fn default() -> Self {
+        FromLayer { value: None, layer: "default" }
+    }
+}
+
+impl<T> Wrapper for FromLayer<T> {
+    type Value = T;
+
+    fn wrap(value: T) -> Self {
+        FromLayer { value: Some(value), layer: "code" }
+    }
+
+    fn as_option(&self) -> Option<&T> {
+        self.value.as_ref()
+    }
+
+    fn into_option(self) -> Option<T> {
+        self.value
+    }
+}
+
+fn from_layer<T>(value: T, layer: &'static str) -> FromLayer<T> {
+    FromLayer { value: Some(value), layer }
+}
+
+#[optional_struct(wrapper = FromLayer, impls(set_count, setters))]
+#[derive(Debug, PartialEq)]
+struct Config {
+    name: String,
+    port: u16,
+    #[optional_skip_wrap]
+    verbose: bool,
+}
+
+#[optional_struct(impls(iter_set_fields))]
+#[derive(Debug, PartialEq)]
+struct Limits {
+    #[optional_wrap_with(FromLayer)]
+    max_connections: u32,
+    timeout: u32,
+}
+
+#[test]
+fn test_wrapper_layers() {
+    let file = OptionalConfig {
+        name: from_layer("file".to_owned(), "file"),
+        port: from_layer(80, "file"),
+        verbose: false,
+    };
+    let env = OptionalConfig {
+        port: from_layer(8080, "env"),
+        ..Default::default()
+    };
+
+    let merged = file.apply(env);
+    assert_eq!(merged.name.layer, "file");
+    assert_eq!(merged.port, from_layer(8080, "env"));
+    assert_eq!(merged.set_count(), 3);
+
+    let config = merged.try_build().unwrap();
+    assert_eq!(config, Config { name: "file".to_owned(), port: 8080, verbose: false });
+}
+
+#[test]
+fn test_wrapper_apply() {
+    let mut config = Config { name: "foo".to_owned(), port: 80, verbose: true };
+    let mut opt_config = OptionalConfig::default();
+    assert!(!opt_config.can_convert());
+    opt_config.set_port(8080u16);
+    assert_eq!(opt_config.port.layer, "code");
+    opt_config.apply_to(&mut config);
+    assert_eq!(config, Config { name: "foo".to_owned(), port: 8080, verbose: false });
+}
+
+#[test]
+fn test_field_wrapper() {
+    let limits = OptionalLimits {
+        max_connections: from_layer(10, "file"),
+        timeout: None,
+    };
+    assert_eq!(limits.iter_set_fields().count(), 1);
+    let limits = limits.build(Limits { max_connections: 1, timeout: 2 });
+    assert_eq!(limits, Limits { max_connections: 10, timeout: 2 });
+}